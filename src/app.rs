@@ -8,7 +8,6 @@ use gpui_component_assets::Assets;
 use crate::clipboard::start_clipboard_history;
 use crate::hotkeys::setup_global_hotkey;
 use crate::ui::popup::{bind_popup_keys, PopupView};
-use std::sync::mpsc;
 
 pub fn run() {
     Application::new().with_assets(Assets).run(|cx: &mut App| {
@@ -16,8 +15,7 @@ pub fn run() {
 
         let bounds = Bounds::centered(None, size(px(750.), px(500.0)), cx);
         bind_popup_keys(cx);
-        let (clipboard_tx, clipboard_rx) = mpsc::channel();
-        start_clipboard_history(cx, clipboard_tx);
+        start_clipboard_history(cx);
         let window = cx
             .open_window(
                 WindowOptions {
@@ -30,7 +28,7 @@ pub fn run() {
                     ..Default::default()
                 },
                 move |window, cx| {
-                    let view = cx.new(|cx| PopupView::new(window, cx, clipboard_rx));
+                    let view = cx.new(|cx| PopupView::new(window, cx));
                     cx.new(|cx| Root::new(view, window, cx))
                 },
             )
@@ -0,0 +1,65 @@
+use regex::Regex;
+
+use crate::clipboard::types::ClipboardEntry;
+use crate::settings::AutoTagRule;
+
+/// Tags the auto-tag rules assign to a newly captured entry.
+pub fn auto_tags(rules: &[AutoTagRule], entry: &ClipboardEntry) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for rule in rules {
+        let tag = rule.tag.trim();
+        if tag.is_empty()
+            || tags
+                .iter()
+                .any(|existing| existing.eq_ignore_ascii_case(tag))
+        {
+            continue;
+        }
+        if rule_matches(rule, entry) {
+            tags.push(tag.to_string());
+        }
+    }
+    tags
+}
+
+fn rule_matches(rule: &AutoTagRule, entry: &ClipboardEntry) -> bool {
+    if let Some(content_type) = rule.content_type.as_deref() {
+        if !content_type.eq_ignore_ascii_case(&entry.content_type) {
+            return false;
+        }
+    }
+    if let Some(app) = rule.app.as_deref() {
+        let app = app.to_lowercase();
+        let from_app = [
+            entry.source_exe_path.as_deref(),
+            entry.source_app_title.as_deref(),
+        ]
+        .into_iter()
+        .flatten()
+        .any(|source| source.to_lowercase().contains(&app));
+        if !from_app {
+            return false;
+        }
+    }
+    if let Some(pattern) = rule.pattern.as_deref() {
+        let regex = match Regex::new(pattern) {
+            Ok(regex) => regex,
+            Err(err) => {
+                eprintln!("Invalid auto-tag pattern for `{}`: {err}", rule.tag);
+                return false;
+            }
+        };
+        let matched = [
+            Some(entry.content.as_str()),
+            entry.text_content.as_deref(),
+            entry.ocr_text.as_deref(),
+        ]
+        .into_iter()
+        .flatten()
+        .any(|text| regex.is_match(text));
+        if !matched {
+            return false;
+        }
+    }
+    true
+}
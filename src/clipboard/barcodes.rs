@@ -0,0 +1,237 @@
+use image::DynamicImage;
+use rxing::BarcodeFormat;
+use serde::{Deserialize, Serialize};
+
+use crate::clipboard::link_metadata::parse_link_url;
+use crate::clipboard::links::{ExtractedLink, LinkKind};
+
+/// A QR code or barcode found in an image entry.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct DecodedCode {
+    /// Human-readable symbology, e.g. `QR Code` or `EAN-13`.
+    pub format: String,
+    pub payload: String,
+}
+
+impl DecodedCode {
+    /// Short description of well-known payloads, shown above the raw text.
+    pub fn summary(&self) -> Option<String> {
+        let payload = self.payload.trim();
+        if let Some(fields) = strip_prefix_ignore_case(payload, "WIFI:") {
+            let network = wifi_field(fields, "S").unwrap_or_default();
+            return Some(format!("Wi-Fi network {network}"));
+        }
+        if strip_prefix_ignore_case(payload, "otpauth://").is_some() {
+            return Some("Two-factor authentication setup".to_string());
+        }
+        if strip_prefix_ignore_case(payload, "mailto:").is_some() {
+            return Some("Email address".to_string());
+        }
+        if strip_prefix_ignore_case(payload, "BEGIN:VCARD").is_some() {
+            return Some("Contact card".to_string());
+        }
+        None
+    }
+
+    pub fn url(&self) -> Option<url::Url> {
+        parse_link_url(&self.payload)
+    }
+}
+
+pub fn codes_to_json(codes: &[DecodedCode]) -> Option<String> {
+    if codes.is_empty() {
+        return None;
+    }
+    serde_json::to_string(codes).ok()
+}
+
+pub fn codes_from_json(raw: &str) -> Vec<DecodedCode> {
+    serde_json::from_str(raw).unwrap_or_default()
+}
+
+/// Scans the image for every supported code. Decoding is CPU bound, so
+/// callers on the async runtime should run it on a blocking thread.
+pub fn decode_codes(image: &DynamicImage) -> Vec<DecodedCode> {
+    let luma = image.to_luma8();
+    let (width, height) = luma.dimensions();
+    if width == 0 || height == 0 {
+        return Vec::new();
+    }
+
+    let results = match rxing::helpers::detect_multiple_in_luma(luma.into_raw(), width, height) {
+        Ok(results) => results,
+        // rxing reports "nothing found" as an error as well.
+        Err(_) => return Vec::new(),
+    };
+
+    let mut codes: Vec<DecodedCode> = Vec::new();
+    for result in results {
+        let payload = result.getText().trim();
+        if payload.is_empty() {
+            continue;
+        }
+        let code = DecodedCode {
+            format: format_label(result.getBarcodeFormat()),
+            payload: payload.to_string(),
+        };
+        if !codes.contains(&code) {
+            codes.push(code);
+        }
+    }
+    codes
+}
+
+pub fn decode_image_bytes(bytes: &[u8]) -> Vec<DecodedCode> {
+    match image::load_from_memory(bytes) {
+        Ok(image) => decode_codes(&image),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Decoded URLs become regular entry links so they show up for `has:link`
+/// and can be previewed. There is no source text, so offsets are zero.
+pub fn code_links(codes: &[DecodedCode]) -> Vec<ExtractedLink> {
+    codes
+        .iter()
+        .filter_map(DecodedCode::url)
+        .map(|url| ExtractedLink {
+            kind: LinkKind::Url,
+            value: url.to_string(),
+            start: 0,
+            end: 0,
+            metadata: None,
+        })
+        .collect()
+}
+
+fn format_label(format: &BarcodeFormat) -> String {
+    let label = match format {
+        BarcodeFormat::QR_CODE => "QR Code",
+        BarcodeFormat::MICRO_QR_CODE => "Micro QR Code",
+        BarcodeFormat::EAN_13 => "EAN-13",
+        BarcodeFormat::EAN_8 => "EAN-8",
+        BarcodeFormat::UPC_A => "UPC-A",
+        BarcodeFormat::UPC_E => "UPC-E",
+        BarcodeFormat::CODE_128 => "Code 128",
+        BarcodeFormat::CODE_39 => "Code 39",
+        BarcodeFormat::DATA_MATRIX => "Data Matrix",
+        BarcodeFormat::PDF_417 => "PDF417",
+        BarcodeFormat::AZTEC => "Aztec",
+        other => return format!("{other:?}"),
+    };
+    label.to_string()
+}
+
+fn strip_prefix_ignore_case<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = text.get(..prefix.len())?;
+    head.eq_ignore_ascii_case(prefix)
+        .then(|| &text[prefix.len()..])
+}
+
+/// Reads one `KEY:value;` field from a `WIFI:` payload, honouring `\` escapes.
+fn wifi_field(fields: &str, key: &str) -> Option<String> {
+    let mut value = String::new();
+    let mut current_key = String::new();
+    let mut in_value = false;
+    let mut chars = fields.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' if in_value => value.extend(chars.next()),
+            ':' if !in_value => in_value = true,
+            ';' => {
+                if current_key.eq_ignore_ascii_case(key) {
+                    return Some(value);
+                }
+                current_key.clear();
+                value.clear();
+                in_value = false;
+            }
+            _ if in_value => value.push(ch),
+            _ => current_key.push(ch),
+        }
+    }
+    (in_value && current_key.eq_ignore_ascii_case(key)).then_some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> Vec<u8> {
+        let path = format!(
+            "{}/tests/fixtures/barcodes/{name}",
+            env!("CARGO_MANIFEST_DIR")
+        );
+        std::fs::read(path).expect("fixture image")
+    }
+
+    fn code(format: &str, payload: &str) -> DecodedCode {
+        DecodedCode {
+            format: format.to_string(),
+            payload: payload.to_string(),
+        }
+    }
+
+    #[test]
+    fn decodes_fixture_codes() {
+        assert_eq!(
+            decode_image_bytes(&fixture("qr_url.png")),
+            vec![code("QR Code", "https://example.com/menu?table=7")]
+        );
+        assert_eq!(
+            decode_image_bytes(&fixture("ean13.png")),
+            vec![code("EAN-13", "4006381333931")]
+        );
+        assert_eq!(
+            decode_image_bytes(&fixture("code128.png")),
+            vec![code("Code 128", "CLIP-2026-0042")]
+        );
+    }
+
+    #[test]
+    fn unreadable_bytes_decode_to_nothing() {
+        assert!(decode_image_bytes(b"not an image").is_empty());
+    }
+
+    #[test]
+    fn only_url_payloads_become_links() {
+        let mut codes = decode_image_bytes(&fixture("qr_url.png"));
+        codes.extend(decode_image_bytes(&fixture("ean13.png")));
+        let links = code_links(&codes);
+
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].kind, LinkKind::Url);
+        assert_eq!(links[0].value, "https://example.com/menu?table=7");
+        assert_eq!((links[0].start, links[0].end), (0, 0));
+    }
+
+    #[test]
+    fn codes_round_trip_through_json() {
+        let codes = vec![
+            code("QR Code", r"WIFI:S:Home \; Office;T:WPA;P:secret;;"),
+            code("Code 128", "CLIP-2026-0042"),
+        ];
+        let json = codes_to_json(&codes).unwrap();
+        assert_eq!(codes_from_json(&json), codes);
+
+        assert_eq!(codes_to_json(&[]), None);
+        assert!(codes_from_json("not json").is_empty());
+    }
+
+    #[test]
+    fn summarizes_known_payloads() {
+        assert_eq!(
+            code("QR Code", r"WIFI:S:Home \; Office;T:WPA;P:secret;;")
+                .summary()
+                .as_deref(),
+            Some("Wi-Fi network Home ; Office")
+        );
+        assert_eq!(
+            code("QR Code", "otpauth://totp/x?secret=y")
+                .summary()
+                .as_deref(),
+            Some("Two-factor authentication setup")
+        );
+        assert_eq!(code("EAN-13", "4006381333931").summary(), None);
+    }
+}
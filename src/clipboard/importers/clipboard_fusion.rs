@@ -0,0 +1,216 @@
+use std::path::Path;
+
+use anyhow::anyhow;
+use sea_orm::{ConnectionTrait, Database, DatabaseConnection, Statement};
+
+use super::{ForeignContent, ForeignHistory, ForeignItem};
+
+/// Accepted names for each column, compared without case. ClipboardFusion
+/// doesn't document its history database, so the history table is found
+/// by its columns rather than by a fixed schema.
+const TEXT_COLUMNS: &[&str] = &["text", "cliptext", "plaintext", "content"];
+const DATE_COLUMNS: &[&str] = &["datecreated", "created", "date", "timestamp"];
+const IMAGE_COLUMNS: &[&str] = &["imagedata", "image", "bitmap"];
+const FILES_COLUMNS: &[&str] = &["filelist", "files"];
+
+/// .NET ticks (100 ns since 0001-01-01) at the Unix epoch.
+const TICKS_AT_UNIX_EPOCH: i64 = 621_355_968_000_000_000;
+const TICKS_PER_SECOND: i64 = 10_000_000;
+
+/// Where the history lives within the database.
+struct HistoryTable {
+    name: String,
+    text: String,
+    date: String,
+    image: Option<String>,
+    files: Option<String>,
+}
+
+/// Reads a ClipboardFusion history database, opened read-only.
+pub async fn read_history(path: &Path) -> anyhow::Result<ForeignHistory> {
+    let raw = path.to_string_lossy().replace('\\', "/");
+    let db = Database::connect(format!("sqlite:///{raw}?mode=ro")).await?;
+    let table = find_history_table(&db)
+        .await?
+        .ok_or_else(|| anyhow!("Not a ClipboardFusion history database"))?;
+
+    let column = |name: &Option<String>| {
+        name.as_ref()
+            .map_or_else(|| "NULL".to_string(), |name| format!("\"{name}\""))
+    };
+    let sql = format!(
+        "SELECT rowid, \"{}\", \"{}\", {}, {} FROM \"{}\" ORDER BY rowid",
+        table.date,
+        table.text,
+        column(&table.image),
+        column(&table.files),
+        table.name
+    );
+    let rows = db
+        .query_all(Statement::from_string(db.get_database_backend(), sql))
+        .await?;
+
+    let mut history = ForeignHistory::default();
+    for row in rows {
+        let id: i64 = row.try_get_by_index(0)?;
+        let Some(created_at) = row
+            .try_get_by_index::<Option<i64>>(1)
+            .ok()
+            .flatten()
+            .and_then(unix_seconds)
+        else {
+            history
+                .skipped
+                .push(format!("ClipboardFusion item {id}: no readable date"));
+            continue;
+        };
+        let text: Option<String> = row.try_get_by_index(2).ok().flatten();
+        let image: Option<Vec<u8>> = row.try_get_by_index(3).ok().flatten();
+        let files: Option<String> = row.try_get_by_index(4).ok().flatten();
+        match item_content(text, image, files) {
+            Some(content) => history.items.push(ForeignItem {
+                created_at,
+                content,
+            }),
+            None => history.skipped.push(format!(
+                "ClipboardFusion item {id}: no text, image or file list"
+            )),
+        }
+    }
+    db.close().await?;
+    Ok(history)
+}
+
+/// The first table with a text and a date column, preferring one named
+/// like a history.
+async fn find_history_table(db: &DatabaseConnection) -> anyhow::Result<Option<HistoryTable>> {
+    let backend = db.get_database_backend();
+    let mut names: Vec<String> = db
+        .query_all(Statement::from_string(
+            backend,
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' \
+             ORDER BY name",
+        ))
+        .await?
+        .into_iter()
+        .map(|row| row.try_get_by_index(0))
+        .collect::<Result<_, _>>()?;
+    names.sort_by_key(|name| !name.to_ascii_lowercase().contains("history"));
+
+    for name in names {
+        let columns: Vec<String> = db
+            .query_all(Statement::from_sql_and_values(
+                backend,
+                "SELECT name FROM pragma_table_info(?)",
+                [name.clone().into()],
+            ))
+            .await?
+            .into_iter()
+            .map(|row| row.try_get_by_index(0))
+            .collect::<Result<_, _>>()?;
+        let find = |candidates: &[&str]| {
+            candidates.iter().find_map(|candidate| {
+                columns
+                    .iter()
+                    .find(|column| column.eq_ignore_ascii_case(candidate))
+                    .cloned()
+            })
+        };
+        if let (Some(text), Some(date)) = (find(TEXT_COLUMNS), find(DATE_COLUMNS)) {
+            return Ok(Some(HistoryTable {
+                name,
+                text,
+                date,
+                image: find(IMAGE_COLUMNS),
+                files: find(FILES_COLUMNS),
+            }));
+        }
+    }
+    Ok(None)
+}
+
+/// Dates are .NET ticks, Unix milliseconds or Unix seconds, told apart by
+/// magnitude.
+fn unix_seconds(value: i64) -> Option<i64> {
+    match value {
+        value if value >= TICKS_AT_UNIX_EPOCH => {
+            Some((value - TICKS_AT_UNIX_EPOCH) / TICKS_PER_SECOND)
+        }
+        value if value >= 100_000_000_000 => Some(value / 1_000),
+        value if value > 0 => Some(value),
+        _ => None,
+    }
+}
+
+/// Files win over images and images over text, as in the watcher.
+fn item_content(
+    text: Option<String>,
+    image: Option<Vec<u8>>,
+    files: Option<String>,
+) -> Option<ForeignContent> {
+    let files: Vec<String> = files
+        .iter()
+        .flat_map(|files| files.lines())
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+        .map(str::to_string)
+        .collect();
+    if !files.is_empty() {
+        return Some(ForeignContent::Files(files));
+    }
+    if let Some(image) = image.filter(|image| !image.is_empty()) {
+        return Some(ForeignContent::Image(image));
+    }
+    text.filter(|text| !text.trim().is_empty())
+        .map(ForeignContent::Text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_clipboard_fusion_items() {
+        let path = Path::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/importers/ClipboardFusion.db"
+        ));
+        let history = async_std::task::block_on(read_history(path)).unwrap();
+
+        let dates: Vec<i64> = history.items.iter().map(|item| item.created_at).collect();
+        assert_eq!(dates, [1700000000, 1700000100, 1700000200, 1700000500]);
+        match &history.items[0].content {
+            ForeignContent::Text(text) => assert_eq!(text, "Hello from ClipboardFusion"),
+            _ => panic!("expected text"),
+        }
+        match &history.items[1].content {
+            ForeignContent::Image(png) => assert!(png.starts_with(b"\x89PNG")),
+            _ => panic!("expected an image"),
+        }
+        match &history.items[2].content {
+            ForeignContent::Files(files) => {
+                assert_eq!(files, &[r"C:\Users\me\a.txt", r"C:\Users\me\b.txt"])
+            }
+            _ => panic!("expected a file list"),
+        }
+
+        assert_eq!(
+            history.skipped,
+            [
+                "ClipboardFusion item 4: no text, image or file list",
+                "ClipboardFusion item 5: no readable date",
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_dates_in_any_unit() {
+        assert_eq!(unix_seconds(1_700_000_000), Some(1_700_000_000));
+        assert_eq!(unix_seconds(1_700_000_000_123), Some(1_700_000_000));
+        assert_eq!(
+            unix_seconds(TICKS_AT_UNIX_EPOCH + 1_700_000_000 * TICKS_PER_SECOND),
+            Some(1_700_000_000)
+        );
+        assert_eq!(unix_seconds(0), None);
+    }
+}
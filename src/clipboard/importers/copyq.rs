@@ -0,0 +1,248 @@
+use std::io::Read;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use anyhow::anyhow;
+use flate2::read::ZlibDecoder;
+
+use super::{ForeignContent, ForeignHistory, ForeignItem};
+
+/// Items written with a compression flag per format.
+const VERSION_COMPRESSED_FLAGS: i32 = -2;
+
+/// Tabs saved by CopyQ plugins (encrypted, synced) start with a header
+/// string such as `CopyQ_encrypted_tab` instead of an item count.
+const PLUGIN_HEADER_PREFIX: &str = "CopyQ_";
+
+/// Reads a CopyQ tab file (`copyq_tab_*.dat`), a Qt `QDataStream` of the
+/// tab's items, newest first. CopyQ keeps no copy times there, so items
+/// are dated backwards from the file's modification time.
+pub fn read_history(path: &Path) -> anyhow::Result<ForeignHistory> {
+    let bytes = std::fs::read(path)?;
+    let saved_at = std::fs::metadata(path)?
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64;
+
+    let mut stream = DataStream::new(&bytes);
+    if stream
+        .peek_string()
+        .is_some_and(|header| header.starts_with(PLUGIN_HEADER_PREFIX))
+    {
+        return Err(anyhow!(
+            "The CopyQ tab is encrypted or synced by a plugin; export it from CopyQ without plugins"
+        ));
+    }
+    let count = stream.i32()?;
+    if count < 0 {
+        return Err(anyhow!("Not a CopyQ tab file"));
+    }
+
+    let mut history = ForeignHistory::default();
+    for row in 0..count {
+        let formats = read_item(&mut stream)
+            .map_err(|err| anyhow!("CopyQ item {row} is unreadable: {err}"))?;
+        match item_content(&formats) {
+            Some(content) => history.items.push(ForeignItem {
+                created_at: saved_at - i64::from(row),
+                content,
+            }),
+            None => history.skipped.push(format!(
+                "CopyQ item {row}: no text, image or file list ({})",
+                formats
+                    .iter()
+                    .map(|(mime, _)| mime.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+        }
+    }
+    Ok(history)
+}
+
+/// One item's formats as `(mime type, bytes)`.
+fn read_item(stream: &mut DataStream) -> anyhow::Result<Vec<(String, Vec<u8>)>> {
+    let version = stream.i32()?;
+    let mut formats = Vec::new();
+    if version == VERSION_COMPRESSED_FLAGS {
+        let count = stream.i32()?;
+        for _ in 0..count {
+            let mime = stream.string()?;
+            let compressed = stream.bool()?;
+            let bytes = stream.bytes()?;
+            let bytes = if compressed {
+                uncompress(&bytes)?
+            } else {
+                bytes
+            };
+            formats.push((mime, bytes));
+        }
+    } else if version >= 0 {
+        // Older CopyQ compressed every format.
+        for _ in 0..version {
+            let mime = stream.string()?;
+            let bytes = stream.bytes()?;
+            let bytes = uncompress(&bytes).unwrap_or(bytes);
+            formats.push((mime, bytes));
+        }
+    } else {
+        return Err(anyhow!("unsupported item version {version}"));
+    }
+    Ok(formats)
+}
+
+fn item_content(formats: &[(String, Vec<u8>)]) -> Option<ForeignContent> {
+    let find = |suffix: &str| {
+        formats
+            .iter()
+            .find(|(mime, _)| mime.ends_with(suffix))
+            .map(|(_, bytes)| bytes)
+    };
+    if let Some(uris) = find("uri-list") {
+        let files: Vec<String> = String::from_utf8_lossy(uris)
+            .lines()
+            .filter_map(local_path)
+            .collect();
+        if !files.is_empty() {
+            return Some(ForeignContent::Files(files));
+        }
+    }
+    if let Some(image) = ["png", "bmp", "jpeg"].into_iter().find_map(find) {
+        return Some(ForeignContent::Image(image.clone()));
+    }
+    find("plain").map(|text| ForeignContent::Text(String::from_utf8_lossy(text).into_owned()))
+}
+
+/// Maps a `file://` URI to a local path, undoing percent-encoding.
+fn local_path(uri: &str) -> Option<String> {
+    let url = url::Url::parse(uri.trim()).ok()?;
+    let path = url.to_file_path().ok()?;
+    Some(path.to_string_lossy().into_owned())
+}
+
+/// Undoes `qCompress`: a big-endian length, then a zlib stream.
+fn uncompress(bytes: &[u8]) -> anyhow::Result<Vec<u8>> {
+    if bytes.is_empty() {
+        return Ok(Vec::new());
+    }
+    let stream = bytes
+        .get(4..)
+        .ok_or_else(|| anyhow!("compressed data is truncated"))?;
+    let mut plain = Vec::new();
+    ZlibDecoder::new(stream).read_to_end(&mut plain)?;
+    Ok(plain)
+}
+
+/// The subset of Qt's big-endian `QDataStream` encoding CopyQ uses.
+struct DataStream<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> DataStream<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, offset: 0 }
+    }
+
+    fn take(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        let slice = self
+            .offset
+            .checked_add(len)
+            .and_then(|end| self.bytes.get(self.offset..end))
+            .ok_or_else(|| anyhow!("unexpected end of file"))?;
+        self.offset += len;
+        Ok(slice)
+    }
+
+    fn u32(&mut self) -> anyhow::Result<u32> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn i32(&mut self) -> anyhow::Result<i32> {
+        Ok(self.u32()? as i32)
+    }
+
+    fn bool(&mut self) -> anyhow::Result<bool> {
+        Ok(self.take(1)?[0] != 0)
+    }
+
+    /// A `QByteArray`; a null array reads as empty.
+    fn bytes(&mut self) -> anyhow::Result<Vec<u8>> {
+        match self.u32()? {
+            u32::MAX => Ok(Vec::new()),
+            len => Ok(self.take(len as usize)?.to_vec()),
+        }
+    }
+
+    /// A `QString`, stored as UTF-16BE.
+    fn string(&mut self) -> anyhow::Result<String> {
+        let bytes = self.bytes()?;
+        let units: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+            .collect();
+        Ok(String::from_utf16_lossy(&units))
+    }
+
+    /// Reads a `QString` at the current position without consuming it.
+    fn peek_string(&self) -> Option<String> {
+        let mut peek = DataStream {
+            bytes: self.bytes,
+            offset: self.offset,
+        };
+        peek.string().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_copyq_items() {
+        let path = Path::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/importers/copyq_tab.dat"
+        ));
+        let history = read_history(path).unwrap();
+
+        assert_eq!(history.items.len(), 5);
+        // Newest first, each a second before the one above it.
+        let newest = history.items[0].created_at;
+        for (row, item) in history.items.iter().enumerate() {
+            assert!(item.created_at <= newest - row as i64);
+        }
+        match &history.items[0].content {
+            ForeignContent::Text(text) => assert_eq!(text, "Hello from CopyQ"),
+            _ => panic!("expected text"),
+        }
+        #[cfg(not(target_os = "windows"))]
+        match &history.items[1].content {
+            ForeignContent::Files(files) => assert_eq!(files, &["/home/me/My Notes.txt"]),
+            _ => panic!("expected a file list"),
+        }
+        match &history.items[2].content {
+            ForeignContent::Text(text) => assert_eq!(text, "compressed text"),
+            _ => panic!("expected text"),
+        }
+        match &history.items[3].content {
+            ForeignContent::Image(png) => assert!(png.starts_with(b"\x89PNG")),
+            _ => panic!("expected an image"),
+        }
+
+        assert_eq!(history.skipped.len(), 1);
+        assert!(history.skipped[0].contains("CopyQ item 4"));
+        assert!(history.skipped[0].contains("owner-window-title"));
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        let dir = crate::test_support::temp_dir("copyq-invalid");
+        let path = dir.join("copyq_tab.dat");
+        std::fs::write(&path, (-5i32).to_be_bytes()).unwrap();
+        assert!(read_history(&path).is_err());
+        std::fs::write(&path, [0, 0, 0, 3, 0xff, 0xff, 0xff, 0xfe]).unwrap();
+        assert!(read_history(&path).is_err());
+    }
+}
@@ -0,0 +1,224 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::anyhow;
+use sea_orm::{ConnectionTrait, Database, Statement};
+
+use super::{ForeignContent, ForeignHistory, ForeignItem};
+
+/// Ditto keeps one `Main` row per clip and one `Data` row per clipboard
+/// format, with the bytes exactly as the clipboard held them.
+const CLIPS_SQL: &str = "SELECT Main.lID, Main.lDate, Main.mText, Data.strClipBoardFormat, \
+     Data.ooData FROM Main LEFT JOIN Data ON Data.lParentID = Main.lID \
+     WHERE Main.bIsGroup = 0 ORDER BY Main.lID";
+
+/// `sizeof(BITMAPFILEHEADER)`, missing from `CF_DIB` data.
+const FILE_HEADER_LEN: usize = 14;
+/// `sizeof(DROPFILES)`, leading `CF_HDROP` data.
+const DROPFILES_LEN: usize = 20;
+
+#[derive(Default)]
+struct Clip {
+    created_at: i64,
+    text: Option<String>,
+    formats: BTreeMap<String, Vec<u8>>,
+}
+
+/// Reads a `Ditto.db`, opened read-only so a running Ditto is undisturbed.
+pub async fn read_history(path: &Path) -> anyhow::Result<ForeignHistory> {
+    let raw = path.to_string_lossy().replace('\\', "/");
+    let db = Database::connect(format!("sqlite:///{raw}?mode=ro")).await?;
+    let backend = db.get_database_backend();
+    let rows = db
+        .query_all(Statement::from_string(backend, CLIPS_SQL))
+        .await
+        .map_err(|err| anyhow!("Not a Ditto database: {err}"))?;
+
+    let mut clips: BTreeMap<i64, Clip> = BTreeMap::new();
+    for row in rows {
+        let id: i64 = row.try_get_by_index(0)?;
+        let clip = clips.entry(id).or_default();
+        clip.created_at = row.try_get_by_index::<Option<i64>>(1)?.unwrap_or_default();
+        clip.text = row.try_get_by_index(2)?;
+        let format: Option<String> = row.try_get_by_index(3)?;
+        let data: Option<Vec<u8>> = row.try_get_by_index(4)?;
+        if let (Some(format), Some(data)) = (format, data) {
+            clip.formats.insert(format, data);
+        }
+    }
+    db.close().await?;
+
+    let mut history = ForeignHistory::default();
+    for (id, clip) in clips {
+        match clip_content(&clip) {
+            Some(content) => history.items.push(ForeignItem {
+                created_at: clip.created_at,
+                content,
+            }),
+            None => history.skipped.push(format!(
+                "Ditto clip {id}: no text, image or file list ({})",
+                clip.formats.keys().cloned().collect::<Vec<_>>().join(", ")
+            )),
+        }
+    }
+    Ok(history)
+}
+
+/// Picks the richest format the history can hold, in the order the watcher
+/// reads the clipboard.
+fn clip_content(clip: &Clip) -> Option<ForeignContent> {
+    if let Some(files) = clip
+        .formats
+        .get("CF_HDROP")
+        .and_then(|data| drop_files(data))
+    {
+        return Some(ForeignContent::Files(files));
+    }
+    if let Some(png) = clip.formats.get("PNG") {
+        return Some(ForeignContent::Image(png.clone()));
+    }
+    if let Some(dib) = clip
+        .formats
+        .get("CF_DIB")
+        .and_then(|data| dib_to_bitmap(data))
+    {
+        return Some(ForeignContent::Image(dib));
+    }
+    let text = clip
+        .formats
+        .get("CF_UNICODETEXT")
+        .map(|data| utf16_text(data))
+        .or_else(|| {
+            clip.formats
+                .get("CF_TEXT")
+                .map(|data| String::from_utf8_lossy(until_nul(data)).into_owned())
+        })
+        .or_else(|| clip.text.clone())?;
+    Some(ForeignContent::Text(text))
+}
+
+/// Decodes NUL-terminated UTF-16LE.
+fn utf16_text(data: &[u8]) -> String {
+    let units: Vec<u16> = data
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .take_while(|unit| *unit != 0)
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+fn until_nul(data: &[u8]) -> &[u8] {
+    let end = data
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(data.len());
+    &data[..end]
+}
+
+/// Parses `DROPFILES` followed by a double-NUL-terminated path list.
+fn drop_files(data: &[u8]) -> Option<Vec<String>> {
+    let offset = u32::from_le_bytes(data.get(0..4)?.try_into().ok()?) as usize;
+    let wide = u32::from_le_bytes(data.get(16..DROPFILES_LEN)?.try_into().ok()?) != 0;
+    let list = data.get(offset..)?;
+    let paths: Vec<String> = if wide {
+        let units: Vec<u16> = list
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .collect();
+        units
+            .split(|unit| *unit == 0)
+            .take_while(|path| !path.is_empty())
+            .map(String::from_utf16_lossy)
+            .collect()
+    } else {
+        list.split(|byte| *byte == 0)
+            .take_while(|path| !path.is_empty())
+            .map(|path| String::from_utf8_lossy(path).into_owned())
+            .collect()
+    };
+    (!paths.is_empty()).then_some(paths)
+}
+
+/// Prepends the `BITMAPFILEHEADER` a `CF_DIB` lacks, giving the same bytes
+/// the watcher stores for bitmaps.
+fn dib_to_bitmap(dib: &[u8]) -> Option<Vec<u8>> {
+    let header_len = u32::from_le_bytes(dib.get(0..4)?.try_into().ok()?) as usize;
+    let bit_count = u16::from_le_bytes(dib.get(14..16)?.try_into().ok()?);
+    let compression = u32::from_le_bytes(dib.get(16..20)?.try_into().ok()?);
+    let colors_used = u32::from_le_bytes(dib.get(32..36)?.try_into().ok()?) as usize;
+    // BI_BITFIELDS masks follow a plain BITMAPINFOHEADER.
+    let masks = if compression == 3 && header_len == 40 {
+        12
+    } else {
+        0
+    };
+    let palette = match (colors_used, bit_count) {
+        (0, 1 | 4 | 8) => 1usize << bit_count,
+        (colors, _) => colors,
+    };
+    let pixels_at = FILE_HEADER_LEN + header_len + masks + palette * 4;
+    let file_len = FILE_HEADER_LEN + dib.len();
+
+    let mut bitmap = Vec::with_capacity(file_len);
+    bitmap.extend_from_slice(b"BM");
+    bitmap.extend_from_slice(&(file_len as u32).to_le_bytes());
+    bitmap.extend_from_slice(&[0; 4]);
+    bitmap.extend_from_slice(&(pixels_at as u32).to_le_bytes());
+    bitmap.extend_from_slice(dib);
+    Some(bitmap)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_ditto_clips() {
+        let path = Path::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/importers/ditto.db"
+        ));
+        let history = async_std::task::block_on(read_history(path)).unwrap();
+
+        let dates: Vec<i64> = history.items.iter().map(|item| item.created_at).collect();
+        assert_eq!(
+            dates,
+            [1700000000, 1700000100, 1700000200, 1700000300, 1700000500, 1700000600]
+        );
+        let texts: Vec<&str> = history
+            .items
+            .iter()
+            .filter_map(|item| match &item.content {
+                ForeignContent::Text(text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            texts,
+            [
+                "Hello from Ditto",
+                "ansi only",
+                "Hello from Ditto",
+                "https://example.com/page"
+            ]
+        );
+        match &history.items[2].content {
+            ForeignContent::Files(files) => assert_eq!(
+                files,
+                &[r"C:\Users\me\report.docx", r"C:\Users\me\notes.txt"]
+            ),
+            _ => panic!("expected a file list"),
+        }
+        match &history.items[3].content {
+            ForeignContent::Image(bitmap) => {
+                assert!(bitmap.starts_with(b"BM"));
+                image::load_from_memory(bitmap).expect("a readable bitmap");
+            }
+            _ => panic!("expected an image"),
+        }
+
+        assert_eq!(history.skipped.len(), 1);
+        assert!(history.skipped[0].contains("Ditto clip 5"));
+        assert!(history.skipped[0].contains("HTML Format"));
+    }
+}
@@ -0,0 +1,334 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use sea_orm::DatabaseConnection;
+
+use crate::clipboard::link_metadata::parse_link_url;
+use crate::clipboard::links::extract_links;
+use crate::clipboard::types::summarize_file_paths;
+use crate::clipboard::url_clean::clean_url;
+use crate::settings::settings;
+use crate::storage::archive::ImportReport;
+use crate::storage::crypto;
+use crate::storage::history::{
+    insert_clipboard_entry, insert_entry_links, load_content_hashes, ClipboardEntryInput,
+    EntryLinkInput,
+};
+use crate::storage::images::{save_image_bytes_in, to_bitmap};
+use crate::storage::path::{images_dir, thumbnails_dir};
+
+pub mod clipboard_fusion;
+pub mod copyq;
+pub mod ditto;
+
+/// Clipboard managers whose history can be imported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ForeignSource {
+    /// Ditto's SQLite database, usually `Ditto.db`.
+    Ditto,
+    /// A CopyQ tab file, e.g. `copyq_tab_JmNsaXBib2FyZA==.dat`.
+    CopyQ,
+    /// ClipboardFusion's SQLite history, told apart from Ditto by a
+    /// `ClipboardFusion` file name.
+    ClipboardFusion,
+}
+
+impl ForeignSource {
+    /// Picks the importer from the file extension; `None` for our own
+    /// archive and JSON exports.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+        let clipboard_fusion = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_ascii_lowercase())
+            .is_some_and(|name| name.contains("clipboardfusion"));
+        match extension.as_deref() {
+            Some("db" | "sqlite") if clipboard_fusion => Some(Self::ClipboardFusion),
+            Some("db" | "sqlite") => Some(Self::Ditto),
+            Some("dat") => Some(Self::CopyQ),
+            _ => None,
+        }
+    }
+}
+
+/// An item read from another manager's history.
+pub struct ForeignItem {
+    /// Unix seconds.
+    pub created_at: i64,
+    pub content: ForeignContent,
+}
+
+pub enum ForeignContent {
+    Text(String),
+    /// Encoded image bytes in any format the `image` crate reads.
+    Image(Vec<u8>),
+    Files(Vec<String>),
+}
+
+/// What an importer read, plus the items it couldn't use and why.
+#[derive(Default)]
+pub struct ForeignHistory {
+    pub items: Vec<ForeignItem>,
+    pub skipped: Vec<String>,
+}
+
+/// Adds another manager's history, oldest first, skipping items whose
+/// content is already stored. No link previews are fetched and no OCR
+/// runs here; the popup's OCR backfill picks up the images.
+pub async fn import_foreign_history(
+    db: &DatabaseConnection,
+    source: ForeignSource,
+    path: &Path,
+) -> anyhow::Result<ImportReport> {
+    import_foreign_history_in(db, source, path, &images_dir()?, &thumbnails_dir()?).await
+}
+
+/// `import_foreign_history`, saving images into the given folders.
+async fn import_foreign_history_in(
+    db: &DatabaseConnection,
+    source: ForeignSource,
+    path: &Path,
+    images_dir: &Path,
+    thumbnails_dir: &Path,
+) -> anyhow::Result<ImportReport> {
+    let mut history = match source {
+        ForeignSource::Ditto => ditto::read_history(path).await?,
+        ForeignSource::CopyQ => copyq::read_history(path)?,
+        ForeignSource::ClipboardFusion => clipboard_fusion::read_history(path).await?,
+    };
+    history.items.sort_by_key(|item| item.created_at);
+
+    let mut report = ImportReport {
+        skipped: history.skipped,
+        ..Default::default()
+    };
+    let mut hashes = load_content_hashes(db).await?;
+    for item in history.items {
+        let is_image = matches!(item.content, ForeignContent::Image(_));
+        match store_item(db, item, &mut hashes, images_dir, thumbnails_dir).await {
+            Ok(true) => {
+                report.imported += 1;
+                report.images += usize::from(is_image);
+            }
+            Ok(false) => report.duplicates += 1,
+            Err(err) => report.skipped.push(err.to_string()),
+        }
+    }
+    Ok(report)
+}
+
+/// Maps an item onto an entry the way the watcher would have captured it.
+/// Returns `false` for duplicates.
+async fn store_item(
+    db: &DatabaseConnection,
+    item: ForeignItem,
+    hashes: &mut HashSet<String>,
+    images_dir: &Path,
+    thumbnails_dir: &Path,
+) -> anyhow::Result<bool> {
+    let mut content_type = "text";
+    let mut text_content = None;
+    let mut image_path = None;
+    let mut file_paths = None;
+    let mut link_url = None;
+    let mut link_clean_url = None;
+    let mut links = Vec::new();
+    let (content, content_hash) = match item.content {
+        ForeignContent::Text(text) => {
+            let trimmed = text.trim();
+            if trimmed.is_empty() {
+                return Err(anyhow::anyhow!("An empty text item"));
+            }
+            let content_hash = crypto::content_hash(trimmed.as_bytes());
+            if hashes.contains(&content_hash) {
+                return Ok(false);
+            }
+            match parse_link_url(trimmed) {
+                Some(url) => {
+                    content_type = "link";
+                    link_clean_url = Some(clean_url(&url, &settings().links.cleaning).to_string());
+                    link_url = Some(url.to_string());
+                }
+                None => links = extract_links(trimmed),
+            }
+            text_content = Some(trimmed.to_string());
+            (trimmed.to_string(), content_hash)
+        }
+        ForeignContent::Image(bytes) => {
+            let bytes = to_bitmap(&bytes)?;
+            let content_hash = crypto::content_hash(&bytes);
+            if hashes.contains(&content_hash) {
+                return Ok(false);
+            }
+            content_type = "image";
+            let path =
+                save_image_bytes_in(images_dir, thumbnails_dir, &content_hash, bytes).await?;
+            image_path = Some(path.to_string_lossy().into_owned());
+            ("Image".to_string(), content_hash)
+        }
+        ForeignContent::Files(paths) => {
+            if paths.is_empty() {
+                return Err(anyhow::anyhow!("A file list without files"));
+            }
+            let json = serde_json::to_string(&paths)?;
+            let content_hash = crypto::content_hash(json.as_bytes());
+            if hashes.contains(&content_hash) {
+                return Ok(false);
+            }
+            content_type = "files";
+            file_paths = Some(json);
+            (summarize_file_paths(&paths), content_hash)
+        }
+    };
+
+    let id = insert_clipboard_entry(
+        db,
+        ClipboardEntryInput {
+            content_type,
+            content_hash: &content_hash,
+            content: &content,
+            text_content: text_content.as_deref(),
+            ocr_text: None,
+            ocr_layout: None,
+            ocr_attempt: None,
+            image_path: image_path.as_deref(),
+            image_codes: None,
+            file_paths: file_paths.as_deref(),
+            link_url: link_url.as_deref(),
+            link_clean_url: link_clean_url.as_deref(),
+            link_title: None,
+            link_description: None,
+            link_site_name: None,
+            link_details: None,
+            source_app_title: None,
+            source_exe_path: None,
+            created_at: Some(item.created_at),
+        },
+    )
+    .await?;
+    let links: Vec<EntryLinkInput> = links
+        .iter()
+        .map(|link| EntryLinkInput {
+            kind: link.kind.as_str(),
+            url: &link.value,
+            start_offset: link.start as i32,
+            end_offset: link.end as i32,
+            title: None,
+            description: None,
+            site_name: None,
+        })
+        .collect();
+    insert_entry_links(db, id, &links).await?;
+    hashes.insert(content_hash);
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::entity::{Column, Entity};
+    use crate::storage::history::open_db;
+    use crate::test_support::{lock_storage, temp_dir};
+    use sea_orm::{EntityTrait, QueryOrder};
+
+    fn fixture(name: &str) -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/importers")
+            .join(name)
+    }
+
+    #[test]
+    fn picks_the_importer_from_the_file() {
+        let source = |name: &str| ForeignSource::from_path(Path::new(name));
+        assert_eq!(source("Ditto.db"), Some(ForeignSource::Ditto));
+        assert_eq!(source("copyq_tab_abc.dat"), Some(ForeignSource::CopyQ));
+        assert_eq!(
+            source("ClipboardFusion.db"),
+            Some(ForeignSource::ClipboardFusion)
+        );
+        assert_eq!(source("export.zip"), None);
+    }
+
+    #[test]
+    fn imports_and_dedupes_foreign_histories() {
+        let _storage = lock_storage();
+        async_std::task::block_on(async {
+            let dir = temp_dir("foreign-import");
+            let db = open_db(&dir.join("history.sqlite"), false).await.unwrap();
+            let import = |source, name: &str| {
+                let path = fixture(name);
+                let db = &db;
+                let dir = &dir;
+                async move {
+                    import_foreign_history_in(
+                        db,
+                        source,
+                        &path,
+                        &dir.join("images"),
+                        &dir.join("thumbnails"),
+                    )
+                    .await
+                    .unwrap()
+                }
+            };
+
+            let report = import(ForeignSource::Ditto, "ditto.db").await;
+            assert_eq!(
+                (report.imported, report.duplicates, report.images),
+                (5, 1, 1)
+            );
+            assert_eq!(report.skipped.len(), 1);
+
+            let entries = Entity::find()
+                .order_by_asc(Column::CreatedAt)
+                .all(&db)
+                .await
+                .unwrap();
+            let kinds: Vec<(&str, i64)> = entries
+                .iter()
+                .map(|entry| (entry.content_type.as_str(), entry.created_at))
+                .collect();
+            assert_eq!(
+                kinds,
+                [
+                    ("text", 1700000000),
+                    ("text", 1700000100),
+                    ("files", 1700000200),
+                    ("image", 1700000300),
+                    ("link", 1700000600),
+                ]
+            );
+            assert_eq!(
+                entries[2].file_paths.as_deref(),
+                Some(r#"["C:\\Users\\me\\report.docx","C:\\Users\\me\\notes.txt"]"#)
+            );
+            let image = entries[3].image_path.as_deref().unwrap();
+            assert!(Path::new(image).starts_with(dir.join("images")));
+            assert!(Path::new(image).is_file());
+            assert_eq!(
+                entries[4].link_url.as_deref(),
+                Some("https://example.com/page")
+            );
+
+            // A second import finds everything already stored.
+            let report = import(ForeignSource::Ditto, "ditto.db").await;
+            assert_eq!((report.imported, report.duplicates), (0, 6));
+
+            let report = import(ForeignSource::CopyQ, "copyq_tab.dat").await;
+            assert_eq!(
+                (report.imported, report.duplicates, report.images),
+                (4, 1, 1)
+            );
+            assert_eq!(report.skipped.len(), 1);
+
+            let report = import(ForeignSource::ClipboardFusion, "ClipboardFusion.db").await;
+            assert_eq!(
+                (report.imported, report.duplicates, report.images),
+                (3, 1, 1)
+            );
+            assert_eq!(report.skipped.len(), 2);
+        });
+    }
+}
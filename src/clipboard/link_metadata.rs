@@ -1,16 +1,45 @@
 use std::time::Duration;
 
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 use async_std::future::timeout;
 use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
 use url::Url;
 
+use crate::clipboard::link_providers::provider_for;
+use crate::clipboard::network::{check_url, fetch_text};
+use crate::settings::settings;
+
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
 #[derive(Clone, Debug)]
 pub struct LinkMetadata {
     pub url: String,
     pub title: Option<String>,
     pub description: Option<String>,
     pub site_name: Option<String>,
+    pub details: Option<LinkDetails>,
+}
+
+/// Structured fields that only site-specific providers know how to fill in.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LinkDetails {
+    /// What the link points at, e.g. `issue`, `pull_request` or `video`.
+    pub kind: Option<String>,
+    pub state: Option<String>,
+    pub author: Option<String>,
+    pub labels: Vec<String>,
+    pub duration: Option<String>,
+}
+
+impl LinkDetails {
+    pub fn to_json(&self) -> Option<String> {
+        serde_json::to_string(self).ok()
+    }
+
+    pub fn from_json(raw: &str) -> Option<Self> {
+        serde_json::from_str(raw).ok()
+    }
 }
 
 pub fn parse_link_url(text: &str) -> Option<Url> {
@@ -25,8 +54,24 @@ pub fn parse_link_url(text: &str) -> Option<Url> {
     }
 }
 
+/// Fetches a preview of `url`. A request the network policy refuses fails
+/// with a `PolicyViolation`, so callers can tell it apart from a page
+/// without metadata.
 pub async fn fetch_link_metadata(url: &Url) -> Result<Option<LinkMetadata>> {
-    let body = match timeout(Duration::from_secs(8), fetch_body(url)).await {
+    let policy = settings().network;
+    check_url(url, &policy).await?;
+
+    if let Some(provider) = provider_for(url) {
+        match provider.fetch(url, &policy).await {
+            Ok(Some(metadata)) => return Ok(Some(metadata)),
+            Ok(None) => {}
+            Err(err) => {
+                eprintln!("{} metadata provider failed: {err}", provider.name());
+            }
+        }
+    }
+
+    let body = match timeout(Duration::from_secs(8), fetch_text(url, &policy)).await {
         Ok(Ok(body)) => body,
         Ok(Err(err)) => return Err(err),
         Err(_) => return Ok(None),
@@ -45,24 +90,11 @@ pub async fn fetch_link_metadata(url: &Url) -> Result<Option<LinkMetadata>> {
         title,
         description,
         site_name,
+        details: None,
     }))
 }
 
-async fn fetch_body(url: &Url) -> Result<String> {
-    let mut response = surf::get(url.as_str())
-        .header("User-Agent", "gpui-clipboard-manager/0.1")
-        .await
-        .map_err(|err| anyhow!(err.to_string()))?;
-    if !response.status().is_success() {
-        return Ok(String::new());
-    }
-    response
-        .body_string()
-        .await
-        .map_err(|err| anyhow!(err.to_string()))
-}
-
-fn title_text(document: &Html) -> Option<String> {
+pub(crate) fn title_text(document: &Html) -> Option<String> {
     let selector = Selector::parse("title").ok()?;
     let title = document
         .select(&selector)
@@ -76,7 +108,7 @@ fn title_text(document: &Html) -> Option<String> {
     }
 }
 
-fn meta_content(document: &Html, selector: &str) -> Option<String> {
+pub(crate) fn meta_content(document: &Html, selector: &str) -> Option<String> {
     let selector = Selector::parse(selector).ok()?;
     let value = document
         .select(&selector)
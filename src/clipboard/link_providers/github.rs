@@ -0,0 +1,170 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use url::Url;
+
+use super::{
+    fetch_json, host_is, json_string, json_strings, short_description, LinkMetadataProvider,
+};
+use crate::clipboard::link_metadata::{LinkDetails, LinkMetadata};
+use crate::settings::NetworkSettings;
+
+const API_BASE: &str = "https://api.github.com";
+
+/// Issues and pull requests on github.com, via the public REST API.
+pub struct GitHubProvider {
+    api_base: Url,
+}
+
+impl Default for GitHubProvider {
+    fn default() -> Self {
+        Self {
+            api_base: Url::parse(API_BASE).expect("valid GitHub API base"),
+        }
+    }
+}
+
+struct GitHubTarget {
+    owner: String,
+    repo: String,
+    number: u64,
+}
+
+fn parse_target(url: &Url) -> Option<GitHubTarget> {
+    if !host_is(url, "github.com") {
+        return None;
+    }
+    let segments: Vec<&str> = url.path_segments()?.collect();
+    match segments.as_slice() {
+        [owner, repo, "issues" | "pull", number, ..] => Some(GitHubTarget {
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            number: number.parse().ok()?,
+        }),
+        _ => None,
+    }
+}
+
+#[async_trait]
+impl LinkMetadataProvider for GitHubProvider {
+    fn name(&self) -> &'static str {
+        "GitHub"
+    }
+
+    fn matches(&self, url: &Url) -> bool {
+        parse_target(url).is_some()
+    }
+
+    async fn fetch(&self, url: &Url, policy: &NetworkSettings) -> Result<Option<LinkMetadata>> {
+        let Some(target) = parse_target(url) else {
+            return Ok(None);
+        };
+        let api_url = self.api_base.join(&format!(
+            "repos/{}/{}/issues/{}",
+            target.owner, target.repo, target.number
+        ))?;
+        let issue = fetch_json(&api_url, policy).await?;
+
+        let is_pull_request = issue.get("pull_request").is_some();
+        let merged = issue
+            .pointer("/pull_request/merged_at")
+            .is_some_and(|value| !value.is_null());
+        let state = if merged {
+            Some("merged".to_string())
+        } else {
+            json_string(&issue, "/state")
+        };
+        let title =
+            json_string(&issue, "/title").map(|title| format!("{title} · #{}", target.number));
+
+        Ok(Some(LinkMetadata {
+            url: url.to_string(),
+            title,
+            description: short_description(json_string(&issue, "/body")),
+            site_name: Some(format!("GitHub · {}/{}", target.owner, target.repo)),
+            details: Some(LinkDetails {
+                kind: Some(
+                    if is_pull_request {
+                        "pull_request"
+                    } else {
+                        "issue"
+                    }
+                    .to_string(),
+                ),
+                state,
+                author: json_string(&issue, "/user/login"),
+                labels: json_strings(&issue, "/labels"),
+                duration: None,
+            }),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::serve;
+
+    fn fixture_provider() -> GitHubProvider {
+        let base = serve(vec![
+            (
+                "/repos/acme/clipper/issues/1412",
+                include_str!(concat!(
+                    env!("CARGO_MANIFEST_DIR"),
+                    "/tests/fixtures/link_providers/github_issue.json"
+                ))
+                .to_string(),
+            ),
+            (
+                "/repos/acme/clipper/issues/1413",
+                include_str!(concat!(
+                    env!("CARGO_MANIFEST_DIR"),
+                    "/tests/fixtures/link_providers/github_merged_pull.json"
+                ))
+                .to_string(),
+            ),
+        ]);
+        GitHubProvider {
+            api_base: Url::parse(&base).unwrap(),
+        }
+    }
+
+    fn fetch(provider: &GitHubProvider, url: &str) -> LinkMetadata {
+        let policy = NetworkSettings {
+            allow_private_networks: true,
+            ..NetworkSettings::default()
+        };
+        async_std::task::block_on(provider.fetch(&Url::parse(url).unwrap(), &policy))
+            .unwrap()
+            .expect("provider returns metadata")
+    }
+
+    #[test]
+    fn reads_issue_state_author_and_labels() {
+        let provider = fixture_provider();
+        let metadata = fetch(&provider, "https://github.com/acme/clipper/issues/1412");
+
+        assert_eq!(
+            metadata.title.as_deref(),
+            Some("Popup loses focus after paste · #1412")
+        );
+        assert_eq!(metadata.site_name.as_deref(), Some("GitHub · acme/clipper"));
+        let details = metadata.details.unwrap();
+        assert_eq!(details.kind.as_deref(), Some("issue"));
+        assert_eq!(details.state.as_deref(), Some("open"));
+        assert_eq!(details.author.as_deref(), Some("octocat"));
+        assert_eq!(details.labels, vec!["bug", "ui"]);
+    }
+
+    #[test]
+    fn merged_pull_requests_report_merged() {
+        let provider = fixture_provider();
+        let metadata = fetch(&provider, "https://github.com/acme/clipper/pull/1413/files");
+
+        assert_eq!(metadata.description.as_deref(), Some("Fixes #1412."));
+        let details = metadata.details.unwrap();
+        assert_eq!(details.kind.as_deref(), Some("pull_request"));
+        assert_eq!(details.state.as_deref(), Some("merged"));
+        assert_eq!(details.author.as_deref(), Some("hubot"));
+        assert_eq!(details.labels, vec!["bug"]);
+    }
+}
@@ -0,0 +1,205 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use url::Url;
+
+use super::{fetch_json, json_string, json_strings, short_description, LinkMetadataProvider};
+use crate::clipboard::link_metadata::{LinkDetails, LinkMetadata};
+use crate::settings::NetworkSettings;
+
+/// Merge requests and issues on gitlab.com or self-hosted `gitlab.*` hosts.
+#[derive(Default)]
+pub struct GitLabProvider {
+    /// Origin of the REST API; defaults to the link's own host.
+    api_base: Option<Url>,
+}
+
+struct GitLabTarget {
+    project: String,
+    kind: &'static str,
+    iid: u64,
+}
+
+fn parse_target(url: &Url) -> Option<GitLabTarget> {
+    let host = url.host_str()?.to_ascii_lowercase();
+    if host != "gitlab.com" && !host.starts_with("gitlab.") {
+        return None;
+    }
+    let path = url.path().trim_matches('/');
+    let (project, rest) = path.split_once("/-/")?;
+    let mut parts = rest.split('/');
+    let kind = match parts.next()? {
+        "merge_requests" => "merge_requests",
+        "issues" => "issues",
+        _ => return None,
+    };
+    let iid = parts.next()?.parse().ok()?;
+    if project.is_empty() {
+        return None;
+    }
+    Some(GitLabTarget {
+        project: project.to_string(),
+        kind,
+        iid,
+    })
+}
+
+#[async_trait]
+impl LinkMetadataProvider for GitLabProvider {
+    fn name(&self) -> &'static str {
+        "GitLab"
+    }
+
+    fn matches(&self, url: &Url) -> bool {
+        parse_target(url).is_some()
+    }
+
+    async fn fetch(&self, url: &Url, policy: &NetworkSettings) -> Result<Option<LinkMetadata>> {
+        let Some(target) = parse_target(url) else {
+            return Ok(None);
+        };
+        let project: String =
+            url::form_urlencoded::byte_serialize(target.project.as_bytes()).collect();
+        let mut api_url = self.api_base.as_ref().unwrap_or(url).clone();
+        api_url.set_path(&format!(
+            "/api/v4/projects/{project}/{}/{}",
+            target.kind, target.iid
+        ));
+        api_url.set_query(None);
+        api_url.set_fragment(None);
+        let item = fetch_json(&api_url, policy).await?;
+
+        let sigil = if target.kind == "merge_requests" {
+            '!'
+        } else {
+            '#'
+        };
+        let title =
+            json_string(&item, "/title").map(|title| format!("{title} · {sigil}{}", target.iid));
+
+        Ok(Some(LinkMetadata {
+            url: url.to_string(),
+            title,
+            description: short_description(json_string(&item, "/description")),
+            site_name: Some(format!("GitLab · {}", target.project)),
+            details: Some(LinkDetails {
+                kind: Some(
+                    if target.kind == "merge_requests" {
+                        "merge_request"
+                    } else {
+                        "issue"
+                    }
+                    .to_string(),
+                ),
+                state: json_string(&item, "/state"),
+                author: json_string(&item, "/author/username"),
+                labels: json_strings(&item, "/labels"),
+                duration: None,
+            }),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::serve;
+
+    fn fixture_provider() -> GitLabProvider {
+        let base = serve(vec![
+            (
+                "/api/v4/projects/acme%2Fdesktop%2Fclipper/merge_requests/87",
+                include_str!(concat!(
+                    env!("CARGO_MANIFEST_DIR"),
+                    "/tests/fixtures/link_providers/gitlab_merge_request.json"
+                ))
+                .to_string(),
+            ),
+            (
+                "/api/v4/projects/acme%2Fclipper/issues/80",
+                include_str!(concat!(
+                    env!("CARGO_MANIFEST_DIR"),
+                    "/tests/fixtures/link_providers/gitlab_issue.json"
+                ))
+                .to_string(),
+            ),
+        ]);
+        GitLabProvider {
+            api_base: Some(Url::parse(&base).unwrap()),
+        }
+    }
+
+    fn fetch(provider: &GitLabProvider, url: &str) -> LinkMetadata {
+        let policy = NetworkSettings {
+            allow_private_networks: true,
+            ..NetworkSettings::default()
+        };
+        async_std::task::block_on(provider.fetch(&Url::parse(url).unwrap(), &policy))
+            .unwrap()
+            .expect("provider returns metadata")
+    }
+
+    #[test]
+    fn reads_merge_request_in_nested_group() {
+        let provider = fixture_provider();
+        let metadata = fetch(
+            &provider,
+            "https://gitlab.com/acme/desktop/clipper/-/merge_requests/87/diffs?view=inline",
+        );
+
+        assert_eq!(
+            metadata.title.as_deref(),
+            Some("Store OCR word boxes · !87")
+        );
+        assert_eq!(
+            metadata.description.as_deref(),
+            Some("Adds a layout column. Closes #80.")
+        );
+        assert_eq!(
+            metadata.site_name.as_deref(),
+            Some("GitLab · acme/desktop/clipper")
+        );
+        let details = metadata.details.unwrap();
+        assert_eq!(details.kind.as_deref(), Some("merge_request"));
+        assert_eq!(details.state.as_deref(), Some("merged"));
+        assert_eq!(details.author.as_deref(), Some("maintainer"));
+        assert_eq!(details.labels, vec!["ocr", "storage"]);
+    }
+
+    #[test]
+    fn reads_issue_on_self_hosted_instance() {
+        let provider = fixture_provider();
+        let metadata = fetch(
+            &provider,
+            "https://gitlab.example.org/acme/clipper/-/issues/80",
+        );
+
+        assert_eq!(
+            metadata.title.as_deref(),
+            Some("Highlight OCR matches on images · #80")
+        );
+        assert_eq!(metadata.description, None);
+        let details = metadata.details.unwrap();
+        assert_eq!(details.kind.as_deref(), Some("issue"));
+        assert_eq!(details.state.as_deref(), Some("opened"));
+        assert_eq!(details.author.as_deref(), Some("reporter"));
+        assert!(details.labels.is_empty());
+    }
+
+    #[test]
+    fn matches_only_gitlab_items() {
+        let provider = GitLabProvider::default();
+        for (url, expected) in [
+            ("https://gitlab.com/acme/clipper/-/merge_requests/1", true),
+            ("https://gitlab.example.org/a/b/-/issues/2", true),
+            ("https://gitlab.com/acme/clipper/-/pipelines/3", false),
+            ("https://gitlab.com/acme/clipper", false),
+            ("https://example.com/acme/clipper/-/issues/2", false),
+        ] {
+            assert_eq!(
+                provider.matches(&Url::parse(url).unwrap()),
+                expected,
+                "{url}"
+            );
+        }
+    }
+}
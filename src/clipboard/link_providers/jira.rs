@@ -0,0 +1,164 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use url::Url;
+
+use super::{fetch_json, json_string, json_strings, short_description, LinkMetadataProvider};
+use crate::clipboard::link_metadata::{LinkDetails, LinkMetadata};
+use crate::settings::NetworkSettings;
+
+/// Jira issues linked as `/browse/KEY-123`. Only instances that allow
+/// anonymous REST access return data; others fall back to the page title.
+#[derive(Default)]
+pub struct JiraProvider {
+    /// Origin of the REST API; defaults to the link's own host.
+    api_base: Option<Url>,
+}
+
+fn issue_key(url: &Url) -> Option<String> {
+    let segments: Vec<&str> = url.path_segments()?.collect();
+    let key = match segments.as_slice() {
+        [.., "browse", key] => *key,
+        _ => return None,
+    };
+    let (project, number) = key.split_once('-')?;
+    let valid_project = !project.is_empty()
+        && project
+            .chars()
+            .all(|ch| ch.is_ascii_uppercase() || ch.is_ascii_digit() || ch == '_');
+    if !valid_project || number.parse::<u64>().is_err() {
+        return None;
+    }
+    Some(key.to_string())
+}
+
+#[async_trait]
+impl LinkMetadataProvider for JiraProvider {
+    fn name(&self) -> &'static str {
+        "Jira"
+    }
+
+    fn matches(&self, url: &Url) -> bool {
+        issue_key(url).is_some()
+    }
+
+    async fn fetch(&self, url: &Url, policy: &NetworkSettings) -> Result<Option<LinkMetadata>> {
+        let Some(key) = issue_key(url) else {
+            return Ok(None);
+        };
+        let mut api_url = self.api_base.as_ref().unwrap_or(url).clone();
+        let prefix = url
+            .path()
+            .rsplit_once("/browse/")
+            .map(|(prefix, _)| prefix)
+            .unwrap_or("");
+        api_url.set_path(&format!("{prefix}/rest/api/2/issue/{key}"));
+        api_url.set_query(Some(
+            "fields=summary,description,status,reporter,labels,issuetype",
+        ));
+        api_url.set_fragment(None);
+        let issue = fetch_json(&api_url, policy).await?;
+        if issue.get("fields").is_none() {
+            return Ok(None);
+        }
+
+        Ok(Some(LinkMetadata {
+            url: url.to_string(),
+            title: json_string(&issue, "/fields/summary")
+                .map(|summary| format!("{key}: {summary}")),
+            description: short_description(json_string(&issue, "/fields/description")),
+            site_name: Some("Jira".to_string()),
+            details: Some(LinkDetails {
+                kind: json_string(&issue, "/fields/issuetype/name")
+                    .or_else(|| Some("ticket".to_string())),
+                state: json_string(&issue, "/fields/status/name"),
+                author: json_string(&issue, "/fields/reporter/displayName"),
+                labels: json_strings(&issue, "/fields/labels"),
+                duration: None,
+            }),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::serve;
+
+    fn fixture_provider() -> JiraProvider {
+        let base = serve(vec![
+            (
+                "/jira/rest/api/2/issue/CLIP-42",
+                include_str!(concat!(
+                    env!("CARGO_MANIFEST_DIR"),
+                    "/tests/fixtures/link_providers/jira_issue.json"
+                ))
+                .to_string(),
+            ),
+            (
+                "/rest/api/2/issue/SEC-7",
+                include_str!(concat!(
+                    env!("CARGO_MANIFEST_DIR"),
+                    "/tests/fixtures/link_providers/jira_unauthorized.json"
+                ))
+                .to_string(),
+            ),
+        ]);
+        JiraProvider {
+            api_base: Some(Url::parse(&base).unwrap()),
+        }
+    }
+
+    fn fetch(provider: &JiraProvider, url: &str) -> Option<LinkMetadata> {
+        let policy = NetworkSettings {
+            allow_private_networks: true,
+            ..NetworkSettings::default()
+        };
+        async_std::task::block_on(provider.fetch(&Url::parse(url).unwrap(), &policy)).unwrap()
+    }
+
+    #[test]
+    fn reads_issue_under_context_path() {
+        let provider = fixture_provider();
+        let metadata = fetch(&provider, "https://issues.example.com/jira/browse/CLIP-42")
+            .expect("provider returns metadata");
+
+        assert_eq!(
+            metadata.title.as_deref(),
+            Some("CLIP-42: Pinned entries are pruned")
+        );
+        assert_eq!(
+            metadata.description.as_deref(),
+            Some("Retention removes pinned entries once the limit is reached.")
+        );
+        assert_eq!(metadata.site_name.as_deref(), Some("Jira"));
+        let details = metadata.details.unwrap();
+        assert_eq!(details.kind.as_deref(), Some("Bug"));
+        assert_eq!(details.state.as_deref(), Some("In Progress"));
+        assert_eq!(details.author.as_deref(), Some("Ana Lima"));
+        assert_eq!(details.labels, vec!["retention", "regression"]);
+    }
+
+    #[test]
+    fn falls_back_without_anonymous_access() {
+        let provider = fixture_provider();
+        assert!(fetch(&provider, "https://example.atlassian.net/browse/SEC-7").is_none());
+    }
+
+    #[test]
+    fn matches_only_issue_keys() {
+        let provider = JiraProvider::default();
+        for (url, expected) in [
+            ("https://example.atlassian.net/browse/CLIP-42", true),
+            ("https://example.com/jira/browse/A_B2-1", true),
+            ("https://example.atlassian.net/browse/clip-42", false),
+            ("https://example.atlassian.net/browse/CLIP", false),
+            ("https://example.atlassian.net/projects/CLIP-42", false),
+        ] {
+            assert_eq!(
+                provider.matches(&Url::parse(url).unwrap()),
+                expected,
+                "{url}"
+            );
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use std::sync::OnceLock;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use url::Url;
+
+use crate::clipboard::link_metadata::LinkMetadata;
+use crate::clipboard::network::fetch_text;
+use crate::settings::NetworkSettings;
+
+mod github;
+mod gitlab;
+mod jira;
+mod stack_overflow;
+mod youtube;
+
+const MAX_DESCRIPTION_CHARS: usize = 280;
+
+static PROVIDERS: OnceLock<Vec<Box<dyn LinkMetadataProvider>>> = OnceLock::new();
+
+/// Site-specific metadata lookup, consulted before the generic OpenGraph
+/// scraper. Returning `Ok(None)` falls back to the generic path. Every
+/// request goes through `policy`.
+#[async_trait]
+pub trait LinkMetadataProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn matches(&self, url: &Url) -> bool;
+
+    async fn fetch(&self, url: &Url, policy: &NetworkSettings) -> Result<Option<LinkMetadata>>;
+}
+
+pub fn providers() -> &'static [Box<dyn LinkMetadataProvider>] {
+    PROVIDERS.get_or_init(|| {
+        vec![
+            Box::new(github::GitHubProvider::default()),
+            Box::new(gitlab::GitLabProvider::default()),
+            Box::new(jira::JiraProvider::default()),
+            Box::new(youtube::YouTubeProvider::default()),
+            Box::new(stack_overflow::StackOverflowProvider::default()),
+        ]
+    })
+}
+
+pub fn provider_for(url: &Url) -> Option<&'static dyn LinkMetadataProvider> {
+    providers()
+        .iter()
+        .find(|provider| provider.matches(url))
+        .map(|provider| provider.as_ref())
+}
+
+async fn fetch_json(url: &Url, policy: &NetworkSettings) -> Result<serde_json::Value> {
+    let body = fetch_text(url, policy).await?;
+    if body.trim().is_empty() {
+        return Err(anyhow!("Empty response from {url}"));
+    }
+    Ok(serde_json::from_str(&body)?)
+}
+
+fn host_is(url: &Url, host: &str) -> bool {
+    url.host_str().is_some_and(|value| {
+        let value = value.to_ascii_lowercase();
+        value == host || value.strip_prefix("www.") == Some(host)
+    })
+}
+
+fn json_string(value: &serde_json::Value, pointer: &str) -> Option<String> {
+    let text = value.pointer(pointer)?.as_str()?.trim();
+    if text.is_empty() {
+        None
+    } else {
+        Some(text.to_string())
+    }
+}
+
+fn json_strings(value: &serde_json::Value, pointer: &str) -> Vec<String> {
+    value
+        .pointer(pointer)
+        .and_then(|value| value.as_array())
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| {
+                    item.as_str()
+                        .or_else(|| item.get("name").and_then(|name| name.as_str()))
+                })
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn short_description(text: Option<String>) -> Option<String> {
+    let text = text?;
+    let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if collapsed.is_empty() {
+        return None;
+    }
+    if collapsed.chars().count() <= MAX_DESCRIPTION_CHARS {
+        return Some(collapsed);
+    }
+    let mut truncated: String = collapsed.chars().take(MAX_DESCRIPTION_CHARS).collect();
+    truncated.push_str("...");
+    Some(truncated)
+}
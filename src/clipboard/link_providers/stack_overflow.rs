@@ -0,0 +1,145 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use scraper::Html;
+use url::Url;
+
+use super::{fetch_json, host_is, json_string, json_strings, LinkMetadataProvider};
+use crate::clipboard::link_metadata::{LinkDetails, LinkMetadata};
+use crate::settings::NetworkSettings;
+
+const API_BASE: &str = "https://api.stackexchange.com";
+
+/// Stack Overflow questions, via the Stack Exchange API.
+pub struct StackOverflowProvider {
+    api_base: Url,
+}
+
+impl Default for StackOverflowProvider {
+    fn default() -> Self {
+        Self {
+            api_base: Url::parse(API_BASE).expect("valid Stack Exchange API base"),
+        }
+    }
+}
+
+fn question_id(url: &Url) -> Option<u64> {
+    if !host_is(url, "stackoverflow.com") {
+        return None;
+    }
+    let segments: Vec<&str> = url.path_segments()?.collect();
+    match segments.as_slice() {
+        ["questions" | "q", id, ..] => id.parse().ok(),
+        _ => None,
+    }
+}
+
+/// The API returns HTML-escaped titles and names.
+fn decode_entities(text: String) -> String {
+    Html::parse_fragment(&text)
+        .root_element()
+        .text()
+        .collect::<String>()
+}
+
+#[async_trait]
+impl LinkMetadataProvider for StackOverflowProvider {
+    fn name(&self) -> &'static str {
+        "Stack Overflow"
+    }
+
+    fn matches(&self, url: &Url) -> bool {
+        question_id(url).is_some()
+    }
+
+    async fn fetch(&self, url: &Url, policy: &NetworkSettings) -> Result<Option<LinkMetadata>> {
+        let Some(id) = question_id(url) else {
+            return Ok(None);
+        };
+        let api_url = self
+            .api_base
+            .join(&format!("2.3/questions/{id}?site=stackoverflow"))?;
+        let response = fetch_json(&api_url, policy).await?;
+        let Some(question) = response.pointer("/items/0") else {
+            return Ok(None);
+        };
+
+        let closed = question
+            .get("closed_date")
+            .is_some_and(|value| !value.is_null());
+        let answered = question
+            .get("is_answered")
+            .and_then(|value| value.as_bool())
+            .unwrap_or(false);
+        let state = if closed {
+            "closed"
+        } else if answered {
+            "answered"
+        } else {
+            "open"
+        };
+        let score = question.get("score").and_then(|value| value.as_i64());
+        let answers = question
+            .get("answer_count")
+            .and_then(|value| value.as_i64());
+        let description = match (score, answers) {
+            (Some(score), Some(answers)) => Some(format!("Score {score} · {answers} answers")),
+            _ => None,
+        };
+
+        Ok(Some(LinkMetadata {
+            url: url.to_string(),
+            title: json_string(question, "/title").map(decode_entities),
+            description,
+            site_name: Some("Stack Overflow".to_string()),
+            details: Some(LinkDetails {
+                kind: Some("question".to_string()),
+                state: Some(state.to_string()),
+                author: json_string(question, "/owner/display_name").map(decode_entities),
+                labels: json_strings(question, "/tags"),
+                duration: None,
+            }),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::serve;
+
+    #[test]
+    fn reads_state_author_and_tags() {
+        let base = serve(vec![(
+            "/2.3/questions/31012923",
+            include_str!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/fixtures/link_providers/stack_overflow_question.json"
+            ))
+            .to_string(),
+        )]);
+        let provider = StackOverflowProvider {
+            api_base: Url::parse(&base).unwrap(),
+        };
+        let policy = NetworkSettings {
+            allow_private_networks: true,
+            ..NetworkSettings::default()
+        };
+        let url = Url::parse("https://stackoverflow.com/questions/31012923/why-cant-i").unwrap();
+        let metadata = async_std::task::block_on(provider.fetch(&url, &policy))
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            metadata.title.as_deref(),
+            Some("Why can't I store a value and a reference to that value in the same struct?")
+        );
+        assert_eq!(
+            metadata.description.as_deref(),
+            Some("Score 42 · 3 answers")
+        );
+        let details = metadata.details.unwrap();
+        assert_eq!(details.state.as_deref(), Some("answered"));
+        assert_eq!(details.author.as_deref(), Some("Jane & Co"));
+        assert_eq!(details.labels, vec!["rust", "lifetimes"]);
+    }
+}
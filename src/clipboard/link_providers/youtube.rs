@@ -0,0 +1,175 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use scraper::Html;
+use url::Url;
+
+use super::{host_is, short_description, LinkMetadataProvider};
+use crate::clipboard::link_metadata::{meta_content, title_text, LinkDetails, LinkMetadata};
+use crate::clipboard::network::fetch_text;
+use crate::settings::NetworkSettings;
+
+const SITE_BASE: &str = "https://www.youtube.com";
+
+/// YouTube videos, read from the watch page's microdata.
+pub struct YouTubeProvider {
+    site_base: Url,
+}
+
+impl Default for YouTubeProvider {
+    fn default() -> Self {
+        Self {
+            site_base: Url::parse(SITE_BASE).expect("valid YouTube base"),
+        }
+    }
+}
+
+fn video_id(url: &Url) -> Option<String> {
+    if host_is(url, "youtu.be") {
+        let id = url.path_segments()?.next()?;
+        return (!id.is_empty()).then(|| id.to_string());
+    }
+    if !host_is(url, "youtube.com") && !host_is(url, "m.youtube.com") {
+        return None;
+    }
+    let segments: Vec<&str> = url.path_segments()?.collect();
+    match segments.as_slice() {
+        ["watch"] => url
+            .query_pairs()
+            .find(|(name, _)| name == "v")
+            .map(|(_, value)| value.into_owned())
+            .filter(|id| !id.is_empty()),
+        ["shorts" | "live" | "embed", id, ..] if !id.is_empty() => Some(id.to_string()),
+        _ => None,
+    }
+}
+
+#[async_trait]
+impl LinkMetadataProvider for YouTubeProvider {
+    fn name(&self) -> &'static str {
+        "YouTube"
+    }
+
+    fn matches(&self, url: &Url) -> bool {
+        video_id(url).is_some()
+    }
+
+    async fn fetch(&self, url: &Url, policy: &NetworkSettings) -> Result<Option<LinkMetadata>> {
+        let Some(id) = video_id(url) else {
+            return Ok(None);
+        };
+        let mut watch_url = self.site_base.join("watch")?;
+        watch_url.query_pairs_mut().append_pair("v", &id);
+        let body = fetch_text(&watch_url, policy).await?;
+        if body.trim().is_empty() {
+            return Ok(None);
+        }
+
+        let document = Html::parse_document(&body);
+        let title = meta_content(&document, "meta[property=\"og:title\"]")
+            .or_else(|| title_text(&document));
+        let description = meta_content(&document, "meta[property=\"og:description\"]")
+            .or_else(|| meta_content(&document, "meta[name=\"description\"]"));
+        let author = meta_content(
+            &document,
+            "span[itemprop=\"author\"] link[itemprop=\"name\"]",
+        );
+        let duration = meta_content(&document, "meta[itemprop=\"duration\"]")
+            .and_then(|raw| format_iso_duration(&raw));
+
+        Ok(Some(LinkMetadata {
+            url: url.to_string(),
+            title,
+            description: short_description(description),
+            site_name: Some("YouTube".to_string()),
+            details: Some(LinkDetails {
+                kind: Some("video".to_string()),
+                state: None,
+                author,
+                labels: Vec::new(),
+                duration,
+            }),
+        }))
+    }
+}
+
+/// Turns an ISO 8601 duration such as `PT1H4M13S` into `1:04:13`.
+fn format_iso_duration(raw: &str) -> Option<String> {
+    let rest = raw.trim().strip_prefix("PT")?;
+    let (mut hours, mut minutes, mut seconds) = (0u64, 0u64, 0u64);
+    let mut number = String::new();
+    for ch in rest.chars() {
+        if ch.is_ascii_digit() {
+            number.push(ch);
+            continue;
+        }
+        let value: u64 = number.parse().ok()?;
+        number.clear();
+        match ch {
+            'H' => hours = value,
+            'M' => minutes = value,
+            'S' => seconds = value,
+            _ => return None,
+        }
+    }
+    if !number.is_empty() {
+        return None;
+    }
+
+    hours += minutes / 60;
+    minutes %= 60;
+    if hours > 0 {
+        Some(format!("{hours}:{minutes:02}:{seconds:02}"))
+    } else {
+        Some(format!("{minutes}:{seconds:02}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::serve;
+
+    fn fetch(url: &str) -> Option<LinkMetadata> {
+        let base = serve(vec![(
+            "/watch",
+            include_str!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/fixtures/link_providers/youtube_watch.html"
+            ))
+            .to_string(),
+        )]);
+        let provider = YouTubeProvider {
+            site_base: Url::parse(&base).unwrap(),
+        };
+        let policy = NetworkSettings {
+            allow_private_networks: true,
+            ..NetworkSettings::default()
+        };
+        async_std::task::block_on(provider.fetch(&Url::parse(url).unwrap(), &policy)).unwrap()
+    }
+
+    #[test]
+    fn reads_title_author_and_duration() {
+        let metadata = fetch("https://youtu.be/dQw4w9WgXcQ").unwrap();
+
+        assert_eq!(metadata.url, "https://youtu.be/dQw4w9WgXcQ");
+        assert_eq!(metadata.title.as_deref(), Some("Rust in 64 minutes"));
+        assert_eq!(
+            metadata.description.as_deref(),
+            Some("A whirlwind tour of ownership, traits and async.")
+        );
+        let details = metadata.details.unwrap();
+        assert_eq!(details.kind.as_deref(), Some("video"));
+        assert_eq!(details.author.as_deref(), Some("Ferris Talks"));
+        assert_eq!(details.duration.as_deref(), Some("1:04:13"));
+    }
+
+    #[test]
+    fn formats_iso_durations() {
+        assert_eq!(format_iso_duration("PT4M5S").as_deref(), Some("4:05"));
+        assert_eq!(format_iso_duration("PT1H4M13S").as_deref(), Some("1:04:13"));
+        assert_eq!(format_iso_duration("PT90M").as_deref(), Some("1:30:00"));
+        assert_eq!(format_iso_duration("P1D"), None);
+        assert_eq!(format_iso_duration("PT12"), None);
+    }
+}
@@ -0,0 +1,258 @@
+use std::sync::OnceLock;
+
+use regex::Regex;
+use url::Url;
+
+use crate::clipboard::link_metadata::LinkMetadata;
+
+static URL_PATTERN: OnceLock<Regex> = OnceLock::new();
+static EMAIL_PATTERN: OnceLock<Regex> = OnceLock::new();
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LinkKind {
+    Url,
+    Email,
+}
+
+impl LinkKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LinkKind::Url => "url",
+            LinkKind::Email => "email",
+        }
+    }
+}
+
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+#[derive(Clone, Debug)]
+pub struct ExtractedLink {
+    pub kind: LinkKind,
+    /// Normalized target: an absolute http(s) URL or a bare email address.
+    pub value: String,
+    /// Byte range of the match inside the source text.
+    pub start: usize,
+    pub end: usize,
+    pub metadata: Option<LinkMetadata>,
+}
+
+pub fn extract_links(text: &str) -> Vec<ExtractedLink> {
+    let url_pattern = URL_PATTERN.get_or_init(|| {
+        Regex::new(r#"(?i)\b(?:https?://|www\.)[^\s<>"'`]+"#).expect("valid url pattern")
+    });
+    let email_pattern = EMAIL_PATTERN.get_or_init(|| {
+        Regex::new(r"(?i)\b(?:mailto:)?[a-z0-9._%+-]+@[a-z0-9-]+(?:\.[a-z0-9-]+)*\.[a-z]{2,}\b")
+            .expect("valid email pattern")
+    });
+
+    let mut links = Vec::new();
+    for found in url_pattern.find_iter(text) {
+        let raw = trim_trailing_punctuation(found.as_str());
+        if raw.is_empty() {
+            continue;
+        }
+        let candidate = if raw.len() >= 4 && raw[..4].eq_ignore_ascii_case("www.") {
+            format!("https://{raw}")
+        } else {
+            raw.to_string()
+        };
+        let Ok(url) = Url::parse(&candidate) else {
+            continue;
+        };
+        if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+            continue;
+        }
+        links.push(ExtractedLink {
+            kind: LinkKind::Url,
+            value: url.to_string(),
+            start: found.start(),
+            end: found.start() + raw.len(),
+            metadata: None,
+        });
+    }
+
+    for found in email_pattern.find_iter(text) {
+        let overlaps_url = links
+            .iter()
+            .any(|link| found.start() < link.end && link.start < found.end());
+        if overlaps_url {
+            continue;
+        }
+        let raw = found.as_str();
+        let address = if raw.len() > 7 && raw[..7].eq_ignore_ascii_case("mailto:") {
+            &raw[7..]
+        } else {
+            raw
+        };
+        links.push(ExtractedLink {
+            kind: LinkKind::Email,
+            value: address.to_string(),
+            start: found.start(),
+            end: found.end(),
+            metadata: None,
+        });
+    }
+
+    links.sort_by_key(|link| link.start);
+    links
+}
+
+/// Drops sentence punctuation that the greedy URL pattern picks up, keeping
+/// closing brackets that balance an opening one inside the URL.
+fn trim_trailing_punctuation(raw: &str) -> &str {
+    let mut end = raw.len();
+    while let Some(ch) = raw[..end].chars().next_back() {
+        let keep = match ch {
+            '.' | ',' | ';' | ':' | '!' | '?' | '\'' | '"' => false,
+            ')' => raw[..end].matches('(').count() >= raw[..end].matches(')').count(),
+            ']' => raw[..end].matches('[').count() >= raw[..end].matches(']').count(),
+            '}' => raw[..end].matches('{').count() >= raw[..end].matches('}').count(),
+            _ => true,
+        };
+        if keep {
+            break;
+        }
+        end -= ch.len_utf8();
+    }
+    &raw[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Each link as `(kind, value, matched source text)`.
+    fn found(text: &str) -> Vec<(LinkKind, String, &str)> {
+        extract_links(text)
+            .into_iter()
+            .map(|link| (link.kind, link.value, &text[link.start..link.end]))
+            .collect()
+    }
+
+    #[test]
+    fn trims_trailing_punctuation() {
+        let cases = [
+            ("see https://example.com/a.", "https://example.com/a"),
+            ("https://example.com/a, then", "https://example.com/a"),
+            ("(https://example.com/a).", "https://example.com/a"),
+            (
+                "is it https://example.com/?q=1?",
+                "https://example.com/?q=1",
+            ),
+            (
+                "\"https://example.com/quoted\"",
+                "https://example.com/quoted",
+            ),
+            ("www.example.com/docs;", "www.example.com/docs"),
+        ];
+        for (input, expected) in cases {
+            let links = found(input);
+            assert_eq!(links.len(), 1, "{input}");
+            assert_eq!(links[0].2, expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn keeps_balanced_brackets() {
+        let cases = [
+            (
+                "(see https://en.wikipedia.org/wiki/Rust_(programming_language))",
+                "https://en.wikipedia.org/wiki/Rust_(programming_language)",
+            ),
+            (
+                "https://example.com/wiki/A_(b)_(c).",
+                "https://example.com/wiki/A_(b)_(c)",
+            ),
+            ("[https://example.com/x]", "https://example.com/x"),
+            ("https://example.com/a[1]", "https://example.com/a[1]"),
+        ];
+        for (input, expected) in cases {
+            let links = found(input);
+            assert_eq!(links.len(), 1, "{input}");
+            assert_eq!(links[0].2, expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn normalizes_values() {
+        let cases = [
+            (
+                "www.example.com/docs",
+                LinkKind::Url,
+                "https://www.example.com/docs",
+            ),
+            ("HTTPS://Example.COM", LinkKind::Url, "https://example.com/"),
+            (
+                "mailto:Alice@Example.org,",
+                LinkKind::Email,
+                "Alice@Example.org",
+            ),
+        ];
+        for (input, kind, value) in cases {
+            let links = found(input);
+            assert_eq!(links.len(), 1, "{input}");
+            assert_eq!((links[0].0, links[0].1.as_str()), (kind, value), "{input}");
+        }
+    }
+
+    #[test]
+    fn separates_emails_and_urls() {
+        let text = "mail bob@example.com or see https://example.com/x, cc ops@example.org.";
+        assert_eq!(
+            found(text),
+            vec![
+                (
+                    LinkKind::Email,
+                    "bob@example.com".to_string(),
+                    "bob@example.com"
+                ),
+                (
+                    LinkKind::Url,
+                    "https://example.com/x".to_string(),
+                    "https://example.com/x"
+                ),
+                (
+                    LinkKind::Email,
+                    "ops@example.org".to_string(),
+                    "ops@example.org"
+                ),
+            ]
+        );
+
+        // The user part of a URL is not a separate address.
+        let links = found("https://user@example.com/path");
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].0, LinkKind::Url);
+    }
+
+    #[test]
+    fn ignores_non_links() {
+        for input in [
+            "",
+            "no links here",
+            "ftp://example.com/file",
+            "user@localhost",
+        ] {
+            assert!(found(input).is_empty(), "{input}");
+        }
+    }
+
+    #[test]
+    fn reports_byte_offsets_in_non_ascii_text() {
+        let text =
+            "Grüße — siehe (https://de.wikipedia.org/wiki/Köln) und schreib an info@example.de!";
+        let links = extract_links(text);
+        assert_eq!(links.len(), 2);
+
+        let url = &links[0];
+        assert_eq!(url.start, text.find("https://").unwrap());
+        assert_eq!(
+            &text[url.start..url.end],
+            "https://de.wikipedia.org/wiki/Köln"
+        );
+        assert_eq!(url.value, "https://de.wikipedia.org/wiki/K%C3%B6ln");
+
+        let email = &links[1];
+        assert_eq!(email.start, text.find("info@").unwrap());
+        assert_eq!(&text[email.start..email.end], "info@example.de");
+    }
+}
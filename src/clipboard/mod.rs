@@ -1,6 +1,16 @@
+#[cfg(target_os = "windows")]
+pub mod auto_tags;
+// Capture, which decodes codes in images, only runs on Windows so far.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub mod barcodes;
+pub mod importers;
 pub mod link_metadata;
+pub mod link_providers;
+pub mod links;
+pub mod network;
 pub mod ocr;
 pub mod types;
+pub mod url_clean;
 pub mod watcher;
 
 #[cfg(target_os = "windows")]
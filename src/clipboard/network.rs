@@ -0,0 +1,377 @@
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use async_std::net::ToSocketAddrs;
+use http_client::isahc::IsahcClient;
+use isahc::config::ResolveMap;
+use isahc::prelude::*;
+use url::Url;
+
+use crate::settings::NetworkSettings;
+
+const MAX_REDIRECTS: usize = 5;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(8);
+
+#[derive(Debug)]
+pub enum PolicyViolation {
+    Offline,
+    UnsupportedScheme(String),
+    MissingHost,
+    DeniedDomain(String),
+    NotAllowlisted(String),
+    PrivateAddress(String),
+    Unresolvable(String),
+}
+
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PolicyViolation::Offline => write!(f, "offline mode is enabled"),
+            PolicyViolation::UnsupportedScheme(scheme) => {
+                write!(f, "scheme `{scheme}` is not fetched")
+            }
+            PolicyViolation::MissingHost => write!(f, "URL has no host"),
+            PolicyViolation::DeniedDomain(host) => write!(f, "{host} is on the deny list"),
+            PolicyViolation::NotAllowlisted(host) => write!(f, "{host} is not on the allow list"),
+            PolicyViolation::PrivateAddress(host) => {
+                write!(f, "{host} points to a private or intranet address")
+            }
+            PolicyViolation::Unresolvable(host) => write!(f, "{host} could not be resolved"),
+        }
+    }
+}
+
+impl std::error::Error for PolicyViolation {}
+
+/// Decides whether the app may issue a request to `url`. Host names are
+/// resolved so that public-looking names pointing at private ranges are
+/// caught as well; the vetted address is returned so the request connects
+/// to it instead of resolving the name a second time. Through a proxy only
+/// the name and literal addresses are checked, since the proxy resolves
+/// the host and the local resolver may not know it.
+pub async fn check_url(
+    url: &Url,
+    policy: &NetworkSettings,
+) -> Result<Option<IpAddr>, PolicyViolation> {
+    if policy.offline {
+        return Err(PolicyViolation::Offline);
+    }
+    match url.scheme() {
+        "http" | "https" => {}
+        other => return Err(PolicyViolation::UnsupportedScheme(other.to_string())),
+    }
+    let host = url
+        .host_str()
+        .map(|host| {
+            host.trim_matches(|ch| ch == '[' || ch == ']')
+                .to_ascii_lowercase()
+        })
+        .ok_or(PolicyViolation::MissingHost)?;
+
+    if policy
+        .deny_domains
+        .iter()
+        .any(|domain| domain_matches(&host, domain))
+    {
+        return Err(PolicyViolation::DeniedDomain(host));
+    }
+    let allowlisted = policy
+        .allow_domains
+        .iter()
+        .any(|domain| domain_matches(&host, domain));
+    if !policy.allow_domains.is_empty() && !allowlisted {
+        return Err(PolicyViolation::NotAllowlisted(host));
+    }
+    if policy.allow_private_networks {
+        return Ok(None);
+    }
+
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        return if is_private_ip(ip) {
+            Err(PolicyViolation::PrivateAddress(host))
+        } else {
+            Ok(None)
+        };
+    }
+    if is_intranet_name(&host) {
+        return Err(PolicyViolation::PrivateAddress(host));
+    }
+    if proxy_url(policy).is_some() {
+        return Ok(None);
+    }
+
+    let port = url.port_or_known_default().unwrap_or(443);
+    let addresses: Vec<IpAddr> = (host.as_str(), port)
+        .to_socket_addrs()
+        .await
+        .map_err(|_| PolicyViolation::Unresolvable(host.clone()))?
+        .map(|address| address.ip())
+        .collect();
+    if addresses.iter().copied().any(is_private_ip) {
+        return Err(PolicyViolation::PrivateAddress(host));
+    }
+    // Prefer IPv4, which every curl build accepts in a resolve entry.
+    let address = addresses
+        .iter()
+        .find(|address| address.is_ipv4())
+        .or(addresses.first())
+        .copied()
+        .ok_or(PolicyViolation::Unresolvable(host))?;
+    Ok(Some(address))
+}
+
+/// Fetches `url` as text, re-checking `policy` on every redirect hop.
+/// Returns an empty body for non-success responses.
+pub async fn fetch_text(url: &Url, policy: &NetworkSettings) -> Result<String> {
+    let mut current = url.clone();
+
+    for _ in 0..=MAX_REDIRECTS {
+        let pinned = check_url(&current, policy).await?;
+        let client = http_client(policy, &current, pinned)?;
+        let mut response = client
+            .get(current.as_str())
+            .header("User-Agent", policy.user_agent.as_str())
+            .await
+            .map_err(|err| anyhow!(err.to_string()))?;
+
+        if response.status().is_redirection() {
+            let Some(location) = response.header("Location").map(|value| value.as_str()) else {
+                return Ok(String::new());
+            };
+            current = current.join(location)?;
+            continue;
+        }
+        if !response.status().is_success() {
+            return Ok(String::new());
+        }
+        return response
+            .body_string()
+            .await
+            .map_err(|err| anyhow!(err.to_string()));
+    }
+
+    Err(anyhow!("Too many redirects for {url}"))
+}
+
+/// A client for one request to `url`. A `pinned` address overrides DNS for
+/// the URL's host, so a name that re-resolves to another address between
+/// the policy check and the connection can't redirect the request. Through
+/// a proxy the proxy resolves the host, so nothing is pinned.
+fn http_client(
+    policy: &NetworkSettings,
+    url: &Url,
+    pinned: Option<IpAddr>,
+) -> Result<surf::Client> {
+    let mut builder = isahc::HttpClient::builder().timeout(REQUEST_TIMEOUT);
+    if let Some(proxy) = proxy_url(policy) {
+        let proxy = proxy
+            .parse::<isahc::http::Uri>()
+            .map_err(|err| anyhow!("Invalid proxy URL: {err}"))?;
+        builder = builder.proxy(Some(proxy));
+    } else if let (Some(address), Some(host), Some(port)) =
+        (pinned, url.host_str(), url.port_or_known_default())
+    {
+        builder = builder.dns_resolve(ResolveMap::new().add(host, port, address));
+    }
+    let client = builder.build().map_err(|err| anyhow!(err.to_string()))?;
+    Ok(surf::Client::with_http_client(IsahcClient::from_client(
+        client,
+    )))
+}
+
+/// The configured proxy, if any.
+fn proxy_url(policy: &NetworkSettings) -> Option<&str> {
+    policy
+        .proxy
+        .as_deref()
+        .map(str::trim)
+        .filter(|proxy| !proxy.is_empty())
+}
+
+/// Domain rules match the domain itself and all of its subdomains.
+fn domain_matches(host: &str, domain: &str) -> bool {
+    let domain = domain.trim().trim_start_matches("*.").to_ascii_lowercase();
+    if domain.is_empty() {
+        return false;
+    }
+    host == domain
+        || host
+            .strip_suffix(domain.as_str())
+            .is_some_and(|prefix| prefix.ends_with('.'))
+}
+
+fn is_intranet_name(host: &str) -> bool {
+    const INTRANET_SUFFIXES: [&str; 6] = [
+        ".local",
+        ".localhost",
+        ".internal",
+        ".intranet",
+        ".lan",
+        ".home.arpa",
+    ];
+    host == "localhost"
+        || !host.contains('.')
+        || INTRANET_SUFFIXES
+            .iter()
+            .any(|suffix| host.ends_with(suffix))
+}
+
+fn is_private_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_private_ipv4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(mapped) => is_private_ipv4(mapped),
+            None => is_private_ipv6(ip),
+        },
+    }
+}
+
+fn is_private_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || a == 0
+        // Carrier-grade NAT, 100.64.0.0/10.
+        || (a == 100 && (64..128).contains(&b))
+        // Benchmarking, 198.18.0.0/15.
+        || (a == 198 && (18..20).contains(&b))
+}
+
+fn is_private_ipv6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        // Unique local, fc00::/7.
+        || (first & 0xfe00) == 0xfc00
+        // Link local, fe80::/10.
+        || (first & 0xffc0) == 0xfe80
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::serve;
+
+    fn check(url: &str, policy: &NetworkSettings) -> Result<Option<IpAddr>, PolicyViolation> {
+        async_std::task::block_on(check_url(&Url::parse(url).unwrap(), policy))
+    }
+
+    #[test]
+    fn allowlisted_hosts_still_get_the_address_check() {
+        let policy = NetworkSettings {
+            allow_domains: vec!["localhost".to_string(), "10.1.2.3".to_string()],
+            ..NetworkSettings::default()
+        };
+        assert!(matches!(
+            check("http://localhost:8080/", &policy),
+            Err(PolicyViolation::PrivateAddress(_))
+        ));
+        assert!(matches!(
+            check("http://10.1.2.3/", &policy),
+            Err(PolicyViolation::PrivateAddress(_))
+        ));
+        assert!(matches!(
+            check("https://example.com/", &policy),
+            Err(PolicyViolation::NotAllowlisted(_))
+        ));
+    }
+
+    #[test]
+    fn proxied_requests_skip_local_resolution() {
+        let policy = NetworkSettings {
+            proxy: Some("http://127.0.0.1:3128".to_string()),
+            ..NetworkSettings::default()
+        };
+        // Only the proxy can resolve this name.
+        assert!(matches!(
+            check("https://unresolvable.invalid/", &policy),
+            Ok(None)
+        ));
+        assert!(matches!(
+            check("http://10.1.2.3/", &policy),
+            Err(PolicyViolation::PrivateAddress(_))
+        ));
+        assert!(matches!(
+            check("http://printer.local/", &policy),
+            Err(PolicyViolation::PrivateAddress(_))
+        ));
+        let blank = NetworkSettings {
+            proxy: Some("  ".to_string()),
+            ..NetworkSettings::default()
+        };
+        assert!(matches!(
+            check("https://unresolvable.invalid/", &blank),
+            Err(PolicyViolation::Unresolvable(_))
+        ));
+    }
+
+    #[test]
+    fn private_networks_setting_skips_the_address_check() {
+        let policy = NetworkSettings {
+            allow_private_networks: true,
+            ..NetworkSettings::default()
+        };
+        assert!(matches!(check("http://localhost/", &policy), Ok(None)));
+        assert!(matches!(check("http://192.168.1.1/", &policy), Ok(None)));
+    }
+
+    #[test]
+    fn checks_ip_literals_without_pinning() {
+        let policy = NetworkSettings::default();
+        assert!(matches!(check("https://93.184.215.14/", &policy), Ok(None)));
+        for url in [
+            "http://127.0.0.1/",
+            "http://[::1]/",
+            "http://[::ffff:10.0.0.1]/",
+            "http://100.64.0.1/",
+            "http://[fd00::1]/",
+        ] {
+            assert!(
+                matches!(check(url, &policy), Err(PolicyViolation::PrivateAddress(_))),
+                "{url}"
+            );
+        }
+    }
+
+    #[test]
+    fn offline_and_deny_list_win() {
+        let offline = NetworkSettings {
+            offline: true,
+            ..NetworkSettings::default()
+        };
+        assert!(matches!(
+            check("https://example.com/", &offline),
+            Err(PolicyViolation::Offline)
+        ));
+        let denied = NetworkSettings {
+            deny_domains: vec!["example.com".to_string()],
+            allow_private_networks: true,
+            ..NetworkSettings::default()
+        };
+        assert!(matches!(
+            check("https://www.example.com/", &denied),
+            Err(PolicyViolation::DeniedDomain(_))
+        ));
+    }
+
+    #[test]
+    fn pinned_address_overrides_dns() {
+        let base = Url::parse(&serve(vec![("/", "pinned".to_string())])).unwrap();
+        // `.invalid` never resolves, so the request only lands through the pin.
+        let url = Url::parse(&format!("http://pinned.invalid:{}/", base.port().unwrap())).unwrap();
+        let pinned = Some(IpAddr::V4(Ipv4Addr::LOCALHOST));
+        let client = http_client(&NetworkSettings::default(), &url, pinned).unwrap();
+        let body = async_std::task::block_on(async {
+            let mut response = client.get(url.as_str()).await.unwrap();
+            response.body_string().await.unwrap()
+        });
+        assert_eq!(body, "pinned");
+    }
+}
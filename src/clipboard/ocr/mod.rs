@@ -0,0 +1,152 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+use crate::settings::{settings, OcrBackend};
+use crate::storage::crypto;
+
+mod tesseract;
+#[cfg(target_os = "windows")]
+mod winrt;
+
+pub use tesseract::TesseractEngine;
+#[cfg(target_os = "windows")]
+pub use winrt::WindowsOcrEngine;
+
+#[async_trait(?Send)]
+pub trait OcrEngine {
+    fn name(&self) -> &'static str;
+
+    async fn recognize(&self, image: &DynamicImage) -> Result<Option<OcrResult>>;
+}
+
+pub struct OcrResult {
+    pub text: String,
+    pub layout: OcrLayout,
+}
+
+/// Recognized words positioned in the pixel space of the source image.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct OcrLayout {
+    pub width: u32,
+    pub height: u32,
+    pub lines: Vec<OcrLine>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct OcrLine {
+    pub words: Vec<OcrWord>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct OcrWord {
+    pub text: String,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    /// Engine confidence in `0.0..=1.0`, when the engine reports one.
+    pub confidence: Option<f32>,
+}
+
+impl OcrLayout {
+    pub fn to_json(&self) -> Option<String> {
+        serde_json::to_string(self).ok()
+    }
+
+    pub fn from_json(raw: &str) -> Option<Self> {
+        serde_json::from_str(raw).ok()
+    }
+
+    /// Joins words into lines the same way engines build the flat text.
+    pub fn text(&self) -> String {
+        self.lines
+            .iter()
+            .map(|line| {
+                line.words
+                    .iter()
+                    .map(|word| word.text.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .filter(|line| !line.trim().is_empty())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Picks the OCR engine for this run. `auto` prefers the platform engine and
+/// falls back to Tesseract when it is installed.
+pub fn select_engine() -> Option<Box<dyn OcrEngine>> {
+    let ocr = settings().ocr;
+    let tesseract = TesseractEngine::new(ocr.tesseract_path.as_deref(), &ocr.languages);
+    match ocr.backend {
+        OcrBackend::Disabled => None,
+        OcrBackend::Tesseract => Some(Box::new(tesseract)),
+        #[cfg(target_os = "windows")]
+        OcrBackend::Windows => Some(Box::new(WindowsOcrEngine::new(&ocr.languages))),
+        #[cfg(not(target_os = "windows"))]
+        OcrBackend::Windows => None,
+        OcrBackend::Auto => {
+            #[cfg(target_os = "windows")]
+            if WindowsOcrEngine::is_available() {
+                return Some(Box::new(WindowsOcrEngine::new(&ocr.languages)));
+            }
+            if tesseract.is_available() {
+                Some(Box::new(tesseract))
+            } else {
+                None
+            }
+        }
+    }
+}
+
+/// Names the current engine and languages, e.g. `Tesseract:en-US+de`.
+/// Stored with every OCR run so the backfill only retries an image once
+/// the configuration changed.
+pub fn attempt_key() -> Option<String> {
+    let engine = select_engine()?;
+    Some(format!(
+        "{}:{}",
+        engine.name(),
+        settings().ocr.languages.join("+")
+    ))
+}
+
+/// Reads and decodes a stored image. This is CPU-bound, so callers run it
+/// off the UI thread.
+pub fn load_image_file(path: &Path) -> Result<Option<DynamicImage>> {
+    let bytes = crypto::open_bytes(std::fs::read(path)?)?;
+    Ok(decode_image(&bytes))
+}
+
+/// `None` for bytes that are not an image or have no pixels.
+fn decode_image(bytes: &[u8]) -> Option<DynamicImage> {
+    let image = image::load_from_memory(bytes).ok()?;
+    (image.width() > 0 && image.height() > 0).then_some(image)
+}
+
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub async fn recognize_image(bytes: &[u8]) -> Result<Option<OcrResult>> {
+    if select_engine().is_none() {
+        return Ok(None);
+    }
+    let bytes = bytes.to_vec();
+    let Some(image) = async_std::task::spawn_blocking(move || decode_image(&bytes)).await else {
+        return Ok(None);
+    };
+    recognize_decoded(&image).await
+}
+
+pub async fn recognize_decoded(image: &DynamicImage) -> Result<Option<OcrResult>> {
+    let Some(engine) = select_engine() else {
+        return Ok(None);
+    };
+    engine
+        .recognize(image)
+        .await
+        .map_err(|err| anyhow!("{} OCR failed: {err}", engine.name()))
+}
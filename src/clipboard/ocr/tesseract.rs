@@ -0,0 +1,285 @@
+use std::collections::HashMap;
+use std::io::{Cursor, Write};
+use std::process::{Command, Stdio};
+use std::sync::{Mutex, OnceLock};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use image::{DynamicImage, ImageOutputFormat};
+
+use super::{OcrEngine, OcrLayout, OcrLine, OcrResult, OcrWord};
+
+/// Probe results by binary, so pointing the settings at another install
+/// takes effect without a restart.
+static AVAILABLE: OnceLock<Mutex<HashMap<String, bool>>> = OnceLock::new();
+
+/// Runs a locally installed `tesseract` binary, feeding the image over stdin.
+pub struct TesseractEngine {
+    binary: String,
+    /// Tesseract traineddata names joined for `-l`, e.g. `eng+deu`.
+    languages: Option<String>,
+}
+
+impl TesseractEngine {
+    pub fn new(binary: Option<&str>, languages: &[String]) -> Self {
+        let binary = binary
+            .map(str::trim)
+            .filter(|binary| !binary.is_empty())
+            .unwrap_or("tesseract")
+            .to_string();
+        let languages: Vec<&str> = languages
+            .iter()
+            .map(|tag| tesseract_language(tag.trim()))
+            .filter(|language| !language.is_empty())
+            .collect();
+        let languages = (!languages.is_empty()).then(|| languages.join("+"));
+        Self { binary, languages }
+    }
+
+    /// Probes each binary once per process; installing Tesseract at a path
+    /// that was already probed requires a restart.
+    pub fn is_available(&self) -> bool {
+        let cache = AVAILABLE.get_or_init(|| Mutex::new(HashMap::new()));
+        if let Some(available) = cache
+            .lock()
+            .ok()
+            .and_then(|cache| cache.get(&self.binary).copied())
+        {
+            return available;
+        }
+        let available = Command::new(&self.binary)
+            .arg("--version")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success());
+        if let Ok(mut cache) = cache.lock() {
+            cache.insert(self.binary.clone(), available);
+        }
+        available
+    }
+}
+
+#[async_trait(?Send)]
+impl OcrEngine for TesseractEngine {
+    fn name(&self) -> &'static str {
+        "Tesseract"
+    }
+
+    async fn recognize(&self, image: &DynamicImage) -> Result<Option<OcrResult>> {
+        let (width, height) = (image.width(), image.height());
+        let image = image.clone();
+        let binary = self.binary.clone();
+        let languages = self.languages.clone();
+
+        // Encoding the PNG is as blocking as running the binary.
+        let output = async_std::task::spawn_blocking(move || -> Result<Vec<u8>> {
+            let mut png = Cursor::new(Vec::new());
+            image.write_to(&mut png, ImageOutputFormat::Png)?;
+            let png = png.into_inner();
+            let mut command = Command::new(&binary);
+            command.args(["stdin", "stdout"]);
+            if let Some(languages) = &languages {
+                command.args(["-l", languages]);
+            }
+            let mut child = command
+                .arg("tsv")
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()?;
+            if let Some(mut stdin) = child.stdin.take() {
+                stdin.write_all(&png)?;
+            }
+            let output = child.wait_with_output()?;
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                return Err(anyhow!("tesseract failed: {}", stderr.trim()));
+            }
+            Ok(output.stdout)
+        })
+        .await?;
+
+        let tsv = String::from_utf8_lossy(&output);
+        let layout = parse_tsv(&tsv, width, height);
+        let text = layout.text();
+        if text.trim().is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(OcrResult { text, layout }))
+        }
+    }
+}
+
+/// Maps BCP-47 tags to Tesseract traineddata names. Unknown values are passed
+/// through so `eng` or `chi_sim` can be configured directly.
+fn tesseract_language(tag: &str) -> &str {
+    let lower = tag.to_ascii_lowercase();
+    let primary = lower.split(['-', '_']).next().unwrap_or_default();
+    match primary {
+        "en" => "eng",
+        "de" => "deu",
+        "fr" => "fra",
+        "es" => "spa",
+        "it" => "ita",
+        "pt" => "por",
+        "nl" => "nld",
+        "sv" => "swe",
+        "da" => "dan",
+        "nb" | "no" => "nor",
+        "fi" => "fin",
+        "pl" => "pol",
+        "cs" => "ces",
+        "tr" => "tur",
+        "ru" => "rus",
+        "uk" => "ukr",
+        "el" => "ell",
+        "ar" => "ara",
+        "he" => "heb",
+        "hi" => "hin",
+        "ja" => "jpn",
+        "ko" => "kor",
+        "zh" if lower.contains("hant") || lower.ends_with("-tw") || lower.ends_with("-hk") => {
+            "chi_tra"
+        }
+        "zh" => "chi_sim",
+        _ => tag,
+    }
+}
+
+/// Parses Tesseract's `tsv` output, keeping word rows (level 5) grouped by
+/// their block/paragraph/line numbers.
+fn parse_tsv(tsv: &str, width: u32, height: u32) -> OcrLayout {
+    const WORD_LEVEL: &str = "5";
+
+    let mut lines: Vec<OcrLine> = Vec::new();
+    let mut current_key: Option<(String, String, String)> = None;
+    for row in tsv.lines().skip(1) {
+        let columns: Vec<&str> = row.split('\t').collect();
+        if columns.len() < 12 || columns[0] != WORD_LEVEL {
+            continue;
+        }
+        let text = columns[11].trim();
+        if text.is_empty() {
+            continue;
+        }
+        let number = |index: usize| columns[index].trim().parse::<f32>().unwrap_or(0.);
+        let confidence = number(10);
+        let word = OcrWord {
+            text: text.to_string(),
+            x: number(6),
+            y: number(7),
+            width: number(8),
+            height: number(9),
+            confidence: (confidence >= 0.).then_some(confidence / 100.),
+        };
+
+        let key = (
+            columns[2].to_string(),
+            columns[3].to_string(),
+            columns[4].to_string(),
+        );
+        if current_key.as_ref() != Some(&key) || lines.is_empty() {
+            lines.push(OcrLine::default());
+            current_key = Some(key);
+        }
+        if let Some(line) = lines.last_mut() {
+            line.words.push(word);
+        }
+    }
+
+    OcrLayout {
+        width,
+        height,
+        lines,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture_image(name: &str) -> DynamicImage {
+        let path = format!("{}/tests/fixtures/ocr/{name}", env!("CARGO_MANIFEST_DIR"));
+        image::open(path).expect("fixture image")
+    }
+
+    /// Tesseract is an optional system dependency, so the recognition tests
+    /// are ignored by default; run them with `cargo test -- --ignored`.
+    fn installed_engine() -> TesseractEngine {
+        let engine = TesseractEngine::new(None, &["en-US".to_string()]);
+        assert!(engine.is_available(), "tesseract is not installed");
+        engine
+    }
+
+    #[test]
+    #[ignore = "requires tesseract"]
+    fn recognizes_fixture_text_with_layout() {
+        let engine = installed_engine();
+        let image = fixture_image("invoice.png");
+        let result = async_std::task::block_on(engine.recognize(&image))
+            .unwrap()
+            .expect("text in fixture");
+
+        assert_eq!(result.text, "Invoice 4821\nTotal due 1337 EUR");
+        assert_eq!(result.layout.width, image.width());
+        assert_eq!(result.layout.height, image.height());
+        assert_eq!(result.layout.lines.len(), 2);
+        let total = &result.layout.lines[1].words[0];
+        assert_eq!(total.text, "Total");
+        assert!(total.y > result.layout.lines[0].words[0].y);
+        assert!(total.confidence.is_some_and(|confidence| confidence > 0.5));
+    }
+
+    #[test]
+    #[ignore = "requires tesseract"]
+    fn blank_images_have_no_text() {
+        let engine = installed_engine();
+        let result = async_std::task::block_on(engine.recognize(&fixture_image("blank.png")));
+        assert!(result.unwrap().is_none());
+    }
+
+    #[test]
+    fn availability_is_probed_per_binary() {
+        let missing = TesseractEngine::new(Some("/nonexistent/tesseract"), &[]);
+        assert!(!missing.is_available());
+        // Any binary that answers `--version` will do.
+        let present = TesseractEngine::new(Some(env!("CARGO")), &[]);
+        assert!(present.is_available());
+        assert!(!missing.is_available());
+    }
+
+    #[test]
+    fn parses_word_rows_into_lines() {
+        let tsv = include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/ocr/invoice.tsv"
+        ));
+        let layout = parse_tsv(tsv, 520, 137);
+
+        assert_eq!(layout.text(), "Invoice 4821\nTotal due 1337 EUR");
+        let invoice = &layout.lines[0].words[0];
+        assert_eq!((invoice.x, invoice.y), (22., 29.));
+        assert_eq!((invoice.width, invoice.height), (130., 29.));
+        assert_eq!(invoice.confidence, Some(0.96));
+    }
+
+    #[test]
+    fn maps_language_tags() {
+        let engine = TesseractEngine::new(
+            Some(" "),
+            &[
+                "en-US".into(),
+                "zh-Hant".into(),
+                "zh-CN".into(),
+                "chi_sim".into(),
+            ],
+        );
+        assert_eq!(engine.binary, "tesseract");
+        assert_eq!(
+            engine.languages.as_deref(),
+            Some("eng+chi_tra+chi_sim+chi_sim")
+        );
+        assert_eq!(TesseractEngine::new(None, &[]).languages, None);
+    }
+}
@@ -0,0 +1,145 @@
+use std::{ptr, slice};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use image::DynamicImage;
+use windows::{
+    core::{ComInterface, HSTRING},
+    Globalization::Language,
+    Graphics::Imaging::{BitmapBufferAccessMode, BitmapPixelFormat, SoftwareBitmap},
+    Media::Ocr::{OcrEngine as WinOcrEngine, OcrResult as WinOcrResult},
+    Win32::System::WinRT::IMemoryBufferByteAccess,
+};
+
+use super::{OcrEngine, OcrLayout, OcrLine, OcrResult, OcrWord};
+
+/// The built-in Windows.Media.Ocr engine. Each configured language gets its
+/// own engine; the result with the most recognized text wins.
+pub struct WindowsOcrEngine {
+    languages: Vec<String>,
+}
+
+impl WindowsOcrEngine {
+    pub fn new(languages: &[String]) -> Self {
+        Self {
+            languages: languages.to_vec(),
+        }
+    }
+
+    pub fn is_available() -> bool {
+        WinOcrEngine::TryCreateFromUserProfileLanguages().is_ok()
+    }
+
+    fn engines(&self) -> Result<Vec<WinOcrEngine>> {
+        let mut engines = Vec::new();
+        for tag in self.languages.iter().map(|tag| tag.trim()) {
+            if tag.is_empty() {
+                continue;
+            }
+            let language = match Language::CreateLanguage(&HSTRING::from(tag)) {
+                Ok(language) => language,
+                Err(err) => {
+                    eprintln!("Unknown OCR language {tag}: {err}");
+                    continue;
+                }
+            };
+            if !WinOcrEngine::IsLanguageSupported(&language).unwrap_or(false) {
+                eprintln!("OCR language {tag} is not installed");
+                continue;
+            }
+            match WinOcrEngine::TryCreateFromLanguage(&language) {
+                Ok(engine) => engines.push(engine),
+                Err(err) => eprintln!("Failed to create OCR engine for {tag}: {err}"),
+            }
+        }
+        if engines.is_empty() {
+            engines.push(WinOcrEngine::TryCreateFromUserProfileLanguages()?);
+        }
+        Ok(engines)
+    }
+}
+
+#[async_trait(?Send)]
+impl OcrEngine for WindowsOcrEngine {
+    fn name(&self) -> &'static str {
+        "Windows.Media.Ocr"
+    }
+
+    async fn recognize(&self, image: &DynamicImage) -> Result<Option<OcrResult>> {
+        let rgba = image.to_rgba8();
+        let width = rgba.width() as i32;
+        let height = rgba.height() as i32;
+        if width <= 0 || height <= 0 {
+            return Ok(None);
+        }
+
+        let bitmap = SoftwareBitmap::Create(BitmapPixelFormat::Rgba8, width, height)?;
+        {
+            let buffer = bitmap.LockBuffer(BitmapBufferAccessMode::Write)?;
+            let reference = buffer.CreateReference()?;
+            let byte_access: IMemoryBufferByteAccess = reference.cast()?;
+            let mut data = ptr::null_mut();
+            let mut capacity = 0u32;
+            unsafe {
+                byte_access.GetBuffer(&mut data, &mut capacity)?;
+            }
+            let dest = unsafe { slice::from_raw_parts_mut(data, capacity as usize) };
+            let src = rgba.as_raw();
+            let copy_len = std::cmp::min(dest.len(), src.len());
+            dest[..copy_len].copy_from_slice(&src[..copy_len]);
+        }
+
+        let mut best: Option<OcrResult> = None;
+        for engine in self.engines()? {
+            let result = engine.RecognizeAsync(&bitmap)?.await?;
+            let Some(result) = convert_result(&result, width as u32, height as u32)? else {
+                continue;
+            };
+            if best
+                .as_ref()
+                .is_none_or(|best| text_weight(&result.text) > text_weight(&best.text))
+            {
+                best = Some(result);
+            }
+        }
+        Ok(best)
+    }
+}
+
+fn convert_result(result: &WinOcrResult, width: u32, height: u32) -> Result<Option<OcrResult>> {
+    let text = result.Text()?.to_string();
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        return Ok(None);
+    }
+
+    let mut lines = Vec::new();
+    for line in result.Lines()? {
+        let mut words = Vec::new();
+        for word in line.Words()? {
+            let rect = word.BoundingRect()?;
+            words.push(OcrWord {
+                text: word.Text()?.to_string(),
+                x: rect.X,
+                y: rect.Y,
+                width: rect.Width,
+                height: rect.Height,
+                confidence: None,
+            });
+        }
+        lines.push(OcrLine { words });
+    }
+
+    Ok(Some(OcrResult {
+        text: trimmed.to_string(),
+        layout: OcrLayout {
+            width,
+            height,
+            lines,
+        },
+    }))
+}
+
+fn text_weight(text: &str) -> usize {
+    text.chars().filter(|ch| !ch.is_whitespace()).count()
+}
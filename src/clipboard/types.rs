@@ -1,30 +1,44 @@
 use crate::clipboard::link_metadata::LinkMetadata;
+use crate::clipboard::links::ExtractedLink;
 
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
 pub struct ClipboardEntry {
     pub content_type: String,
     pub content_hash: String,
     pub content: String,
     pub text_content: Option<String>,
     pub ocr_text: Option<String>,
+    pub ocr_layout: Option<String>,
+    pub ocr_attempt: Option<String>,
     pub image_path: Option<String>,
+    pub image_codes: Option<String>,
     pub file_paths: Option<String>,
     pub link_url: Option<String>,
+    pub link_clean_url: Option<String>,
     pub link_title: Option<String>,
     pub link_description: Option<String>,
     pub link_site_name: Option<String>,
+    pub link_details: Option<String>,
     pub source_app_title: Option<String>,
     pub source_exe_path: Option<String>,
+    pub links: Vec<ExtractedLink>,
 }
 
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
 pub struct ClipboardEntryInput {
     pub content_type: String,
     pub content_hash: String,
     pub content: String,
     pub text_content: Option<String>,
     pub ocr_text: Option<String>,
+    pub ocr_layout: Option<String>,
+    pub ocr_attempt: Option<String>,
     pub image_path: Option<String>,
+    pub image_codes: Option<String>,
     pub file_paths: Option<String>,
     pub link_metadata: Option<LinkMetadata>,
+    pub link_clean_url: Option<String>,
+    pub links: Vec<ExtractedLink>,
 }
 
 impl From<ClipboardEntryInput> for ClipboardEntry {
@@ -35,20 +49,27 @@ impl From<ClipboardEntryInput> for ClipboardEntry {
             content,
             text_content,
             ocr_text,
+            ocr_layout,
+            ocr_attempt,
             image_path,
+            image_codes,
             file_paths,
             link_metadata,
+            link_clean_url,
+            links,
         } = input;
 
-        let (link_url, link_title, link_description, link_site_name) = match link_metadata {
-            Some(metadata) => (
-                Some(metadata.url),
-                metadata.title,
-                metadata.description,
-                metadata.site_name,
-            ),
-            None => (None, None, None, None),
-        };
+        let (link_url, link_title, link_description, link_site_name, link_details) =
+            match link_metadata {
+                Some(metadata) => (
+                    Some(metadata.url),
+                    metadata.title,
+                    metadata.description,
+                    metadata.site_name,
+                    metadata.details.and_then(|details| details.to_json()),
+                ),
+                None => (None, None, None, None, None),
+            };
 
         ClipboardEntry {
             content_type,
@@ -56,14 +77,41 @@ impl From<ClipboardEntryInput> for ClipboardEntry {
             content,
             text_content,
             ocr_text,
+            ocr_layout,
+            ocr_attempt,
             image_path,
+            image_codes,
             file_paths,
             link_url,
+            link_clean_url,
             link_title,
             link_description,
             link_site_name,
+            link_details,
             source_app_title: None,
             source_exe_path: None,
+            links,
         }
     }
 }
+
+pub fn summarize_file_paths(paths: &[String]) -> String {
+    let mut names: Vec<String> = Vec::with_capacity(paths.len());
+    for path in paths {
+        let name = std::path::Path::new(path)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(path);
+        names.push(name.to_string());
+    }
+
+    let summary = names.join(", ");
+    const MAX_LEN: usize = 500;
+    if summary.len() <= MAX_LEN {
+        summary
+    } else {
+        let mut truncated = summary[..MAX_LEN].to_string();
+        truncated.push_str("...");
+        truncated
+    }
+}
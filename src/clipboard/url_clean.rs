@@ -0,0 +1,234 @@
+use url::Url;
+
+use crate::settings::{RedirectorRule, UrlCleaningSettings};
+
+/// Redirectors can wrap each other (e.g. SafeLinks around a Google redirect),
+/// but never legitimately more than a few levels deep.
+const MAX_UNWRAP_DEPTH: usize = 5;
+
+/// Unwraps known redirectors and drops tracking parameters. Only http(s)
+/// URLs are touched; other schemes come back as they are.
+pub fn clean_url(url: &Url, rules: &UrlCleaningSettings) -> Url {
+    if !rules.enabled || !matches!(url.scheme(), "http" | "https") {
+        return url.clone();
+    }
+
+    let mut current = url.clone();
+    for _ in 0..MAX_UNWRAP_DEPTH {
+        match unwrap_redirector(&current, &rules.redirectors) {
+            Some(target) => current = target,
+            None => break,
+        }
+    }
+
+    strip_tracking_params(&mut current, &rules.strip_params);
+    current
+}
+
+fn unwrap_redirector(url: &Url, redirectors: &[RedirectorRule]) -> Option<Url> {
+    let host = url.host_str()?.to_ascii_lowercase();
+    let target = redirectors
+        .iter()
+        .filter(|rule| {
+            host_matches(&host, &rule.host)
+                && rule
+                    .path
+                    .as_deref()
+                    .is_none_or(|path| url.path().eq_ignore_ascii_case(path))
+        })
+        .find_map(|rule| {
+            url.query_pairs()
+                .find(|(name, _)| name == rule.param.as_str())
+                .map(|(_, value)| value.into_owned())
+        })?;
+
+    let target = Url::parse(target.trim()).ok()?;
+    match target.scheme() {
+        "http" | "https" => Some(target),
+        _ => None,
+    }
+}
+
+fn host_matches(host: &str, rule_host: &str) -> bool {
+    let rule_host = rule_host.trim().to_ascii_lowercase();
+    if rule_host.is_empty() {
+        return false;
+    }
+    host == rule_host
+        || host
+            .strip_suffix(rule_host.as_str())
+            .is_some_and(|prefix| prefix.ends_with('.'))
+}
+
+fn strip_tracking_params(url: &mut Url, patterns: &[String]) {
+    if url.query().is_none() {
+        return;
+    }
+
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect();
+    let kept: Vec<&(String, String)> = pairs
+        .iter()
+        .filter(|(name, _)| !patterns.iter().any(|pattern| param_matches(name, pattern)))
+        .collect();
+    if kept.len() == pairs.len() {
+        return;
+    }
+
+    if kept.is_empty() {
+        url.set_query(None);
+        return;
+    }
+    url.query_pairs_mut().clear().extend_pairs(
+        kept.iter()
+            .map(|(name, value)| (name.as_str(), value.as_str())),
+    );
+}
+
+/// Parameter patterns are matched case-insensitively; a trailing `*` matches
+/// any suffix, so `utm_*` covers `utm_source`, `utm_medium`, and friends.
+fn param_matches(name: &str, pattern: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    let pattern = pattern.trim().to_ascii_lowercase();
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => name == pattern,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clean(input: &str) -> String {
+        let url = Url::parse(input).expect(input);
+        clean_url(&url, &UrlCleaningSettings::default()).to_string()
+    }
+
+    /// Wraps `target` in a Google `/url?q=` redirect.
+    fn google_wrap(target: &str) -> String {
+        Url::parse_with_params("https://www.google.com/url", [("q", target)])
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn strips_tracking_params() {
+        let cases = [
+            (
+                "https://example.com/post?utm_source=news&utm_medium=email&utm_campaign=fall&id=3",
+                "https://example.com/post?id=3",
+            ),
+            (
+                "https://example.com/?fbclid=IwAR0abc",
+                "https://example.com/",
+            ),
+            (
+                "https://shop.example.com/item/42?gclid=Cj0KCQ&color=red",
+                "https://shop.example.com/item/42?color=red",
+            ),
+            (
+                "https://example.com/list?mc_cid=1a2b&mc_eid=3c4d",
+                "https://example.com/list",
+            ),
+            (
+                "https://example.com/a?UTM_Source=x&Page=2",
+                "https://example.com/a?Page=2",
+            ),
+            (
+                "https://example.com/a?utm_source=x#section-2",
+                "https://example.com/a#section-2",
+            ),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(clean(input), expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn unwraps_redirectors() {
+        let cases = [
+            (
+                "https://www.google.com/url?sa=t&rct=j&q=https%3A%2F%2Fexample.com%2Fdocs%3Futm_source%3Dgoogle%26v%3D2&usg=AOv",
+                "https://example.com/docs?v=2",
+            ),
+            (
+                "https://www.google.com/url?url=https://example.org/&sa=D",
+                "https://example.org/",
+            ),
+            (
+                "https://nam12.safelinks.protection.outlook.com/?url=https%3A%2F%2Fexample.com%2Freport%3Fid%3D7%26utm_medium%3Demail&data=05%7C01%7C&sdata=abc%3D&reserved=0",
+                "https://example.com/report?id=7",
+            ),
+            (
+                "https://l.facebook.com/l.php?u=https%3A%2F%2Fexample.com%2F%3Ffbclid%3Dabc&h=AT0",
+                "https://example.com/",
+            ),
+            (
+                "https://www.youtube.com/redirect?event=video_description&q=https%3A%2F%2Fexample.com%2Fshop",
+                "https://example.com/shop",
+            ),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(clean(input), expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn unwraps_nested_redirectors_up_to_the_cap() {
+        let target = "https://example.com/final?utm_source=deep";
+        let mut wrapped = target.to_string();
+        for _ in 0..MAX_UNWRAP_DEPTH {
+            wrapped = google_wrap(&wrapped);
+        }
+        assert_eq!(clean(&wrapped), "https://example.com/final");
+
+        // One level past the cap stays wrapped once.
+        let too_deep = google_wrap(&wrapped);
+        assert_eq!(clean(&too_deep), google_wrap(target));
+    }
+
+    #[test]
+    fn suffix_patterns_match_prefixes_only() {
+        let rules = UrlCleaningSettings {
+            strip_params: vec!["ref_*".to_string(), "session".to_string()],
+            ..UrlCleaningSettings::default()
+        };
+        let url =
+            Url::parse("https://example.com/?ref_src=tw&REF_URL=x&referrer=a&session=1&sessions=2")
+                .unwrap();
+        assert_eq!(
+            clean_url(&url, &rules).as_str(),
+            "https://example.com/?referrer=a&sessions=2"
+        );
+    }
+
+    #[test]
+    fn leaves_other_urls_unchanged() {
+        let unchanged = [
+            "https://example.com/docs#utm_source=anchor",
+            "https://example.com/search?q=rust+url&page=2",
+            "https://example.com/a%20b?x=%2F",
+            "ftp://files.example.com/pub/file.txt?utm_source=x",
+            "mailto:someone@example.com?subject=utm_source",
+            "https://www.google.com/search?q=https%3A%2F%2Fexample.com",
+            "https://www.google.com/url?q=javascript%3Aalert(1)",
+            "https://notgoogle.com/url?q=https%3A%2F%2Fexample.com",
+        ];
+        for input in unchanged {
+            assert_eq!(clean(input), Url::parse(input).unwrap().as_str(), "{input}");
+        }
+    }
+
+    #[test]
+    fn disabled_rules_change_nothing() {
+        let rules = UrlCleaningSettings {
+            enabled: false,
+            ..UrlCleaningSettings::default()
+        };
+        let url = Url::parse("https://example.com/?utm_source=x").unwrap();
+        assert_eq!(clean_url(&url, &rules), url);
+    }
+}
@@ -1,32 +1,64 @@
 use gpui::App;
+use std::sync::{Mutex, OnceLock};
+#[cfg(target_os = "windows")]
 use std::time::Duration;
 
+#[cfg(target_os = "windows")]
+use crate::clipboard::auto_tags::auto_tags;
+#[cfg(target_os = "windows")]
+use crate::clipboard::barcodes::{code_links, codes_to_json, decode_image_bytes};
 #[cfg(target_os = "windows")]
 use crate::clipboard::link_metadata::{fetch_link_metadata, parse_link_url, LinkMetadata};
 #[cfg(target_os = "windows")]
-use crate::clipboard::ocr::extract_text_from_image;
+use crate::clipboard::links::{extract_links, ExtractedLink, LinkKind};
 #[cfg(target_os = "windows")]
-use crate::clipboard::types::{ClipboardEntry, ClipboardEntryInput};
+use crate::clipboard::network::PolicyViolation;
 #[cfg(target_os = "windows")]
-use crate::clipboard::windows::{active_window_source, summarize_file_paths};
+use crate::clipboard::ocr::{attempt_key, recognize_image};
 #[cfg(target_os = "windows")]
-use crate::storage::history::{
-    insert_clipboard_entry, load_last_hash, open_db,
-    ClipboardEntryInput as StorageClipboardEntryInput,
-};
+use crate::clipboard::types::{summarize_file_paths, ClipboardEntry, ClipboardEntryInput};
 #[cfg(target_os = "windows")]
-use crate::storage::images::save_image_bytes;
+use crate::clipboard::url_clean::clean_url;
+#[cfg(target_os = "windows")]
+use crate::clipboard::windows::active_window_source;
+#[cfg(target_os = "windows")]
+use crate::settings::settings;
 #[cfg(target_os = "windows")]
-use crate::storage::path::default_db_path;
+use crate::storage::crypto;
 #[cfg(target_os = "windows")]
-use crate::utils::hash_bytes;
+use crate::storage::history::{ClipboardEntryInput as StorageClipboardEntryInput, EntryLinkInput};
+#[cfg(target_os = "windows")]
+use crate::storage::images::save_image_bytes;
+#[cfg(target_os = "windows")]
+use crate::storage::service::Storage;
 #[cfg(target_os = "windows")]
 use clipboard_win::{formats, Clipboard, Format, Getter};
-use std::sync::mpsc::Sender;
-use std::sync::{Mutex, OnceLock};
 
 static IGNORE_HASH: OnceLock<Mutex<Option<String>>> = OnceLock::new();
 
+#[cfg(target_os = "windows")]
+/// What one poll found on the clipboard, before OCR, link lookups and the
+/// rest of the work that turns it into an entry.
+enum ClipboardData {
+    Files(Vec<String>),
+    /// Bitmap bytes as the clipboard hands them over.
+    Image(Vec<u8>),
+    /// Trimmed, never empty.
+    Text(String),
+}
+
+#[cfg(target_os = "windows")]
+impl ClipboardData {
+    /// Hashes the raw data, so unchanged polls stay cheap.
+    fn content_hash(&self) -> anyhow::Result<String> {
+        Ok(match self {
+            Self::Files(files) => crypto::content_hash(serde_json::to_string(files)?.as_bytes()),
+            Self::Image(bytes) => crypto::content_hash(bytes),
+            Self::Text(text) => crypto::content_hash(text.as_bytes()),
+        })
+    }
+}
+
 pub fn ignore_next_hash(hash: String) {
     let lock = IGNORE_HASH.get_or_init(|| Mutex::new(None));
     if let Ok(mut guard) = lock.lock() {
@@ -34,31 +66,27 @@ pub fn ignore_next_hash(hash: String) {
     }
 }
 
-pub fn start_clipboard_history(cx: &mut App, update_tx: Sender<()>) {
+/// Captures clipboard changes into the shared storage, whose events tell
+/// the popup about new entries.
+pub fn start_clipboard_history(cx: &mut App) {
     #[cfg(not(target_os = "windows"))]
-    let _ = update_tx;
+    let _ = cx;
 
     #[cfg(target_os = "windows")]
-    {
-        if let Err(err) = start_windows_clipboard_history(cx, update_tx) {
-            eprintln!("Failed to start clipboard history: {err}");
-        }
-    }
+    start_windows_clipboard_history(cx);
 }
 
 #[cfg(target_os = "windows")]
-fn start_windows_clipboard_history(cx: &mut App, update_tx: Sender<()>) -> anyhow::Result<()> {
-    let db_path = default_db_path()?;
-
+fn start_windows_clipboard_history(cx: &mut App) {
     cx.spawn(async move |cx| {
-        let db = match open_db(&db_path).await {
-            Ok(db) => db,
+        let storage = match Storage::shared().await {
+            Ok(storage) => storage,
             Err(err) => {
                 eprintln!("Failed to open clipboard database: {err}");
                 return;
             }
         };
-        let mut last_hash = match load_last_hash(&db).await {
+        let mut last_hash = match storage.last_hash().await {
             Ok(hash) => hash,
             Err(err) => {
                 eprintln!("Failed to load clipboard history: {err}");
@@ -67,42 +95,10 @@ fn start_windows_clipboard_history(cx: &mut App, update_tx: Sender<()>) -> anyho
         };
 
         loop {
-            match read_clipboard_entry().await {
-                Ok(Some(entry)) => {
-                    if last_hash.as_deref() != Some(entry.content_hash.as_str()) {
-                        let ignore_hash = IGNORE_HASH
-                            .get_or_init(|| Mutex::new(None))
-                            .lock()
-                            .ok()
-                            .and_then(|mut guard| guard.take());
-
-                        if ignore_hash.as_deref() == Some(entry.content_hash.as_str()) {
-                            last_hash = Some(entry.content_hash);
-                        } else if let Err(err) = insert_clipboard_entry(
-                            &db,
-                            StorageClipboardEntryInput {
-                                content_type: &entry.content_type,
-                                content_hash: &entry.content_hash,
-                                content: &entry.content,
-                                text_content: entry.text_content.as_deref(),
-                                ocr_text: entry.ocr_text.as_deref(),
-                                image_path: entry.image_path.as_deref(),
-                                file_paths: entry.file_paths.as_deref(),
-                                link_url: entry.link_url.as_deref(),
-                                link_title: entry.link_title.as_deref(),
-                                link_description: entry.link_description.as_deref(),
-                                link_site_name: entry.link_site_name.as_deref(),
-                                source_app_title: entry.source_app_title.as_deref(),
-                                source_exe_path: entry.source_exe_path.as_deref(),
-                            },
-                        )
-                        .await
-                        {
-                            eprintln!("Failed to write clipboard entry: {err}");
-                        } else {
-                            last_hash = Some(entry.content_hash);
-                            let _ = update_tx.send(());
-                        }
+            match read_clipboard() {
+                Ok(Some(data)) => {
+                    if let Err(err) = capture(&storage, &mut last_hash, data).await {
+                        eprintln!("Failed to write clipboard entry: {err}");
                     }
                 }
                 Ok(None) => {}
@@ -117,12 +113,80 @@ fn start_windows_clipboard_history(cx: &mut App, update_tx: Sender<()>) -> anyho
         }
     })
     .detach();
+}
 
+#[cfg(target_os = "windows")]
+/// Stores `data` unless it is what was seen last or what the app itself just
+/// put on the clipboard.
+async fn capture(
+    storage: &Storage,
+    last_hash: &mut Option<String>,
+    data: ClipboardData,
+) -> anyhow::Result<()> {
+    let content_hash = data.content_hash()?;
+    if last_hash.as_deref() == Some(content_hash.as_str()) {
+        return Ok(());
+    }
+
+    let ignore_hash = IGNORE_HASH
+        .get_or_init(|| Mutex::new(None))
+        .lock()
+        .ok()
+        .and_then(|mut guard| guard.take());
+    if ignore_hash.as_deref() != Some(content_hash.as_str()) {
+        let entry = build_entry(data, content_hash.clone()).await?;
+        store_clipboard_entry(storage, &entry).await?;
+    }
+    *last_hash = Some(content_hash);
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+async fn store_clipboard_entry(storage: &Storage, entry: &ClipboardEntry) -> anyhow::Result<()> {
+    let input = StorageClipboardEntryInput {
+        content_type: &entry.content_type,
+        content_hash: &entry.content_hash,
+        content: &entry.content,
+        text_content: entry.text_content.as_deref(),
+        ocr_text: entry.ocr_text.as_deref(),
+        ocr_layout: entry.ocr_layout.as_deref(),
+        ocr_attempt: entry.ocr_attempt.as_deref(),
+        image_path: entry.image_path.as_deref(),
+        image_codes: entry.image_codes.as_deref(),
+        file_paths: entry.file_paths.as_deref(),
+        link_url: entry.link_url.as_deref(),
+        link_clean_url: entry.link_clean_url.as_deref(),
+        link_title: entry.link_title.as_deref(),
+        link_description: entry.link_description.as_deref(),
+        link_site_name: entry.link_site_name.as_deref(),
+        link_details: entry.link_details.as_deref(),
+        source_app_title: entry.source_app_title.as_deref(),
+        source_exe_path: entry.source_exe_path.as_deref(),
+        created_at: None,
+    };
+    let links: Vec<EntryLinkInput> = entry
+        .links
+        .iter()
+        .map(|link| {
+            let metadata = link.metadata.as_ref();
+            EntryLinkInput {
+                kind: link.kind.as_str(),
+                url: &link.value,
+                start_offset: link.start as i32,
+                end_offset: link.end as i32,
+                title: metadata.and_then(|metadata| metadata.title.as_deref()),
+                description: metadata.and_then(|metadata| metadata.description.as_deref()),
+                site_name: metadata.and_then(|metadata| metadata.site_name.as_deref()),
+            }
+        })
+        .collect();
+    let tags = auto_tags(&settings().tags.auto_rules, entry);
+    storage.add_entry(input, &links, &tags).await?;
     Ok(())
 }
 
 #[cfg(target_os = "windows")]
-async fn read_clipboard_entry() -> anyhow::Result<Option<ClipboardEntry>> {
+fn read_clipboard() -> anyhow::Result<Option<ClipboardData>> {
     let _clip = Clipboard::new_attempts(10)
         .map_err(|err| anyhow::anyhow!("Clipboard open failed: {err}"))?;
 
@@ -132,19 +196,7 @@ async fn read_clipboard_entry() -> anyhow::Result<Option<ClipboardEntry>> {
             .read_clipboard(&mut files)
             .map_err(|err| anyhow::anyhow!("Clipboard read failed: {err}"))?;
         if !files.is_empty() {
-            let file_paths = serde_json::to_string(&files)?;
-            let content_hash = hash_bytes(file_paths.as_bytes());
-            let content = summarize_file_paths(&files);
-            return Ok(Some(build_entry(ClipboardEntryInput {
-                content_type: "files".to_string(),
-                content_hash,
-                content,
-                text_content: None,
-                ocr_text: None,
-                image_path: None,
-                file_paths: Some(file_paths),
-                link_metadata: None,
-            })));
+            return Ok(Some(ClipboardData::Files(files)));
         }
     }
 
@@ -154,81 +206,167 @@ async fn read_clipboard_entry() -> anyhow::Result<Option<ClipboardEntry>> {
             .read_clipboard(&mut bytes)
             .map_err(|err| anyhow::anyhow!("Clipboard read failed: {err}"))?;
         if !bytes.is_empty() {
-            let content_hash = hash_bytes(&bytes);
-            let image_path = save_image_bytes(&content_hash, &bytes)?;
-            let ocr_text = match extract_text_from_image(&bytes).await {
-                Ok(text) => text,
+            return Ok(Some(ClipboardData::Image(bytes)));
+        }
+    }
+
+    if formats::Unicode.is_format_avail() {
+        let mut text = String::new();
+        let _ = formats::Unicode
+            .read_clipboard(&mut text)
+            .map_err(|err| anyhow::anyhow!("Clipboard read failed: {err}"))?;
+        let trimmed = text.trim();
+        if !trimmed.is_empty() {
+            return Ok(Some(ClipboardData::Text(trimmed.to_string())));
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(target_os = "windows")]
+async fn build_entry(data: ClipboardData, content_hash: String) -> anyhow::Result<ClipboardEntry> {
+    let input = match data {
+        ClipboardData::Files(files) => {
+            let file_paths = serde_json::to_string(&files)?;
+            ClipboardEntryInput {
+                content_type: "files".to_string(),
+                content_hash,
+                content: summarize_file_paths(&files),
+                text_content: None,
+                ocr_text: None,
+                ocr_layout: None,
+                ocr_attempt: None,
+                image_path: None,
+                image_codes: None,
+                file_paths: Some(file_paths),
+                link_metadata: None,
+                link_clean_url: None,
+                links: Vec::new(),
+            }
+        }
+        ClipboardData::Image(bytes) => {
+            let image_path = save_image_bytes(&content_hash, bytes.clone()).await?;
+            // A failed run stays unmarked so the backfill retries it.
+            let (ocr_text, ocr_layout, ocr_attempt) = match recognize_image(&bytes).await {
+                Ok(Some(result)) => (Some(result.text), result.layout.to_json(), attempt_key()),
+                Ok(None) => (None, None, attempt_key()),
                 Err(err) => {
                     eprintln!("Failed to OCR image: {err}");
-                    None
+                    (None, None, None)
                 }
             };
-            return Ok(Some(build_entry(ClipboardEntryInput {
+            let codes = async_std::task::spawn_blocking(move || decode_image_bytes(&bytes)).await;
+            ClipboardEntryInput {
                 content_type: "image".to_string(),
                 content_hash,
                 content: "Image".to_string(),
                 text_content: None,
                 ocr_text,
+                ocr_layout,
+                ocr_attempt,
                 image_path: Some(image_path.to_string_lossy().to_string()),
+                image_codes: codes_to_json(&codes),
                 file_paths: None,
                 link_metadata: None,
-            })));
+                link_clean_url: None,
+                links: code_links(&codes),
+            }
         }
-    }
-
-    if formats::Unicode.is_format_avail() {
-        let mut text = String::new();
-        let _ = formats::Unicode
-            .read_clipboard(&mut text)
-            .map_err(|err| anyhow::anyhow!("Clipboard read failed: {err}"))?;
-        let trimmed = text.trim();
-        if !trimmed.is_empty() {
-            let content_hash = hash_bytes(trimmed.as_bytes());
-            if let Some(url) = parse_link_url(trimmed) {
-                let mut link_metadata = match fetch_link_metadata(&url).await {
-                    Ok(metadata) => metadata,
-                    Err(err) => {
-                        eprintln!("Failed to fetch link metadata: {err}");
-                        None
+        ClipboardData::Text(text) => {
+            if let Some(url) = parse_link_url(&text) {
+                let current_settings = settings();
+                let cleaned = clean_url(&url, &current_settings.links.cleaning);
+                let mut link_metadata = if current_settings.network.auto_fetch {
+                    match fetch_link_metadata(&cleaned).await {
+                        Ok(metadata) => metadata,
+                        // Capture skips what the policy blocks without noise.
+                        Err(err) if err.is::<PolicyViolation>() => None,
+                        Err(err) => {
+                            eprintln!("Failed to fetch link metadata: {err}");
+                            None
+                        }
                     }
+                } else {
+                    None
                 };
-                if link_metadata.is_none() {
-                    link_metadata = Some(LinkMetadata {
-                        url: url.to_string(),
-                        title: None,
-                        description: None,
-                        site_name: None,
-                    });
+                match link_metadata.as_mut() {
+                    Some(metadata) => metadata.url = url.to_string(),
+                    None => {
+                        link_metadata = Some(LinkMetadata {
+                            url: url.to_string(),
+                            title: None,
+                            description: None,
+                            site_name: None,
+                            details: None,
+                        });
+                    }
                 }
-                return Ok(Some(build_entry(ClipboardEntryInput {
+                ClipboardEntryInput {
                     content_type: "link".to_string(),
                     content_hash,
-                    content: trimmed.to_string(),
-                    text_content: Some(trimmed.to_string()),
+                    content: text.clone(),
+                    text_content: Some(text),
                     ocr_text: None,
+                    ocr_layout: None,
+                    ocr_attempt: None,
                     image_path: None,
+                    image_codes: None,
                     file_paths: None,
                     link_metadata,
-                })));
+                    link_clean_url: Some(cleaned.to_string()),
+                    links: Vec::new(),
+                }
+            } else {
+                let links = enrich_text_links(extract_links(&text)).await;
+                ClipboardEntryInput {
+                    content_type: "text".to_string(),
+                    content_hash,
+                    content: text.clone(),
+                    text_content: Some(text),
+                    ocr_text: None,
+                    ocr_layout: None,
+                    ocr_attempt: None,
+                    image_path: None,
+                    image_codes: None,
+                    file_paths: None,
+                    link_metadata: None,
+                    link_clean_url: None,
+                    links,
+                }
             }
-            return Ok(Some(build_entry(ClipboardEntryInput {
-                content_type: "text".to_string(),
-                content_hash,
-                content: trimmed.to_string(),
-                text_content: Some(trimmed.to_string()),
-                ocr_text: None,
-                image_path: None,
-                file_paths: None,
-                link_metadata: None,
-            })));
         }
+    };
+    Ok(with_source(input))
+}
+
+#[cfg(target_os = "windows")]
+async fn enrich_text_links(mut links: Vec<ExtractedLink>) -> Vec<ExtractedLink> {
+    let current_settings = settings();
+    let link_settings = current_settings.links;
+    if !link_settings.enrich_text_links || !current_settings.network.auto_fetch {
+        return links;
     }
 
-    Ok(None)
+    for link in links
+        .iter_mut()
+        .filter(|link| link.kind == LinkKind::Url)
+        .take(link_settings.max_enriched_links)
+    {
+        let Some(url) = parse_link_url(&link.value) else {
+            continue;
+        };
+        match fetch_link_metadata(&url).await {
+            Ok(metadata) => link.metadata = metadata,
+            Err(err) if err.is::<PolicyViolation>() => {}
+            Err(err) => eprintln!("Failed to fetch link metadata: {err}"),
+        }
+    }
+    links
 }
 
 #[cfg(target_os = "windows")]
-fn build_entry(input: ClipboardEntryInput) -> ClipboardEntry {
+fn with_source(input: ClipboardEntryInput) -> ClipboardEntry {
     let (source_app_title, source_exe_path) = active_window_source();
     let mut entry = ClipboardEntry::from(input);
     entry.source_app_title = source_app_title;
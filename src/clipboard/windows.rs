@@ -72,24 +72,3 @@ fn active_window_exe_path(hwnd: HWND) -> Option<String> {
         Some(trimmed.to_string())
     }
 }
-
-pub fn summarize_file_paths(paths: &[String]) -> String {
-    let mut names: Vec<String> = Vec::with_capacity(paths.len());
-    for path in paths {
-        let name = std::path::Path::new(path)
-            .file_name()
-            .and_then(|name| name.to_str())
-            .unwrap_or(path);
-        names.push(name.to_string());
-    }
-
-    let summary = names.join(", ");
-    const MAX_LEN: usize = 500;
-    if summary.len() <= MAX_LEN {
-        summary
-    } else {
-        let mut truncated = summary[..MAX_LEN].to_string();
-        truncated.push_str("...");
-        truncated
-    }
-}
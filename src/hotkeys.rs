@@ -10,7 +10,7 @@ use std::time::Duration;
 use crate::ui::popup::PopupView;
 
 #[cfg(not(target_os = "windows"))]
-const HOTKEY_MODS: Modifiers = Modifiers::ALT | Modifiers::SHIFT;
+const HOTKEY_MODS: Modifiers = Modifiers::ALT.union(Modifiers::SHIFT);
 #[cfg(not(target_os = "windows"))]
 const HOTKEY_KEY: Code = Code::KeyV;
 
@@ -21,7 +21,7 @@ struct HotKeyRegistration {
 }
 
 #[cfg(not(target_os = "windows"))]
-impl Global for HotKeyRegistration {}
+impl gpui::Global for HotKeyRegistration {}
 
 pub fn setup_global_hotkey(cx: &mut App, handle: WindowHandle<Root>) -> anyhow::Result<()> {
     let (event_tx, event_rx) = mpsc::channel::<()>();
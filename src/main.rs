@@ -3,7 +3,10 @@ mod app;
 mod clipboard;
 mod hotkeys;
 mod migration;
+mod settings;
 mod storage;
+#[cfg(test)]
+mod test_support;
 mod ui;
 mod utils;
 
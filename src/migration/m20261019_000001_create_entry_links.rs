@@ -0,0 +1,90 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(EntryLinks::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(EntryLinks::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(EntryLinks::EntryId).integer().not_null())
+                    .col(ColumnDef::new(EntryLinks::Kind).string().not_null())
+                    .col(ColumnDef::new(EntryLinks::Url).string().not_null())
+                    .col(ColumnDef::new(EntryLinks::StartOffset).integer().not_null())
+                    .col(ColumnDef::new(EntryLinks::EndOffset).integer().not_null())
+                    .col(ColumnDef::new(EntryLinks::Title).string())
+                    .col(ColumnDef::new(EntryLinks::Description).string())
+                    .col(ColumnDef::new(EntryLinks::SiteName).string())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_entry_links_entry_id")
+                            .from(EntryLinks::Table, EntryLinks::EntryId)
+                            .to(ClipboardEntries::Table, ClipboardEntries::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_entry_links_entry_id")
+                    .table(EntryLinks::Table)
+                    .if_not_exists()
+                    .col(EntryLinks::EntryId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_entry_links_url")
+                    .table(EntryLinks::Table)
+                    .if_not_exists()
+                    .col(EntryLinks::Url)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(EntryLinks::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum EntryLinks {
+    Table,
+    Id,
+    EntryId,
+    Kind,
+    Url,
+    StartOffset,
+    EndOffset,
+    Title,
+    Description,
+    SiteName,
+}
+
+#[derive(DeriveIden)]
+enum ClipboardEntries {
+    Table,
+    Id,
+}
@@ -0,0 +1,90 @@
+use sea_orm_migration::prelude::*;
+
+/// Columns of `clipboard_entries` mirrored into the full-text index, in index
+/// column order. The search code relies on this order for `bm25` weights.
+const INDEXED_COLUMNS: [&str; 12] = [
+    "content",
+    "text_content",
+    "ocr_text",
+    "image_codes",
+    "file_paths",
+    "link_url",
+    "link_clean_url",
+    "link_title",
+    "link_description",
+    "link_site_name",
+    "source_app_title",
+    "source_exe_path",
+];
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        let columns = INDEXED_COLUMNS.join(", ");
+        let new_values = prefixed_columns("new");
+        let old_values = prefixed_columns("old");
+
+        db.execute_unprepared(&format!(
+            "CREATE VIRTUAL TABLE IF NOT EXISTS clipboard_entries_fts USING fts5(\
+             {columns}, content='clipboard_entries', content_rowid='id', \
+             tokenize='unicode61 remove_diacritics 2')"
+        ))
+        .await?;
+        db.execute_unprepared(&format!(
+            "CREATE TRIGGER IF NOT EXISTS clipboard_entries_fts_ai \
+             AFTER INSERT ON clipboard_entries BEGIN \
+             INSERT INTO clipboard_entries_fts(rowid, {columns}) VALUES (new.id, {new_values}); \
+             END"
+        ))
+        .await?;
+        db.execute_unprepared(&format!(
+            "CREATE TRIGGER IF NOT EXISTS clipboard_entries_fts_ad \
+             AFTER DELETE ON clipboard_entries BEGIN \
+             INSERT INTO clipboard_entries_fts(clipboard_entries_fts, rowid, {columns}) \
+             VALUES ('delete', old.id, {old_values}); \
+             END"
+        ))
+        .await?;
+        db.execute_unprepared(&format!(
+            "CREATE TRIGGER IF NOT EXISTS clipboard_entries_fts_au \
+             AFTER UPDATE ON clipboard_entries BEGIN \
+             INSERT INTO clipboard_entries_fts(clipboard_entries_fts, rowid, {columns}) \
+             VALUES ('delete', old.id, {old_values}); \
+             INSERT INTO clipboard_entries_fts(rowid, {columns}) VALUES (new.id, {new_values}); \
+             END"
+        ))
+        .await?;
+        db.execute_unprepared(
+            "INSERT INTO clipboard_entries_fts(clipboard_entries_fts) VALUES ('rebuild')",
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        for trigger in [
+            "clipboard_entries_fts_ai",
+            "clipboard_entries_fts_ad",
+            "clipboard_entries_fts_au",
+        ] {
+            db.execute_unprepared(&format!("DROP TRIGGER IF EXISTS {trigger}"))
+                .await?;
+        }
+        db.execute_unprepared("DROP TABLE IF EXISTS clipboard_entries_fts")
+            .await?;
+        Ok(())
+    }
+}
+
+fn prefixed_columns(prefix: &str) -> String {
+    INDEXED_COLUMNS
+        .iter()
+        .map(|column| format!("{prefix}.{column}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::{ConnectionTrait, Statement};
+
+use crate::storage::tokenizer::search_terms;
+
+/// Columns of `clipboard_entries` mirrored into the full-text index, in index
+/// column order. Same as before plus `search_terms`, which goes last so the
+/// earlier `bm25` weights keep their positions.
+const INDEXED_COLUMNS: [&str; 13] = [
+    "content",
+    "text_content",
+    "ocr_text",
+    "image_codes",
+    "file_paths",
+    "link_url",
+    "link_clean_url",
+    "link_title",
+    "link_description",
+    "link_site_name",
+    "source_app_title",
+    "source_exe_path",
+    "search_terms",
+];
+
+/// Entry columns tokenized into `search_terms` for existing rows.
+const TERM_SOURCE_COLUMNS: [&str; 9] = [
+    "content",
+    "text_content",
+    "ocr_text",
+    "image_codes",
+    "file_paths",
+    "link_url",
+    "link_title",
+    "link_description",
+    "link_site_name",
+];
+
+const FTS_TRIGGERS: [&str; 3] = [
+    "clipboard_entries_fts_ai",
+    "clipboard_entries_fts_ad",
+    "clipboard_entries_fts_au",
+];
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ClipboardEntries::Table)
+                    .add_column(ColumnDef::new(ClipboardEntries::SearchTerms).string())
+                    .to_owned(),
+            )
+            .await?;
+
+        // Fill the column before the index exists, so the update triggers
+        // don't re-index every row twice.
+        drop_fts(manager).await?;
+        backfill_search_terms(manager).await?;
+        create_fts(manager, &INDEXED_COLUMNS).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        drop_fts(manager).await?;
+        create_fts(manager, &INDEXED_COLUMNS[..INDEXED_COLUMNS.len() - 1]).await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ClipboardEntries::Table)
+                    .drop_column(ClipboardEntries::SearchTerms)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+async fn backfill_search_terms(manager: &SchemaManager<'_>) -> Result<(), DbErr> {
+    let db = manager.get_connection();
+    let backend = db.get_database_backend();
+
+    let mut link_texts: HashMap<i32, Vec<String>> = HashMap::new();
+    let links = db
+        .query_all(Statement::from_string(
+            backend,
+            "SELECT entry_id, title, description, site_name FROM entry_links",
+        ))
+        .await?;
+    for row in links {
+        let entry_id: i32 = row.try_get("", "entry_id")?;
+        for column in ["title", "description", "site_name"] {
+            if let Some(text) = row.try_get::<Option<String>>("", column)? {
+                link_texts.entry(entry_id).or_default().push(text);
+            }
+        }
+    }
+
+    let rows = db
+        .query_all(Statement::from_string(
+            backend,
+            format!(
+                "SELECT id, {} FROM clipboard_entries",
+                TERM_SOURCE_COLUMNS.join(", ")
+            ),
+        ))
+        .await?;
+    for row in rows {
+        let id: i32 = row.try_get("", "id")?;
+        let mut fields = Vec::new();
+        for column in TERM_SOURCE_COLUMNS {
+            fields.extend(row.try_get::<Option<String>>("", column)?);
+        }
+        fields.dedup();
+        let links = link_texts.remove(&id).unwrap_or_default();
+        let terms = search_terms(fields.iter().chain(&links).map(String::as_str));
+        let Some(terms) = terms else {
+            continue;
+        };
+        db.execute(Statement::from_sql_and_values(
+            backend,
+            "UPDATE clipboard_entries SET search_terms = ? WHERE id = ?",
+            [terms.into(), id.into()],
+        ))
+        .await?;
+    }
+    Ok(())
+}
+
+async fn drop_fts(manager: &SchemaManager<'_>) -> Result<(), DbErr> {
+    let db = manager.get_connection();
+    for trigger in FTS_TRIGGERS {
+        db.execute_unprepared(&format!("DROP TRIGGER IF EXISTS {trigger}"))
+            .await?;
+    }
+    db.execute_unprepared("DROP TABLE IF EXISTS clipboard_entries_fts")
+        .await?;
+    Ok(())
+}
+
+async fn create_fts(manager: &SchemaManager<'_>, indexed_columns: &[&str]) -> Result<(), DbErr> {
+    let db = manager.get_connection();
+    let columns = indexed_columns.join(", ");
+    let prefixed = |prefix: &str| {
+        indexed_columns
+            .iter()
+            .map(|column| format!("{prefix}.{column}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let new_values = prefixed("new");
+    let old_values = prefixed("old");
+
+    db.execute_unprepared(&format!(
+        "CREATE VIRTUAL TABLE clipboard_entries_fts USING fts5(\
+         {columns}, content='clipboard_entries', content_rowid='id', \
+         tokenize='unicode61 remove_diacritics 2')"
+    ))
+    .await?;
+    db.execute_unprepared(&format!(
+        "CREATE TRIGGER clipboard_entries_fts_ai \
+         AFTER INSERT ON clipboard_entries BEGIN \
+         INSERT INTO clipboard_entries_fts(rowid, {columns}) VALUES (new.id, {new_values}); \
+         END"
+    ))
+    .await?;
+    db.execute_unprepared(&format!(
+        "CREATE TRIGGER clipboard_entries_fts_ad \
+         AFTER DELETE ON clipboard_entries BEGIN \
+         INSERT INTO clipboard_entries_fts(clipboard_entries_fts, rowid, {columns}) \
+         VALUES ('delete', old.id, {old_values}); \
+         END"
+    ))
+    .await?;
+    db.execute_unprepared(&format!(
+        "CREATE TRIGGER clipboard_entries_fts_au \
+         AFTER UPDATE ON clipboard_entries BEGIN \
+         INSERT INTO clipboard_entries_fts(clipboard_entries_fts, rowid, {columns}) \
+         VALUES ('delete', old.id, {old_values}); \
+         INSERT INTO clipboard_entries_fts(rowid, {columns}) VALUES (new.id, {new_values}); \
+         END"
+    ))
+    .await?;
+    db.execute_unprepared(
+        "INSERT INTO clipboard_entries_fts(clipboard_entries_fts) VALUES ('rebuild')",
+    )
+    .await?;
+    Ok(())
+}
+
+#[derive(DeriveIden)]
+enum ClipboardEntries {
+    Table,
+    SearchTerms,
+}
@@ -0,0 +1,106 @@
+use sea_orm_migration::prelude::*;
+
+/// Columns the full-text index mirrors, as of `m20261019_000007`.
+const INDEXED_COLUMNS: [&str; 13] = [
+    "content",
+    "text_content",
+    "ocr_text",
+    "image_codes",
+    "file_paths",
+    "link_url",
+    "link_clean_url",
+    "link_title",
+    "link_description",
+    "link_site_name",
+    "source_app_title",
+    "source_exe_path",
+    "search_terms",
+];
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ClipboardEntries::Table)
+                    .add_column(ColumnDef::new(ClipboardEntries::PinOrder).integer())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("idx_clipboard_entries_pin_order")
+                    .table(ClipboardEntries::Table)
+                    .col(ClipboardEntries::PinOrder)
+                    .to_owned(),
+            )
+            .await?;
+        // Pinning and reordering only touch `pin_order`; limit the index
+        // trigger to the columns it mirrors so they don't re-index the text.
+        recreate_update_trigger(
+            manager,
+            &format!("UPDATE OF {}", INDEXED_COLUMNS.join(", ")),
+        )
+        .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        recreate_update_trigger(manager, "UPDATE").await?;
+        manager
+            .drop_index(
+                Index::drop()
+                    .if_exists()
+                    .name("idx_clipboard_entries_pin_order")
+                    .table(ClipboardEntries::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ClipboardEntries::Table)
+                    .drop_column(ClipboardEntries::PinOrder)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+async fn recreate_update_trigger(manager: &SchemaManager<'_>, event: &str) -> Result<(), DbErr> {
+    let db = manager.get_connection();
+    let columns = INDEXED_COLUMNS.join(", ");
+    let prefixed = |prefix: &str| {
+        INDEXED_COLUMNS
+            .iter()
+            .map(|column| format!("{prefix}.{column}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let new_values = prefixed("new");
+    let old_values = prefixed("old");
+
+    db.execute_unprepared("DROP TRIGGER IF EXISTS clipboard_entries_fts_au")
+        .await?;
+    db.execute_unprepared(&format!(
+        "CREATE TRIGGER clipboard_entries_fts_au \
+         AFTER {event} ON clipboard_entries BEGIN \
+         INSERT INTO clipboard_entries_fts(clipboard_entries_fts, rowid, {columns}) \
+         VALUES ('delete', old.id, {old_values}); \
+         INSERT INTO clipboard_entries_fts(rowid, {columns}) VALUES (new.id, {new_values}); \
+         END"
+    ))
+    .await?;
+    Ok(())
+}
+
+#[derive(DeriveIden)]
+enum ClipboardEntries {
+    Table,
+    PinOrder,
+}
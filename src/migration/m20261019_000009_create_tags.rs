@@ -0,0 +1,107 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Tags::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Tags::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    // Tags differing only in case are the same tag.
+                    .col(
+                        ColumnDef::new(Tags::Name)
+                            .string()
+                            .not_null()
+                            .unique_key()
+                            .extra("COLLATE NOCASE"),
+                    )
+                    .col(ColumnDef::new(Tags::CreatedAt).big_integer().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(EntryTags::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(EntryTags::EntryId).integer().not_null())
+                    .col(ColumnDef::new(EntryTags::TagId).integer().not_null())
+                    .primary_key(
+                        Index::create()
+                            .col(EntryTags::EntryId)
+                            .col(EntryTags::TagId),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_entry_tags_entry_id")
+                            .from(EntryTags::Table, EntryTags::EntryId)
+                            .to(ClipboardEntries::Table, ClipboardEntries::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_entry_tags_tag_id")
+                            .from(EntryTags::Table, EntryTags::TagId)
+                            .to(Tags::Table, Tags::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_entry_tags_tag_id")
+                    .table(EntryTags::Table)
+                    .if_not_exists()
+                    .col(EntryTags::TagId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(EntryTags::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Tags::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Tags {
+    Table,
+    Id,
+    Name,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum EntryTags {
+    Table,
+    EntryId,
+    TagId,
+}
+
+#[derive(DeriveIden)]
+enum ClipboardEntries {
+    Table,
+    Id,
+}
@@ -0,0 +1,87 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Rows record whether they are stored encrypted, instead of
+        // guessing from the ciphertext prefix.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ClipboardEntries::Table)
+                    .add_column(
+                        ColumnDef::new(ClipboardEntries::Encrypted)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(EntryLinks::Table)
+                    .add_column(
+                        ColumnDef::new(EntryLinks::Encrypted)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Histories encrypted before the flag existed had every column of
+        // a converted row sealed together, so `content` tells them apart.
+        // Stays case-sensitive, unlike LIKE.
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "UPDATE clipboard_entries SET encrypted = 1 \
+             WHERE substr(content, 1, 5) = 'gcm1:' \
+             AND EXISTS (SELECT 1 FROM storage_meta WHERE key = 'encryption_check')",
+        )
+        .await?;
+        db.execute_unprepared(
+            "UPDATE entry_links SET encrypted = 1 \
+             WHERE substr(url, 1, 5) = 'gcm1:' \
+             AND EXISTS (SELECT 1 FROM storage_meta WHERE key = 'encryption_check')",
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(EntryLinks::Table)
+                    .drop_column(EntryLinks::Encrypted)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ClipboardEntries::Table)
+                    .drop_column(ClipboardEntries::Encrypted)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ClipboardEntries {
+    Table,
+    Encrypted,
+}
+
+#[derive(DeriveIden)]
+enum EntryLinks {
+    Table,
+    Encrypted,
+}
@@ -3,6 +3,18 @@ use sea_orm_migration::prelude::*;
 mod m20260110_000001_create_clipboard_entries;
 mod m20260110_000002_add_ocr_text;
 mod m20260110_000003_add_link_metadata;
+mod m20261019_000001_create_entry_links;
+mod m20261019_000002_add_link_clean_url;
+mod m20261019_000003_add_link_details;
+mod m20261019_000004_add_ocr_layout;
+mod m20261019_000005_add_image_codes;
+mod m20261019_000006_create_entries_fts;
+mod m20261019_000007_add_search_terms;
+mod m20261019_000008_add_pin_order;
+mod m20261019_000009_create_tags;
+mod m20261019_000010_create_storage_meta;
+mod m20261019_000011_add_ocr_attempt;
+mod m20261019_000012_add_encrypted_flags;
 
 pub struct Migrator;
 
@@ -13,6 +25,18 @@ impl MigratorTrait for Migrator {
             Box::new(m20260110_000001_create_clipboard_entries::Migration),
             Box::new(m20260110_000002_add_ocr_text::Migration),
             Box::new(m20260110_000003_add_link_metadata::Migration),
+            Box::new(m20261019_000001_create_entry_links::Migration),
+            Box::new(m20261019_000002_add_link_clean_url::Migration),
+            Box::new(m20261019_000003_add_link_details::Migration),
+            Box::new(m20261019_000004_add_ocr_layout::Migration),
+            Box::new(m20261019_000005_add_image_codes::Migration),
+            Box::new(m20261019_000006_create_entries_fts::Migration),
+            Box::new(m20261019_000007_add_search_terms::Migration),
+            Box::new(m20261019_000008_add_pin_order::Migration),
+            Box::new(m20261019_000009_create_tags::Migration),
+            Box::new(m20261019_000010_create_storage_meta::Migration),
+            Box::new(m20261019_000011_add_ocr_attempt::Migration),
+            Box::new(m20261019_000012_add_encrypted_flags::Migration),
         ]
     }
 }
@@ -0,0 +1,374 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{OnceLock, RwLock};
+
+use serde::{Deserialize, Serialize};
+
+use crate::storage::path::settings_path;
+
+static SETTINGS: OnceLock<RwLock<Settings>> = OnceLock::new();
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Settings {
+    pub links: LinkSettings,
+    pub network: NetworkSettings,
+    pub ocr: OcrSettings,
+    pub search: SearchSettings,
+    pub tags: TagSettings,
+    pub retention: RetentionSettings,
+    pub encryption: EncryptionSettings,
+    pub storage: StorageSettings,
+    pub backup: BackupSettings,
+}
+
+/// Snapshots of the database taken in the background. Image files aren't
+/// included; they stay in the images folder.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct BackupSettings {
+    pub enabled: bool,
+    /// Hours between backups while the app is open.
+    pub interval_hours: u64,
+    /// Keep the newest backup of each of this many recent days.
+    pub keep_daily: usize,
+    /// Keep the newest backup of each of this many recent weeks.
+    pub keep_weekly: usize,
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_hours: 24,
+            keep_daily: 7,
+            keep_weekly: 4,
+        }
+    }
+}
+
+/// Where the history lives when it was moved away from the platform
+/// default. Ignored under `--data-dir`, its environment variable and
+/// portable mode.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct StorageSettings {
+    pub data_dir: Option<PathBuf>,
+    /// The directory the history was moved out of, emptied on the next
+    /// start.
+    pub moved_from: Option<PathBuf>,
+}
+
+/// Encryption of entry text, links and image files at rest. Turning it on
+/// or off converts the existing history the next time the app starts; see
+/// `storage::encryption::prepare_encryption` for what stays readable.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct EncryptionSettings {
+    pub enabled: bool,
+    /// Only used when encryption is first turned on; later starts reuse the
+    /// source the history was encrypted with.
+    pub key_source: KeySource,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeySource {
+    /// A random key kept in the OS credential store.
+    #[default]
+    Keyring,
+    /// A key derived from the passphrase in `CLIPBOARD_HISTORY_PASSPHRASE`.
+    Passphrase,
+}
+
+/// Limits enforced by the background pruner. Pinned and tagged entries are
+/// never pruned and don't count towards the entry limits.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RetentionSettings {
+    /// Keep at most this many entries, dropping the oldest.
+    pub max_entries: Option<usize>,
+    pub max_age_days: Option<u64>,
+    /// Upper bound on stored text and images, including pinned and tagged
+    /// entries.
+    pub max_total_mb: Option<u64>,
+    /// Per content type (`text`, `image`, `link`, `files`) limits. A type's
+    /// `max_age_days` replaces the global one; its `max_entries` applies on
+    /// top of the global count.
+    pub content_types: HashMap<String, RetentionLimits>,
+    /// Minutes between pruning runs while the app is open.
+    pub interval_minutes: u64,
+}
+
+impl Default for RetentionSettings {
+    fn default() -> Self {
+        Self {
+            max_entries: None,
+            max_age_days: None,
+            max_total_mb: None,
+            content_types: HashMap::new(),
+            interval_minutes: 60,
+        }
+    }
+}
+
+impl RetentionSettings {
+    pub fn has_limits(&self) -> bool {
+        self.max_entries.is_some()
+            || self.max_age_days.is_some()
+            || self.max_total_mb.is_some()
+            || self
+                .content_types
+                .values()
+                .any(|limits| limits.max_entries.is_some() || limits.max_age_days.is_some())
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RetentionLimits {
+    pub max_entries: Option<usize>,
+    pub max_age_days: Option<u64>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TagSettings {
+    /// Tags applied to newly captured entries.
+    pub auto_rules: Vec<AutoTagRule>,
+}
+
+/// Tags a captured entry when every condition that is set holds.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AutoTagRule {
+    pub tag: String,
+    /// `text`, `image`, `link` or `files`.
+    pub content_type: Option<String>,
+    /// Case-insensitive part of the source executable path or window title.
+    pub app: Option<String>,
+    /// Regular expression matched against the entry's text.
+    pub pattern: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SearchSettings {
+    /// Typo-tolerant matching for search terms; toggled from the popup.
+    pub fuzzy: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LinkSettings {
+    /// Fetch page metadata for URLs found inside text entries.
+    pub enrich_text_links: bool,
+    /// Upper bound on how many links of a single text entry get enriched.
+    pub max_enriched_links: usize,
+    /// What Enter (and double click) copies for link entries.
+    pub enter_action: LinkEnterAction,
+    pub cleaning: UrlCleaningSettings,
+}
+
+impl Default for LinkSettings {
+    fn default() -> Self {
+        Self {
+            enrich_text_links: false,
+            max_enriched_links: 5,
+            enter_action: LinkEnterAction::CopyClean,
+            cleaning: UrlCleaningSettings::default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct OcrSettings {
+    pub backend: OcrBackend,
+    /// BCP-47 language tags (e.g. `en-US`, `de`, `ja`) to recognize. Empty
+    /// falls back to the Windows profile languages, or English for Tesseract.
+    pub languages: Vec<String>,
+    /// Path to the `tesseract` binary when it is not on `PATH`.
+    pub tesseract_path: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OcrBackend {
+    #[default]
+    Auto,
+    Windows,
+    Tesseract,
+    Disabled,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct NetworkSettings {
+    /// Never issue any network request.
+    pub offline: bool,
+    /// Fetch link previews as soon as a link is copied. When off, previews
+    /// are only fetched from the entry's context menu.
+    pub auto_fetch: bool,
+    /// When non-empty, only these domains (and their subdomains) are fetched.
+    pub allow_domains: Vec<String>,
+    pub deny_domains: Vec<String>,
+    /// Allow requests to loopback, private, link-local and intranet hosts.
+    pub allow_private_networks: bool,
+    pub user_agent: String,
+    /// HTTP(S) proxy URL, e.g. `http://proxy.example.com:8080`.
+    pub proxy: Option<String>,
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        Self {
+            offline: false,
+            auto_fetch: true,
+            allow_domains: Vec::new(),
+            deny_domains: Vec::new(),
+            allow_private_networks: false,
+            user_agent: concat!("gpui-clipboard-manager/", env!("CARGO_PKG_VERSION")).to_string(),
+            proxy: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkEnterAction {
+    #[default]
+    CopyClean,
+    CopyOriginal,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct UrlCleaningSettings {
+    pub enabled: bool,
+    /// Query parameter names to drop; a trailing `*` matches any suffix.
+    pub strip_params: Vec<String>,
+    pub redirectors: Vec<RedirectorRule>,
+}
+
+/// A redirect wrapper whose real destination lives in a query parameter.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RedirectorRule {
+    /// Matches the host itself and any of its subdomains.
+    pub host: String,
+    #[serde(default)]
+    pub path: Option<String>,
+    pub param: String,
+}
+
+impl Default for UrlCleaningSettings {
+    fn default() -> Self {
+        let strip_params = [
+            "utm_*",
+            "fbclid",
+            "gclid",
+            "gclsrc",
+            "dclid",
+            "gbraid",
+            "wbraid",
+            "msclkid",
+            "yclid",
+            "twclid",
+            "igshid",
+            "mc_cid",
+            "mc_eid",
+            "_hsenc",
+            "_hsmi",
+            "mkt_tok",
+            "oly_anon_id",
+            "oly_enc_id",
+            "vero_id",
+            "ref_src",
+        ]
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+
+        let redirectors = [
+            ("google.com", Some("/url"), "q"),
+            ("google.com", Some("/url"), "url"),
+            ("safelinks.protection.outlook.com", None, "url"),
+            ("l.facebook.com", Some("/l.php"), "u"),
+            ("lm.facebook.com", Some("/l.php"), "u"),
+            ("l.instagram.com", None, "u"),
+            ("youtube.com", Some("/redirect"), "q"),
+            ("slack-redir.net", Some("/link"), "url"),
+            ("t.umblr.com", Some("/redirect"), "z"),
+        ]
+        .into_iter()
+        .map(|(host, path, param)| RedirectorRule {
+            host: host.to_string(),
+            path: path.map(str::to_string),
+            param: param.to_string(),
+        })
+        .collect();
+
+        Self {
+            enabled: true,
+            strip_params,
+            redirectors,
+        }
+    }
+}
+
+pub fn settings() -> Settings {
+    let lock = SETTINGS.get_or_init(|| RwLock::new(load_settings()));
+    match lock.read() {
+        Ok(guard) => guard.clone(),
+        Err(_) => Settings::default(),
+    }
+}
+
+/// Applies `change` to the in-memory settings and writes them back to disk.
+pub fn update_settings(change: impl FnOnce(&mut Settings)) {
+    let lock = SETTINGS.get_or_init(|| RwLock::new(load_settings()));
+    let snapshot = match lock.write() {
+        Ok(mut guard) => {
+            change(&mut guard);
+            guard.clone()
+        }
+        Err(_) => return,
+    };
+    if let Err(err) = save_settings(&snapshot) {
+        eprintln!("Failed to save settings: {err}");
+    }
+}
+
+fn save_settings(settings: &Settings) -> anyhow::Result<()> {
+    let path = settings_path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&path, serde_json::to_string_pretty(settings)?)?;
+    Ok(())
+}
+
+fn load_settings() -> Settings {
+    let path = match settings_path() {
+        Ok(path) => path,
+        Err(err) => {
+            eprintln!("Failed to resolve settings path: {err}");
+            return Settings::default();
+        }
+    };
+    let raw = match std::fs::read_to_string(&path) {
+        Ok(raw) => raw,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Settings::default(),
+        Err(err) => {
+            eprintln!("Failed to read settings: {err}");
+            return Settings::default();
+        }
+    };
+    match serde_json::from_str(&raw) {
+        Ok(settings) => settings,
+        Err(err) => {
+            eprintln!("Failed to parse settings: {err}");
+            Settings::default()
+        }
+    }
+}
@@ -0,0 +1,1117 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::anyhow;
+use async_std::channel::{bounded, Receiver, Sender};
+use sea_orm::{DatabaseConnection, EntityTrait, QuerySelect};
+use serde::{Deserialize, Serialize};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::storage::crypto;
+use crate::storage::entity::{Column, Entity, Model};
+use crate::storage::entry_link;
+use crate::storage::history::{
+    insert_clipboard_entry, insert_entry_links, load_entries_page, load_entry_links,
+    load_entry_tags, set_entry_pinned, tag_entry, ClipboardEntryInput, EntryLinkInput,
+};
+use crate::storage::images::{read_image_bytes, save_image_bytes_in};
+use crate::storage::path::{images_dir, thumbnails_dir};
+use crate::storage::search_query::SearchQuery;
+use crate::utils::{hash_bytes, is_sha256_hex};
+
+/// Identifies our archives and JSON exports.
+pub const ARCHIVE_FORMAT: &str = "gpui-clipboard-manager-history";
+
+/// Bumped whenever an older importer couldn't read the new layout.
+pub const ARCHIVE_VERSION: u32 = 1;
+
+/// Header line followed by one entry per line.
+const MANIFEST_NAME: &str = "manifest.ndjson";
+const BLOB_DIR: &str = "blobs";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// A zip of the NDJSON manifest and the image files. The only format
+    /// that keeps images, and the one to import from.
+    Archive,
+    /// The manifest's entries as one JSON document, without images.
+    Json,
+    Csv,
+    Markdown,
+}
+
+impl ExportFormat {
+    /// Picks the format from the file extension; unknown extensions get
+    /// the archive.
+    pub fn from_path(path: &Path) -> Self {
+        let extension = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("json") => Self::Json,
+            Some("csv") => Self::Csv,
+            Some("md" | "markdown") => Self::Markdown,
+            _ => Self::Archive,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct ArchiveHeader {
+    format: String,
+    version: u32,
+    exported_at: i64,
+    entry_count: usize,
+}
+
+impl ArchiveHeader {
+    fn new(entry_count: usize) -> Self {
+        Self {
+            format: ARCHIVE_FORMAT.to_string(),
+            version: ARCHIVE_VERSION,
+            exported_at: now(),
+            entry_count,
+        }
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.format != ARCHIVE_FORMAT {
+            return Err(anyhow!("Not a clipboard history export"));
+        }
+        if self.version == 0 || self.version > ARCHIVE_VERSION {
+            return Err(anyhow!(
+                "The export is version {}, but this app reads up to version {ARCHIVE_VERSION}",
+                self.version
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct JsonExport {
+    #[serde(flatten)]
+    header: ArchiveHeader,
+    entries: Vec<ArchiveEntry>,
+}
+
+/// An entry as written to exports. Text is always plaintext, even when the
+/// history is encrypted.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ArchiveEntry {
+    pub content_type: String,
+    pub content_hash: String,
+    pub content: String,
+    /// Unix seconds.
+    pub created_at: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text_content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ocr_text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ocr_layout: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image_codes: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_paths: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link_clean_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link_title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link_description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link_site_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link_details: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_app_title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_exe_path: Option<String>,
+    /// Position among pinned entries; `None` when not pinned.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pin_order: Option<i32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub links: Vec<ArchiveLink>,
+    /// File name of the image under `blobs/` in the archive.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ArchiveLink {
+    pub kind: String,
+    pub url: String,
+    pub start_offset: i32,
+    pub end_offset: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub site_name: Option<String>,
+}
+
+impl ArchiveEntry {
+    /// Exports carry plain hashes, which the importing history keys for
+    /// itself. A keyed image hash is left empty until the image is read.
+    fn new(entry: Model, links: Vec<entry_link::Model>, tags: Vec<String>) -> Self {
+        let mut archived = Self {
+            content_type: entry.content_type,
+            content_hash: entry.content_hash,
+            content: entry.content,
+            created_at: entry.created_at,
+            text_content: entry.text_content,
+            ocr_text: entry.ocr_text,
+            ocr_layout: entry.ocr_layout,
+            image_codes: entry.image_codes,
+            file_paths: entry.file_paths,
+            link_url: entry.link_url,
+            link_clean_url: entry.link_clean_url,
+            link_title: entry.link_title,
+            link_description: entry.link_description,
+            link_site_name: entry.link_site_name,
+            link_details: entry.link_details,
+            source_app_title: entry.source_app_title,
+            source_exe_path: entry.source_exe_path,
+            pin_order: entry.pin_order,
+            tags,
+            links: links
+                .into_iter()
+                .map(|link| ArchiveLink {
+                    kind: link.kind,
+                    url: link.url,
+                    start_offset: link.start_offset,
+                    end_offset: link.end_offset,
+                    title: link.title,
+                    description: link.description,
+                    site_name: link.site_name,
+                })
+                .collect(),
+            image: None,
+        };
+        match content_hash(&archived) {
+            Some(hash) => archived.content_hash = hash,
+            None if crypto::is_keyed_hash(&archived.content_hash) => archived.content_hash.clear(),
+            None => {}
+        }
+        archived
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExportReport {
+    pub entries: usize,
+    pub images: usize,
+}
+
+impl fmt::Display for ExportReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Exported {} entries and {} images",
+            self.entries, self.images
+        )
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    pub imported: usize,
+    /// Entries already in the history; their pins and tags were merged.
+    pub duplicates: usize,
+    pub images: usize,
+    /// Entries that couldn't be read or stored, with the reason.
+    pub skipped: Vec<String>,
+}
+
+impl fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Imported {} entries and {} images; {} already present, {} skipped",
+            self.imported,
+            self.images,
+            self.duplicates,
+            self.skipped.len()
+        )
+    }
+}
+
+/// Entries loaded and written per step of an export.
+const EXPORT_PAGE_SIZE: u64 = 250;
+
+/// An entry on its way into an export, with the stored image it refers to.
+type ExportRecord = (ArchiveEntry, Option<String>);
+
+/// Writes the entries matching `query` (or all of them), in the format the
+/// file extension of `path` asks for. Entries are loaded a page at a time
+/// and written on a blocking thread; a failed export leaves no file behind.
+pub async fn export_history(
+    db: &DatabaseConnection,
+    query: Option<&SearchQuery>,
+    path: &Path,
+) -> anyhow::Result<ExportReport> {
+    let format = ExportFormat::from_path(path);
+    let (pages, received) = bounded(2);
+    let writer = {
+        let path = path.to_path_buf();
+        async_std::task::spawn_blocking(move || write_export(format, &path, received))
+    };
+    let loaded = send_export_pages(db, query, &pages).await;
+    drop(pages);
+    match (loaded, writer.await) {
+        (Ok(entries), Ok(images)) => Ok(ExportReport { entries, images }),
+        (Err(err), _) | (_, Err(err)) => {
+            let _ = std::fs::remove_file(path);
+            Err(err)
+        }
+    }
+}
+
+/// Hands the matching entries to the writer page by page and returns how
+/// many were sent. Stops early when the writer has given up.
+async fn send_export_pages(
+    db: &DatabaseConnection,
+    query: Option<&SearchQuery>,
+    pages: &Sender<Vec<ExportRecord>>,
+) -> anyhow::Result<usize> {
+    let mut sent = 0;
+    let mut after = None;
+    loop {
+        let page = load_entries_page(db, query, false, after.as_ref(), EXPORT_PAGE_SIZE).await?;
+        let ids: Vec<i32> = page.entries.iter().map(|entry| entry.id).collect();
+        let mut links = load_entry_links(db, &ids).await?;
+        let mut tags = load_entry_tags(db, &ids).await?;
+        let records: Vec<ExportRecord> = page
+            .entries
+            .into_iter()
+            .map(|entry| {
+                let image_path = entry.image_path.clone();
+                let entry_links = links.remove(&entry.id).unwrap_or_default();
+                let entry_tags = tags
+                    .remove(&entry.id)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|tag| tag.name)
+                    .collect();
+                (
+                    ArchiveEntry::new(entry, entry_links, entry_tags),
+                    image_path,
+                )
+            })
+            .collect();
+        sent += records.len();
+        if pages.send(records).await.is_err() {
+            break;
+        }
+        match page.next {
+            Some(next) => after = Some(next),
+            None => break,
+        }
+    }
+    Ok(sent)
+}
+
+/// Writes the pages arriving on `pages` to `path` and returns the number of
+/// image files written.
+fn write_export(
+    format: ExportFormat,
+    path: &Path,
+    pages: Receiver<Vec<ExportRecord>>,
+) -> anyhow::Result<usize> {
+    let file = BufWriter::new(File::create(path)?);
+    let records = std::iter::from_fn(|| pages.recv_blocking().ok()).flatten();
+    match format {
+        ExportFormat::Archive => write_archive(file, &path.with_extension("manifest.tmp"), records),
+        ExportFormat::Json => write_json(file, records).map(|()| 0),
+        ExportFormat::Csv => write_csv(file, records).map(|()| 0),
+        ExportFormat::Markdown => write_markdown(file, records).map(|()| 0),
+    }
+}
+
+/// Returns the number of image files written. The manifest is collected in
+/// `manifest_path` until the entry count for its header is known.
+fn write_archive(
+    file: impl Write + Seek,
+    manifest_path: &Path,
+    records: impl Iterator<Item = ExportRecord>,
+) -> anyhow::Result<usize> {
+    let written = write_archive_parts(file, manifest_path, records);
+    let _ = std::fs::remove_file(manifest_path);
+    written
+}
+
+fn write_archive_parts(
+    file: impl Write + Seek,
+    manifest_path: &Path,
+    records: impl Iterator<Item = ExportRecord>,
+) -> anyhow::Result<usize> {
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    // Bitmaps shrink nearly as well at the fastest level, at a fraction of
+    // the time.
+    let image_options = options.compression_level(Some(1));
+
+    // Images are shared between entries; store each once, named by the
+    // plain hash of its bytes.
+    let mut blobs: HashMap<String, String> = HashMap::new();
+    let mut manifest = BufWriter::new(File::create(manifest_path)?);
+    let mut count = 0;
+    for (mut entry, image_path) in records {
+        count += 1;
+        if let Some(image_path) = image_path {
+            let hash = match blobs.get(&image_path) {
+                Some(hash) => Some(hash.clone()),
+                None => match read_image_bytes(Path::new(&image_path)) {
+                    Ok(bytes) => {
+                        let hash = hash_bytes(&bytes);
+                        zip.start_file(format!("{BLOB_DIR}/{hash}.bmp"), image_options)?;
+                        zip.write_all(&bytes)?;
+                        blobs.insert(image_path, hash.clone());
+                        Some(hash)
+                    }
+                    Err(err) => {
+                        eprintln!("Skipping image {image_path}: {err}");
+                        None
+                    }
+                },
+            };
+            if let Some(hash) = hash {
+                entry.image = Some(format!("{hash}.bmp"));
+                entry.content_hash = hash;
+            }
+        }
+        serde_json::to_writer(&mut manifest, &entry)?;
+        manifest.write_all(b"\n")?;
+    }
+    manifest.flush()?;
+    drop(manifest);
+
+    zip.start_file(MANIFEST_NAME, options)?;
+    serde_json::to_writer(&mut zip, &ArchiveHeader::new(count))?;
+    zip.write_all(b"\n")?;
+    std::io::copy(&mut File::open(manifest_path)?, &mut zip)?;
+    zip.finish()?.flush()?;
+    let images: HashSet<&String> = blobs.values().collect();
+    Ok(images.len())
+}
+
+/// Streams the JSON export. The header fields follow the entries, since the
+/// entry count is only known at the end.
+fn write_json(
+    mut file: impl Write,
+    records: impl Iterator<Item = ExportRecord>,
+) -> anyhow::Result<()> {
+    file.write_all(b"{\"entries\":[")?;
+    let mut count = 0;
+    for (mut entry, image_path) in records {
+        if entry.content_hash.is_empty() {
+            if let Some(bytes) = image_path.and_then(|path| read_image_bytes(Path::new(&path)).ok())
+            {
+                entry.content_hash = hash_bytes(&bytes);
+            }
+        }
+        if count > 0 {
+            file.write_all(b",")?;
+        }
+        file.write_all(b"\n")?;
+        serde_json::to_writer(&mut file, &entry)?;
+        count += 1;
+    }
+    let header = serde_json::to_string(&ArchiveHeader::new(count))?;
+    // Splice the header's fields into the enclosing object.
+    writeln!(file, "\n],{}", &header[1..])?;
+    file.flush()?;
+    Ok(())
+}
+
+fn write_csv(
+    mut file: impl Write,
+    records: impl Iterator<Item = ExportRecord>,
+) -> anyhow::Result<()> {
+    writeln!(
+        file,
+        "created_at,content_type,content,source_app,pinned,tags"
+    )?;
+    for (entry, _) in records {
+        let fields = [
+            format_timestamp(entry.created_at),
+            entry.content_type.clone(),
+            entry.content.clone(),
+            entry.source_app_title.clone().unwrap_or_default(),
+            entry.pin_order.is_some().to_string(),
+            entry.tags.join(";"),
+        ];
+        let row: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+        writeln!(file, "{}", row.join(","))?;
+    }
+    file.flush()?;
+    Ok(())
+}
+
+/// Quotes a field when it contains a delimiter, quote or line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn write_markdown(
+    mut file: impl Write,
+    records: impl Iterator<Item = ExportRecord>,
+) -> anyhow::Result<()> {
+    writeln!(file, "# Clipboard history")?;
+    for (entry, _) in records {
+        writeln!(file)?;
+        let mut heading = format!(
+            "## {} · {}",
+            format_timestamp(entry.created_at),
+            entry.content_type
+        );
+        if entry.pin_order.is_some() {
+            heading.push_str(" · pinned");
+        }
+        writeln!(file, "{heading}")?;
+        if let Some(app) = entry.source_app_title.as_deref() {
+            writeln!(file, "\nFrom: {app}")?;
+        }
+        if !entry.tags.is_empty() {
+            writeln!(file, "\nTags: {}", entry.tags.join(", "))?;
+        }
+        let text = match entry.content_type.as_str() {
+            "image" => entry.ocr_text.as_deref().unwrap_or("(image)"),
+            _ => entry.text_content.as_deref().unwrap_or(&entry.content),
+        };
+        // A fence longer than any backtick run in the text can't be closed
+        // early by it.
+        let longest_run = text.split(|ch| ch != '`').map(str::len).max().unwrap_or(0);
+        let fence = "`".repeat(longest_run.max(2) + 1);
+        writeln!(file, "\n{fence}\n{text}\n{fence}")?;
+    }
+    file.flush()?;
+    Ok(())
+}
+
+/// Merges an archive (`.zip`) or JSON export into the history. Entries
+/// whose content is already stored are not duplicated, but gain the
+/// exported pins and tags. Importing the same file twice changes nothing.
+pub async fn import_history(db: &DatabaseConnection, path: &Path) -> anyhow::Result<ImportReport> {
+    import_history_in(db, path, &images_dir()?, &thumbnails_dir()?).await
+}
+
+/// `import_history` storing images in the given images and thumbnails
+/// folders.
+async fn import_history_in(
+    db: &DatabaseConnection,
+    path: &Path,
+    images_dir: &Path,
+    thumbnails_dir: &Path,
+) -> anyhow::Result<ImportReport> {
+    let (entries, archive) = {
+        let path = path.to_path_buf();
+        async_std::task::spawn_blocking(move || read_export(&path)).await?
+    };
+    let archive = archive.map(|zip| Arc::new(Mutex::new(zip)));
+
+    let mut existing: HashMap<String, (i32, bool)> = Entity::find()
+        .select_only()
+        .column(Column::ContentHash)
+        .column(Column::Id)
+        .column(Column::PinOrder)
+        .into_tuple::<(String, i32, Option<i32>)>()
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|(hash, id, pin_order)| (hash, (id, pin_order.is_some())))
+        .collect();
+
+    let mut report = ImportReport::default();
+    let mut pins = Vec::new();
+    for entry in entries {
+        if entry.content_type.is_empty() {
+            report.skipped.push("An entry without a type".to_string());
+            continue;
+        }
+        let image = match (entry.image.clone(), archive.clone()) {
+            (Some(name), Some(archive)) => {
+                match async_std::task::spawn_blocking(move || read_blob(&archive, &name)).await {
+                    Ok(bytes) => Some(bytes),
+                    Err(err) => {
+                        report
+                            .skipped
+                            .push(format!("Entry {}: {err}", entry.content_hash));
+                        continue;
+                    }
+                }
+            }
+            _ => None,
+        };
+        let Some(plain_hash) = import_hash(&entry, image.as_deref()) else {
+            report
+                .skipped
+                .push("An entry without a usable content hash".to_string());
+            continue;
+        };
+        // Stored hashes are keyed while encryption is on.
+        let content_hash = crypto::protect_hash(&plain_hash);
+        let (id, pinned) = match existing.get(&content_hash) {
+            Some(&found) => {
+                report.duplicates += 1;
+                found
+            }
+            None => {
+                let has_image = image.is_some();
+                let imported =
+                    import_entry(db, &entry, &content_hash, image, images_dir, thumbnails_dir)
+                        .await;
+                match imported {
+                    Ok(id) => {
+                        report.imported += 1;
+                        report.images += usize::from(has_image);
+                        existing.insert(content_hash.clone(), (id, false));
+                        (id, false)
+                    }
+                    Err(err) => {
+                        report.skipped.push(format!("Entry {plain_hash}: {err}"));
+                        continue;
+                    }
+                }
+            }
+        };
+        for tag in &entry.tags {
+            if let Err(err) = tag_entry(db, id, tag).await {
+                report.skipped.push(format!("Tag `{tag}`: {err}"));
+            }
+        }
+        if let (Some(order), false) = (entry.pin_order, pinned) {
+            pins.push((order, id));
+            existing.insert(content_hash, (id, true));
+        }
+    }
+
+    // Each newly pinned entry goes on top, so pin in reverse to keep the
+    // exported order above the existing pins.
+    pins.sort();
+    for (_, id) in pins.into_iter().rev() {
+        set_entry_pinned(db, id, true).await?;
+    }
+    Ok(report)
+}
+
+/// Reads the entries of an archive or JSON export, keeping the archive open
+/// for its images.
+fn read_export(path: &Path) -> anyhow::Result<(Vec<ArchiveEntry>, Option<ZipArchive<File>>)> {
+    match ExportFormat::from_path(path) {
+        ExportFormat::Archive => {
+            let mut zip = ZipArchive::new(File::open(path)?)?;
+            let entries = read_manifest(BufReader::new(zip.by_name(MANIFEST_NAME)?))?;
+            Ok((entries, Some(zip)))
+        }
+        ExportFormat::Json => {
+            let export: JsonExport = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+            export.header.validate()?;
+            Ok((export.entries, None))
+        }
+        ExportFormat::Csv | ExportFormat::Markdown => Err(anyhow!(
+            "CSV and Markdown exports can't be imported; use an archive or JSON export"
+        )),
+    }
+}
+
+fn read_manifest(reader: impl BufRead) -> anyhow::Result<Vec<ArchiveEntry>> {
+    let mut lines = reader.lines();
+    let header = lines
+        .next()
+        .ok_or_else(|| anyhow!("The archive manifest is empty"))??;
+    let header: ArchiveHeader = serde_json::from_str(&header)
+        .map_err(|err| anyhow!("Can't read the archive header: {err}"))?;
+    header.validate()?;
+
+    let mut entries = Vec::with_capacity(header.entry_count.min(EXPORT_PAGE_SIZE as usize));
+    for (index, line) in lines.enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry = serde_json::from_str(&line)
+            .map_err(|err| anyhow!("Manifest line {}: {err}", index + 2))?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+fn read_blob(archive: &Mutex<ZipArchive<File>>, name: &str) -> anyhow::Result<Vec<u8>> {
+    let mut archive = archive.lock().unwrap_or_else(PoisonError::into_inner);
+    let mut bytes = Vec::new();
+    archive
+        .by_name(&format!("{BLOB_DIR}/{name}"))?
+        .read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// The plain hash to store an imported entry under, computed from its image
+/// or content the way capture does. The file's own hash is only used for
+/// images that weren't included, and only when it is a well-formed SHA-256,
+/// since it ends up in image file names.
+fn import_hash(entry: &ArchiveEntry, image: Option<&[u8]>) -> Option<String> {
+    match image {
+        Some(bytes) => Some(hash_bytes(bytes)),
+        None => content_hash(entry)
+            .or_else(|| Some(entry.content_hash.clone()).filter(|hash| is_sha256_hex(hash))),
+    }
+}
+
+/// The plain hash of an entry without an image: over the path list for
+/// files and over the content otherwise.
+fn content_hash(entry: &ArchiveEntry) -> Option<String> {
+    match (entry.content_type.as_str(), entry.file_paths.as_deref()) {
+        ("image", _) | ("files", None) => None,
+        ("files", Some(paths)) => Some(hash_bytes(paths.as_bytes())),
+        _ => Some(hash_bytes(entry.content.as_bytes())),
+    }
+}
+
+async fn import_entry(
+    db: &DatabaseConnection,
+    entry: &ArchiveEntry,
+    content_hash: &str,
+    image: Option<Vec<u8>>,
+    images_dir: &Path,
+    thumbnails_dir: &Path,
+) -> anyhow::Result<i32> {
+    let image_path = match image {
+        Some(bytes) => {
+            let path = save_image_bytes_in(images_dir, thumbnails_dir, content_hash, bytes).await?;
+            Some(path.to_string_lossy().into_owned())
+        }
+        None => None,
+    };
+    let id = insert_clipboard_entry(
+        db,
+        ClipboardEntryInput {
+            content_type: &entry.content_type,
+            content_hash,
+            content: &entry.content,
+            text_content: entry.text_content.as_deref(),
+            ocr_text: entry.ocr_text.as_deref(),
+            ocr_layout: entry.ocr_layout.as_deref(),
+            ocr_attempt: None,
+            image_path: image_path.as_deref(),
+            image_codes: entry.image_codes.as_deref(),
+            file_paths: entry.file_paths.as_deref(),
+            link_url: entry.link_url.as_deref(),
+            link_clean_url: entry.link_clean_url.as_deref(),
+            link_title: entry.link_title.as_deref(),
+            link_description: entry.link_description.as_deref(),
+            link_site_name: entry.link_site_name.as_deref(),
+            link_details: entry.link_details.as_deref(),
+            source_app_title: entry.source_app_title.as_deref(),
+            source_exe_path: entry.source_exe_path.as_deref(),
+            created_at: Some(entry.created_at),
+        },
+    )
+    .await?;
+    let links: Vec<EntryLinkInput> = entry
+        .links
+        .iter()
+        .map(|link| EntryLinkInput {
+            kind: &link.kind,
+            url: &link.url,
+            start_offset: link.start_offset,
+            end_offset: link.end_offset,
+            title: link.title.as_deref(),
+            description: link.description.as_deref(),
+            site_name: link.site_name.as_deref(),
+        })
+        .collect();
+    insert_entry_links(db, id, &links).await?;
+    Ok(id)
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
+
+/// `YYYY-MM-DD HH:MM:SS` in UTC.
+fn format_timestamp(timestamp: i64) -> String {
+    const DAY: i64 = 24 * 60 * 60;
+    let (year, month, day) = civil_from_days(timestamp.div_euclid(DAY));
+    let seconds = timestamp.rem_euclid(DAY);
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// Proleptic Gregorian date for days since 1970-01-01 (Howard Hinnant's
+/// `civil_from_days`).
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::path::PathBuf;
+
+    use aes_gcm::{Aes256Gcm, Key};
+    use image::{ImageOutputFormat, Rgb, RgbImage};
+    use sea_orm::{ColumnTrait, QueryFilter, QueryOrder};
+
+    use super::*;
+    use crate::storage::history::open_db;
+    use crate::test_support::{lock_storage, temp_dir};
+
+    struct History {
+        dir: PathBuf,
+        db: DatabaseConnection,
+    }
+
+    impl History {
+        async fn open(dir: &Path, name: &str) -> Self {
+            let dir = dir.join(name);
+            std::fs::create_dir_all(&dir).unwrap();
+            let db = open_db(&dir.join("history.sqlite"), false).await.unwrap();
+            Self { dir, db }
+        }
+
+        async fn import(&self, path: &Path) -> anyhow::Result<ImportReport> {
+            import_history_in(
+                &self.db,
+                path,
+                &self.dir.join("images"),
+                &self.dir.join("thumbnails"),
+            )
+            .await
+        }
+
+        async fn insert_text(&self, text: &str, created_at: i64) -> i32 {
+            insert_clipboard_entry(
+                &self.db,
+                ClipboardEntryInput {
+                    content_type: "text",
+                    content_hash: &crypto::content_hash(text.as_bytes()),
+                    content: text,
+                    created_at: Some(created_at),
+                    ..ClipboardEntryInput::default()
+                },
+            )
+            .await
+            .unwrap()
+        }
+
+        async fn insert_image(&self, bytes: &[u8], created_at: i64) -> i32 {
+            let hash = crypto::content_hash(bytes);
+            let path = save_image_bytes_in(
+                &self.dir.join("images"),
+                &self.dir.join("thumbnails"),
+                &hash,
+                bytes.to_vec(),
+            )
+            .await
+            .unwrap();
+            insert_clipboard_entry(
+                &self.db,
+                ClipboardEntryInput {
+                    content_type: "image",
+                    content_hash: &hash,
+                    content: "Image",
+                    image_path: Some(&path.to_string_lossy()),
+                    created_at: Some(created_at),
+                    ..ClipboardEntryInput::default()
+                },
+            )
+            .await
+            .unwrap()
+        }
+
+        /// `(content, created_at, tags)` oldest first, then the pinned
+        /// contents from the top.
+        async fn snapshot(&self) -> (Vec<(String, i64, Vec<String>)>, Vec<String>) {
+            let entries = Entity::find()
+                .order_by_asc(Column::CreatedAt)
+                .all(&self.db)
+                .await
+                .unwrap();
+            let ids: Vec<i32> = entries.iter().map(|entry| entry.id).collect();
+            let mut tags = load_entry_tags(&self.db, &ids).await.unwrap();
+            let mut pinned: Vec<(i32, String)> = entries
+                .iter()
+                .filter_map(|entry| Some((entry.pin_order?, entry.content.clone())))
+                .collect();
+            pinned.sort();
+            let rows = entries
+                .into_iter()
+                .map(|entry| {
+                    let mut names: Vec<String> = tags
+                        .remove(&entry.id)
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|tag| tag.name)
+                        .collect();
+                    names.sort();
+                    (entry.content, entry.created_at, names)
+                })
+                .collect();
+            let pinned = pinned.into_iter().map(|(_, content)| content).collect();
+            (rows, pinned)
+        }
+    }
+
+    fn bitmap(shade: u8) -> Vec<u8> {
+        let mut bytes = Cursor::new(Vec::new());
+        RgbImage::from_pixel(2, 2, Rgb([shade; 3]))
+            .write_to(&mut bytes, ImageOutputFormat::Bmp)
+            .unwrap();
+        bytes.into_inner()
+    }
+
+    /// Two texts, one with a tag and a link, and an image; both the tagged
+    /// text and the image are pinned.
+    async fn fill(history: &History) {
+        let tagged = history.insert_text("see https://example.com", 100).await;
+        tag_entry(&history.db, tagged, "work").await.unwrap();
+        insert_entry_links(
+            &history.db,
+            tagged,
+            &[EntryLinkInput {
+                kind: "url",
+                url: "https://example.com",
+                start_offset: 4,
+                end_offset: 23,
+                title: Some("Example"),
+                description: None,
+                site_name: None,
+            }],
+        )
+        .await
+        .unwrap();
+        history.insert_text("plain note", 200).await;
+        let image = history.insert_image(&bitmap(40), 300).await;
+        set_entry_pinned(&history.db, image, true).await.unwrap();
+        set_entry_pinned(&history.db, tagged, true).await.unwrap();
+    }
+
+    #[test]
+    fn exports_round_trip_through_import() {
+        let _storage = lock_storage();
+        async_std::task::block_on(async {
+            let dir = temp_dir("archive-round-trip");
+            let source = History::open(&dir, "source").await;
+            fill(&source).await;
+
+            for (name, images) in [("history.zip", 1), ("history.json", 0)] {
+                let path = dir.join(name);
+                let exported = export_history(&source.db, None, &path).await.unwrap();
+                assert_eq!(exported, ExportReport { entries: 3, images });
+
+                let target = History::open(&dir, &format!("target-{name}")).await;
+                let report = target.import(&path).await.unwrap();
+                assert_eq!(report.imported, 3, "{name}: {:?}", report.skipped);
+                assert_eq!(report.images, images);
+                assert!(report.skipped.is_empty(), "{name}: {:?}", report.skipped);
+                assert_eq!(target.snapshot().await, source.snapshot().await, "{name}");
+
+                let tagged = Entity::find()
+                    .filter(Column::Content.eq("see https://example.com"))
+                    .one(&target.db)
+                    .await
+                    .unwrap()
+                    .unwrap();
+                let links = load_entry_links(&target.db, &[tagged.id]).await.unwrap();
+                let link = &links[&tagged.id][0];
+                assert_eq!(
+                    (link.url.as_str(), link.title.as_deref()),
+                    ("https://example.com", Some("Example"))
+                );
+
+                let image = Entity::find()
+                    .filter(Column::ContentType.eq("image"))
+                    .one(&target.db)
+                    .await
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(image.content_hash, hash_bytes(&bitmap(40)));
+                match image.image_path {
+                    Some(image_path) => {
+                        assert!(Path::new(&image_path).starts_with(target.dir.join("images")));
+                        assert_eq!(
+                            read_image_bytes(Path::new(&image_path)).unwrap(),
+                            bitmap(40)
+                        );
+                    }
+                    None => assert_eq!(images, 0),
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn imports_merge_into_an_existing_history() {
+        let _storage = lock_storage();
+        async_std::task::block_on(async {
+            let dir = temp_dir("archive-merge");
+            let source = History::open(&dir, "source").await;
+            fill(&source).await;
+            let path = dir.join("history.zip");
+            export_history(&source.db, None, &path).await.unwrap();
+
+            let target = History::open(&dir, "target").await;
+            target.insert_text("plain note", 50).await;
+            target.insert_image(&bitmap(40), 60).await;
+            target.insert_text("only here", 70).await;
+
+            let report = target.import(&path).await.unwrap();
+            assert_eq!((report.imported, report.duplicates), (1, 2));
+            assert_eq!(report.images, 0);
+            let (rows, pinned) = target.snapshot().await;
+            assert_eq!(rows.len(), 4);
+            // The image was already stored, but still picks up its pin.
+            assert_eq!(pinned, ["see https://example.com", "Image"]);
+
+            let again = target.import(&path).await.unwrap();
+            assert_eq!((again.imported, again.duplicates), (0, 3));
+            assert_eq!(target.snapshot().await, (rows, pinned));
+        });
+    }
+
+    #[test]
+    fn exports_plain_hashes_that_import_keys_again() {
+        let _storage = lock_storage();
+        async_std::task::block_on(async {
+            let dir = temp_dir("archive-encrypted");
+            crypto::install(Key::<Aes256Gcm>::from_slice(&[3; 32]), true).unwrap();
+            let history = History::open(&dir, "history").await;
+            history.insert_text("secret note", 100).await;
+            history.insert_image(&bitmap(90), 200).await;
+
+            let path = dir.join("history.zip");
+            export_history(&history.db, None, &path).await.unwrap();
+            let (entries, _) = read_export(&path).unwrap();
+            let mut hashes: Vec<&str> = entries
+                .iter()
+                .map(|entry| entry.content_hash.as_str())
+                .collect();
+            hashes.sort();
+            let mut expected = [hash_bytes(b"secret note"), hash_bytes(&bitmap(90))];
+            expected.sort();
+            assert_eq!(hashes, expected);
+
+            let report = history.import(&path).await.unwrap();
+            assert_eq!((report.imported, report.duplicates), (0, 2));
+        });
+    }
+
+    #[test]
+    fn imports_ignore_the_hashes_in_the_file() {
+        let _storage = lock_storage();
+        async_std::task::block_on(async {
+            let dir = temp_dir("archive-hashes");
+            let history = History::open(&dir, "history").await;
+            let path = dir.join("crafted.json");
+            let entry = |content_type: &str, content_hash: &str| ArchiveEntry {
+                content_type: content_type.to_string(),
+                content_hash: content_hash.to_string(),
+                content: "crafted".to_string(),
+                ..ArchiveEntry::default()
+            };
+            let export = JsonExport {
+                header: ArchiveHeader::new(3),
+                entries: vec![
+                    entry("text", "../../outside"),
+                    entry("image", "../../outside"),
+                    entry("image", &hash_bytes(b"missing image")),
+                ],
+            };
+            std::fs::write(&path, serde_json::to_vec(&export).unwrap()).unwrap();
+
+            let report = history.import(&path).await.unwrap();
+            assert_eq!(report.imported, 2);
+            assert_eq!(
+                report.skipped,
+                ["An entry without a usable content hash".to_string()]
+            );
+            let mut hashes: Vec<String> = Entity::find()
+                .all(&history.db)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|entry| entry.content_hash)
+                .collect();
+            hashes.sort();
+            let mut expected = [hash_bytes(b"crafted"), hash_bytes(b"missing image")];
+            expected.sort();
+            assert_eq!(hashes, expected);
+        });
+    }
+
+    #[test]
+    fn imports_reject_newer_versions() {
+        let _storage = lock_storage();
+        async_std::task::block_on(async {
+            let dir = temp_dir("archive-version");
+            let history = History::open(&dir, "history").await;
+            let header = ArchiveHeader {
+                version: ARCHIVE_VERSION + 1,
+                ..ArchiveHeader::new(0)
+            };
+
+            let json = dir.join("newer.json");
+            let export = JsonExport {
+                header: header.clone(),
+                entries: Vec::new(),
+            };
+            std::fs::write(&json, serde_json::to_vec(&export).unwrap()).unwrap();
+
+            let archive = dir.join("newer.zip");
+            let mut zip = ZipWriter::new(File::create(&archive).unwrap());
+            zip.start_file(MANIFEST_NAME, SimpleFileOptions::default())
+                .unwrap();
+            serde_json::to_writer(&mut zip, &header).unwrap();
+            zip.finish().unwrap();
+
+            for path in [json, archive] {
+                let err = history.import(&path).await.unwrap_err();
+                assert!(
+                    err.to_string().contains("is version 2"),
+                    "{}: {err}",
+                    path.display()
+                );
+            }
+        });
+    }
+}
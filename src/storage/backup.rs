@@ -0,0 +1,504 @@
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::anyhow;
+use sea_orm::{ConnectionTrait, Database, DatabaseConnection, Statement};
+
+use crate::settings::BackupSettings;
+use crate::storage::integrity::database_errors;
+use crate::storage::path::{backups_dir, data_dir, default_db_path, quarantine_dir, DB_FILE};
+
+const SECS_PER_DAY: i64 = 24 * 60 * 60;
+const SECS_PER_HOUR: i64 = 60 * 60;
+
+const BACKUP_PREFIX: &str = "clipboard_history-";
+const BACKUP_EXTENSION: &str = "db";
+/// A validated backup next to the database, swapped in on the next start.
+const PENDING_RESTORE: &str = "clipboard_history.db.restore";
+
+/// A database snapshot in the backups folder.
+#[derive(Clone, Debug)]
+pub struct Backup {
+    pub path: PathBuf,
+    /// Unix seconds, taken from the file name.
+    pub created_at: i64,
+    pub bytes: u64,
+}
+
+/// The database failed to open or its check found problems at startup.
+#[derive(Clone, Debug)]
+pub struct CorruptDatabase {
+    pub reason: String,
+}
+
+impl fmt::Display for CorruptDatabase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The clipboard database is damaged: {}", self.reason)
+    }
+}
+
+impl std::error::Error for CorruptDatabase {}
+
+/// Result of `recover_corrupt_database`.
+#[derive(Clone, Debug)]
+pub struct RecoveryReport {
+    pub quarantined: PathBuf,
+    /// `None` when no backup passed validation; the history starts empty.
+    pub restored: Option<Backup>,
+    /// Entries of the restored backup whose image file is gone.
+    pub missing_images: usize,
+}
+
+impl fmt::Display for RecoveryReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Moved the damaged database to {}",
+            self.quarantined.display()
+        )?;
+        match &self.restored {
+            Some(backup) => write!(f, " and restored {}", backup.path.display())?,
+            None => write!(f, "; no usable backup, starting a new history")?,
+        }
+        write_missing_images(f, self.missing_images)
+    }
+}
+
+/// Result of `stage_restore`.
+#[derive(Clone, Debug)]
+pub struct RestoreReport {
+    pub backup: PathBuf,
+    /// Entries of the backup whose image file is gone. Backups hold the
+    /// database only, so images deleted since can't be brought back.
+    pub missing_images: usize,
+}
+
+impl fmt::Display for RestoreReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Restoring {} on restart", self.backup.display())?;
+        write_missing_images(f, self.missing_images)
+    }
+}
+
+fn write_missing_images(f: &mut fmt::Formatter<'_>, missing: usize) -> fmt::Result {
+    match missing {
+        0 => Ok(()),
+        1 => write!(f, "; 1 entry refers to an image that is no longer on disk"),
+        missing => write!(
+            f,
+            "; {missing} entries refer to images that are no longer on disk"
+        ),
+    }
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
+
+/// `path` with `suffix` appended to its file name.
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Snapshots the database into the backups folder. `VACUUM INTO` gives a
+/// consistent copy while capture keeps writing.
+pub async fn create_backup(db: &DatabaseConnection) -> anyhow::Result<Backup> {
+    create_backup_in(db, &backups_dir()?).await
+}
+
+async fn create_backup_in(db: &DatabaseConnection, dir: &Path) -> anyhow::Result<Backup> {
+    fs::create_dir_all(dir)?;
+    let created_at = now();
+    let path = dir.join(format!("{BACKUP_PREFIX}{created_at}.{BACKUP_EXTENSION}"));
+    // Written under a temporary name so a half-written file is never
+    // listed as a backup.
+    let partial = with_suffix(&path, ".tmp");
+    let _ = fs::remove_file(&partial);
+    let quoted = partial.to_string_lossy().replace('\'', "''");
+    db.execute_unprepared(&format!("VACUUM INTO '{quoted}'"))
+        .await?;
+    fs::rename(&partial, &path)?;
+    let bytes = fs::metadata(&path).map_or(0, |metadata| metadata.len());
+    Ok(Backup {
+        path,
+        created_at,
+        bytes,
+    })
+}
+
+/// Takes a backup when the newest one is older than `interval_hours`, then
+/// drops the backups the rotation no longer keeps.
+pub async fn run_scheduled_backup(
+    db: &DatabaseConnection,
+    policy: &BackupSettings,
+) -> anyhow::Result<Option<Backup>> {
+    if !policy.enabled {
+        return Ok(None);
+    }
+    let interval = policy.interval_hours.max(1) as i64 * SECS_PER_HOUR;
+    if list_backups()?
+        .first()
+        .is_some_and(|latest| now() - latest.created_at < interval)
+    {
+        return Ok(None);
+    }
+    let backup = create_backup(db).await?;
+    rotate_backups(policy)?;
+    Ok(Some(backup))
+}
+
+/// Every backup, newest first.
+pub fn list_backups() -> anyhow::Result<Vec<Backup>> {
+    list_backups_in(&backups_dir()?)
+}
+
+fn list_backups_in(dir: &Path) -> anyhow::Result<Vec<Backup>> {
+    let files = match fs::read_dir(dir) {
+        Ok(files) => files,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err.into()),
+    };
+    let mut backups = Vec::new();
+    for file in files {
+        let file = file?;
+        let path = file.path();
+        if path.extension().is_none_or(|ext| ext != BACKUP_EXTENSION) {
+            continue;
+        }
+        let Some(created_at) = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.strip_prefix(BACKUP_PREFIX))
+            .and_then(|secs| secs.parse().ok())
+        else {
+            continue;
+        };
+        let bytes = file.metadata().map_or(0, |metadata| metadata.len());
+        backups.push(Backup {
+            path,
+            created_at,
+            bytes,
+        });
+    }
+    backups.sort_by_key(|backup| Reverse(backup.created_at));
+    Ok(backups)
+}
+
+/// Keeps the newest backup of each of the last `keep_daily` days and
+/// `keep_weekly` weeks that have one, plus the newest overall.
+fn rotate_backups(policy: &BackupSettings) -> anyhow::Result<usize> {
+    let backups = list_backups()?;
+    let mut days = HashSet::new();
+    let mut weeks = HashSet::new();
+    let mut removed = 0;
+    for (index, backup) in backups.iter().enumerate() {
+        let day = backup.created_at.div_euclid(SECS_PER_DAY);
+        // The epoch fell on a Thursday; shift so weeks start on Monday.
+        let week = (day + 3).div_euclid(7);
+        let mut keep = index == 0;
+        if days.len() < policy.keep_daily && days.insert(day) {
+            keep = true;
+        }
+        if weeks.len() < policy.keep_weekly && weeks.insert(week) {
+            keep = true;
+        }
+        if keep {
+            continue;
+        }
+        match fs::remove_file(&backup.path) {
+            Ok(()) => removed += 1,
+            Err(err) => eprintln!("Failed to remove {}: {err}", backup.path.display()),
+        }
+    }
+    Ok(removed)
+}
+
+/// Deletes the backups in `dir` that hold unencrypted rows and takes a
+/// fresh backup in their place, so turning encryption on leaves no
+/// plaintext copy of the history behind. Returns how many were deleted.
+pub async fn replace_plaintext_backups(
+    db: &DatabaseConnection,
+    dir: &Path,
+) -> anyhow::Result<usize> {
+    let mut removed = 0;
+    for backup in list_backups_in(dir)? {
+        // One that can't be read is no use for a restore either.
+        let plaintext = is_plaintext_backup(&backup.path)
+            .await
+            .unwrap_or_else(|err| {
+                eprintln!("Failed to read backup {}: {err}", backup.path.display());
+                true
+            });
+        if plaintext {
+            fs::remove_file(&backup.path)?;
+            removed += 1;
+        }
+    }
+    if removed > 0 {
+        create_backup_in(db, dir).await?;
+        eprintln!("Replaced {removed} backups taken before encryption was turned on");
+    }
+    Ok(removed)
+}
+
+/// Whether a backup holds rows stored unencrypted. Backups from before
+/// rows were flagged count as plaintext.
+async fn is_plaintext_backup(path: &Path) -> anyhow::Result<bool> {
+    let raw = path.to_string_lossy().replace('\\', "/");
+    let db = Database::connect(format!("sqlite:///{raw}?mode=ro")).await?;
+    let row = db
+        .query_one(Statement::from_string(
+            db.get_database_backend(),
+            "SELECT (SELECT COUNT(*) FROM clipboard_entries WHERE encrypted = 0) \
+             + (SELECT COUNT(*) FROM entry_links WHERE encrypted = 0)",
+        ))
+        .await;
+    db.close().await?;
+    Ok(match row {
+        Ok(row) => row.map_or(0, |row| row.try_get_by_index::<i64>(0).unwrap_or(1)) > 0,
+        Err(_) => true,
+    })
+}
+
+/// Entries of the history at `path` whose image file no longer exists.
+async fn count_missing_images(path: &Path) -> anyhow::Result<usize> {
+    let raw = path.to_string_lossy().replace('\\', "/");
+    let db = Database::connect(format!("sqlite:///{raw}?mode=ro")).await?;
+    let rows = db
+        .query_all(Statement::from_string(
+            db.get_database_backend(),
+            "SELECT image_path FROM clipboard_entries WHERE image_path IS NOT NULL",
+        ))
+        .await;
+    db.close().await?;
+    let mut missing = 0;
+    for row in rows? {
+        let image_path: String = row.try_get_by_index(0)?;
+        if !Path::new(&image_path).is_file() {
+            missing += 1;
+        }
+    }
+    Ok(missing)
+}
+
+/// Opens `path` read-only and checks that it is an intact clipboard
+/// history.
+pub async fn validate_backup(path: &Path) -> anyhow::Result<()> {
+    let raw = path.to_string_lossy().replace('\\', "/");
+    let db = Database::connect(format!("sqlite:///{raw}?mode=ro")).await?;
+    let result = validate_history(&db).await;
+    db.close().await?;
+    result
+}
+
+async fn validate_history(db: &DatabaseConnection) -> anyhow::Result<()> {
+    let errors = database_errors(db).await?;
+    if let Some(error) = errors.first() {
+        return Err(anyhow!("The backup failed its check: {error}"));
+    }
+    let backend = db.get_database_backend();
+    db.query_one(Statement::from_string(
+        backend,
+        "SELECT COUNT(*) FROM clipboard_entries",
+    ))
+    .await
+    .map_err(|err| anyhow!("Not a clipboard history backup: {err}"))?;
+    Ok(())
+}
+
+/// Validates `backup` and puts a copy next to the database, to replace it
+/// on the next start. The running app keeps the current database open, so
+/// callers stop writing and restart.
+pub async fn stage_restore(backup: &Path) -> anyhow::Result<RestoreReport> {
+    validate_backup(backup).await?;
+    let missing_images = count_missing_images(backup).await?;
+    copy_into_place(backup, &data_dir()?.join(PENDING_RESTORE))?;
+    Ok(RestoreReport {
+        backup: backup.to_path_buf(),
+        missing_images,
+    })
+}
+
+/// Copies `from` to `to` through a temporary file, so `to` either keeps its
+/// old contents or holds the whole copy.
+fn copy_into_place(from: &Path, to: &Path) -> anyhow::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let partial = with_suffix(to, ".tmp");
+    fs::copy(from, &partial)?;
+    fs::File::open(&partial)?.sync_all()?;
+    fs::rename(&partial, to)?;
+    Ok(())
+}
+
+/// Swaps in a backup staged by `stage_restore`, setting the replaced
+/// database aside. Runs before the database is opened.
+pub fn finish_pending_restore() {
+    let (Ok(db_path), Ok(dir)) = (default_db_path(), data_dir()) else {
+        return;
+    };
+    let pending = dir.join(PENDING_RESTORE);
+    if !pending.exists() {
+        return;
+    }
+    if let Err(err) = quarantine_database("replaced") {
+        eprintln!("Failed to set the current database aside: {err}");
+        return;
+    }
+    match fs::rename(&pending, &db_path) {
+        Ok(()) => eprintln!("Restored the clipboard history from a backup"),
+        Err(err) => eprintln!("Failed to restore the backup: {err}"),
+    }
+}
+
+/// Checks the database before it is opened. A missing file is fine; it is
+/// created on open.
+pub async fn check_database(path: &Path) -> Result<(), CorruptDatabase> {
+    if !path.exists() {
+        return Ok(());
+    }
+    let raw = path.to_string_lossy().replace('\\', "/");
+    let reason = match Database::connect(format!("sqlite:///{raw}?mode=ro")).await {
+        Ok(db) => {
+            let errors = database_errors(&db).await;
+            let _ = db.close().await;
+            match errors {
+                Ok(errors) => errors.into_iter().next(),
+                Err(err) => Some(err.to_string()),
+            }
+        }
+        Err(err) => Some(err.to_string()),
+    };
+    match reason {
+        Some(reason) => Err(CorruptDatabase { reason }),
+        None => Ok(()),
+    }
+}
+
+/// Sets the damaged database aside and copies in the newest backup that
+/// passes validation. Nothing may have the database open.
+pub async fn recover_corrupt_database() -> anyhow::Result<RecoveryReport> {
+    let quarantined = quarantine_database("damaged")?;
+    let db_path = default_db_path()?;
+    for backup in list_backups()? {
+        if let Err(err) = validate_backup(&backup.path).await {
+            eprintln!("Skipping backup {}: {err}", backup.path.display());
+            continue;
+        }
+        let missing_images = count_missing_images(&backup.path).await?;
+        copy_into_place(&backup.path, &db_path)?;
+        return Ok(RecoveryReport {
+            quarantined,
+            restored: Some(backup),
+            missing_images,
+        });
+    }
+    Ok(RecoveryReport {
+        quarantined,
+        restored: None,
+        missing_images: 0,
+    })
+}
+
+/// Moves the database with its WAL and shared-memory files into a new
+/// quarantine folder, keeping them together so the copy stays consistent.
+fn quarantine_database(label: &str) -> anyhow::Result<PathBuf> {
+    let dir = quarantine_dir()?.join(format!("{}-{label}", now()));
+    fs::create_dir_all(&dir)?;
+    let data = data_dir()?;
+    for name in [
+        DB_FILE.to_string(),
+        format!("{DB_FILE}-wal"),
+        format!("{DB_FILE}-shm"),
+    ] {
+        match fs::rename(data.join(&name), dir.join(&name)) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => return Err(err.into()),
+            _ => {}
+        }
+    }
+    Ok(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::history::{insert_clipboard_entry, open_db, ClipboardEntryInput};
+    use crate::test_support::{lock_storage, temp_dir};
+
+    async fn insert_image(db: &DatabaseConnection, hash: &str, path: &Path) {
+        insert_clipboard_entry(
+            db,
+            ClipboardEntryInput {
+                content_type: "image",
+                content_hash: hash,
+                content: "Image",
+                image_path: Some(&path.to_string_lossy()),
+                ..ClipboardEntryInput::default()
+            },
+        )
+        .await
+        .unwrap();
+    }
+
+    #[test]
+    fn restore_reports_images_that_are_gone() {
+        let _storage = lock_storage();
+        async_std::task::block_on(async {
+            let dir = temp_dir("backup-missing-images");
+            let db = open_db(&dir.join("history.sqlite"), false).await.unwrap();
+            let kept = dir.join("image_kept.bmp");
+            fs::write(&kept, b"BM").unwrap();
+            insert_image(&db, "kept", &kept).await;
+            insert_image(&db, "gone", &dir.join("image_gone.bmp")).await;
+
+            let backup = create_backup_in(&db, &dir.join("backups")).await.unwrap();
+            let missing_images = count_missing_images(&backup.path).await.unwrap();
+            assert_eq!(missing_images, 1);
+            let report = RestoreReport {
+                backup: backup.path,
+                missing_images,
+            };
+            assert!(report
+                .to_string()
+                .ends_with("; 1 entry refers to an image that is no longer on disk"));
+        });
+    }
+
+    #[test]
+    fn replaces_backups_holding_plaintext() {
+        let _storage = lock_storage();
+        async_std::task::block_on(async {
+            let dir = temp_dir("backup-plaintext");
+            let backups = dir.join("backups");
+            let db = open_db(&dir.join("history.sqlite"), false).await.unwrap();
+
+            // An empty history has nothing to leak.
+            let empty = create_backup_in(&db, &backups).await.unwrap();
+            assert!(!is_plaintext_backup(&empty.path).await.unwrap());
+            fs::remove_file(&empty.path).unwrap();
+
+            insert_image(&db, "plain", &dir.join("image_plain.bmp")).await;
+            let plain = create_backup_in(&db, &backups).await.unwrap();
+            assert!(is_plaintext_backup(&plain.path).await.unwrap());
+            fs::write(
+                backups.join(format!("{BACKUP_PREFIX}1.db")),
+                b"not a database",
+            )
+            .unwrap();
+
+            assert_eq!(replace_plaintext_backups(&db, &backups).await.unwrap(), 2);
+            assert_eq!(list_backups_in(&backups).unwrap().len(), 1);
+        });
+    }
+}
@@ -0,0 +1,354 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{PoisonError, RwLock};
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::anyhow;
+use argon2::Argon2;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::utils::hash_bytes;
+
+/// Leads encrypted text columns, followed by base64 of the nonce and the
+/// ciphertext.
+pub const TEXT_PREFIX: &str = "gcm1:";
+
+/// Leads encrypted image files, followed by the nonce and the ciphertext.
+const BLOB_MAGIC: &[u8] = b"GCMBLOB1";
+
+/// Leads content hashes keyed with the history key.
+pub const KEYED_HASH_PREFIX: &str = "hmac-";
+
+/// Domain separation for the key used on content hashes.
+const HASH_KEY_LABEL: &[u8] = b"gpui-clipboard-manager content hash";
+
+const NONCE_LEN: usize = 12;
+const SALT_LEN: usize = 16;
+
+const KEYRING_SERVICE: &str = "gpui-clipboard-manager";
+const KEYRING_USER: &str = "history-key";
+
+pub const PASSPHRASE_ENV: &str = "CLIPBOARD_HISTORY_PASSPHRASE";
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// The unlocked history key.
+struct HistoryKey {
+    key: Key<Aes256Gcm>,
+    cipher: Aes256Gcm,
+    /// Keys content hashes, so a stored hash can't confirm a guessed secret.
+    hash_key: HmacSha256,
+}
+
+impl HistoryKey {
+    fn new(key: &Key<Aes256Gcm>) -> Self {
+        let mut label = <HmacSha256 as Mac>::new_from_slice(key).expect("HMAC takes any key");
+        label.update(HASH_KEY_LABEL);
+        let hash_key = <HmacSha256 as Mac>::new_from_slice(&label.finalize().into_bytes())
+            .expect("HMAC takes any key");
+        Self {
+            key: *key,
+            cipher: Aes256Gcm::new(key),
+            hash_key,
+        }
+    }
+}
+
+/// The installed key, leaked so lookups can hand out plain references. A
+/// process installs at most one.
+static KEY: RwLock<Option<&'static HistoryKey>> = RwLock::new(None);
+static ENCRYPT_WRITES: AtomicBool = AtomicBool::new(false);
+
+fn history_key() -> Option<&'static HistoryKey> {
+    *KEY.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Makes the history key available to `seal` and `open`. A process keeps
+/// the first key it installs, so installing a different one later (after
+/// a passphrase change, or restoring a backup made under another key) is an
+/// error rather than a silent switch.
+pub fn install(key: &Key<Aes256Gcm>, encrypt_writes: bool) -> anyhow::Result<()> {
+    let installed = *KEY
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get_or_insert_with(|| Box::leak(Box::new(HistoryKey::new(key))));
+    if installed.key != *key {
+        return Err(anyhow!(
+            "The history was encrypted with a different key than the one in use; \
+             restart the app to unlock it"
+        ));
+    }
+    ENCRYPT_WRITES.store(encrypt_writes, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Forgets the installed key. Only tests switch keys within one process,
+/// holding `test_support::lock_storage`.
+#[cfg(test)]
+pub fn reset() {
+    *KEY.write().unwrap_or_else(PoisonError::into_inner) = None;
+    ENCRYPT_WRITES.store(false, Ordering::Relaxed);
+}
+
+/// The installed history key.
+pub fn key() -> Option<Key<Aes256Gcm>> {
+    history_key().map(|key| key.key)
+}
+
+pub fn cipher() -> Option<&'static Aes256Gcm> {
+    history_key().map(|key| &key.cipher)
+}
+
+/// Whether new text and images are written encrypted.
+pub fn is_enabled() -> bool {
+    ENCRYPT_WRITES.load(Ordering::Relaxed) && history_key().is_some()
+}
+
+/// The `content_hash` stored for `bytes`: SHA-256, keyed with the history
+/// key while encryption is on.
+pub fn content_hash(bytes: &[u8]) -> String {
+    protect_hash(&hash_bytes(bytes))
+}
+
+/// Keys a plain SHA-256 content hash while encryption is on. Hashes that
+/// are already keyed pass through.
+pub fn protect_hash(hash: &str) -> String {
+    match history_key().filter(|_| is_enabled()) {
+        Some(key) if !is_keyed_hash(hash) => keyed_hash(key, hash),
+        _ => hash.to_string(),
+    }
+}
+
+pub fn is_keyed_hash(hash: &str) -> bool {
+    hash.starts_with(KEYED_HASH_PREFIX)
+}
+
+fn keyed_hash(key: &HistoryKey, hash: &str) -> String {
+    let mut mac = key.hash_key.clone();
+    mac.update(hash.as_bytes());
+    let digest = mac.finalize().into_bytes();
+    let mut output = String::with_capacity(KEYED_HASH_PREFIX.len() + digest.len() * 2);
+    output.push_str(KEYED_HASH_PREFIX);
+    for byte in digest {
+        output.push_str(&format!("{byte:02x}"));
+    }
+    output
+}
+
+/// Encrypts `text` for storage when encryption is on.
+pub fn seal(text: &str) -> anyhow::Result<String> {
+    match cipher().filter(|_| is_enabled()) {
+        Some(cipher) => encrypt_text(cipher, text),
+        None => Ok(text.to_string()),
+    }
+}
+
+pub fn seal_opt(text: Option<&str>) -> anyhow::Result<Option<String>> {
+    text.map(seal).transpose()
+}
+
+/// Decrypts a column of a row stored encrypted. Without the key, or if the
+/// data is damaged, the stored text is returned as it is.
+pub fn open(text: String) -> String {
+    let Some(cipher) = cipher() else {
+        return text;
+    };
+    match decrypt_text(cipher, &text) {
+        Ok(plain) => plain,
+        Err(err) => {
+            eprintln!("Failed to decrypt stored text: {err}");
+            text
+        }
+    }
+}
+
+pub fn open_opt(text: Option<String>) -> Option<String> {
+    text.map(open)
+}
+
+/// Encrypts image bytes for storage when encryption is on.
+pub fn seal_bytes(bytes: &[u8]) -> anyhow::Result<Vec<u8>> {
+    match cipher().filter(|_| is_enabled()) {
+        Some(cipher) => encrypt_bytes(cipher, bytes),
+        None => Ok(bytes.to_vec()),
+    }
+}
+
+/// Decrypts stored image bytes; unencrypted files pass through.
+pub fn open_bytes(bytes: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+    if !is_sealed_bytes(&bytes) {
+        return Ok(bytes);
+    }
+    let cipher =
+        cipher().ok_or_else(|| anyhow!("The image is encrypted and the history is locked"))?;
+    decrypt_bytes(cipher, &bytes)
+}
+
+pub fn is_sealed_bytes(bytes: &[u8]) -> bool {
+    bytes.starts_with(BLOB_MAGIC)
+}
+
+pub fn encrypt_text(cipher: &Aes256Gcm, text: &str) -> anyhow::Result<String> {
+    let sealed = encrypt(cipher, text.as_bytes())?;
+    Ok(format!("{TEXT_PREFIX}{}", BASE64.encode(sealed)))
+}
+
+pub fn decrypt_text(cipher: &Aes256Gcm, text: &str) -> anyhow::Result<String> {
+    let encoded = text
+        .strip_prefix(TEXT_PREFIX)
+        .ok_or_else(|| anyhow!("Text is not encrypted"))?;
+    let plain = decrypt(cipher, &BASE64.decode(encoded)?)?;
+    Ok(String::from_utf8(plain)?)
+}
+
+pub fn encrypt_bytes(cipher: &Aes256Gcm, bytes: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut sealed = BLOB_MAGIC.to_vec();
+    sealed.extend(encrypt(cipher, bytes)?);
+    Ok(sealed)
+}
+
+pub fn decrypt_bytes(cipher: &Aes256Gcm, bytes: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let sealed = bytes
+        .strip_prefix(BLOB_MAGIC)
+        .ok_or_else(|| anyhow!("Data is not encrypted"))?;
+    decrypt(cipher, sealed)
+}
+
+/// A fresh nonce followed by the ciphertext.
+fn encrypt(cipher: &Aes256Gcm, plain: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plain)
+        .map_err(|_| anyhow!("Encryption failed"))?;
+    let mut sealed = nonce.to_vec();
+    sealed.extend(ciphertext);
+    Ok(sealed)
+}
+
+fn decrypt(cipher: &Aes256Gcm, sealed: &[u8]) -> anyhow::Result<Vec<u8>> {
+    if sealed.len() < NONCE_LEN {
+        return Err(anyhow!("Encrypted data is truncated"));
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| anyhow!("Decryption failed; wrong key or damaged data"))
+}
+
+/// The key kept in the OS credential store, generated on first use.
+pub fn keyring_key() -> anyhow::Result<Key<Aes256Gcm>> {
+    let entry = keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER)?;
+    match entry.get_password() {
+        Ok(encoded) => {
+            let bytes = BASE64.decode(encoded)?;
+            if bytes.len() != 32 {
+                return Err(anyhow!("The stored history key has the wrong length"));
+            }
+            Ok(*Key::<Aes256Gcm>::from_slice(&bytes))
+        }
+        Err(keyring::Error::NoEntry) => {
+            let key = Aes256Gcm::generate_key(&mut OsRng);
+            entry.set_password(&BASE64.encode(key))?;
+            Ok(key)
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+pub fn passphrase_key(passphrase: &str, salt: &[u8]) -> anyhow::Result<Key<Aes256Gcm>> {
+    let mut key = Key::<Aes256Gcm>::default();
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|err| anyhow!("Failed to derive the history key: {err}"))?;
+    Ok(key)
+}
+
+pub fn generate_salt() -> [u8; SALT_LEN] {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    salt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::lock_storage;
+
+    fn test_key(seed: u8) -> Key<Aes256Gcm> {
+        *Key::<Aes256Gcm>::from_slice(&[seed; 32])
+    }
+
+    #[test]
+    fn seal_and_open_round_trip() {
+        let _storage = lock_storage();
+        // Nothing is installed yet.
+        assert_eq!(seal("secret").unwrap(), "secret");
+
+        install(&test_key(1), true).unwrap();
+        let sealed = seal("secret").unwrap();
+        assert!(sealed.starts_with(TEXT_PREFIX));
+        assert!(!sealed.contains("secret"));
+        assert_ne!(sealed, seal("secret").unwrap(), "nonces are fresh");
+        assert_eq!(open(sealed), "secret");
+
+        // Plaintext that looks like ciphertext is sealed like any other.
+        let lookalike = seal("gcm1:not encrypted").unwrap();
+        assert_eq!(open(lookalike), "gcm1:not encrypted");
+
+        let bytes = seal_bytes(b"BM image bytes").unwrap();
+        assert!(is_sealed_bytes(&bytes));
+        assert_eq!(open_bytes(bytes).unwrap(), b"BM image bytes");
+        assert_eq!(open_bytes(b"BM plain".to_vec()).unwrap(), b"BM plain");
+    }
+
+    #[test]
+    fn tampered_text_fails_to_decrypt() {
+        let cipher = Aes256Gcm::new(&test_key(2));
+        let sealed = encrypt_text(&cipher, "secret").unwrap();
+        let other = Aes256Gcm::new(&test_key(3));
+        assert!(decrypt_text(&other, &sealed).is_err());
+        assert!(decrypt_text(&cipher, "gcm1:AAAA").is_err());
+        assert!(decrypt_text(&cipher, "secret").is_err());
+    }
+
+    #[test]
+    fn passphrase_key_depends_on_passphrase_and_salt() {
+        let salt = [7u8; SALT_LEN];
+        let key = passphrase_key("hunter2", &salt).unwrap();
+        assert_eq!(key, passphrase_key("hunter2", &salt).unwrap());
+        assert_ne!(key, passphrase_key("hunter3", &salt).unwrap());
+        assert_ne!(key, passphrase_key("hunter2", &[8u8; SALT_LEN]).unwrap());
+        assert_ne!(generate_salt(), generate_salt());
+    }
+
+    #[test]
+    fn install_rejects_a_different_key() {
+        let _storage = lock_storage();
+        install(&test_key(4), true).unwrap();
+        install(&test_key(4), false).unwrap();
+        assert!(!is_enabled());
+        assert!(install(&test_key(5), true).is_err());
+        assert_eq!(key(), Some(test_key(4)));
+    }
+
+    #[test]
+    fn content_hashes_are_keyed_while_enabled() {
+        let _storage = lock_storage();
+        let plain = hash_bytes(b"secret");
+        assert_eq!(content_hash(b"secret"), plain);
+
+        install(&test_key(6), true).unwrap();
+        let keyed = content_hash(b"secret");
+        assert!(is_keyed_hash(&keyed));
+        assert!(!keyed.contains(&plain));
+        assert_eq!(keyed, protect_hash(&plain));
+        assert_eq!(protect_hash(&keyed), keyed);
+
+        let other = HistoryKey::new(&test_key(7));
+        assert_ne!(keyed_hash(&other, &plain), keyed);
+    }
+}
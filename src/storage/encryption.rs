@@ -0,0 +1,651 @@
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use aes_gcm::{Aes256Gcm, Key, KeyInit};
+use anyhow::anyhow;
+use async_std::sync::Mutex;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder,
+    QuerySelect, Statement,
+};
+
+use crate::settings::{settings, EncryptionSettings, KeySource};
+use crate::storage::backup::replace_plaintext_backups;
+use crate::storage::crypto::{
+    self, decrypt_bytes, decrypt_text, encrypt_bytes, encrypt_text, generate_salt, keyring_key,
+    passphrase_key, PASSPHRASE_ENV,
+};
+use crate::storage::entity::{Column, Entity, Model};
+use crate::storage::entry_link;
+use crate::storage::history::refresh_search_terms;
+use crate::storage::images::clear_thumbnails;
+use crate::storage::path::{backups_dir, images_dir, thumbnails_dir};
+use crate::utils::hash_bytes;
+
+const SALT_KEY: &str = "encryption_salt";
+const SOURCE_KEY: &str = "encryption_key_source";
+/// A known value encrypted with the history key, to tell a wrong key apart
+/// from damaged data.
+const CHECK_KEY: &str = "encryption_check";
+const CHECK_VALUE: &str = "gpui-clipboard-manager";
+
+/// Rows converted per query.
+const CONVERT_BATCH: u64 = 200;
+
+static PREPARE_LOCK: OnceLock<Mutex<()>> = OnceLock::new();
+
+/// What converting the history depends on outside the database.
+struct EncryptionSetup {
+    settings: EncryptionSettings,
+    images_dir: PathBuf,
+    thumbnails_dir: PathBuf,
+    backups_dir: PathBuf,
+    passphrase: Option<String>,
+}
+
+/// Unlocks the history key and converts the stored history to match
+/// `settings().encryption`, encrypting or decrypting existing entries,
+/// links and images. Cheap once the history is converted.
+///
+/// Encrypted: every text column of entries and links (content, OCR text
+/// and layout, codes, file paths, link URLs and metadata, source app) and
+/// the image files. The full-text index only ever sees ciphertext,
+/// thumbnails are deleted, and backups holding plaintext are replaced by a
+/// fresh one.
+///
+/// Readable: timestamps, content types, pins, tag names, link kinds and
+/// offsets, each row's `encrypted` flag and `ocr_attempt` key, and content
+/// hashes, which are keyed with the history key so they can't confirm a
+/// guessed secret. Image file names carry the hash they were saved under,
+/// so images saved before encryption was turned on keep a plain SHA-256 of
+/// their bytes in the name.
+pub async fn prepare_encryption(db: &DatabaseConnection) -> anyhow::Result<()> {
+    let setup = EncryptionSetup {
+        settings: settings().encryption,
+        images_dir: images_dir()?,
+        thumbnails_dir: thumbnails_dir()?,
+        backups_dir: backups_dir()?,
+        passphrase: std::env::var(PASSPHRASE_ENV).ok(),
+    };
+    apply_encryption(db, &setup).await
+}
+
+async fn apply_encryption(db: &DatabaseConnection, setup: &EncryptionSetup) -> anyhow::Result<()> {
+    let _guard = PREPARE_LOCK.get_or_init(|| Mutex::new(())).lock().await;
+    let enabled = setup.settings.enabled;
+    let check = load_meta(db, CHECK_KEY).await?;
+    if !enabled && check.is_none() {
+        return Ok(());
+    }
+
+    // A key installed earlier is only reused if it opens this history.
+    let key = match (crypto::key(), check.as_deref()) {
+        (Some(key), Some(check)) if decrypt_text(&Aes256Gcm::new(&key), check).is_ok() => key,
+        _ => unlock(db, setup, check.as_deref()).await?,
+    };
+    crypto::install(&key, enabled)?;
+    let cipher = Aes256Gcm::new(&key);
+
+    if enabled {
+        let converted =
+            convert_entries(db, &cipher, true).await? + convert_links(db, &cipher, true).await?;
+        convert_images(&cipher, &setup.images_dir, true)?;
+        clear_thumbnails(&setup.thumbnails_dir)?;
+        if converted > 0 {
+            purge_freed_pages(db).await?;
+        }
+        replace_plaintext_backups(db, &setup.backups_dir).await?;
+    } else {
+        convert_entries(db, &cipher, false).await?;
+        convert_links(db, &cipher, false).await?;
+        convert_images(&cipher, &setup.images_dir, false)?;
+        for key in [CHECK_KEY, SOURCE_KEY, SALT_KEY] {
+            delete_meta(db, key).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Loads the key the history was encrypted with, or sets one up when
+/// encryption is turned on for the first time.
+async fn unlock(
+    db: &DatabaseConnection,
+    setup: &EncryptionSetup,
+    check: Option<&str>,
+) -> anyhow::Result<Key<Aes256Gcm>> {
+    let source = match load_meta(db, SOURCE_KEY).await? {
+        Some(source) => parse_key_source(&source)?,
+        None => setup.settings.key_source,
+    };
+    let key = match source {
+        KeySource::Keyring => keyring_key()?,
+        KeySource::Passphrase => {
+            let passphrase = setup.passphrase.as_deref().ok_or_else(|| {
+                anyhow!("Set {PASSPHRASE_ENV} to unlock the encrypted clipboard history")
+            })?;
+            let salt = match load_meta(db, SALT_KEY).await? {
+                Some(salt) => BASE64.decode(salt)?,
+                None => {
+                    let salt = generate_salt();
+                    store_meta(db, SALT_KEY, &BASE64.encode(salt)).await?;
+                    salt.to_vec()
+                }
+            };
+            passphrase_key(passphrase, &salt)?
+        }
+    };
+    let cipher = Aes256Gcm::new(&key);
+
+    match check {
+        Some(check) => {
+            decrypt_text(&cipher, check)
+                .map_err(|_| anyhow!("The encryption key doesn't match the clipboard history"))?;
+        }
+        None => {
+            let source = match source {
+                KeySource::Keyring => "keyring",
+                KeySource::Passphrase => "passphrase",
+            };
+            store_meta(db, SOURCE_KEY, source).await?;
+            store_meta(db, CHECK_KEY, &encrypt_text(&cipher, CHECK_VALUE)?).await?;
+        }
+    }
+    Ok(key)
+}
+
+fn parse_key_source(source: &str) -> anyhow::Result<KeySource> {
+    match source {
+        "keyring" => Ok(KeySource::Keyring),
+        "passphrase" => Ok(KeySource::Passphrase),
+        other => Err(anyhow!("Unknown history key source `{other}`")),
+    }
+}
+
+/// Decrypts the text columns of an entry read from the database.
+pub fn open_entry(entry: Model) -> Model {
+    if !entry.encrypted {
+        return entry;
+    }
+    Model {
+        content: crypto::open(entry.content),
+        text_content: crypto::open_opt(entry.text_content),
+        ocr_text: crypto::open_opt(entry.ocr_text),
+        ocr_layout: crypto::open_opt(entry.ocr_layout),
+        image_codes: crypto::open_opt(entry.image_codes),
+        file_paths: crypto::open_opt(entry.file_paths),
+        link_url: crypto::open_opt(entry.link_url),
+        link_clean_url: crypto::open_opt(entry.link_clean_url),
+        link_title: crypto::open_opt(entry.link_title),
+        link_description: crypto::open_opt(entry.link_description),
+        link_site_name: crypto::open_opt(entry.link_site_name),
+        link_details: crypto::open_opt(entry.link_details),
+        source_app_title: crypto::open_opt(entry.source_app_title),
+        source_exe_path: crypto::open_opt(entry.source_exe_path),
+        ..entry
+    }
+}
+
+pub fn open_link(link: entry_link::Model) -> entry_link::Model {
+    if !link.encrypted {
+        return link;
+    }
+    entry_link::Model {
+        url: crypto::open(link.url),
+        title: crypto::open_opt(link.title),
+        description: crypto::open_opt(link.description),
+        site_name: crypto::open_opt(link.site_name),
+        ..link
+    }
+}
+
+/// Encrypts a plaintext column, or decrypts a sealed one. A column that
+/// doesn't decrypt is kept as it is, so one damaged row can't block
+/// opening the history.
+fn convert_text(cipher: &Aes256Gcm, text: &str, encrypt: bool) -> anyhow::Result<String> {
+    if encrypt {
+        return encrypt_text(cipher, text);
+    }
+    Ok(decrypt_text(cipher, text).unwrap_or_else(|err| {
+        eprintln!("Failed to decrypt stored text: {err}");
+        text.to_string()
+    }))
+}
+
+fn convert_opt(
+    cipher: &Aes256Gcm,
+    text: Option<&str>,
+    encrypt: bool,
+) -> anyhow::Result<Option<String>> {
+    text.map(|text| convert_text(cipher, text, encrypt))
+        .transpose()
+}
+
+/// The content hash to store once a row is converted. Keyed hashes of text
+/// and files are recomputed from the decrypted content when encryption is
+/// turned off; image hashes were taken over the original clipboard bytes,
+/// so those stay keyed.
+fn convert_hash(entry: &Model, encrypt: bool) -> String {
+    if encrypt {
+        return crypto::protect_hash(&entry.content_hash);
+    }
+    if !crypto::is_keyed_hash(&entry.content_hash) {
+        return entry.content_hash.clone();
+    }
+    match (entry.content_type.as_str(), entry.file_paths.as_deref()) {
+        ("text" | "link", _) => hash_bytes(entry.content.as_bytes()),
+        ("files", Some(paths)) => hash_bytes(paths.as_bytes()),
+        _ => entry.content_hash.clone(),
+    }
+}
+
+/// Each row is converted in a single update together with its
+/// `encrypted` flag.
+async fn convert_entries(
+    db: &DatabaseConnection,
+    cipher: &Aes256Gcm,
+    encrypt: bool,
+) -> anyhow::Result<usize> {
+    let mut converted = 0;
+    loop {
+        let entries = Entity::find()
+            .filter(Column::Encrypted.eq(!encrypt))
+            .order_by_asc(Column::Id)
+            .limit(CONVERT_BATCH)
+            .all(db)
+            .await?;
+        if entries.is_empty() {
+            return Ok(converted);
+        }
+        for entry in entries {
+            let content = convert_text(cipher, &entry.content, encrypt)?;
+            let file_paths = convert_opt(cipher, entry.file_paths.as_deref(), encrypt)?;
+            let hash = if encrypt {
+                convert_hash(&entry, true)
+            } else {
+                convert_hash(
+                    &Model {
+                        content: content.clone(),
+                        file_paths: file_paths.clone(),
+                        ..entry.clone()
+                    },
+                    false,
+                )
+            };
+            let columns = [
+                (Column::TextContent, entry.text_content.as_deref()),
+                (Column::OcrText, entry.ocr_text.as_deref()),
+                (Column::OcrLayout, entry.ocr_layout.as_deref()),
+                (Column::ImageCodes, entry.image_codes.as_deref()),
+                (Column::LinkUrl, entry.link_url.as_deref()),
+                (Column::LinkCleanUrl, entry.link_clean_url.as_deref()),
+                (Column::LinkTitle, entry.link_title.as_deref()),
+                (Column::LinkDescription, entry.link_description.as_deref()),
+                (Column::LinkSiteName, entry.link_site_name.as_deref()),
+                (Column::LinkDetails, entry.link_details.as_deref()),
+                (Column::SourceAppTitle, entry.source_app_title.as_deref()),
+                (Column::SourceExePath, entry.source_exe_path.as_deref()),
+            ];
+            let mut update = Entity::update_many()
+                .col_expr(Column::Content, Expr::value(content))
+                .col_expr(Column::FilePaths, Expr::value(file_paths))
+                .col_expr(Column::ContentHash, Expr::value(hash))
+                .col_expr(Column::Encrypted, Expr::value(encrypt));
+            for (column, value) in columns {
+                update = update.col_expr(column, Expr::value(convert_opt(cipher, value, encrypt)?));
+            }
+            update.filter(Column::Id.eq(entry.id)).exec(db).await?;
+            // Clears the plaintext terms, or rebuilds them after decrypting.
+            refresh_search_terms(db, entry.id).await?;
+            converted += 1;
+        }
+    }
+}
+
+async fn convert_links(
+    db: &DatabaseConnection,
+    cipher: &Aes256Gcm,
+    encrypt: bool,
+) -> anyhow::Result<usize> {
+    let mut converted = 0;
+    loop {
+        let links = entry_link::Entity::find()
+            .filter(entry_link::Column::Encrypted.eq(!encrypt))
+            .order_by_asc(entry_link::Column::Id)
+            .limit(CONVERT_BATCH)
+            .all(db)
+            .await?;
+        if links.is_empty() {
+            return Ok(converted);
+        }
+        for link in links {
+            entry_link::Entity::update_many()
+                .col_expr(
+                    entry_link::Column::Url,
+                    Expr::value(convert_text(cipher, &link.url, encrypt)?),
+                )
+                .col_expr(
+                    entry_link::Column::Title,
+                    Expr::value(convert_opt(cipher, link.title.as_deref(), encrypt)?),
+                )
+                .col_expr(
+                    entry_link::Column::Description,
+                    Expr::value(convert_opt(cipher, link.description.as_deref(), encrypt)?),
+                )
+                .col_expr(
+                    entry_link::Column::SiteName,
+                    Expr::value(convert_opt(cipher, link.site_name.as_deref(), encrypt)?),
+                )
+                .col_expr(entry_link::Column::Encrypted, Expr::value(encrypt))
+                .filter(entry_link::Column::Id.eq(link.id))
+                .exec(db)
+                .await?;
+            converted += 1;
+        }
+    }
+}
+
+/// Rewrites image files through a temporary file, so an interrupted run
+/// never leaves a half-written image behind. Images are bitmaps, which
+/// never start with the encrypted file header.
+fn convert_images(cipher: &Aes256Gcm, dir: &Path, encrypt: bool) -> anyhow::Result<()> {
+    if !dir.is_dir() {
+        return Ok(());
+    }
+    for file in std::fs::read_dir(dir)? {
+        let path = file?.path();
+        if !path.is_file() || path.extension().is_some_and(|ext| ext == "tmp") {
+            continue;
+        }
+        let bytes = std::fs::read(&path)?;
+        let converted = match (encrypt, crypto::is_sealed_bytes(&bytes)) {
+            (true, false) => encrypt_bytes(cipher, &bytes)?,
+            (false, true) => match decrypt_bytes(cipher, &bytes) {
+                Ok(plain) => plain,
+                Err(err) => {
+                    eprintln!("Failed to decrypt image {}: {err}", path.display());
+                    continue;
+                }
+            },
+            _ => continue,
+        };
+        let temp = path.with_extension("tmp");
+        std::fs::write(&temp, converted)?;
+        std::fs::rename(&temp, &path)?;
+    }
+    Ok(())
+}
+
+/// Replaced plaintext survives in deleted full-text index segments, in
+/// free database pages and in the WAL until they are rewritten.
+async fn purge_freed_pages(db: &DatabaseConnection) -> anyhow::Result<()> {
+    db.execute_unprepared(
+        "INSERT INTO clipboard_entries_fts(clipboard_entries_fts) VALUES ('optimize')",
+    )
+    .await?;
+    db.execute_unprepared("VACUUM").await?;
+    // VACUUM goes through the WAL; copy it back and empty it.
+    db.execute_unprepared("PRAGMA wal_checkpoint(TRUNCATE)")
+        .await?;
+    Ok(())
+}
+
+async fn load_meta(db: &DatabaseConnection, key: &str) -> anyhow::Result<Option<String>> {
+    let row = db
+        .query_one(Statement::from_sql_and_values(
+            db.get_database_backend(),
+            "SELECT value FROM storage_meta WHERE key = ?",
+            [key.into()],
+        ))
+        .await?;
+    Ok(row.map(|row| row.try_get_by_index(0)).transpose()?)
+}
+
+async fn store_meta(db: &DatabaseConnection, key: &str, value: &str) -> anyhow::Result<()> {
+    db.execute(Statement::from_sql_and_values(
+        db.get_database_backend(),
+        "INSERT INTO storage_meta (key, value) VALUES (?, ?) \
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        [key.into(), value.into()],
+    ))
+    .await?;
+    Ok(())
+}
+
+async fn delete_meta(db: &DatabaseConnection, key: &str) -> anyhow::Result<()> {
+    db.execute(Statement::from_sql_and_values(
+        db.get_database_backend(),
+        "DELETE FROM storage_meta WHERE key = ?",
+        [key.into()],
+    ))
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::history::{
+        insert_clipboard_entry, insert_entry_links, load_entries_page, open_db,
+        ClipboardEntryInput, EntryLinkInput,
+    };
+    use crate::storage::search_query::parse_query;
+    use crate::test_support::{lock_storage, temp_dir};
+
+    const SECRET: &str = "correct horse battery staple";
+    const SECRET_URL: &str = "https://example.com/hidden-page";
+    const IMAGE_MARKER: &str = "plaintext image marker";
+
+    fn setup(dir: &Path, enabled: bool, passphrase: &str) -> EncryptionSetup {
+        EncryptionSetup {
+            settings: EncryptionSettings {
+                enabled,
+                key_source: KeySource::Passphrase,
+            },
+            images_dir: dir.join("images"),
+            thumbnails_dir: dir.join("thumbnails"),
+            backups_dir: dir.join("backups"),
+            passphrase: Some(passphrase.to_string()),
+        }
+    }
+
+    async fn insert_text(db: &DatabaseConnection, text: &str) -> i32 {
+        let hash = hash_bytes(text.as_bytes());
+        insert_clipboard_entry(
+            db,
+            ClipboardEntryInput {
+                content_type: "text",
+                content_hash: &hash,
+                content: text,
+                text_content: Some(text),
+                ..ClipboardEntryInput::default()
+            },
+        )
+        .await
+        .unwrap()
+    }
+
+    fn contains(haystack: &[u8], needle: &str) -> bool {
+        haystack
+            .windows(needle.len())
+            .any(|window| window == needle.as_bytes())
+    }
+
+    fn assert_no_plaintext(path: &Path, needles: &[&str]) {
+        let Ok(bytes) = std::fs::read(path) else {
+            return;
+        };
+        for needle in needles {
+            assert!(
+                !contains(&bytes, needle),
+                "{} still contains `{needle}`",
+                path.display()
+            );
+        }
+    }
+
+    #[test]
+    fn encrypts_the_history_at_rest_and_back() {
+        let _storage = lock_storage();
+        async_std::task::block_on(async {
+            let dir = temp_dir("encryption-at-rest");
+            let db_path = dir.join("history.sqlite");
+            let db = open_db(&db_path, false).await.unwrap();
+
+            let text = insert_text(&db, &format!("{SECRET} and {SECRET_URL}")).await;
+            insert_entry_links(
+                &db,
+                text,
+                &[EntryLinkInput {
+                    kind: "url",
+                    url: SECRET_URL,
+                    start_offset: 0,
+                    end_offset: SECRET_URL.len() as i32,
+                    title: Some("Hidden page"),
+                    description: None,
+                    site_name: None,
+                }],
+            )
+            .await
+            .unwrap();
+            let lookalike = insert_text(&db, "gcm1:looks encrypted").await;
+            let shouting = insert_text(&db, "GCM1:SHOUTING").await;
+
+            let images = dir.join("images");
+            std::fs::create_dir_all(&images).unwrap();
+            let image_path = images.join("image_test.bmp");
+            std::fs::write(&image_path, format!("BM{IMAGE_MARKER}")).unwrap();
+            let image_path_text = image_path.to_string_lossy().into_owned();
+            insert_clipboard_entry(
+                &db,
+                ClipboardEntryInput {
+                    content_type: "image",
+                    content_hash: &hash_bytes(IMAGE_MARKER.as_bytes()),
+                    content: "Image",
+                    ocr_text: Some(IMAGE_MARKER),
+                    image_path: Some(&image_path_text),
+                    ..ClipboardEntryInput::default()
+                },
+            )
+            .await
+            .unwrap();
+
+            let backups = dir.join("backups");
+            std::fs::create_dir_all(&backups).unwrap();
+            let backup = backups.join("clipboard_history-1.db");
+            db.execute_unprepared(&format!("VACUUM INTO '{}'", backup.to_string_lossy()))
+                .await
+                .unwrap();
+
+            let on_disk = |path: &Path| std::fs::read(path).unwrap_or_default();
+            let wal_path = dir.join("history.sqlite-wal");
+            assert!(
+                contains(&on_disk(&db_path), SECRET) || contains(&on_disk(&wal_path), SECRET),
+                "the plaintext is on disk before encrypting"
+            );
+
+            apply_encryption(&db, &setup(&dir, true, "hunter2"))
+                .await
+                .unwrap();
+
+            let plain_hash = hash_bytes(format!("{SECRET} and {SECRET_URL}").as_bytes());
+            let needles = [
+                SECRET,
+                SECRET_URL,
+                "Hidden page",
+                IMAGE_MARKER,
+                "looks encrypted",
+                "SHOUTING",
+                plain_hash.as_str(),
+            ];
+            assert_no_plaintext(&db_path, &needles);
+            assert_no_plaintext(&wal_path, &needles);
+            assert_no_plaintext(&image_path, &needles);
+            // The plaintext backup was replaced by an encrypted one.
+            assert!(!backup.exists());
+            let backup_files: Vec<_> = std::fs::read_dir(&backups)
+                .unwrap()
+                .map(|file| file.unwrap().path())
+                .collect();
+            assert_eq!(backup_files.len(), 1);
+            assert_no_plaintext(&backup_files[0], &needles);
+            let stored = Entity::find_by_id(text).one(&db).await.unwrap().unwrap();
+            assert!(stored.encrypted);
+            assert!(crypto::is_keyed_hash(&stored.content_hash));
+            assert_eq!(stored.search_terms, None);
+
+            // Search decrypts, and sealed-looking plaintext survived intact.
+            let query = parse_query("horse").unwrap().unwrap();
+            let page = load_entries_page(&db, Some(&query), false, None, 50)
+                .await
+                .unwrap();
+            let found: Vec<i32> = page.entries.iter().map(|entry| entry.id).collect();
+            assert_eq!(found, vec![text]);
+            let entry = Entity::find_by_id(lookalike)
+                .one(&db)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(open_entry(entry).content, "gcm1:looks encrypted");
+
+            // Opening again converts nothing and keeps the key.
+            apply_encryption(&db, &setup(&dir, true, "hunter2"))
+                .await
+                .unwrap();
+
+            apply_encryption(&db, &setup(&dir, false, "hunter2"))
+                .await
+                .unwrap();
+            let stored = Entity::find_by_id(text).one(&db).await.unwrap().unwrap();
+            assert!(!stored.encrypted);
+            assert_eq!(stored.content, format!("{SECRET} and {SECRET_URL}"));
+            assert_eq!(stored.content_hash, plain_hash);
+            assert!(stored.search_terms.is_some());
+            let link = entry_link::Entity::find()
+                .filter(entry_link::Column::EntryId.eq(text))
+                .one(&db)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(link.url, SECRET_URL);
+            assert_eq!(link.title.as_deref(), Some("Hidden page"));
+            for (id, content) in [
+                (lookalike, "gcm1:looks encrypted"),
+                (shouting, "GCM1:SHOUTING"),
+            ] {
+                let stored = Entity::find_by_id(id).one(&db).await.unwrap().unwrap();
+                assert_eq!(stored.content, content);
+            }
+            assert_eq!(
+                std::fs::read(&image_path).unwrap(),
+                format!("BM{IMAGE_MARKER}").into_bytes()
+            );
+            assert_eq!(load_meta(&db, CHECK_KEY).await.unwrap(), None);
+        });
+    }
+
+    #[test]
+    fn rejects_a_key_that_does_not_match() {
+        let _storage = lock_storage();
+        async_std::task::block_on(async {
+            let dir = temp_dir("encryption-wrong-key");
+            let db = open_db(&dir.join("history.sqlite"), false).await.unwrap();
+            apply_encryption(&db, &setup(&dir, true, "hunter2"))
+                .await
+                .unwrap();
+            let check = load_meta(&db, CHECK_KEY).await.unwrap();
+            assert!(check.is_some());
+
+            let err = unlock(&db, &setup(&dir, true, "wrong"), check.as_deref())
+                .await
+                .unwrap_err();
+            assert!(err.to_string().contains("doesn't match"), "{err}");
+            let key = unlock(&db, &setup(&dir, true, "hunter2"), check.as_deref())
+                .await
+                .unwrap();
+            assert_eq!(crypto::key(), Some(key));
+        });
+    }
+}
@@ -11,15 +11,33 @@ pub struct Model {
     pub content_hash: String,
     pub text_content: Option<String>,
     pub ocr_text: Option<String>,
+    /// JSON-encoded `OcrLayout` with word positions for `ocr_text`.
+    pub ocr_layout: Option<String>,
+    /// The `ocr::attempt_key` of the last OCR run, kept even when it found
+    /// no text so the backfill does not retry the image.
+    pub ocr_attempt: Option<String>,
     pub image_path: Option<String>,
+    /// JSON-encoded `Vec<DecodedCode>` for QR codes and barcodes in the image.
+    pub image_codes: Option<String>,
 
     pub file_paths: Option<String>,
     pub link_url: Option<String>,
+    pub link_clean_url: Option<String>,
     pub link_title: Option<String>,
     pub link_description: Option<String>,
     pub link_site_name: Option<String>,
+    /// JSON-encoded `LinkDetails` from a site-specific metadata provider.
+    pub link_details: Option<String>,
     pub source_app_title: Option<String>,
     pub source_exe_path: Option<String>,
+    /// Folded words and CJK bigrams of the searchable text, see
+    /// `tokenizer::search_terms`.
+    pub search_terms: Option<String>,
+    /// Position among pinned entries, lowest first; `None` when not pinned.
+    /// Pinned entries are only removed by an explicit delete.
+    pub pin_order: Option<i32>,
+    /// Whether the text columns are sealed with the history key.
+    pub encrypted: bool,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -0,0 +1,384 @@
+use crate::storage::tokenizer::{fold, fold_char};
+
+const SCORE_MATCH: i64 = 16;
+const BONUS_BOUNDARY: i64 = 8;
+const BONUS_CONSECUTIVE: i64 = 4;
+const PENALTY_GAP_START: i64 = 3;
+const PENALTY_GAP_EXTENSION: i64 = 1;
+const PENALTY_TYPO: i64 = 24;
+
+/// Subsequence matches scoring at or below this share of the plain
+/// per-character score are too scattered to be useful.
+const MIN_SCORE_DIVISOR: i64 = 2;
+
+/// Only this many word-start anchors are tried per pattern.
+const MAX_ANCHORS: usize = 64;
+
+/// Longer words are skipped by the edit-distance fallback.
+const MAX_TYPO_WORD_LEN: usize = 32;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FuzzyMatch {
+    pub score: i64,
+    /// Byte offsets of the matched characters in the text, ascending.
+    pub positions: Vec<usize>,
+}
+
+struct TextChar {
+    byte: usize,
+    lower: char,
+    boundary: bool,
+}
+
+/// Scores a single pattern (one search token) against `text`, in the spirit
+/// of fzf: the pattern's characters must appear in order, and word starts and
+/// runs of consecutive characters score higher. Patterns with no such match
+/// fall back to comparing whole words by edit distance, so `recieve` still
+/// finds `receive`.
+pub fn fuzzy_match(pattern: &str, text: &str) -> Option<FuzzyMatch> {
+    let pattern: Vec<char> = fold(pattern).chars().collect();
+    if pattern.is_empty() {
+        return Some(FuzzyMatch {
+            score: 0,
+            positions: Vec::new(),
+        });
+    }
+    let chars = text_chars(text);
+
+    match (
+        subsequence_match(&pattern, &chars),
+        typo_match(&pattern, &chars),
+    ) {
+        (Some(subsequence), Some(typo)) if typo.score > subsequence.score => Some(typo),
+        (Some(subsequence), _) => Some(subsequence),
+        (None, typo) => typo,
+    }
+}
+
+fn text_chars(text: &str) -> Vec<TextChar> {
+    let mut chars = Vec::new();
+    let mut prev: Option<char> = None;
+    for (byte, ch) in text.char_indices() {
+        let boundary = match prev {
+            None => true,
+            Some(prev) => {
+                (!prev.is_alphanumeric() && ch.is_alphanumeric())
+                    || (prev.is_lowercase() && ch.is_uppercase())
+            }
+        };
+        // Folding may expand a character (`ß` → `ss`); the pieces share its
+        // byte offset.
+        let mut first = true;
+        fold_char(ch, |lower| {
+            chars.push(TextChar {
+                byte,
+                lower,
+                boundary: boundary && first,
+            });
+            first = false;
+        });
+        prev = Some(ch);
+    }
+    chars
+}
+
+fn subsequence_match(pattern: &[char], chars: &[TextChar]) -> Option<FuzzyMatch> {
+    let mut best: Option<(i64, Vec<usize>)> = None;
+    let mut consider = |indices: Vec<usize>| {
+        let score = score_indices(&indices, chars);
+        if best.as_ref().is_none_or(|(best, _)| score > *best) {
+            best = Some((score, indices));
+        }
+    };
+
+    // The tightest window ending at the first complete match, as fzf v1 does.
+    let end = greedy_forward(pattern, chars, 0)?;
+    let start = tightest_start(pattern, chars, end);
+    if let Some(indices) = greedy_indices(pattern, chars, start) {
+        consider(indices);
+    }
+    // Matches anchored at word starts usually read better than the first one.
+    for anchor in chars
+        .iter()
+        .enumerate()
+        .filter(|(_, ch)| ch.boundary && ch.lower == pattern[0])
+        .map(|(index, _)| index)
+        .take(MAX_ANCHORS)
+    {
+        if let Some(indices) = greedy_indices(pattern, chars, anchor) {
+            consider(indices);
+        }
+    }
+
+    let (score, indices) = best?;
+    if score <= pattern.len() as i64 * SCORE_MATCH / MIN_SCORE_DIVISOR {
+        return None;
+    }
+    let mut positions: Vec<usize> = indices.into_iter().map(|index| chars[index].byte).collect();
+    positions.dedup();
+    Some(FuzzyMatch { score, positions })
+}
+
+/// Index of the character completing the pattern when scanning from `from`.
+fn greedy_forward(pattern: &[char], chars: &[TextChar], from: usize) -> Option<usize> {
+    let mut pattern_index = 0;
+    for (index, ch) in chars.iter().enumerate().skip(from) {
+        if ch.lower == pattern[pattern_index] {
+            pattern_index += 1;
+            if pattern_index == pattern.len() {
+                return Some(index);
+            }
+        }
+    }
+    None
+}
+
+fn tightest_start(pattern: &[char], chars: &[TextChar], end: usize) -> usize {
+    let mut pattern_index = pattern.len();
+    for index in (0..=end).rev() {
+        if chars[index].lower == pattern[pattern_index - 1] {
+            pattern_index -= 1;
+            if pattern_index == 0 {
+                return index;
+            }
+        }
+    }
+    0
+}
+
+/// Matches each pattern character at its first occurrence after `start`.
+fn greedy_indices(pattern: &[char], chars: &[TextChar], start: usize) -> Option<Vec<usize>> {
+    let mut indices = Vec::with_capacity(pattern.len());
+    let mut cursor = start;
+    for &wanted in pattern {
+        let found = chars
+            .iter()
+            .enumerate()
+            .skip(cursor)
+            .find(|(_, ch)| ch.lower == wanted)
+            .map(|(index, _)| index)?;
+        indices.push(found);
+        cursor = found + 1;
+    }
+    Some(indices)
+}
+
+fn score_indices(indices: &[usize], chars: &[TextChar]) -> i64 {
+    let mut score = 0;
+    let mut prev: Option<usize> = None;
+    // Characters continuing a run keep the bonus of the run's first character,
+    // so `clip` in `clipboard` beats `c l i p`.
+    let mut run_bonus = 0;
+    for (position, &index) in indices.iter().enumerate() {
+        score += SCORE_MATCH;
+        let boundary_bonus = match (chars[index].boundary, position) {
+            (false, _) => 0,
+            // The first character landing on a word start matters most.
+            (true, 0) => BONUS_BOUNDARY * 2,
+            (true, _) => BONUS_BOUNDARY,
+        };
+        match prev {
+            Some(prev) if index == prev + 1 => {
+                score += boundary_bonus.max(run_bonus).max(BONUS_CONSECUTIVE);
+            }
+            Some(prev) => {
+                let gap = (index - prev - 1) as i64;
+                score += boundary_bonus - PENALTY_GAP_START - PENALTY_GAP_EXTENSION * (gap - 1);
+                run_bonus = boundary_bonus.min(BONUS_BOUNDARY);
+            }
+            None => {
+                score += boundary_bonus;
+                run_bonus = boundary_bonus.min(BONUS_BOUNDARY);
+            }
+        }
+        prev = Some(index);
+    }
+    score
+}
+
+/// Compares the pattern with each word (and each word's prefix of the same
+/// length) by optimal string alignment distance. Short patterns get no typo
+/// budget since almost anything is one edit away from them.
+fn typo_match(pattern: &[char], chars: &[TextChar]) -> Option<FuzzyMatch> {
+    let allowed = match pattern.len() {
+        0..=3 => return None,
+        4..=7 => 1,
+        _ => 2,
+    };
+
+    let mut best: Option<FuzzyMatch> = None;
+    let mut index = 0;
+    while index < chars.len() {
+        if !chars[index].lower.is_alphanumeric() {
+            index += 1;
+            continue;
+        }
+        let start = index;
+        while index < chars.len() && chars[index].lower.is_alphanumeric() {
+            index += 1;
+        }
+        let word = &chars[start..index];
+        if word.len() > MAX_TYPO_WORD_LEN {
+            continue;
+        }
+        let lowered: Vec<char> = word.iter().map(|ch| ch.lower).collect();
+
+        let mut candidates = vec![lowered.len()];
+        if lowered.len() > pattern.len() {
+            candidates.push(pattern.len());
+        }
+        for len in candidates {
+            let distance = edit_distance(pattern, &lowered[..len]);
+            if distance == 0 || distance > allowed {
+                continue;
+            }
+            let matched = pattern.len() as i64;
+            // What an exact match of the word would score, minus the typos.
+            let score = matched * SCORE_MATCH + BONUS_BOUNDARY * 2 + (matched - 1) * BONUS_BOUNDARY
+                - distance as i64 * PENALTY_TYPO;
+            if best.as_ref().is_none_or(|best| score > best.score) {
+                let mut positions: Vec<usize> = word[..len].iter().map(|ch| ch.byte).collect();
+                positions.dedup();
+                best = Some(FuzzyMatch { score, positions });
+            }
+        }
+    }
+    best
+}
+
+/// Levenshtein distance that also counts swapping two adjacent characters as
+/// a single edit.
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let width = b.len() + 1;
+    let mut rows = vec![0usize; (a.len() + 1) * width];
+    for (j, cell) in rows.iter_mut().enumerate().take(width) {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        rows[i * width] = i;
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut value = (rows[(i - 1) * width + j] + 1)
+                .min(rows[i * width + j - 1] + 1)
+                .min(rows[(i - 1) * width + j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                value = value.min(rows[(i - 2) * width + j - 2] + 1);
+            }
+            rows[i * width + j] = value;
+        }
+    }
+    rows[a.len() * width + b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(pattern: &str, text: &str) -> i64 {
+        fuzzy_match(pattern, text)
+            .unwrap_or_else(|| panic!("`{pattern}` should match `{text}`"))
+            .score
+    }
+
+    fn pattern(text: &str) -> Vec<char> {
+        fold(text).chars().collect()
+    }
+
+    #[test]
+    fn consecutive_runs_beat_scattered_characters() {
+        assert!(score("clip", "clipboard") > score("clip", "c l i p"));
+        assert!(score("clip", "clipboard") > score("clip", "cool lips"));
+    }
+
+    #[test]
+    fn word_starts_beat_mid_word_matches() {
+        assert!(score("bar", "foo barn") > score("bar", "foobarn"));
+        // camelCase humps count as word starts.
+        assert!(score("cm", "ClipManager") > score("cm", "scrimmage"));
+
+        // The match anchors at the word start even when an earlier mid-word
+        // occurrence completes first.
+        let found = fuzzy_match("port", "import port").unwrap();
+        assert_eq!(found.positions, vec![7, 8, 9, 10]);
+    }
+
+    #[test]
+    fn ranking_is_deterministic() {
+        let texts = ["c l i p", "clipboard", "eclipse", "a clip"];
+        let mut ranked: Vec<(i64, &str)> = texts
+            .iter()
+            .map(|text| (score("clip", text), *text))
+            .collect();
+        ranked.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(b.1)));
+        let order: Vec<&str> = ranked.into_iter().map(|(_, text)| text).collect();
+        assert_eq!(order, vec!["a clip", "clipboard", "c l i p", "eclipse"]);
+    }
+
+    #[test]
+    fn typos_match_whole_words() {
+        assert!(subsequence_match(&pattern("recieve"), &text_chars("please receive it")).is_none());
+        let found = typo_match(&pattern("recieve"), &text_chars("please receive it")).unwrap();
+        assert_eq!(found.positions, (7..14).collect::<Vec<_>>());
+
+        let found = fuzzy_match("recieve", "please receive it").unwrap();
+        assert_eq!(found.positions, (7..14).collect::<Vec<_>>());
+        // A typo costs more than any exact match of the same word.
+        assert!(found.score < score("receive", "please receive it"));
+        // Transpositions count as one edit.
+        assert!(fuzzy_match("tset", "run the test").is_some());
+        // Long patterns may be two edits away, but not three.
+        assert!(fuzzy_match("clipbaord", "clpbrd").is_none());
+        assert!(fuzzy_match("kliipboard", "clipboard").is_some());
+    }
+
+    #[test]
+    fn short_patterns_get_no_typo_budget() {
+        for (short, text) in [("teh", "the"), ("cat", "cut"), ("ab", "ba")] {
+            assert!(
+                typo_match(&pattern(short), &text_chars(text)).is_none(),
+                "{short}"
+            );
+            assert!(fuzzy_match(short, text).is_none(), "{short}");
+        }
+        assert!(typo_match(&pattern("thee"), &text_chars("three")).is_some());
+    }
+
+    #[test]
+    fn folding_keeps_positions_on_char_boundaries() {
+        for (pattern, text) in [
+            ("strasse", "Die Straße"),
+            ("cafe", "Un café crème"),
+            ("uber", "Über alles"),
+            ("aeon", "Æon flux"),
+            ("ｃｌｉｐ", "clipboard"),
+            ("clip", "ｃｌｉｐ ｂｏａｒｄ"),
+        ] {
+            let found = fuzzy_match(pattern, text)
+                .unwrap_or_else(|| panic!("`{pattern}` should match `{text}`"));
+            assert!(!found.positions.is_empty(), "{pattern}");
+            assert!(
+                found.positions.windows(2).all(|pair| pair[0] < pair[1]),
+                "{pattern}: {:?}",
+                found.positions
+            );
+            for position in found.positions {
+                assert!(text.is_char_boundary(position), "{pattern} at {position}");
+            }
+        }
+
+        // Both halves of an expanded `ß` map to the same byte.
+        let found = fuzzy_match("strasse", "Straße").unwrap();
+        assert_eq!(found.positions, vec![0, 1, 2, 3, 4, 6]);
+    }
+
+    #[test]
+    fn empty_patterns_match_everything() {
+        assert_eq!(
+            fuzzy_match("", "anything"),
+            Some(FuzzyMatch {
+                score: 0,
+                positions: Vec::new(),
+            })
+        );
+    }
+}
@@ -1,26 +1,63 @@
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+use sea_orm::sea_query::{Alias, Expr, Func, LikeExpr, OnConflict, Query, SimpleExpr};
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, Condition, ConnectOptions, Database, DatabaseConnection,
-    EntityTrait, QueryFilter, QueryOrder, QuerySelect, Set,
+    ActiveModelTrait, ColumnTrait, Condition, ConnectOptions, ConnectionTrait, Database,
+    DatabaseConnection, EntityTrait, FromQueryResult, IdenStatic, JoinType, Order, QueryFilter,
+    QueryOrder, QuerySelect, Select, Set, TransactionTrait,
 };
 
 use crate::migration::Migrator;
+use crate::storage::crypto;
+use crate::storage::encryption::{open_entry, open_link};
 use crate::storage::entity::{ActiveModel, Column, Entity, Model};
+use crate::storage::entry_link;
+use crate::storage::entry_tag;
+use crate::storage::fuzzy::fuzzy_match;
+use crate::storage::integrity::release_image;
+use crate::storage::query_eval::{Candidate, QueryMatcher};
+use crate::storage::search_query::{HasFilter, QueryNode, QueryTerm, SearchQuery};
+use crate::storage::tag;
+use crate::storage::tokenizer::{query_terms, search_terms};
 use sea_orm_migration::MigratorTrait;
+use sqlx::sqlite::{SqliteJournalMode, SqliteSynchronous};
 
-pub async fn open_db(path: &Path) -> anyhow::Result<DatabaseConnection> {
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Opens and migrates the database at `path`. `secure_delete` overwrites
+/// deleted content, for encrypted histories; converting the history to
+/// match the encryption settings is `encryption::prepare_encryption`'s job.
+pub async fn open_db(path: &Path, secure_delete: bool) -> anyhow::Result<DatabaseConnection> {
     if let Some(parent) = path.parent() {
         std::fs::create_dir_all(parent)?;
     }
     let db_url = sqlite_url(path);
-    let options = ConnectOptions::new(db_url);
+    let mut options = ConnectOptions::new(db_url);
+    options.map_sqlx_sqlite_opts(move |opts| {
+        // Backs `/regex/` search terms. WAL lets the popup read while
+        // capture writes, and the timeout waits out a concurrent writer
+        // instead of failing with `database is locked`.
+        let opts = opts
+            .with_regexp()
+            .journal_mode(SqliteJournalMode::Wal)
+            .synchronous(SqliteSynchronous::Normal)
+            .busy_timeout(BUSY_TIMEOUT);
+        // Overwrite deleted content so replaced plaintext doesn't linger.
+        if secure_delete {
+            opts.pragma("secure_delete", "ON")
+        } else {
+            opts
+        }
+    });
     let db = Database::connect(options).await?;
     Migrator::up(&db, None).await?;
     Ok(db)
 }
 
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
 pub async fn load_last_hash(db: &DatabaseConnection) -> anyhow::Result<Option<String>> {
     let hash = Entity::find()
         .select_only()
@@ -32,92 +69,1247 @@ pub async fn load_last_hash(db: &DatabaseConnection) -> anyhow::Result<Option<St
     Ok(hash)
 }
 
+/// Every stored `content_hash`, for skipping entries that are already in
+/// the history.
+pub async fn load_content_hashes(db: &DatabaseConnection) -> anyhow::Result<HashSet<String>> {
+    let hashes = Entity::find()
+        .select_only()
+        .column(Column::ContentHash)
+        .into_tuple::<String>()
+        .all(db)
+        .await?;
+    Ok(hashes.into_iter().collect())
+}
+
+/// Sort position of the last entry of a page; the next page starts right
+/// after it, so entries captured while scrolling don't shift later pages.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PageCursor {
+    /// When search ranking was computed, reused so every page ranks the
+    /// same way.
+    clock: i64,
+    pin_order: Option<i32>,
+    /// Search rank, lower first; 0 when the list isn't ranked.
+    rank: f64,
+    id: i32,
+}
+
+impl PageCursor {
+    /// Whether an entry at this sort position comes after the cursor.
+    fn is_before(&self, pin_order: Option<i32>, rank: f64, id: i32) -> bool {
+        (self.pin_order.is_none(), self.pin_order)
+            .cmp(&(pin_order.is_none(), pin_order))
+            .then(self.rank.total_cmp(&rank))
+            .then(id.cmp(&self.id))
+            .is_lt()
+    }
+
+    /// The same check as `is_before`, as a SQL condition. `rank` is the
+    /// expression the list is ranked by, if any.
+    fn condition(&self, rank: Option<&SimpleExpr>) -> Condition {
+        let older = Column::Id.lt(self.id);
+        let later = match rank {
+            Some(rank) => Condition::any()
+                .add(Expr::expr(rank.clone()).gt(self.rank))
+                .add(Expr::expr(rank.clone()).eq(self.rank).and(older)),
+            None => Condition::all().add(older),
+        };
+        match self.pin_order {
+            Some(pin_order) => Condition::any()
+                .add(Column::PinOrder.gt(pin_order))
+                .add(Column::PinOrder.is_null())
+                .add(
+                    Condition::all()
+                        .add(Column::PinOrder.eq(pin_order))
+                        .add(later),
+                ),
+            None => Condition::all().add(Column::PinOrder.is_null()).add(later),
+        }
+    }
+}
+
+/// One page of the history list.
+pub struct EntryPage {
+    pub entries: Vec<Model>,
+    /// Where the next page starts; `None` once the list is exhausted.
+    pub next: Option<PageCursor>,
+}
+
+impl EntryPage {
+    fn new(entries: Vec<Model>, next: Option<PageCursor>) -> Self {
+        Self { entries, next }
+    }
+}
+
+/// The entries after `after` (or from the top) in list order: pinned
+/// first in pin order, then by search rank, then newest first.
 pub async fn load_entries_page(
     db: &DatabaseConnection,
-    query: Option<&str>,
-    offset: u64,
+    query: Option<&SearchQuery>,
+    fuzzy: bool,
+    after: Option<&PageCursor>,
     limit: u64,
-) -> anyhow::Result<Vec<Model>> {
+) -> anyhow::Result<EntryPage> {
+    load_page(db, query, fuzzy, None, after, limit).await
+}
+
+/// The entry with `id` if `query` matches it, for adding a new capture to
+/// a list that is already loaded.
+pub async fn load_matching_entry(
+    db: &DatabaseConnection,
+    query: Option<&SearchQuery>,
+    fuzzy: bool,
+    id: i32,
+) -> anyhow::Result<Option<Model>> {
+    let page = load_page(db, query, fuzzy, Some(id), None, 1).await?;
+    Ok(page.entries.into_iter().next())
+}
+
+/// `load_entries_page`, optionally limited to the single entry `only`.
+async fn load_page(
+    db: &DatabaseConnection,
+    query: Option<&SearchQuery>,
+    fuzzy: bool,
+    only: Option<i32>,
+    after: Option<&PageCursor>,
+    limit: u64,
+) -> anyhow::Result<EntryPage> {
+    let clock = after.map_or_else(unix_now, |cursor| cursor.clock);
+    // Pinned entries come first in their own order, even when searching.
     let mut select = Entity::find()
-        .order_by_desc(Column::Id)
-        .offset(offset)
-        .limit(limit);
+        .order_by(Expr::col((Entity, Column::PinOrder)).is_null(), Order::Asc)
+        .order_by_asc(Column::PinOrder);
+    if let Some(id) = only {
+        select = select.filter(Column::Id.eq(id));
+    }
 
+    let mut ranking = None;
     if let Some(query) = query {
-        let mut condition = Condition::all();
-        for token in query.split_whitespace() {
-            let token = token.trim();
-            if token.is_empty() {
-                continue;
+        if crypto::is_enabled() {
+            return load_encrypted_page(db, query, fuzzy, only, after, limit, clock).await;
+        }
+        let fuzzy_terms = query.fuzzy_terms();
+        if fuzzy && !fuzzy_terms.is_empty() {
+            return load_fuzzy_page(db, query, &fuzzy_terms, only, after, limit, clock).await;
+        }
+
+        select = select.filter(compile_node(&query.root));
+
+        let terms: Vec<String> = query
+            .ranking_terms()
+            .into_iter()
+            .filter_map(fts_expression)
+            .collect();
+        if !terms.is_empty() {
+            let fts_query = terms.join(" OR ");
+            join_fts_scores(&mut select, &fts_query);
+            let rank = rank_expression(clock);
+            select = select.order_by(rank.clone(), Order::Asc);
+            ranking = Some((fts_query, rank));
+        }
+    }
+
+    if let Some(cursor) = after {
+        let mut cursor = *cursor;
+        if let Some((fts_query, _)) = &ranking {
+            // bm25 scores shift as entries come and go, so the cursor's
+            // entry is ranked again against the history as it is now.
+            if let Some(rank) = load_rank(db, fts_query, clock, cursor.id).await? {
+                cursor.rank = rank;
             }
+        }
+        select = select.filter(cursor.condition(ranking.as_ref().map(|(_, rank)| rank)));
+    }
+    let entries: Vec<Model> = select
+        .order_by_desc(Column::Id)
+        .limit(limit)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(open_entry)
+        .collect();
+
+    let next = match entries.last() {
+        Some(last) if entries.len() as u64 == limit => {
+            let rank = match &ranking {
+                Some((fts_query, _)) => load_rank(db, fts_query, clock, last.id)
+                    .await?
+                    .unwrap_or_default(),
+                None => 0.0,
+            };
+            Some(PageCursor {
+                clock,
+                pin_order: last.pin_order,
+                rank,
+                id: last.id,
+            })
+        }
+        _ => None,
+    };
+    Ok(EntryPage::new(entries, next))
+}
+
+/// Joins each entry's bm25 score for `fts_query` as `fts.score`.
+fn join_fts_scores(select: &mut Select<Entity>, fts_query: &str) {
+    QuerySelect::query(select).join_subquery(
+        JoinType::LeftJoin,
+        Query::select()
+            .expr_as(Expr::cust("rowid"), Alias::new("rowid"))
+            .expr_as(Expr::cust(bm25_expression()), Alias::new("score"))
+            .from(Alias::new(FTS_TABLE))
+            .and_where(Expr::cust_with_values(
+                format!("{FTS_TABLE} MATCH ?"),
+                [fts_query.to_string()],
+            ))
+            .to_owned(),
+        Alias::new("fts"),
+        Expr::col((Alias::new("fts"), Alias::new("rowid")))
+            .equals((Alias::new("clipboard_entries"), Alias::new("id"))),
+    );
+}
+
+/// Search rank as of `clock`, lower first. bm25 scores are negative with
+/// lower meaning better; dividing by the age factor pulls older entries
+/// towards zero.
+fn rank_expression(clock: i64) -> SimpleExpr {
+    Expr::cust_with_values(
+        format!(
+            "COALESCE(fts.score, 0.0) / \
+             (1.0 + MAX(? - clipboard_entries.created_at, 0) / {RECENCY_DECAY_SECS}.0)"
+        ),
+        [clock],
+    )
+}
+
+/// The rank of one entry, for the cursor after it; `None` once the entry
+/// is deleted.
+async fn load_rank(
+    db: &DatabaseConnection,
+    fts_query: &str,
+    clock: i64,
+    id: i32,
+) -> anyhow::Result<Option<f64>> {
+    let mut select = Entity::find()
+        .select_only()
+        .column_as(rank_expression(clock), "rank")
+        .filter(Column::Id.eq(id));
+    join_fts_scores(&mut select, fts_query);
+    Ok(select.into_tuple::<f64>().one(db).await?)
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
+
+/// Entries read per batch by searches that match in Rust rather than SQL.
+/// Every matching entry is scanned; only sort keys are kept between batches.
+const SCAN_BATCH_SIZE: u64 = 2_000;
+
+/// Characters of each field considered when fuzzy scoring.
+const FUZZY_FIELD_CHARS: usize = 4_096;
+
+/// Sort key of an entry matched in Rust. The entry itself is loaded again
+/// only if it lands on the requested page.
+struct ScoredEntry {
+    score: i64,
+    pin_order: Option<i32>,
+    id: i32,
+}
+
+impl ScoredEntry {
+    fn new(score: i64, entry: &Model) -> Self {
+        Self {
+            score,
+            pin_order: entry.pin_order,
+            id: entry.id,
+        }
+    }
+
+    /// Cursor rank; a higher score ranks lower.
+    fn rank(&self) -> f64 {
+        -(self.score as f64)
+    }
+}
+
+/// Up to `SCAN_BATCH_SIZE` entries matching `condition` that are older than
+/// `before`, newest first.
+async fn load_scan_batch(
+    db: &DatabaseConnection,
+    condition: &Condition,
+    only: Option<i32>,
+    before: Option<i32>,
+) -> anyhow::Result<Vec<Model>> {
+    let mut select = Entity::find().filter(condition.clone());
+    if let Some(id) = only {
+        select = select.filter(Column::Id.eq(id));
+    }
+    if let Some(before) = before {
+        select = select.filter(Column::Id.lt(before));
+    }
+    Ok(select
+        .order_by_desc(Column::Id)
+        .limit(SCAN_BATCH_SIZE)
+        .all(db)
+        .await?)
+}
+
+async fn load_fuzzy_page(
+    db: &DatabaseConnection,
+    query: &SearchQuery,
+    terms: &[&str],
+    only: Option<i32>,
+    after: Option<&PageCursor>,
+    limit: u64,
+    clock: i64,
+) -> anyhow::Result<EntryPage> {
+    let condition = compile_without_fuzzy_terms(&query.root);
+    let terms: Arc<Vec<String>> = Arc::new(terms.iter().map(|term| term.to_string()).collect());
+
+    let mut scored = Vec::new();
+    let mut before = None;
+    loop {
+        let batch = load_scan_batch(db, &condition, only, before).await?;
+        let Some(last) = batch.last() else {
+            break;
+        };
+        before = Some(last.id);
+        let exhausted = (batch.len() as u64) < SCAN_BATCH_SIZE;
+        let terms = Arc::clone(&terms);
+        scored.extend(
+            async_std::task::spawn_blocking(move || {
+                batch
+                    .into_iter()
+                    .map(open_entry)
+                    .filter_map(|entry| {
+                        Some(ScoredEntry::new(fuzzy_score(&terms, &entry)?, &entry))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .await,
+        );
+        if exhausted {
+            break;
+        }
+    }
+    page_by_score(db, scored, after, limit, clock).await
+}
+
+/// Encrypted text can't be matched in SQL, so every entry is decrypted in
+/// batches and the whole query is evaluated here instead.
+async fn load_encrypted_page(
+    db: &DatabaseConnection,
+    query: &SearchQuery,
+    fuzzy: bool,
+    only: Option<i32>,
+    after: Option<&PageCursor>,
+    limit: u64,
+    clock: i64,
+) -> anyhow::Result<EntryPage> {
+    let query = Arc::new(query.clone());
+    let terms: Arc<Vec<String>> = Arc::new(if fuzzy {
+        query
+            .fuzzy_terms()
+            .into_iter()
+            .map(str::to_string)
+            .collect()
+    } else {
+        Vec::new()
+    });
+
+    let mut scored = Vec::new();
+    let mut before = None;
+    loop {
+        let batch = load_scan_batch(db, &Condition::all(), only, before).await?;
+        let Some(last) = batch.last() else {
+            break;
+        };
+        before = Some(last.id);
+        let exhausted = (batch.len() as u64) < SCAN_BATCH_SIZE;
+
+        let ids: Vec<i32> = batch.iter().map(|entry| entry.id).collect();
+        let mut links = load_entry_links(db, &ids).await?;
+        let mut tags = load_entry_tags(db, &ids).await?;
+        let candidates: Vec<Candidate> = batch
+            .into_iter()
+            .map(|entry| {
+                let links = links.remove(&entry.id).unwrap_or_default();
+                let tags = tags
+                    .remove(&entry.id)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|tag| tag.name)
+                    .collect();
+                Candidate::new(open_entry(entry), links, tags)
+            })
+            .collect();
+
+        let query = Arc::clone(&query);
+        let terms = Arc::clone(&terms);
+        scored.extend(
+            async_std::task::spawn_blocking(move || {
+                let matcher = QueryMatcher::new(&query.root);
+                candidates
+                    .into_iter()
+                    .filter(|candidate| {
+                        if terms.is_empty() {
+                            matcher.matches(candidate)
+                        } else {
+                            matcher.matches_without_fuzzy_terms(candidate)
+                        }
+                    })
+                    .filter_map(|candidate| {
+                        let entry = candidate.entry;
+                        Some(ScoredEntry::new(fuzzy_score(&terms, &entry)?, &entry))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .await,
+        );
+        if exhausted {
+            break;
+        }
+    }
+    page_by_score(db, scored, after, limit, clock).await
+}
+
+/// Sum of the terms' fuzzy scores, or `None` if any term doesn't match.
+fn fuzzy_score(terms: &[String], entry: &Model) -> Option<i64> {
+    if terms.is_empty() {
+        return Some(0);
+    }
+    let haystack = fuzzy_haystack(entry);
+    let mut score = 0;
+    for term in terms {
+        score += fuzzy_match(term, &haystack)?.score;
+    }
+    Some(score)
+}
+
+/// Orders scored entries pinned first, then by score and recency, and
+/// loads the page after `after`.
+async fn page_by_score(
+    db: &DatabaseConnection,
+    mut scored: Vec<ScoredEntry>,
+    after: Option<&PageCursor>,
+    limit: u64,
+    clock: i64,
+) -> anyhow::Result<EntryPage> {
+    scored.sort_by(|a, b| {
+        (a.pin_order.is_none(), a.pin_order)
+            .cmp(&(b.pin_order.is_none(), b.pin_order))
+            .then(b.score.cmp(&a.score))
+            .then(b.id.cmp(&a.id))
+    });
+    let page: Vec<ScoredEntry> = scored
+        .into_iter()
+        .filter(|entry| {
+            after.is_none_or(|cursor| cursor.is_before(entry.pin_order, entry.rank(), entry.id))
+        })
+        .take(limit as usize)
+        .collect();
+    let next = match page.last() {
+        Some(last) if page.len() as u64 == limit => Some(PageCursor {
+            clock,
+            pin_order: last.pin_order,
+            rank: last.rank(),
+            id: last.id,
+        }),
+        _ => None,
+    };
+
+    let ids: Vec<i32> = page.iter().map(|entry| entry.id).collect();
+    let mut loaded: HashMap<i32, Model> = Entity::find()
+        .filter(Column::Id.is_in(ids.iter().copied()))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|entry| (entry.id, entry))
+        .collect();
+    let entries = ids
+        .iter()
+        .filter_map(|id| loaded.remove(id))
+        .map(open_entry)
+        .collect();
+    Ok(EntryPage::new(entries, next))
+}
+
+/// Compiles the query minus the terms returned by `fuzzy_terms`.
+fn compile_without_fuzzy_terms(root: &QueryNode) -> Condition {
+    match root {
+        QueryNode::Term(QueryTerm::Text(_)) => Condition::all(),
+        QueryNode::And(children) => children
+            .iter()
+            .filter(|child| !matches!(child, QueryNode::Term(QueryTerm::Text(_))))
+            .fold(Condition::all(), |condition, child| {
+                condition.add(compile_node(child))
+            }),
+        root => compile_node(root),
+    }
+}
+
+fn fuzzy_haystack(entry: &Model) -> String {
+    [
+        Some(entry.content.as_str()),
+        entry.text_content.as_deref(),
+        entry.ocr_text.as_deref(),
+        entry.image_codes.as_deref(),
+        entry.file_paths.as_deref(),
+        entry.link_url.as_deref(),
+        entry.link_title.as_deref(),
+        entry.link_description.as_deref(),
+        entry.link_site_name.as_deref(),
+        entry.source_app_title.as_deref(),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|field| match field.char_indices().nth(FUZZY_FIELD_CHARS) {
+        Some((end, _)) => &field[..end],
+        None => field,
+    })
+    .collect::<Vec<_>>()
+    .join("\n")
+}
+
+fn compile_node(node: &QueryNode) -> Condition {
+    match node {
+        QueryNode::And(children) => children.iter().fold(Condition::all(), |condition, child| {
+            condition.add(compile_node(child))
+        }),
+        QueryNode::Or(children) => children.iter().fold(Condition::any(), |condition, child| {
+            condition.add(compile_node(child))
+        }),
+        QueryNode::Not(child) => compile_node(child).not(),
+        QueryNode::Term(term) => compile_term(term),
+    }
+}
+
+fn compile_term(term: &QueryTerm) -> Condition {
+    match term {
+        QueryTerm::Text(text) | QueryTerm::Phrase(text) => {
+            let Some(expression) = fts_expression(term) else {
+                // Nothing the index tokenizes, e.g. lone punctuation.
+                return Condition::all().add(nullable_contains(Column::Content, text));
+            };
+            // Extracted link titles are covered through `search_terms`.
+            Condition::all().add(Column::Id.in_subquery(fts_match(expression)))
+        }
+        QueryTerm::Regex(pattern) => SEARCH_COLUMNS.iter().fold(Condition::any(), |any, column| {
+            any.add(Expr::cust_with_values(
+                format!(
+                    "COALESCE(clipboard_entries.{}, '') REGEXP ?",
+                    column.as_str()
+                ),
+                [pattern.clone()],
+            ))
+        }),
+        QueryTerm::Type(content_type) => {
+            Condition::all().add(Column::ContentType.eq(content_type.as_str()))
+        }
+        QueryTerm::App(app) => Condition::any()
+            .add(nullable_contains(Column::SourceExePath, app))
+            .add(nullable_contains(Column::SourceAppTitle, app)),
+        QueryTerm::Site(site) => site_filter(site),
+        QueryTerm::Before(timestamp) => Condition::all().add(Column::CreatedAt.lt(*timestamp)),
+        QueryTerm::After(timestamp) => Condition::all().add(Column::CreatedAt.gte(*timestamp)),
+        QueryTerm::Has(filter) => has_filter(*filter),
+        QueryTerm::Pinned => Condition::all().add(Column::PinOrder.is_not_null()),
+        QueryTerm::Tag(name) => Condition::all().add(
+            Column::Id.in_subquery(
+                Query::select()
+                    .column((entry_tag::Entity, entry_tag::Column::EntryId))
+                    .from(entry_tag::Entity)
+                    .inner_join(
+                        tag::Entity,
+                        Expr::col((tag::Entity, tag::Column::Id))
+                            .equals((entry_tag::Entity, entry_tag::Column::TagId)),
+                    )
+                    // `tags.name` compares without regard to case.
+                    .and_where(Expr::col((tag::Entity, tag::Column::Name)).eq(name.as_str()))
+                    .to_owned(),
+            ),
+        ),
+    }
+}
+
+/// Columns a `/regex/` is matched against.
+const SEARCH_COLUMNS: [Column; 11] = [
+    Column::Content,
+    Column::TextContent,
+    Column::OcrText,
+    Column::ImageCodes,
+    Column::FilePaths,
+    Column::LinkUrl,
+    Column::LinkCleanUrl,
+    Column::LinkTitle,
+    Column::LinkDescription,
+    Column::LinkSiteName,
+    Column::SourceAppTitle,
+];
+
+/// `LIKE` that treats NULL as an empty string, so negating it keeps rows
+/// where the column is unset. `value` is matched literally.
+fn nullable_contains(column: Column, value: &str) -> sea_orm::sea_query::SimpleExpr {
+    Expr::expr(Func::coalesce([
+        Expr::col((Entity, column)).into(),
+        Expr::val("").into(),
+    ]))
+    .like(LikeExpr::new(format!("%{}%", escape_like(value))).escape('\\'))
+}
+
+/// Escapes the `LIKE` wildcards in user text, for use with `ESCAPE '\'`.
+fn escape_like(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for ch in value.chars() {
+        if matches!(ch, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(ch);
+    }
+    escaped
+}
+
+/// Matches link entries and extracted links whose host is `site` or one of
+/// its subdomains.
+fn site_filter(site: &str) -> Condition {
+    let patterns = [
+        format!("%://{site}/%"),
+        format!("%://{site}"),
+        format!("%://{site}:%"),
+        format!("%://%.{site}/%"),
+        format!("%://%.{site}"),
+        format!("%://%.{site}:%"),
+    ];
+    let mut condition = Condition::any();
+    let mut link_condition = Condition::any();
+    for pattern in patterns {
+        for column in [Column::LinkUrl, Column::LinkCleanUrl] {
             condition = condition.add(
-                Condition::any()
-                    .add(Column::Content.contains(token))
-                    .add(Column::TextContent.contains(token))
-                    .add(Column::OcrText.contains(token))
-                    .add(Column::FilePaths.contains(token))
-                    .add(Column::LinkUrl.contains(token))
-                    .add(Column::LinkTitle.contains(token))
-                    .add(Column::LinkDescription.contains(token))
-                    .add(Column::LinkSiteName.contains(token))
-                    .add(Column::SourceAppTitle.contains(token))
-                    .add(Column::SourceExePath.contains(token)),
+                Expr::expr(Func::coalesce([
+                    Expr::col((Entity, column)).into(),
+                    Expr::val("").into(),
+                ]))
+                .like(pattern.as_str()),
             );
         }
-        select = select.filter(condition);
+        link_condition = link_condition.add(entry_link::Column::Url.like(pattern.as_str()));
+    }
+    condition.add(
+        Column::Id.in_subquery(
+            Query::select()
+                .column(entry_link::Column::EntryId)
+                .from(entry_link::Entity)
+                .cond_where(link_condition)
+                .to_owned(),
+        ),
+    )
+}
+
+const FTS_TABLE: &str = "clipboard_entries_fts";
+
+/// An entry this old ranks at half of its text relevance.
+const RECENCY_DECAY_SECS: i64 = 7 * 24 * 60 * 60;
+
+/// Per-column `bm25` weights, in the column order of the FTS migration.
+/// Titles are what people remember; app and path hints matter least.
+const FTS_COLUMN_WEIGHTS: [f32; 13] = [
+    1.0,  // content
+    1.0,  // text_content
+    0.75, // ocr_text
+    1.0,  // image_codes
+    1.0,  // file_paths
+    1.0,  // link_url
+    1.0,  // link_clean_url
+    2.0,  // link_title
+    0.75, // link_description
+    1.0,  // link_site_name
+    0.5,  // source_app_title
+    0.25, // source_exe_path
+    1.0,  // search_terms
+];
+
+fn bm25_expression() -> String {
+    let weights = FTS_COLUMN_WEIGHTS
+        .iter()
+        .map(|weight| weight.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("bm25({FTS_TABLE}, {weights})")
+}
+
+/// Turns a text term into an FTS5 query over the shared tokenizer's terms:
+/// words match as prefixes (the way the highlighter does) and phrases match
+/// as consecutive words. This replaced the old `LIKE '%term%'` scan, so a
+/// term no longer matches in the middle of a word: `clip` finds `clipboard`
+/// but `board` does not. CJK text becomes a phrase of character pairs, which
+/// `search_terms` stores. Returns `None` for terms with nothing to index,
+/// such as lone punctuation.
+fn fts_expression(term: &QueryTerm) -> Option<String> {
+    let (text, prefix) = match term {
+        QueryTerm::Text(text) => (text, true),
+        QueryTerm::Phrase(text) => (text, false),
+        _ => return None,
+    };
+    let terms = query_terms(text);
+    if terms.is_empty() {
+        return None;
+    }
+    // Terms are made of letters and digits only, so they need no escaping.
+    let quoted = format!("\"{}\"", terms.join(" "));
+    Some(if prefix { format!("{quoted}*") } else { quoted })
+}
+
+fn fts_match(expression: String) -> sea_orm::sea_query::SelectStatement {
+    Query::select()
+        .expr(Expr::cust("rowid"))
+        .from(Alias::new(FTS_TABLE))
+        .and_where(Expr::cust_with_values(
+            format!("{FTS_TABLE} MATCH ?"),
+            [expression],
+        ))
+        .to_owned()
+}
+
+/// Maps `has:link` / `has:email` onto the extracted links table, and
+/// `has:code` onto images with a decoded QR code or barcode.
+fn has_filter(filter: HasFilter) -> Condition {
+    let kind = match filter {
+        HasFilter::Link => "url",
+        HasFilter::Email => "email",
+        HasFilter::Code => return Condition::all().add(Column::ImageCodes.is_not_null()),
+    };
+    let linked_entries = Column::Id.in_subquery(
+        Query::select()
+            .column(entry_link::Column::EntryId)
+            .from(entry_link::Entity)
+            .and_where(entry_link::Column::Kind.eq(kind))
+            .to_owned(),
+    );
+    match filter {
+        HasFilter::Link => Condition::any()
+            .add(Column::ContentType.eq("link"))
+            .add(linked_entries),
+        _ => Condition::all().add(linked_entries),
     }
+}
 
-    let entries = select.all(db).await?;
-    Ok(entries)
+pub async fn load_entry_links(
+    db: &DatabaseConnection,
+    entry_ids: &[i32],
+) -> anyhow::Result<HashMap<i32, Vec<entry_link::Model>>> {
+    if entry_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let links = entry_link::Entity::find()
+        .filter(entry_link::Column::EntryId.is_in(entry_ids.iter().copied()))
+        .order_by_asc(entry_link::Column::EntryId)
+        .order_by_asc(entry_link::Column::StartOffset)
+        .all(db)
+        .await?;
+    let mut grouped: HashMap<i32, Vec<entry_link::Model>> = HashMap::new();
+    for link in links {
+        grouped
+            .entry(link.entry_id)
+            .or_default()
+            .push(open_link(link));
+    }
+    Ok(grouped)
 }
 
+/// A tag in use, with the number of entries carrying it.
+#[derive(Clone, Debug, PartialEq, FromQueryResult)]
+pub struct TagSummary {
+    pub id: i32,
+    pub name: String,
+    pub entry_count: i64,
+}
+
+/// Tags on at least one entry, by name.
+pub async fn load_tags(db: &DatabaseConnection) -> anyhow::Result<Vec<TagSummary>> {
+    let mut select = tag::Entity::find()
+        .select_only()
+        .column(tag::Column::Id)
+        .column(tag::Column::Name)
+        .column_as(entry_tag::Column::EntryId.count(), "entry_count")
+        .group_by(tag::Column::Id)
+        .order_by_asc(tag::Column::Name);
+    QuerySelect::query(&mut select).inner_join(
+        entry_tag::Entity,
+        Expr::col((entry_tag::Entity, entry_tag::Column::TagId))
+            .equals((tag::Entity, tag::Column::Id)),
+    );
+    let tags = select.into_model::<TagSummary>().all(db).await?;
+    Ok(tags)
+}
+
+pub async fn load_entry_tags(
+    db: &DatabaseConnection,
+    entry_ids: &[i32],
+) -> anyhow::Result<HashMap<i32, Vec<tag::Model>>> {
+    if entry_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let mut select = tag::Entity::find()
+        .select_only()
+        .column(entry_tag::Column::EntryId)
+        .column(tag::Column::Id)
+        .column(tag::Column::Name)
+        .column(tag::Column::CreatedAt)
+        .filter(entry_tag::Column::EntryId.is_in(entry_ids.iter().copied()))
+        .order_by_asc(tag::Column::Name);
+    QuerySelect::query(&mut select).inner_join(
+        entry_tag::Entity,
+        Expr::col((entry_tag::Entity, entry_tag::Column::TagId))
+            .equals((tag::Entity, tag::Column::Id)),
+    );
+    let rows = select
+        .into_tuple::<(i32, i32, String, i64)>()
+        .all(db)
+        .await?;
+    let mut grouped: HashMap<i32, Vec<tag::Model>> = HashMap::new();
+    for (entry_id, id, name, created_at) in rows {
+        grouped.entry(entry_id).or_default().push(tag::Model {
+            id,
+            name,
+            created_at,
+        });
+    }
+    Ok(grouped)
+}
+
+/// Adds the tag called `name` to the entry, creating the tag if needed.
+pub async fn tag_entry(db: &impl ConnectionTrait, entry_id: i32, name: &str) -> anyhow::Result<()> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(anyhow::anyhow!("Tag names can't be empty"));
+    }
+    let created_at = unix_now();
+    tag::Entity::insert(tag::ActiveModel {
+        name: Set(name.to_string()),
+        created_at: Set(created_at),
+        ..Default::default()
+    })
+    .on_conflict(
+        OnConflict::column(tag::Column::Name)
+            .do_nothing()
+            .to_owned(),
+    )
+    .exec_without_returning(db)
+    .await?;
+    let tag_id = tag::Entity::find()
+        .select_only()
+        .column(tag::Column::Id)
+        .filter(tag::Column::Name.eq(name))
+        .into_tuple::<i32>()
+        .one(db)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Tag `{name}` was not saved"))?;
+    entry_tag::Entity::insert(entry_tag::ActiveModel {
+        entry_id: Set(entry_id),
+        tag_id: Set(tag_id),
+    })
+    .on_conflict(
+        OnConflict::columns([entry_tag::Column::EntryId, entry_tag::Column::TagId])
+            .do_nothing()
+            .to_owned(),
+    )
+    .exec_without_returning(db)
+    .await?;
+    Ok(())
+}
+
+pub async fn untag_entry(
+    db: &DatabaseConnection,
+    entry_id: i32,
+    tag_id: i32,
+) -> anyhow::Result<()> {
+    entry_tag::Entity::delete_many()
+        .filter(entry_tag::Column::EntryId.eq(entry_id))
+        .filter(entry_tag::Column::TagId.eq(tag_id))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+#[derive(Default)]
 pub struct ClipboardEntryInput<'a> {
     pub content_type: &'a str,
     pub content_hash: &'a str,
     pub content: &'a str,
     pub text_content: Option<&'a str>,
     pub ocr_text: Option<&'a str>,
+    pub ocr_layout: Option<&'a str>,
+    pub ocr_attempt: Option<&'a str>,
     pub image_path: Option<&'a str>,
+    pub image_codes: Option<&'a str>,
     pub file_paths: Option<&'a str>,
     pub link_url: Option<&'a str>,
+    pub link_clean_url: Option<&'a str>,
     pub link_title: Option<&'a str>,
     pub link_description: Option<&'a str>,
     pub link_site_name: Option<&'a str>,
+    pub link_details: Option<&'a str>,
     pub source_app_title: Option<&'a str>,
     pub source_exe_path: Option<&'a str>,
+    /// Unix seconds; `None` stamps the entry with the current time.
+    pub created_at: Option<i64>,
+}
+
+pub struct EntryLinkInput<'a> {
+    pub kind: &'a str,
+    pub url: &'a str,
+    pub start_offset: i32,
+    pub end_offset: i32,
+    pub title: Option<&'a str>,
+    pub description: Option<&'a str>,
+    pub site_name: Option<&'a str>,
 }
 
 pub async fn insert_clipboard_entry(
-    db: &DatabaseConnection,
+    db: &impl ConnectionTrait,
     input: ClipboardEntryInput<'_>,
-) -> anyhow::Result<()> {
-    let created_at = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_secs() as i64;
+) -> anyhow::Result<i32> {
+    let created_at = input.created_at.unwrap_or_else(unix_now);
+    // Plaintext terms would defeat encryption; search decrypts instead.
+    let terms = if crypto::is_enabled() {
+        None
+    } else {
+        entry_search_terms(
+            [
+                Some(input.content),
+                input.text_content,
+                input.ocr_text,
+                input.image_codes,
+                input.file_paths,
+                input.link_url,
+                input.link_title,
+                input.link_description,
+                input.link_site_name,
+            ],
+            [],
+        )
+    };
     let model = ActiveModel {
-        content: Set(input.content.to_string()),
+        content: Set(crypto::seal(input.content)?),
         created_at: Set(created_at),
         content_type: Set(input.content_type.to_string()),
         content_hash: Set(input.content_hash.to_string()),
-        text_content: Set(input.text_content.map(str::to_string)),
-        ocr_text: Set(input.ocr_text.map(str::to_string)),
+        text_content: Set(crypto::seal_opt(input.text_content)?),
+        ocr_text: Set(crypto::seal_opt(input.ocr_text)?),
+        ocr_layout: Set(crypto::seal_opt(input.ocr_layout)?),
+        ocr_attempt: Set(input.ocr_attempt.map(str::to_string)),
         image_path: Set(input.image_path.map(str::to_string)),
-        file_paths: Set(input.file_paths.map(str::to_string)),
-        link_url: Set(input.link_url.map(str::to_string)),
-        link_title: Set(input.link_title.map(str::to_string)),
-        link_description: Set(input.link_description.map(str::to_string)),
-        link_site_name: Set(input.link_site_name.map(str::to_string)),
-        source_app_title: Set(input.source_app_title.map(str::to_string)),
-        source_exe_path: Set(input.source_exe_path.map(str::to_string)),
+        image_codes: Set(crypto::seal_opt(input.image_codes)?),
+        file_paths: Set(crypto::seal_opt(input.file_paths)?),
+        link_url: Set(crypto::seal_opt(input.link_url)?),
+        link_clean_url: Set(crypto::seal_opt(input.link_clean_url)?),
+        link_title: Set(crypto::seal_opt(input.link_title)?),
+        link_description: Set(crypto::seal_opt(input.link_description)?),
+        link_site_name: Set(crypto::seal_opt(input.link_site_name)?),
+        link_details: Set(crypto::seal_opt(input.link_details)?),
+        source_app_title: Set(crypto::seal_opt(input.source_app_title)?),
+        source_exe_path: Set(crypto::seal_opt(input.source_exe_path)?),
+        search_terms: Set(terms),
+        encrypted: Set(crypto::is_enabled()),
         ..Default::default()
     };
-    model.insert(db).await?;
+    let model = model.insert(db).await?;
+    Ok(model.id)
+}
+
+pub async fn insert_entry_links(
+    db: &impl ConnectionTrait,
+    entry_id: i32,
+    links: &[EntryLinkInput<'_>],
+) -> anyhow::Result<()> {
+    if links.is_empty() {
+        return Ok(());
+    }
+    let mut models = Vec::with_capacity(links.len());
+    for link in links {
+        models.push(entry_link::ActiveModel {
+            entry_id: Set(entry_id),
+            kind: Set(link.kind.to_string()),
+            url: Set(crypto::seal(link.url)?),
+            start_offset: Set(link.start_offset),
+            end_offset: Set(link.end_offset),
+            title: Set(crypto::seal_opt(link.title)?),
+            description: Set(crypto::seal_opt(link.description)?),
+            site_name: Set(crypto::seal_opt(link.site_name)?),
+            encrypted: Set(crypto::is_enabled()),
+            ..Default::default()
+        });
+    }
+    entry_link::Entity::insert_many(models).exec(db).await?;
+    refresh_search_terms(db, entry_id).await
+}
+
+pub struct LinkMetadataUpdate<'a> {
+    pub title: Option<&'a str>,
+    pub description: Option<&'a str>,
+    pub site_name: Option<&'a str>,
+    /// Only stored for link entries; extracted links keep the generic fields.
+    pub details: Option<&'a str>,
+}
+
+pub async fn update_link_metadata(
+    db: &DatabaseConnection,
+    id: i32,
+    update: LinkMetadataUpdate<'_>,
+) -> anyhow::Result<()> {
+    Entity::update_many()
+        .col_expr(
+            Column::LinkTitle,
+            Expr::value(crypto::seal_opt(update.title)?),
+        )
+        .col_expr(
+            Column::LinkDescription,
+            Expr::value(crypto::seal_opt(update.description)?),
+        )
+        .col_expr(
+            Column::LinkSiteName,
+            Expr::value(crypto::seal_opt(update.site_name)?),
+        )
+        .col_expr(
+            Column::LinkDetails,
+            Expr::value(crypto::seal_opt(update.details)?),
+        )
+        .filter(Column::Id.eq(id))
+        .exec(db)
+        .await?;
+    refresh_search_terms(db, id).await
+}
+
+pub async fn update_entry_link_metadata(
+    db: &DatabaseConnection,
+    link_id: i32,
+    update: LinkMetadataUpdate<'_>,
+) -> anyhow::Result<()> {
+    entry_link::Entity::update_many()
+        .col_expr(
+            entry_link::Column::Title,
+            Expr::value(crypto::seal_opt(update.title)?),
+        )
+        .col_expr(
+            entry_link::Column::Description,
+            Expr::value(crypto::seal_opt(update.description)?),
+        )
+        .col_expr(
+            entry_link::Column::SiteName,
+            Expr::value(crypto::seal_opt(update.site_name)?),
+        )
+        .filter(entry_link::Column::Id.eq(link_id))
+        .exec(db)
+        .await?;
+    let entry_id = entry_link::Entity::find_by_id(link_id)
+        .select_only()
+        .column(entry_link::Column::EntryId)
+        .into_tuple::<i32>()
+        .one(db)
+        .await?;
+    match entry_id {
+        Some(entry_id) => refresh_search_terms(db, entry_id).await,
+        None => Ok(()),
+    }
+}
+
+/// Images without OCR text that have not been tried under `attempt` yet.
+pub async fn load_images_missing_ocr(
+    db: &DatabaseConnection,
+    attempt: &str,
+) -> anyhow::Result<Vec<(i32, String)>> {
+    let entries = Entity::find()
+        .filter(Column::ContentType.eq("image"))
+        .filter(Column::OcrText.is_null())
+        .filter(Column::ImagePath.is_not_null())
+        .filter(
+            Condition::any()
+                .add(Column::OcrAttempt.is_null())
+                .add(Column::OcrAttempt.ne(attempt)),
+        )
+        .order_by_desc(Column::CreatedAt)
+        .all(db)
+        .await?;
+    Ok(entries
+        .into_iter()
+        .filter_map(|entry| Some((entry.id, entry.image_path?)))
+        .collect())
+}
+
+pub async fn update_ocr_result(
+    db: &DatabaseConnection,
+    id: i32,
+    text: Option<&str>,
+    layout: Option<&str>,
+    attempt: &str,
+) -> anyhow::Result<()> {
+    Entity::update_many()
+        .col_expr(Column::OcrText, Expr::value(crypto::seal_opt(text)?))
+        .col_expr(Column::OcrLayout, Expr::value(crypto::seal_opt(layout)?))
+        .col_expr(Column::OcrAttempt, Expr::value(attempt))
+        .filter(Column::Id.eq(id))
+        .exec(db)
+        .await?;
+    refresh_search_terms(db, id).await
+}
+
+/// Recomputes `search_terms` after any of the text it is built from changed.
+/// Encrypted histories keep no terms.
+pub async fn refresh_search_terms(db: &impl ConnectionTrait, id: i32) -> anyhow::Result<()> {
+    let Some(entry) = Entity::find_by_id(id).one(db).await? else {
+        return Ok(());
+    };
+    if crypto::is_enabled() {
+        if entry.search_terms.is_some() {
+            Entity::update_many()
+                .col_expr(Column::SearchTerms, Expr::value(None::<String>))
+                .filter(Column::Id.eq(id))
+                .exec(db)
+                .await?;
+        }
+        return Ok(());
+    }
+    let entry = open_entry(entry);
+    let links: Vec<entry_link::Model> = entry_link::Entity::find()
+        .filter(entry_link::Column::EntryId.eq(id))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(open_link)
+        .collect();
+    let link_texts = links.iter().flat_map(|link| {
+        [
+            link.title.as_deref(),
+            link.description.as_deref(),
+            link.site_name.as_deref(),
+        ]
+        .into_iter()
+        .flatten()
+    });
+    let terms = entry_search_terms(
+        [
+            Some(entry.content.as_str()),
+            entry.text_content.as_deref(),
+            entry.ocr_text.as_deref(),
+            entry.image_codes.as_deref(),
+            entry.file_paths.as_deref(),
+            entry.link_url.as_deref(),
+            entry.link_title.as_deref(),
+            entry.link_description.as_deref(),
+            entry.link_site_name.as_deref(),
+        ],
+        link_texts,
+    );
+    if terms == entry.search_terms {
+        return Ok(());
+    }
+    Entity::update_many()
+        .col_expr(Column::SearchTerms, Expr::value(terms))
+        .filter(Column::Id.eq(id))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+fn entry_search_terms<'a>(
+    fields: [Option<&'a str>; 9],
+    link_texts: impl IntoIterator<Item = &'a str>,
+) -> Option<String> {
+    let mut fields: Vec<&str> = fields.into_iter().flatten().collect();
+    // Text entries often repeat `content` in `text_content`.
+    fields.dedup();
+    search_terms(fields.into_iter().chain(link_texts))
+}
+
+/// Pins the entry above all other pinned entries, or unpins it.
+pub async fn set_entry_pinned(
+    db: &DatabaseConnection,
+    id: i32,
+    pinned: bool,
+) -> anyhow::Result<()> {
+    let txn = db.begin().await?;
+    let mut ids = pinned_entry_ids(&txn).await?;
+    ids.retain(|&pinned_id| pinned_id != id);
+    if pinned {
+        ids.insert(0, id);
+    } else {
+        Entity::update_many()
+            .col_expr(Column::PinOrder, Expr::value(Option::<i32>::None))
+            .filter(Column::Id.eq(id))
+            .exec(&txn)
+            .await?;
+    }
+    write_pin_order(&txn, &ids).await?;
+    txn.commit().await?;
+    Ok(())
+}
+
+/// Moves a pinned entry to the position currently held by `target_id`,
+/// shifting the entries in between by one.
+pub async fn move_pinned_entry(
+    db: &DatabaseConnection,
+    id: i32,
+    target_id: i32,
+) -> anyhow::Result<()> {
+    let txn = db.begin().await?;
+    let mut ids = pinned_entry_ids(&txn).await?;
+    let from = ids.iter().position(|&pinned_id| pinned_id == id);
+    let to = ids.iter().position(|&pinned_id| pinned_id == target_id);
+    let (Some(from), Some(to)) = (from, to) else {
+        return Ok(());
+    };
+    let id = ids.remove(from);
+    ids.insert(to, id);
+    write_pin_order(&txn, &ids).await?;
+    txn.commit().await?;
+    Ok(())
+}
+
+async fn pinned_entry_ids(db: &impl ConnectionTrait) -> anyhow::Result<Vec<i32>> {
+    let ids = Entity::find()
+        .select_only()
+        .column(Column::Id)
+        .filter(Column::PinOrder.is_not_null())
+        .order_by_asc(Column::PinOrder)
+        .order_by_desc(Column::Id)
+        .into_tuple::<i32>()
+        .all(db)
+        .await?;
+    Ok(ids)
+}
+
+/// Renumbers the pinned entries `0..` in the given order.
+async fn write_pin_order(db: &impl ConnectionTrait, ids: &[i32]) -> anyhow::Result<()> {
+    for (order, id) in ids.iter().enumerate() {
+        Entity::update_many()
+            .col_expr(Column::PinOrder, Expr::value(order as i32))
+            .filter(Column::Id.eq(*id))
+            .filter(
+                Condition::any()
+                    .add(Column::PinOrder.is_null())
+                    .add(Column::PinOrder.ne(order as i32)),
+            )
+            .exec(db)
+            .await?;
+    }
     Ok(())
 }
 
+/// Deletes the entry, and its image file unless another entry shares it.
 pub async fn delete_clipboard_entry(db: &DatabaseConnection, id: i32) -> anyhow::Result<()> {
+    let image_path = Entity::find_by_id(id)
+        .select_only()
+        .column(Column::ImagePath)
+        .into_tuple::<Option<String>>()
+        .one(db)
+        .await?
+        .flatten();
     Entity::delete_by_id(id).exec(db).await?;
+    if let Some(path) = image_path {
+        release_image(db, &path).await?;
+    }
     Ok(())
 }
 
@@ -125,3 +1317,478 @@ fn sqlite_url(path: &Path) -> String {
     let raw = path.to_string_lossy().replace('\\', "/");
     format!("sqlite:///{raw}?mode=rwc")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::search_query::parse_query;
+    use crate::test_support::{lock_storage, temp_dir};
+
+    async fn test_db(label: &str) -> DatabaseConnection {
+        open_db(&temp_dir(label).join("history.sqlite"), false)
+            .await
+            .expect("open test database")
+    }
+
+    async fn insert_image(db: &DatabaseConnection, hash: &str, attempt: Option<&str>) -> i32 {
+        let path = format!("/images/{hash}.bmp");
+        insert_clipboard_entry(
+            db,
+            ClipboardEntryInput {
+                content_type: "image",
+                content_hash: hash,
+                content: "Image",
+                image_path: Some(&path),
+                ocr_attempt: attempt,
+                ..ClipboardEntryInput::default()
+            },
+        )
+        .await
+        .unwrap()
+    }
+
+    #[test]
+    fn backfill_skips_images_tried_under_the_same_key() {
+        let _storage = lock_storage();
+        async_std::task::block_on(async {
+            let db = test_db("ocr-attempts").await;
+            let fresh = insert_image(&db, "fresh", None).await;
+            let tried = insert_image(&db, "tried", Some("Tesseract:en")).await;
+            let other = insert_image(&db, "other", Some("Tesseract:de")).await;
+
+            let ids = |pending: Vec<(i32, String)>| {
+                let mut ids: Vec<i32> = pending.into_iter().map(|(id, _)| id).collect();
+                ids.sort();
+                ids
+            };
+            let pending = load_images_missing_ocr(&db, "Tesseract:en").await.unwrap();
+            assert_eq!(ids(pending), vec![fresh, other]);
+
+            // An empty result still counts as tried.
+            update_ocr_result(&db, fresh, None, None, "Tesseract:en")
+                .await
+                .unwrap();
+            update_ocr_result(&db, other, Some("Total"), None, "Tesseract:en")
+                .await
+                .unwrap();
+            let pending = load_images_missing_ocr(&db, "Tesseract:en").await.unwrap();
+            assert!(pending.is_empty());
+
+            // Changing the languages retries what found nothing.
+            let pending = load_images_missing_ocr(&db, "Tesseract:en+de")
+                .await
+                .unwrap();
+            assert_eq!(ids(pending), vec![fresh, tried]);
+        });
+    }
+
+    async fn insert_text(db: &DatabaseConnection, text: &str) -> i32 {
+        insert_clipboard_entry(
+            db,
+            ClipboardEntryInput {
+                content_type: "text",
+                content_hash: text,
+                content: text,
+                text_content: Some(text),
+                ..ClipboardEntryInput::default()
+            },
+        )
+        .await
+        .unwrap()
+    }
+
+    async fn search(db: &DatabaseConnection, input: &str) -> Vec<i32> {
+        let query = parse_query(input).unwrap().unwrap();
+        let mut ids: Vec<i32> = load_entries_page(db, Some(&query), false, None, 50)
+            .await
+            .unwrap()
+            .entries
+            .into_iter()
+            .map(|entry| entry.id)
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn text_terms_match_word_prefixes() {
+        let _storage = lock_storage();
+        async_std::task::block_on(async {
+            let db = test_db("prefix-search").await;
+            let clipboard = insert_text(&db, "Clipboard manager notes").await;
+            let keyboard = insert_text(&db, "new keyboard layout").await;
+            let cafe = insert_text(&db, "Meet at the Café").await;
+
+            assert_eq!(search(&db, "clip").await, vec![clipboard]);
+            assert_eq!(search(&db, "KEY").await, vec![keyboard]);
+            assert_eq!(search(&db, "cafe").await, vec![cafe]);
+            // Substrings inside a word do not match.
+            assert!(search(&db, "board").await.is_empty());
+            assert!(search(&db, "anager").await.is_empty());
+            // Phrases match whole consecutive words.
+            assert_eq!(search(&db, "\"clipboard manager\"").await, vec![clipboard]);
+            assert!(search(&db, "\"clip manager\"").await.is_empty());
+        });
+    }
+
+    #[test]
+    fn like_fallback_matches_wildcards_literally() {
+        let _storage = lock_storage();
+        async_std::task::block_on(async {
+            let db = test_db("like-escape").await;
+            let percent = insert_text(&db, "50% off").await;
+            let underscore = insert_text(&db, "snake_case").await;
+            let backslash = insert_text(&db, r"C:\temp").await;
+            insert_text(&db, "plain words").await;
+
+            // Lone punctuation is not indexed, so these go through `LIKE`.
+            assert_eq!(search(&db, "%").await, vec![percent]);
+            assert_eq!(search(&db, "_").await, vec![underscore]);
+            assert_eq!(search(&db, r"\").await, vec![backslash]);
+            assert!(search(&db, "%_").await.is_empty());
+        });
+    }
+
+    /// Inserts `count` plain text entries in one statement.
+    async fn insert_filler(db: &DatabaseConnection, count: usize) {
+        let mut insert = Query::insert()
+            .into_table(Entity)
+            .columns([
+                Column::Content,
+                Column::CreatedAt,
+                Column::ContentType,
+                Column::ContentHash,
+            ])
+            .to_owned();
+        for index in 0..count {
+            insert.values_panic([
+                format!("filler entry {index}").into(),
+                unix_now().into(),
+                "text".into(),
+                format!("filler-{index}").into(),
+            ]);
+        }
+        db.execute(db.get_database_backend().build(&insert))
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn fuzzy_search_scans_past_the_first_batch() {
+        let _storage = lock_storage();
+        async_std::task::block_on(async {
+            let db = test_db("fuzzy-scan").await;
+            let oldest = insert_text(&db, "quarterly invoice draft").await;
+            insert_filler(&db, SCAN_BATCH_SIZE as usize * 2).await;
+            let middle = insert_text(&db, "invoice for the kitchen").await;
+            insert_filler(&db, SCAN_BATCH_SIZE as usize).await;
+            let newest = insert_text(&db, "the invoice").await;
+
+            let query = parse_query("invocie").unwrap().unwrap();
+            let first = load_entries_page(&db, Some(&query), true, None, 2)
+                .await
+                .unwrap();
+            let cursor = first.next.expect("a second page");
+            let second = load_entries_page(&db, Some(&query), true, Some(&cursor), 2)
+                .await
+                .unwrap();
+            assert!(second.next.is_none());
+
+            let mut found: Vec<i32> = first
+                .entries
+                .iter()
+                .chain(&second.entries)
+                .map(|entry| entry.id)
+                .collect();
+            found.sort();
+            assert_eq!(found, vec![oldest, middle, newest]);
+        });
+    }
+
+    /// Every page of `query` at `limit` entries each, joined up.
+    async fn all_pages(
+        db: &DatabaseConnection,
+        query: Option<&SearchQuery>,
+        fuzzy: bool,
+        limit: u64,
+    ) -> Vec<i32> {
+        let mut ids = Vec::new();
+        let mut after = None;
+        loop {
+            let page = load_entries_page(db, query, fuzzy, after.as_ref(), limit)
+                .await
+                .unwrap();
+            assert!(page.entries.len() as u64 <= limit);
+            ids.extend(page.entries.iter().map(|entry| entry.id));
+            match page.next {
+                Some(next) => after = Some(next),
+                None => return ids,
+            }
+        }
+    }
+
+    /// Texts sharing the same second, so identical ones rank exactly alike.
+    async fn insert_dated(db: &DatabaseConnection, text: &str, hash: &str) -> i32 {
+        insert_clipboard_entry(
+            db,
+            ClipboardEntryInput {
+                content_type: "text",
+                content_hash: hash,
+                content: text,
+                text_content: Some(text),
+                created_at: Some(1_700_000_000),
+                ..ClipboardEntryInput::default()
+            },
+        )
+        .await
+        .unwrap()
+    }
+
+    /// Pinned entries, ranked matches with ties, and plain matches.
+    async fn paging_db(label: &str) -> DatabaseConnection {
+        let db = test_db(label).await;
+        let texts = [
+            "invoice",
+            "invoice",
+            "invoice",
+            "invoice invoice draft",
+            "kitchen invoice for the flat",
+            "kitchen tiles",
+            "invoice",
+            "draft notes",
+            "the quarterly invoice draft",
+            "invoice",
+        ];
+        let mut ids = Vec::new();
+        for (index, text) in texts.into_iter().enumerate() {
+            ids.push(insert_dated(&db, text, &format!("paging-{index}")).await);
+        }
+        for index in [2, 5, 8] {
+            set_entry_pinned(&db, ids[index], true).await.unwrap();
+        }
+        db
+    }
+
+    const PAGING_QUERIES: [&str; 4] = ["", "invoice", "invoice draft", "kitchen"];
+
+    async fn assert_pages_join_up(db: &DatabaseConnection) {
+        for input in PAGING_QUERIES {
+            let query = parse_query(input).unwrap();
+            for fuzzy in [false, true] {
+                let full = all_pages(db, query.as_ref(), fuzzy, 100).await;
+                assert!(!full.is_empty(), "`{input}` matches nothing");
+                for limit in [1, 2, 3, 4] {
+                    let paged = all_pages(db, query.as_ref(), fuzzy, limit).await;
+                    assert_eq!(paged, full, "`{input}`, fuzzy: {fuzzy}, {limit} per page");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn pages_join_up_to_the_full_list() {
+        let _storage = lock_storage();
+        async_std::task::block_on(async {
+            let db = paging_db("paging").await;
+
+            let everything = all_pages(&db, None, false, 100).await;
+            assert_eq!(everything.len(), 10);
+            // Of the pins, "kitchen tiles" doesn't match.
+            let ranked = all_pages(&db, parse_query("invoice").unwrap().as_ref(), false, 100).await;
+            assert_eq!(ranked[..2], [everything[0], everything[2]]);
+            assert_pages_join_up(&db).await;
+
+            // Encrypted histories page in Rust rather than in SQL.
+            crypto::install(&[9; 32].into(), true).unwrap();
+            assert_pages_join_up(&db).await;
+        });
+    }
+
+    #[test]
+    fn pages_after_a_capture_continue_where_they_left_off() {
+        let _storage = lock_storage();
+        async_std::task::block_on(async {
+            let db = paging_db("paging-capture").await;
+            for input in PAGING_QUERIES {
+                let query = parse_query(input).unwrap();
+                let first = load_entries_page(&db, query.as_ref(), false, None, 3)
+                    .await
+                    .unwrap();
+                let captured = insert_text(&db, &format!("{input} invoice draft kitchen")).await;
+                let mut paged: Vec<i32> = first.entries.iter().map(|entry| entry.id).collect();
+                let mut after = first.next;
+                while let Some(cursor) = after {
+                    let page = load_entries_page(&db, query.as_ref(), false, Some(&cursor), 3)
+                        .await
+                        .unwrap();
+                    paged.extend(page.entries.iter().map(|entry| entry.id));
+                    after = page.next;
+                }
+
+                // A capture sorting into the page already shown is left for
+                // the list to add; one sorting after it turns up later.
+                let mut full = all_pages(&db, query.as_ref(), false, 100).await;
+                let position = full.iter().position(|&id| id == captured);
+                if position.is_some_and(|position| position < first.entries.len()) {
+                    full.retain(|&id| id != captured);
+                }
+                assert_eq!(paged, full, "`{input}`");
+                let matching = load_matching_entry(&db, query.as_ref(), false, captured)
+                    .await
+                    .unwrap();
+                assert_eq!(matching.map(|entry| entry.id), Some(captured), "`{input}`");
+
+                delete_clipboard_entry(&db, captured).await.unwrap();
+            }
+
+            let query = parse_query("kitchen").unwrap();
+            let other = insert_text(&db, "unrelated words").await;
+            let matching = load_matching_entry(&db, query.as_ref(), false, other)
+                .await
+                .unwrap();
+            assert!(matching.is_none());
+        });
+    }
+
+    const BENCH_ENTRIES: usize = 500_000;
+    const BENCH_WORDS: [&str; 24] = [
+        "invoice",
+        "meeting",
+        "deploy",
+        "rust",
+        "async",
+        "kitchen",
+        "budget",
+        "review",
+        "server",
+        "release",
+        "coffee",
+        "ticket",
+        "design",
+        "report",
+        "backup",
+        "travel",
+        "paris",
+        "quarterly",
+        "build",
+        "error",
+        "password",
+        "schedule",
+        "draft",
+        "notes",
+    ];
+
+    /// Seeds `BENCH_ENTRIES` text entries spread over a year, with rows
+    /// built like the insert path builds them so the FTS triggers run.
+    async fn seed_bench_entries(db: &DatabaseConnection) {
+        let now = unix_now();
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        let txn = db.begin().await.unwrap();
+        for batch in 0..BENCH_ENTRIES / 500 {
+            let mut insert = Query::insert()
+                .into_table(Entity)
+                .columns([
+                    Column::Content,
+                    Column::CreatedAt,
+                    Column::ContentType,
+                    Column::ContentHash,
+                    Column::TextContent,
+                    Column::SearchTerms,
+                ])
+                .to_owned();
+            for row in 0..500 {
+                let words: Vec<&str> = (0..8)
+                    .map(|_| BENCH_WORDS[next() as usize % BENCH_WORDS.len()])
+                    .collect();
+                let content = format!("{} {}", words.join(" "), batch * 500 + row);
+                let terms = entry_search_terms(
+                    [
+                        Some(content.as_str()),
+                        None,
+                        None,
+                        None,
+                        None,
+                        None,
+                        None,
+                        None,
+                        None,
+                    ],
+                    [],
+                );
+                let age = (next() % (365 * 24 * 60 * 60)) as i64;
+                insert.values_panic([
+                    content.clone().into(),
+                    (now - age).into(),
+                    "text".into(),
+                    format!("bench-{batch}-{row}").into(),
+                    content.into(),
+                    terms.into(),
+                ]);
+            }
+            txn.execute(txn.get_database_backend().build(&insert))
+                .await
+                .unwrap();
+        }
+        txn.commit().await.unwrap();
+    }
+
+    fn percentile(sorted: &[Duration], percent: usize) -> Duration {
+        sorted[(sorted.len() * percent / 100).min(sorted.len() - 1)]
+    }
+
+    /// Ranked search latency over a large history. Run with
+    /// `cargo test --release bench_ranked_search -- --ignored --nocapture`.
+    #[test]
+    #[ignore = "benchmark; seeds 500k entries"]
+    fn bench_ranked_search() {
+        let _storage = lock_storage();
+        async_std::task::block_on(async {
+            let db = test_db("bench-search").await;
+            let started = std::time::Instant::now();
+            seed_bench_entries(&db).await;
+            println!("seeded {BENCH_ENTRIES} entries in {:?}", started.elapsed());
+
+            for input in [
+                "invoice",
+                "rust async",
+                "quarterly report",
+                "\"coffee budget\"",
+                "deploy -error",
+            ] {
+                let query = parse_query(input).unwrap().unwrap();
+                let mut first_pages = Vec::new();
+                let mut next_pages = Vec::new();
+                for _ in 0..20 {
+                    let started = std::time::Instant::now();
+                    let page = load_entries_page(&db, Some(&query), false, None, 50)
+                        .await
+                        .unwrap();
+                    first_pages.push(started.elapsed());
+                    assert_eq!(page.entries.len(), 50);
+
+                    let cursor = page.next.expect("more results");
+                    let started = std::time::Instant::now();
+                    load_entries_page(&db, Some(&query), false, Some(&cursor), 50)
+                        .await
+                        .unwrap();
+                    next_pages.push(started.elapsed());
+                }
+                first_pages.sort();
+                next_pages.sort();
+                println!(
+                    "{input:<20} first page p50 {:>10.2?} p95 {:>10.2?} | next page p50 {:>10.2?} p95 {:>10.2?}",
+                    percentile(&first_pages, 50),
+                    percentile(&first_pages, 95),
+                    percentile(&next_pages, 50),
+                    percentile(&next_pages, 95),
+                );
+            }
+        });
+    }
+}
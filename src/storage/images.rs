@@ -1,14 +1,127 @@
-use std::fs;
-use std::path::PathBuf;
-
-use crate::storage::path::{image_path_for_hash, images_dir};
-
-pub fn save_image_bytes(hash: &str, bytes: &[u8]) -> anyhow::Result<PathBuf> {
-    let dir = images_dir()?;
-    fs::create_dir_all(&dir)?;
-    let path = image_path_for_hash(hash)?;
-    if !path.exists() {
-        fs::write(&path, bytes)?;
-    }
-    Ok(path)
-}
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use image::{ImageFormat, ImageOutputFormat};
+
+use crate::storage::crypto;
+use crate::storage::path::{image_path_in, images_dir, thumbnails_dir};
+
+/// Longest side of the history list thumbnails, in pixels.
+const THUMBNAIL_SIZE: u32 = 720;
+
+/// Tells apart the temporary files of concurrent saves.
+static NEXT_TEMP: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub async fn save_image_bytes(hash: &str, bytes: Vec<u8>) -> anyhow::Result<PathBuf> {
+    save_image_bytes_in(&images_dir()?, &thumbnails_dir()?, hash, bytes).await
+}
+
+/// `save_image_bytes` into the given images and thumbnails folders. The
+/// writing and the thumbnail run on a blocking thread.
+pub async fn save_image_bytes_in(
+    images_dir: &Path,
+    thumbnails_dir: &Path,
+    hash: &str,
+    bytes: Vec<u8>,
+) -> anyhow::Result<PathBuf> {
+    let images_dir = images_dir.to_path_buf();
+    let thumbnails_dir = thumbnails_dir.to_path_buf();
+    let path = image_path_in(&images_dir, hash);
+    async_std::task::spawn_blocking(move || {
+        if path.exists() {
+            return Ok(path);
+        }
+        fs::create_dir_all(&images_dir)?;
+        write_replacing(&path, &crypto::seal_bytes(&bytes)?)?;
+        // A thumbnail is a plaintext copy, so encrypted histories go without.
+        if !crypto::is_enabled() {
+            if let Err(err) = save_thumbnail(&thumbnails_dir, &path, &bytes) {
+                eprintln!("Failed to save thumbnail: {err}");
+            }
+        }
+        Ok(path)
+    })
+    .await
+}
+
+/// Writes under a temporary name and renames it into place, so readers
+/// never see a partly written image.
+fn write_replacing(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let temp = path.with_extension(format!("{}.tmp", NEXT_TEMP.fetch_add(1, Ordering::Relaxed)));
+    let written = fs::write(&temp, contents).and_then(|()| fs::rename(&temp, path));
+    if written.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    written
+}
+
+/// Stored images are bitmaps, which is what the Windows clipboard hands
+/// back; anything else is converted.
+pub fn to_bitmap(bytes: &[u8]) -> anyhow::Result<Vec<u8>> {
+    if bytes.starts_with(b"BM") {
+        return Ok(bytes.to_vec());
+    }
+    let image = image::load_from_memory(bytes)
+        .map_err(|err| anyhow::anyhow!("An unreadable image: {err}"))?;
+    let mut bitmap = Cursor::new(Vec::new());
+    image.write_to(&mut bitmap, ImageOutputFormat::Bmp)?;
+    Ok(bitmap.into_inner())
+}
+
+/// Reads a stored image file, decrypting it if needed.
+pub fn read_image_bytes(path: &Path) -> anyhow::Result<Vec<u8>> {
+    crypto::open_bytes(fs::read(path)?)
+}
+
+/// The cached thumbnail of a stored image, if there is one.
+pub fn thumbnail_path(image_path: &Path) -> Option<PathBuf> {
+    let path = thumbnail_path_for(image_path).ok()?;
+    path.is_file().then_some(path)
+}
+
+fn thumbnail_path_for(image_path: &Path) -> anyhow::Result<PathBuf> {
+    thumbnail_path_in(&thumbnails_dir()?, image_path)
+}
+
+fn thumbnail_path_in(dir: &Path, image_path: &Path) -> anyhow::Result<PathBuf> {
+    let name = image_path
+        .file_stem()
+        .ok_or_else(|| anyhow::anyhow!("Image path has no file name"))?;
+    Ok(dir.join(name).with_extension("png"))
+}
+
+fn save_thumbnail(dir: &Path, image_path: &Path, bytes: &[u8]) -> anyhow::Result<()> {
+    let image = image::load_from_memory(bytes)?;
+    let path = thumbnail_path_in(dir, image_path)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    image
+        .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+        .save_with_format(path, ImageFormat::Png)?;
+    Ok(())
+}
+
+pub fn remove_thumbnail(image_path: &Path) {
+    if let Ok(dir) = thumbnails_dir() {
+        remove_thumbnail_in(&dir, image_path);
+    }
+}
+
+/// Deletes the thumbnail of `image_path` cached in `dir`, if there is one.
+pub fn remove_thumbnail_in(dir: &Path, image_path: &Path) {
+    if let Ok(path) = thumbnail_path_in(dir, image_path) {
+        let _ = fs::remove_file(path);
+    }
+}
+
+/// Deletes every cached thumbnail in `dir`, usually `thumbnails_dir()`.
+pub fn clear_thumbnails(dir: &Path) -> anyhow::Result<()> {
+    match fs::remove_dir_all(dir) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+        _ => Ok(()),
+    }
+}
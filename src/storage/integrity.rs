@@ -0,0 +1,361 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter,
+    QuerySelect, Statement,
+};
+
+use crate::storage::entity::{Column, Entity};
+use crate::storage::images::{remove_thumbnail, remove_thumbnail_in};
+use crate::storage::path::{images_dir, thumbnails_dir};
+
+const DANGLING_LINKS_SQL: &str =
+    "entry_links WHERE entry_id NOT IN (SELECT id FROM clipboard_entries)";
+const DANGLING_TAGS_SQL: &str =
+    "entry_tags WHERE entry_id NOT IN (SELECT id FROM clipboard_entries) \
+     OR tag_id NOT IN (SELECT id FROM tags)";
+
+/// Image files younger than this may belong to an entry still being
+/// captured, so they are never treated as unused.
+const ORPHAN_GRACE: Duration = Duration::from_secs(10 * 60);
+
+/// Findings of `verify_storage`.
+#[derive(Clone, Debug, Default)]
+pub struct IntegrityReport {
+    /// Problems reported by SQLite's `quick_check`; empty when healthy.
+    pub database_errors: Vec<String>,
+    pub search_index_ok: bool,
+    /// Image files referenced by at least one entry.
+    pub image_files: usize,
+    /// Files in the images folder that no entry references.
+    pub orphaned_files: Vec<PathBuf>,
+    pub orphaned_bytes: u64,
+    /// Entries whose image file is gone. These can't be repaired.
+    pub missing_images: Vec<i32>,
+    /// Link and tag rows pointing at entries or tags that no longer exist.
+    pub dangling_rows: u64,
+    pub repaired: bool,
+}
+
+impl IntegrityReport {
+    /// Whether `verify_storage` with `repair` would change anything.
+    pub fn needs_repair(&self) -> bool {
+        !self.orphaned_files.is_empty() || !self.search_index_ok || self.dangling_rows > 0
+    }
+
+    pub fn summary_lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        if self.database_errors.is_empty() {
+            lines.push("Database: ok".to_string());
+        } else {
+            lines.push(format!(
+                "Database: {} problems found by SQLite",
+                self.database_errors.len()
+            ));
+            lines.extend(self.database_errors.iter().take(5).cloned());
+        }
+        lines.push(format!(
+            "Search index: {}",
+            match (self.search_index_ok, self.repaired) {
+                (true, _) => "ok",
+                (false, true) => "rebuilt",
+                (false, false) => "out of sync",
+            }
+        ));
+        lines.push(format!("Images in use: {}", self.image_files));
+        if !self.orphaned_files.is_empty() {
+            lines.push(format!(
+                "Unused image files: {} ({:.1} MB){}",
+                self.orphaned_files.len(),
+                self.orphaned_bytes as f64 / (1024.0 * 1024.0),
+                if self.repaired { ", deleted" } else { "" }
+            ));
+        }
+        if self.dangling_rows > 0 {
+            lines.push(format!(
+                "Leftover link and tag rows: {}{}",
+                self.dangling_rows,
+                if self.repaired { ", deleted" } else { "" }
+            ));
+        }
+        if !self.missing_images.is_empty() {
+            lines.push(format!(
+                "Entries whose image file is missing: {}",
+                self.missing_images.len()
+            ));
+        }
+        lines
+    }
+}
+
+impl fmt::Display for IntegrityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.summary_lines().join("\n"))
+    }
+}
+
+/// Problems SQLite's `quick_check` finds; empty when the file is healthy.
+pub async fn database_errors(db: &DatabaseConnection) -> anyhow::Result<Vec<String>> {
+    let backend = db.get_database_backend();
+    let rows = db
+        .query_all(Statement::from_string(backend, "PRAGMA quick_check"))
+        .await?;
+    let mut errors = Vec::new();
+    for row in rows {
+        let message: String = row.try_get_by_index(0)?;
+        if message != "ok" {
+            errors.push(message);
+        }
+    }
+    Ok(errors)
+}
+
+/// Checks the database, the full-text index and the images folder against
+/// each other. With `repair`, rebuilds the index and deletes unused image
+/// files and leftover rows; entries are never deleted.
+pub async fn verify_storage(
+    db: &DatabaseConnection,
+    repair: bool,
+) -> anyhow::Result<IntegrityReport> {
+    verify_storage_in(db, &images_dir()?, &thumbnails_dir()?, repair).await
+}
+
+async fn verify_storage_in(
+    db: &DatabaseConnection,
+    images_dir: &Path,
+    thumbnails_dir: &Path,
+    repair: bool,
+) -> anyhow::Result<IntegrityReport> {
+    let backend = db.get_database_backend();
+    let mut report = IntegrityReport {
+        database_errors: database_errors(db).await?,
+        ..IntegrityReport::default()
+    };
+
+    report.search_index_ok = db
+        .execute_unprepared(
+            "INSERT INTO clipboard_entries_fts(clipboard_entries_fts, rank) \
+             VALUES ('integrity-check', 1)",
+        )
+        .await
+        .is_ok();
+
+    for table in [DANGLING_LINKS_SQL, DANGLING_TAGS_SQL] {
+        let row = db
+            .query_one(Statement::from_string(
+                backend,
+                format!("SELECT COUNT(*) FROM {table}"),
+            ))
+            .await?;
+        if let Some(row) = row {
+            report.dangling_rows += row.try_get_by_index::<i64>(0)?.max(0) as u64;
+        }
+    }
+
+    // Images are stored once per content hash, named after it, so entries
+    // reference a blob through the file name.
+    let image_paths = Entity::find()
+        .select_only()
+        .column(Column::Id)
+        .column(Column::ImagePath)
+        .filter(Column::ImagePath.is_not_null())
+        .into_tuple::<(i32, String)>()
+        .all(db)
+        .await?;
+    let mut references: HashMap<String, usize> = HashMap::new();
+    for (id, path) in &image_paths {
+        let path = Path::new(path);
+        if !path.is_file() {
+            report.missing_images.push(*id);
+        }
+        if let Some(name) = path.file_name() {
+            *references
+                .entry(name.to_string_lossy().into_owned())
+                .or_default() += 1;
+        }
+    }
+
+    if images_dir.is_dir() {
+        for file in std::fs::read_dir(images_dir)? {
+            let file = file?;
+            let metadata = file.metadata()?;
+            let is_recent = metadata
+                .modified()
+                .ok()
+                .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+                .is_none_or(|age| age < ORPHAN_GRACE);
+            if !metadata.is_file() {
+                continue;
+            }
+            let name = file.file_name().to_string_lossy().into_owned();
+            if references.contains_key(&name) {
+                report.image_files += 1;
+            } else if !is_recent {
+                report.orphaned_bytes += metadata.len();
+                report.orphaned_files.push(file.path());
+            }
+        }
+    }
+
+    if repair && report.needs_repair() {
+        if !report.search_index_ok {
+            db.execute_unprepared(
+                "INSERT INTO clipboard_entries_fts(clipboard_entries_fts) VALUES ('rebuild')",
+            )
+            .await?;
+        }
+        for table in [DANGLING_LINKS_SQL, DANGLING_TAGS_SQL] {
+            db.execute_unprepared(&format!("DELETE FROM {table}"))
+                .await?;
+        }
+        for path in &report.orphaned_files {
+            remove_thumbnail_in(thumbnails_dir, path);
+            if let Err(err) = std::fs::remove_file(path) {
+                eprintln!("Failed to remove {}: {err}", path.display());
+            }
+        }
+        report.repaired = true;
+    }
+    Ok(report)
+}
+
+/// Deletes an image file once no entry references it anymore. Returns the
+/// bytes freed.
+pub async fn release_image(db: &impl ConnectionTrait, path: &str) -> anyhow::Result<Option<u64>> {
+    let still_used = Entity::find()
+        .filter(Column::ImagePath.eq(path))
+        .count(db)
+        .await?
+        > 0;
+    if still_used {
+        return Ok(None);
+    }
+    let bytes = std::fs::metadata(path).map_or(0, |metadata| metadata.len());
+    remove_thumbnail(Path::new(path));
+    match std::fs::remove_file(path) {
+        Ok(()) => Ok(Some(bytes)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+
+    use super::*;
+    use crate::storage::history::{insert_clipboard_entry, open_db, ClipboardEntryInput};
+    use crate::test_support::{lock_storage, temp_dir};
+
+    async fn insert_image(db: &DatabaseConnection, hash: &str, path: &Path) -> i32 {
+        let path = path.to_string_lossy();
+        insert_clipboard_entry(
+            db,
+            ClipboardEntryInput {
+                content_type: "image",
+                content_hash: hash,
+                content: "Image",
+                image_path: Some(&path),
+                ..ClipboardEntryInput::default()
+            },
+        )
+        .await
+        .unwrap()
+    }
+
+    /// Writes an image file dated `age` ago.
+    fn write_file(path: &Path, age: Duration) {
+        fs::write(path, b"BM").unwrap();
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(SystemTime::now() - age)
+            .unwrap();
+    }
+
+    #[test]
+    fn counts_shared_images_once_and_flags_missing_ones() {
+        let _storage = lock_storage();
+        async_std::task::block_on(async {
+            let dir = temp_dir("verify-references");
+            let db = open_db(&dir.join("history.sqlite"), false).await.unwrap();
+            let images = dir.join("images");
+            fs::create_dir_all(&images).unwrap();
+            let shared = images.join("image_a.bmp");
+            let single = images.join("image_b.bmp");
+            write_file(&shared, Duration::ZERO);
+            write_file(&single, Duration::ZERO);
+
+            insert_image(&db, "a", &shared).await;
+            insert_image(&db, "a2", &shared).await;
+            insert_image(&db, "b", &single).await;
+            let missing = insert_image(&db, "c", &images.join("image_c.bmp")).await;
+
+            let report = verify_storage_in(&db, &images, &dir.join("thumbnails"), false)
+                .await
+                .unwrap();
+            assert_eq!(report.image_files, 2);
+            assert_eq!(report.missing_images, [missing]);
+            assert!(report.orphaned_files.is_empty());
+            assert!(report.search_index_ok);
+            assert!(!report.needs_repair());
+
+            // A file stays in use until its last entry goes.
+            let first = insert_image(&db, "d", &single).await;
+            Entity::delete_by_id(first).exec(&db).await.unwrap();
+            let report = verify_storage_in(&db, &images, &dir.join("thumbnails"), false)
+                .await
+                .unwrap();
+            assert_eq!(report.image_files, 2);
+        });
+    }
+
+    #[test]
+    fn repair_deletes_old_unreferenced_files() {
+        let _storage = lock_storage();
+        async_std::task::block_on(async {
+            let dir = temp_dir("verify-orphans");
+            let db = open_db(&dir.join("history.sqlite"), false).await.unwrap();
+            let images = dir.join("images");
+            let thumbnails = dir.join("thumbnails");
+            fs::create_dir_all(&images).unwrap();
+            fs::create_dir_all(&thumbnails).unwrap();
+            let used = images.join("image_used.bmp");
+            let orphan = images.join("image_orphan.bmp");
+            let fresh = images.join("image_fresh.bmp");
+            let hour = Duration::from_secs(60 * 60);
+            write_file(&used, hour);
+            write_file(&orphan, hour);
+            write_file(&fresh, Duration::ZERO);
+            fs::write(thumbnails.join("image_orphan.png"), b"png").unwrap();
+            insert_image(&db, "used", &used).await;
+
+            let report = verify_storage_in(&db, &images, &thumbnails, false)
+                .await
+                .unwrap();
+            assert_eq!(report.orphaned_files, vec![orphan.clone()]);
+            assert_eq!(report.orphaned_bytes, 2);
+            assert!(report.needs_repair());
+            assert!(orphan.exists(), "checking alone deletes nothing");
+
+            let report = verify_storage_in(&db, &images, &thumbnails, true)
+                .await
+                .unwrap();
+            assert!(report.repaired);
+            assert!(!orphan.exists());
+            assert!(!thumbnails.join("image_orphan.png").exists());
+            // Recent files may belong to a capture still in progress.
+            assert!(fresh.exists());
+            assert!(used.exists());
+
+            let report = verify_storage_in(&db, &images, &thumbnails, false)
+                .await
+                .unwrap();
+            assert!(!report.needs_repair());
+        });
+    }
+}
@@ -1,4 +1,19 @@
+pub mod archive;
+pub mod backup;
+pub mod crypto;
+pub mod encryption;
 pub mod entity;
+pub mod entry_link;
+pub mod entry_tag;
+pub mod fuzzy;
 pub mod history;
 pub mod images;
+pub mod integrity;
 pub mod path;
+pub mod query_eval;
+pub mod relocate;
+pub mod retention;
+pub mod search_query;
+pub mod service;
+pub mod tag;
+pub mod tokenizer;
@@ -1,25 +1,211 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
-fn local_data_dir() -> anyhow::Result<PathBuf> {
-    let base = std::env::var_os("LOCALAPPDATA")
-        .map(PathBuf::from)
-        .unwrap_or_else(|| PathBuf::from("."));
-    let folder = if cfg!(debug_assertions) {
+use anyhow::anyhow;
+
+use crate::settings::settings;
+
+/// Keeps settings, history and cache under this directory instead of the
+/// platform locations. `--data-dir <path>` on the command line wins.
+pub const DATA_DIR_ENV: &str = "CLIPBOARD_MANAGER_DATA_DIR";
+const DATA_DIR_FLAG: &str = "--data-dir";
+const PORTABLE_FLAG: &str = "--portable";
+/// A file next to the executable that turns on portable mode, keeping
+/// everything in a `data` folder beside it.
+const PORTABLE_MARKER: &str = "portable";
+
+pub const DB_FILE: &str = "clipboard_history.db";
+pub const IMAGES_FOLDER: &str = "clipboard_images";
+pub const BACKUPS_FOLDER: &str = "backups";
+
+static ROOT: OnceLock<Option<Root>> = OnceLock::new();
+static DATA_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// A single directory holding settings, history and cache, chosen at
+/// launch.
+struct Root {
+    dir: PathBuf,
+    source: &'static str,
+}
+
+fn app_folder() -> &'static str {
+    if cfg!(debug_assertions) {
         "gpui-clipboard-manager-dev"
     } else {
         "gpui-clipboard-manager"
+    }
+}
+
+fn root() -> Option<&'static Root> {
+    ROOT.get_or_init(|| {
+        let mut args = std::env::args().skip(1);
+        let mut portable = false;
+        while let Some(arg) = args.next() {
+            if arg == DATA_DIR_FLAG {
+                if let Some(dir) = args.next() {
+                    return Some(Root {
+                        dir: PathBuf::from(dir),
+                        source: DATA_DIR_FLAG,
+                    });
+                }
+            } else if let Some(dir) = arg.strip_prefix("--data-dir=") {
+                return Some(Root {
+                    dir: PathBuf::from(dir),
+                    source: DATA_DIR_FLAG,
+                });
+            } else if arg == PORTABLE_FLAG {
+                portable = true;
+            }
+        }
+        if let Some(dir) = std::env::var_os(DATA_DIR_ENV).filter(|dir| !dir.is_empty()) {
+            return Some(Root {
+                dir: PathBuf::from(dir),
+                source: DATA_DIR_ENV,
+            });
+        }
+        let exe_dir = std::env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(Path::to_path_buf))?;
+        if portable || exe_dir.join(PORTABLE_MARKER).exists() {
+            return Some(Root {
+                dir: exe_dir.join("data"),
+                source: "portable mode",
+            });
+        }
+        None
+    })
+    .as_ref()
+}
+
+/// An absolute path from an XDG variable; relative values are ignored as
+/// the spec requires.
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn xdg_dir(var: &str, fallback: &str) -> anyhow::Result<PathBuf> {
+    if let Some(dir) = std::env::var_os(var)
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+    {
+        return Ok(dir);
+    }
+    Ok(home_dir()?.join(fallback))
+}
+
+#[cfg(not(target_os = "windows"))]
+fn home_dir() -> anyhow::Result<PathBuf> {
+    std::env::var_os("HOME")
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+        .ok_or_else(|| anyhow!("HOME is not set; set {DATA_DIR_ENV} to choose a data directory"))
+}
+
+#[cfg(target_os = "windows")]
+fn platform_dir(kind: DirKind) -> anyhow::Result<PathBuf> {
+    let base = std::env::var_os("LOCALAPPDATA")
+        .map(PathBuf::from)
+        .ok_or_else(|| {
+            anyhow!("LOCALAPPDATA is not set; set {DATA_DIR_ENV} to choose a data directory")
+        })?;
+    let dir = base.join(app_folder());
+    Ok(match kind {
+        DirKind::Cache => dir.join("cache"),
+        DirKind::Config | DirKind::Data => dir,
+    })
+}
+
+#[cfg(target_os = "macos")]
+fn platform_dir(kind: DirKind) -> anyhow::Result<PathBuf> {
+    let library = home_dir()?.join("Library");
+    Ok(match kind {
+        DirKind::Cache => library.join("Caches"),
+        DirKind::Config | DirKind::Data => library.join("Application Support"),
+    }
+    .join(app_folder()))
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn platform_dir(kind: DirKind) -> anyhow::Result<PathBuf> {
+    let base = match kind {
+        DirKind::Config => xdg_dir("XDG_CONFIG_HOME", ".config")?,
+        DirKind::Data => xdg_dir("XDG_DATA_HOME", ".local/share")?,
+        DirKind::Cache => xdg_dir("XDG_CACHE_HOME", ".cache")?,
+    };
+    Ok(base.join(app_folder()))
+}
+
+#[derive(Clone, Copy)]
+enum DirKind {
+    Config,
+    Data,
+    Cache,
+}
+
+fn config_dir() -> anyhow::Result<PathBuf> {
+    match root() {
+        Some(root) => Ok(root.dir.clone()),
+        None => platform_dir(DirKind::Config),
+    }
+}
+
+/// Where the database and images live: the launch override, else the
+/// directory they were moved to, else the platform default. Resolved once
+/// per launch, so a move takes effect after a restart.
+pub fn data_dir() -> anyhow::Result<PathBuf> {
+    if let Some(dir) = DATA_DIR.get() {
+        return Ok(dir.clone());
+    }
+    let dir = match root() {
+        Some(root) => root.dir.clone(),
+        None => match settings().storage.data_dir {
+            Some(dir) => dir,
+            None => platform_dir(DirKind::Data)?,
+        },
     };
-    Ok(base.join(folder))
+    Ok(DATA_DIR.get_or_init(|| dir).clone())
+}
+
+pub fn cache_dir() -> anyhow::Result<PathBuf> {
+    match root() {
+        Some(root) => Ok(root.dir.join("cache")),
+        None => platform_dir(DirKind::Cache),
+    }
+}
+
+/// Why the data directory can't be moved from the app, if it can't.
+pub fn data_dir_override() -> Option<&'static str> {
+    root().map(|root| root.source)
 }
 
 pub fn default_db_path() -> anyhow::Result<PathBuf> {
-    Ok(local_data_dir()?.join("clipboard_history.db"))
+    Ok(data_dir()?.join(DB_FILE))
+}
+
+pub fn settings_path() -> anyhow::Result<PathBuf> {
+    Ok(config_dir()?.join("settings.json"))
 }
 
 pub fn images_dir() -> anyhow::Result<PathBuf> {
-    Ok(local_data_dir()?.join("clipboard_images"))
+    Ok(data_dir()?.join(IMAGES_FOLDER))
 }
 
+#[cfg(target_os = "windows")]
 pub fn image_path_for_hash(hash: &str) -> anyhow::Result<PathBuf> {
-    Ok(images_dir()?.join(format!("image_{hash}.bmp")))
+    Ok(image_path_in(&images_dir()?, hash))
+}
+
+/// Where the image with content hash `hash` is stored within `dir`.
+pub fn image_path_in(dir: &Path, hash: &str) -> PathBuf {
+    dir.join(format!("image_{hash}.bmp"))
+}
+
+pub fn backups_dir() -> anyhow::Result<PathBuf> {
+    Ok(data_dir()?.join(BACKUPS_FOLDER))
+}
+
+/// Where damaged or replaced databases are set aside instead of deleted.
+pub fn quarantine_dir() -> anyhow::Result<PathBuf> {
+    Ok(data_dir()?.join("quarantine"))
+}
+
+pub fn thumbnails_dir() -> anyhow::Result<PathBuf> {
+    Ok(cache_dir()?.join("thumbnails"))
 }
@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+
+use regex::Regex;
+
+use crate::storage::entity::Model;
+use crate::storage::entry_link;
+use crate::storage::search_query::{HasFilter, QueryNode, QueryTerm};
+use crate::storage::tokenizer::{fold, query_terms, search_terms};
+
+/// A decrypted entry with everything a query looks at.
+pub struct Candidate {
+    pub entry: Model,
+    pub links: Vec<entry_link::Model>,
+    /// Tag names.
+    pub tags: Vec<String>,
+    /// The entry's words, as `search_terms` would store them.
+    terms: Vec<String>,
+}
+
+impl Candidate {
+    pub fn new(entry: Model, links: Vec<entry_link::Model>, tags: Vec<String>) -> Self {
+        let fields = [
+            Some(entry.content.as_str()),
+            entry.text_content.as_deref(),
+            entry.ocr_text.as_deref(),
+            entry.image_codes.as_deref(),
+            entry.file_paths.as_deref(),
+            entry.link_url.as_deref(),
+            entry.link_clean_url.as_deref(),
+            entry.link_title.as_deref(),
+            entry.link_description.as_deref(),
+            entry.link_site_name.as_deref(),
+            entry.source_app_title.as_deref(),
+            entry.source_exe_path.as_deref(),
+        ];
+        let link_texts = links.iter().flat_map(|link| {
+            [
+                link.title.as_deref(),
+                link.description.as_deref(),
+                link.site_name.as_deref(),
+            ]
+        });
+        let terms = search_terms(fields.into_iter().chain(link_texts).flatten())
+            .map(|terms| terms.split(' ').map(str::to_string).collect())
+            .unwrap_or_default();
+        Self {
+            entry,
+            links,
+            tags,
+            terms,
+        }
+    }
+}
+
+/// Evaluates a query against entries in memory, for histories whose text
+/// is encrypted and can't be searched in SQL. Mirrors the SQL compiled by
+/// `history`, with text terms matched against the same folded words.
+pub struct QueryMatcher<'a> {
+    root: &'a QueryNode,
+    regexes: HashMap<&'a str, Regex>,
+}
+
+impl<'a> QueryMatcher<'a> {
+    pub fn new(root: &'a QueryNode) -> Self {
+        let mut regexes = HashMap::new();
+        collect_regexes(root, &mut regexes);
+        Self { root, regexes }
+    }
+
+    pub fn matches(&self, candidate: &Candidate) -> bool {
+        self.node_matches(self.root, candidate)
+    }
+
+    /// Like `matches`, minus the terms `SearchQuery::fuzzy_terms` returns.
+    pub fn matches_without_fuzzy_terms(&self, candidate: &Candidate) -> bool {
+        match self.root {
+            QueryNode::Term(QueryTerm::Text(_)) => true,
+            QueryNode::And(children) => children
+                .iter()
+                .filter(|child| !matches!(child, QueryNode::Term(QueryTerm::Text(_))))
+                .all(|child| self.node_matches(child, candidate)),
+            root => self.node_matches(root, candidate),
+        }
+    }
+
+    fn node_matches(&self, node: &QueryNode, candidate: &Candidate) -> bool {
+        match node {
+            QueryNode::And(children) => children
+                .iter()
+                .all(|child| self.node_matches(child, candidate)),
+            QueryNode::Or(children) => children
+                .iter()
+                .any(|child| self.node_matches(child, candidate)),
+            QueryNode::Not(child) => !self.node_matches(child, candidate),
+            QueryNode::Term(term) => self.term_matches(term, candidate),
+        }
+    }
+
+    fn term_matches(&self, term: &QueryTerm, candidate: &Candidate) -> bool {
+        let entry = &candidate.entry;
+        match term {
+            QueryTerm::Text(text) | QueryTerm::Phrase(text) => {
+                let needle = query_terms(text);
+                if needle.is_empty() {
+                    return fold(&entry.content).contains(&fold(text));
+                }
+                contains_words(
+                    &candidate.terms,
+                    &needle,
+                    matches!(term, QueryTerm::Text(_)),
+                )
+            }
+            QueryTerm::Regex(pattern) => self.regexes.get(pattern.as_str()).is_some_and(|regex| {
+                searchable_fields(entry).any(|field| regex.is_match(field.unwrap_or("")))
+            }),
+            QueryTerm::Type(content_type) => entry.content_type == *content_type,
+            QueryTerm::App(app) => [
+                entry.source_exe_path.as_deref(),
+                entry.source_app_title.as_deref(),
+            ]
+            .into_iter()
+            .flatten()
+            .any(|field| contains_ignore_case(field, app)),
+            QueryTerm::Site(site) => [entry.link_url.as_deref(), entry.link_clean_url.as_deref()]
+                .into_iter()
+                .flatten()
+                .chain(candidate.links.iter().map(|link| link.url.as_str()))
+                .any(|url| is_on_site(url, site)),
+            QueryTerm::Before(timestamp) => entry.created_at < *timestamp,
+            QueryTerm::After(timestamp) => entry.created_at >= *timestamp,
+            QueryTerm::Has(HasFilter::Link) => {
+                entry.content_type == "link"
+                    || candidate.links.iter().any(|link| link.kind == "url")
+            }
+            QueryTerm::Has(HasFilter::Email) => {
+                candidate.links.iter().any(|link| link.kind == "email")
+            }
+            QueryTerm::Has(HasFilter::Code) => entry.image_codes.is_some(),
+            QueryTerm::Pinned => entry.pin_order.is_some(),
+            QueryTerm::Tag(name) => candidate
+                .tags
+                .iter()
+                .any(|tag| tag.eq_ignore_ascii_case(name)),
+        }
+    }
+}
+
+fn collect_regexes<'a>(node: &'a QueryNode, regexes: &mut HashMap<&'a str, Regex>) {
+    match node {
+        QueryNode::And(children) | QueryNode::Or(children) => {
+            for child in children {
+                collect_regexes(child, regexes);
+            }
+        }
+        QueryNode::Not(child) => collect_regexes(child, regexes),
+        QueryNode::Term(QueryTerm::Regex(pattern)) => {
+            if let Ok(regex) = Regex::new(pattern) {
+                regexes.insert(pattern.as_str(), regex);
+            }
+        }
+        QueryNode::Term(_) => {}
+    }
+}
+
+/// The columns a `/regex/` is matched against, as `SEARCH_COLUMNS` in SQL.
+fn searchable_fields(entry: &Model) -> impl Iterator<Item = Option<&str>> {
+    [
+        Some(entry.content.as_str()),
+        entry.text_content.as_deref(),
+        entry.ocr_text.as_deref(),
+        entry.image_codes.as_deref(),
+        entry.file_paths.as_deref(),
+        entry.link_url.as_deref(),
+        entry.link_clean_url.as_deref(),
+        entry.link_title.as_deref(),
+        entry.link_description.as_deref(),
+        entry.link_site_name.as_deref(),
+        entry.source_app_title.as_deref(),
+    ]
+    .into_iter()
+}
+
+/// Whether `needle` appears as consecutive words, the last one matching as
+/// a prefix when `prefix` is set.
+fn contains_words(words: &[String], needle: &[String], prefix: bool) -> bool {
+    let last = needle.len() - 1;
+    words.windows(needle.len()).any(|window| {
+        window
+            .iter()
+            .zip(needle)
+            .enumerate()
+            .all(|(index, (word, term))| {
+                if prefix && index == last {
+                    word.starts_with(term.as_str())
+                } else {
+                    word == term
+                }
+            })
+    })
+}
+
+/// Case-insensitive for ASCII, like SQLite's `LIKE`.
+fn contains_ignore_case(text: &str, part: &str) -> bool {
+    text.to_ascii_lowercase()
+        .contains(&part.to_ascii_lowercase())
+}
+
+/// Whether the URL's host is `site` or one of its subdomains.
+fn is_on_site(url: &str, site: &str) -> bool {
+    let Some((_, rest)) = url.split_once("://") else {
+        return false;
+    };
+    let host = rest
+        .split(['/', ':'])
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    let site = site.to_ascii_lowercase();
+    host == site || host.ends_with(&format!(".{site}"))
+}
@@ -0,0 +1,350 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, Database, DatabaseConnection, EntityTrait, QueryFilter,
+    QuerySelect, TransactionTrait,
+};
+
+use crate::settings::{settings, update_settings};
+use crate::storage::entity::{Column, Entity};
+use crate::storage::integrity::database_errors;
+use crate::storage::path::{data_dir, data_dir_override, BACKUPS_FOLDER, DB_FILE, IMAGES_FOLDER};
+
+/// Result of `copy_data_dir`.
+#[derive(Clone, Debug)]
+pub struct MoveReport {
+    pub target: PathBuf,
+    pub images: usize,
+}
+
+impl fmt::Display for MoveReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Copied the history and {} image(s) to {}",
+            self.images,
+            self.target.display()
+        )
+    }
+}
+
+/// Copies the database, images and backups into `target` and points the settings
+/// at it. The running app keeps the old copy open, so callers stop writing
+/// and restart; the old directory is emptied on the next start.
+pub async fn copy_data_dir(db: &DatabaseConnection, target: &Path) -> anyhow::Result<MoveReport> {
+    if let Some(source) = data_dir_override() {
+        return Err(anyhow!(
+            "The data directory is set by {source}; change it there"
+        ));
+    }
+    let current = data_dir()?;
+    let report = copy_data_dir_from(db, &current, target).await?;
+    update_settings(|settings| {
+        settings.storage.data_dir = Some(target.to_path_buf());
+        settings.storage.moved_from = Some(current);
+    });
+    Ok(report)
+}
+
+/// `copy_data_dir` out of the data directory `current`, leaving the
+/// settings alone.
+async fn copy_data_dir_from(
+    db: &DatabaseConnection,
+    current: &Path,
+    target: &Path,
+) -> anyhow::Result<MoveReport> {
+    let old_images = current.join(IMAGES_FOLDER);
+    if target == current || target.starts_with(&old_images) {
+        return Err(anyhow!(
+            "Choose a folder outside the current data directory"
+        ));
+    }
+    let target_db = target.join(DB_FILE);
+    if target_db.exists() {
+        return Err(anyhow!("{} already holds a history", target.display()));
+    }
+    std::fs::create_dir_all(target)?;
+
+    // A consistent snapshot, even while other connections are reading.
+    let quoted = target_db.to_string_lossy().replace('\'', "''");
+    db.execute_unprepared(&format!("VACUUM INTO '{quoted}'"))
+        .await?;
+
+    let new_images = target.join(IMAGES_FOLDER);
+    let images = {
+        let (old_backups, new_backups) =
+            (current.join(BACKUPS_FOLDER), target.join(BACKUPS_FOLDER));
+        let new_images = new_images.clone();
+        async_std::task::spawn_blocking(move || {
+            let images = copy_files(&old_images, &new_images)?;
+            copy_files(&old_backups, &new_backups)?;
+            anyhow::Ok(images)
+        })
+        .await?
+    };
+    finish_copy(&target_db, &new_images).await?;
+    Ok(MoveReport {
+        target: target.to_path_buf(),
+        images,
+    })
+}
+
+/// Points the copied database at the copied images and checks it. A copy
+/// that fails the check is deleted, so the old directory stays in use.
+async fn finish_copy(target_db: &Path, new_images: &Path) -> anyhow::Result<()> {
+    let raw = target_db.to_string_lossy().replace('\\', "/");
+    let moved = Database::connect(format!("sqlite:///{raw}?mode=rw")).await?;
+    let rewritten = rewrite_image_paths(&moved, new_images).await;
+    let checked = match rewritten {
+        Ok(()) => quick_check(&moved).await,
+        Err(err) => Err(err),
+    };
+    moved.close().await?;
+    if let Err(err) = checked {
+        let _ = std::fs::remove_file(target_db);
+        return Err(err);
+    }
+    Ok(())
+}
+
+/// Copies the finished files of `from` into `to`, skipping `.tmp` files
+/// still being written.
+fn copy_files(from: &Path, to: &Path) -> anyhow::Result<usize> {
+    let mut copied = 0;
+    if !from.is_dir() {
+        return Ok(copied);
+    }
+    std::fs::create_dir_all(to)?;
+    for file in std::fs::read_dir(from)? {
+        let path = file?.path();
+        if !path.is_file() || path.extension().is_some_and(|ext| ext == "tmp") {
+            continue;
+        }
+        if let Some(name) = path.file_name() {
+            std::fs::copy(&path, to.join(name))?;
+            copied += 1;
+        }
+    }
+    Ok(copied)
+}
+
+/// Points every `image_path` at the same file name in `images`.
+async fn rewrite_image_paths(db: &DatabaseConnection, images: &Path) -> anyhow::Result<()> {
+    let rows: Vec<(i32, String)> = Entity::find()
+        .select_only()
+        .column(Column::Id)
+        .column(Column::ImagePath)
+        .filter(Column::ImagePath.is_not_null())
+        .into_tuple()
+        .all(db)
+        .await?;
+    let txn = db.begin().await?;
+    for (id, path) in rows {
+        let Some(name) = Path::new(&path).file_name() else {
+            continue;
+        };
+        let moved = images.join(name).to_string_lossy().into_owned();
+        Entity::update_many()
+            .col_expr(Column::ImagePath, Expr::value(moved))
+            .filter(Column::Id.eq(id))
+            .exec(&txn)
+            .await?;
+    }
+    txn.commit().await?;
+    Ok(())
+}
+
+async fn quick_check(db: &DatabaseConnection) -> anyhow::Result<()> {
+    let errors = database_errors(db).await?;
+    if let Some(error) = errors.first() {
+        return Err(anyhow!("The copied database failed its check: {error}"));
+    }
+    Ok(())
+}
+
+/// Deletes the history left behind by a move, once nothing has it open.
+pub fn finish_data_dir_move() {
+    let Some(old) = settings().storage.moved_from else {
+        return;
+    };
+    if data_dir().is_ok_and(|current| current != old) && !remove_history(&old) {
+        return;
+    }
+    update_settings(|settings| settings.storage.moved_from = None);
+}
+
+/// Deletes the database, images and backups in `dir`, leaving anything
+/// else there alone. Returns `false` when something couldn't be removed,
+/// so the next start tries again.
+fn remove_history(dir: &Path) -> bool {
+    for name in [
+        DB_FILE.to_string(),
+        format!("{DB_FILE}-wal"),
+        format!("{DB_FILE}-shm"),
+    ] {
+        let path = dir.join(name);
+        if let Err(err) = std::fs::remove_file(&path) {
+            if err.kind() != std::io::ErrorKind::NotFound {
+                eprintln!("Failed to remove {}: {err}", path.display());
+                return false;
+            }
+        }
+    }
+    for folder in [IMAGES_FOLDER, BACKUPS_FOLDER] {
+        if let Err(err) = std::fs::remove_dir_all(dir.join(folder)) {
+            if err.kind() != std::io::ErrorKind::NotFound {
+                eprintln!("Failed to remove the old {folder}: {err}");
+                return false;
+            }
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use sea_orm::PaginatorTrait;
+
+    use super::*;
+    use crate::storage::history::{insert_clipboard_entry, open_db, ClipboardEntryInput};
+    use crate::test_support::{lock_storage, temp_dir};
+
+    async fn insert_image(db: &DatabaseConnection, path: &Path) -> i32 {
+        let path = path.to_string_lossy();
+        insert_clipboard_entry(
+            db,
+            ClipboardEntryInput {
+                content_type: "image",
+                content_hash: &path,
+                content: "Image",
+                image_path: Some(&path),
+                ..ClipboardEntryInput::default()
+            },
+        )
+        .await
+        .unwrap()
+    }
+
+    async fn image_path(db: &DatabaseConnection, id: i32) -> Option<PathBuf> {
+        let entry = Entity::find_by_id(id).one(db).await.unwrap().unwrap();
+        entry.image_path.map(PathBuf::from)
+    }
+
+    #[test]
+    fn copies_the_history_and_rewrites_image_paths() {
+        let _storage = lock_storage();
+        async_std::task::block_on(async {
+            let dir = temp_dir("relocate-copy");
+            let (current, target) = (dir.join("current"), dir.join("target"));
+            let images = current.join(IMAGES_FOLDER);
+            let backups = current.join(BACKUPS_FOLDER);
+            fs::create_dir_all(&images).unwrap();
+            fs::create_dir_all(&backups).unwrap();
+            fs::write(images.join("image_a.bmp"), b"BM").unwrap();
+            fs::write(images.join("image_b.1.tmp"), b"BM").unwrap();
+            fs::write(backups.join("clipboard_history-1.db"), b"backup").unwrap();
+            let db = open_db(&current.join(DB_FILE), false).await.unwrap();
+            let id = insert_image(&db, &images.join("image_a.bmp")).await;
+
+            let report = copy_data_dir_from(&db, &current, &target).await.unwrap();
+            assert_eq!(report.images, 1);
+            let new_images = target.join(IMAGES_FOLDER);
+            assert!(new_images.join("image_a.bmp").is_file());
+            assert!(!new_images.join("image_b.1.tmp").exists());
+            assert!(target
+                .join(BACKUPS_FOLDER)
+                .join("clipboard_history-1.db")
+                .is_file());
+
+            let moved = open_db(&target.join(DB_FILE), false).await.unwrap();
+            assert_eq!(
+                image_path(&moved, id).await,
+                Some(new_images.join("image_a.bmp"))
+            );
+            // The old copy is left as it was until the next start.
+            assert_eq!(image_path(&db, id).await, Some(images.join("image_a.bmp")));
+            assert!(images.join("image_a.bmp").is_file());
+
+            assert!(copy_data_dir_from(&db, &current, &target).await.is_err());
+            assert!(copy_data_dir_from(&db, &current, &images.join("nested"))
+                .await
+                .is_err());
+        });
+    }
+
+    #[test]
+    fn a_copy_that_fails_its_check_is_removed() {
+        let _storage = lock_storage();
+        async_std::task::block_on(async {
+            let dir = temp_dir("relocate-check");
+            let db = open_db(&dir.join(DB_FILE), false).await.unwrap();
+            let id = insert_image(&db, &dir.join(IMAGES_FOLDER).join("image_a.bmp")).await;
+            // Rows too long for a page, whose overflow pages only the check
+            // follows.
+            db.execute_unprepared(
+                "CREATE TABLE padding (data BLOB); \
+                 WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 20) \
+                 INSERT INTO padding SELECT randomblob(10000) FROM n",
+            )
+            .await
+            .unwrap();
+
+            let target = dir.join("target");
+            fs::create_dir_all(&target).unwrap();
+            let copy = target.join(DB_FILE);
+            let quoted = copy.to_string_lossy().replace('\'', "''");
+            db.execute_unprepared(&format!("VACUUM INTO '{quoted}'"))
+                .await
+                .unwrap();
+            // Point the end of the last overflow chain past the end of the
+            // file. Overflow pages start with the next page's number.
+            let mut bytes = fs::read(&copy).unwrap();
+            let pages = bytes.len() / 4096;
+            let last = (0..pages)
+                .rev()
+                .map(|page| page * 4096)
+                .find(|&start| bytes[start..start + 4] == [0; 4])
+                .unwrap();
+            bytes[last..last + 4].copy_from_slice(&(pages as u32 + 100).to_be_bytes());
+            fs::write(&copy, bytes).unwrap();
+
+            let err = finish_copy(&copy, &target.join(IMAGES_FOLDER))
+                .await
+                .unwrap_err();
+            assert!(err.to_string().contains("failed its check"), "{err}");
+            assert!(!copy.exists());
+            // The history being moved is untouched.
+            assert_eq!(Entity::find().count(&db).await.unwrap(), 1);
+            assert_eq!(
+                image_path(&db, id).await,
+                Some(dir.join(IMAGES_FOLDER).join("image_a.bmp"))
+            );
+        });
+    }
+
+    #[test]
+    fn cleanup_removes_only_the_history() {
+        let dir = temp_dir("relocate-cleanup");
+        fs::write(dir.join(DB_FILE), b"db").unwrap();
+        fs::write(dir.join(format!("{DB_FILE}-wal")), b"wal").unwrap();
+        for folder in [IMAGES_FOLDER, BACKUPS_FOLDER] {
+            fs::create_dir_all(dir.join(folder)).unwrap();
+            fs::write(dir.join(folder).join("file"), b"data").unwrap();
+        }
+        fs::write(dir.join("notes.txt"), b"mine").unwrap();
+
+        assert!(remove_history(&dir));
+        assert!(!dir.join(DB_FILE).exists());
+        assert!(!dir.join(format!("{DB_FILE}-wal")).exists());
+        assert!(!dir.join(IMAGES_FOLDER).exists());
+        assert!(!dir.join(BACKUPS_FOLDER).exists());
+        assert!(dir.join("notes.txt").is_file());
+        // Nothing left to remove is not a failure.
+        assert!(remove_history(&dir));
+    }
+}
@@ -0,0 +1,417 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use sea_orm::sea_query::{Expr, Query};
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, QuerySelect};
+
+use crate::settings::RetentionSettings;
+use crate::storage::entity::{Column, Entity};
+use crate::storage::entry_tag;
+use crate::storage::integrity::release_image;
+
+const SECS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Rows deleted per statement, well under SQLite's bound parameter limit.
+const DELETE_BATCH: usize = 500;
+
+/// Stored text of an entry in bytes, as counted against `max_total_mb`.
+const TEXT_BYTES_SQL: &str = "LENGTH(CAST(content AS BLOB)) \
+     + COALESCE(LENGTH(CAST(text_content AS BLOB)), 0) \
+     + COALESCE(LENGTH(CAST(ocr_text AS BLOB)), 0) \
+     + COALESCE(LENGTH(CAST(ocr_layout AS BLOB)), 0) \
+     + COALESCE(LENGTH(CAST(image_codes AS BLOB)), 0) \
+     + COALESCE(LENGTH(CAST(file_paths AS BLOB)), 0) \
+     + COALESCE(LENGTH(CAST(link_details AS BLOB)), 0) \
+     + COALESCE(LENGTH(CAST(search_terms AS BLOB)), 0)";
+
+/// What a pruning run removed.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PruneReport {
+    pub entries: usize,
+    pub images: usize,
+    /// Text and image bytes freed.
+    pub bytes: u64,
+}
+
+impl fmt::Display for PruneReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Removed {} old entries and {} images ({:.1} MB)",
+            self.entries,
+            self.images,
+            self.bytes as f64 / (1024.0 * 1024.0)
+        )
+    }
+}
+
+/// Size and age of an entry, as seen by the pruner.
+struct EntryUsage {
+    id: i32,
+    content_type: String,
+    created_at: i64,
+    image_path: Option<String>,
+    text_bytes: u64,
+    image_bytes: u64,
+    /// Pinned or tagged.
+    protected: bool,
+}
+
+/// Deletes the entries that fall outside the retention limits, oldest
+/// first, along with images no remaining entry uses.
+pub async fn prune_history(
+    db: &DatabaseConnection,
+    policy: &RetentionSettings,
+) -> anyhow::Result<PruneReport> {
+    if !policy.has_limits() {
+        return Ok(PruneReport::default());
+    }
+    let entries = load_usage(db).await?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64;
+    let doomed = select_entries_to_prune(&entries, policy, now);
+    if doomed.is_empty() {
+        return Ok(PruneReport::default());
+    }
+
+    let mut report = PruneReport::default();
+    let ids: Vec<i32> = doomed.iter().map(|&index| entries[index].id).collect();
+    let mut deleted = HashSet::new();
+    for batch in ids.chunks(DELETE_BATCH) {
+        // Re-check protection in the statement itself, in case an entry was
+        // pinned or tagged since the usage was loaded.
+        Entity::delete_many()
+            .filter(Column::Id.is_in(batch.iter().copied()))
+            .filter(Column::PinOrder.is_null())
+            .filter(
+                Column::Id.not_in_subquery(
+                    Query::select()
+                        .column(entry_tag::Column::EntryId)
+                        .from(entry_tag::Entity)
+                        .to_owned(),
+                ),
+            )
+            .exec(db)
+            .await?;
+        let kept: HashSet<i32> = Entity::find()
+            .select_only()
+            .column(Column::Id)
+            .filter(Column::Id.is_in(batch.iter().copied()))
+            .into_tuple()
+            .all(db)
+            .await?
+            .into_iter()
+            .collect();
+        deleted.extend(batch.iter().copied().filter(|id| !kept.contains(id)));
+    }
+    report.entries = deleted.len();
+
+    // Only what was actually deleted counts, and only its images can be
+    // freed.
+    let mut image_paths = HashSet::new();
+    for entry in doomed
+        .iter()
+        .map(|&index| &entries[index])
+        .filter(|entry| deleted.contains(&entry.id))
+    {
+        report.bytes += entry.text_bytes;
+        if let Some(path) = entry.image_path.as_deref() {
+            image_paths.insert(path);
+        }
+    }
+    for path in image_paths {
+        match release_image(db, path).await {
+            Ok(Some(bytes)) => {
+                report.images += 1;
+                report.bytes += bytes;
+            }
+            Ok(None) => {}
+            Err(err) => eprintln!("Failed to remove image {path}: {err}"),
+        }
+    }
+    Ok(report)
+}
+
+/// All entries, newest first.
+async fn load_usage(db: &DatabaseConnection) -> anyhow::Result<Vec<EntryUsage>> {
+    let rows = Entity::find()
+        .select_only()
+        .column(Column::Id)
+        .column(Column::ContentType)
+        .column(Column::CreatedAt)
+        .column(Column::ImagePath)
+        .column_as(Expr::cust(TEXT_BYTES_SQL), "text_bytes")
+        .column_as(
+            Expr::cust(
+                "pin_order IS NOT NULL \
+                 OR id IN (SELECT entry_id FROM entry_tags)",
+            ),
+            "protected",
+        )
+        .order_by_desc(Column::Id)
+        .into_tuple::<(i32, String, i64, Option<String>, i64, bool)>()
+        .all(db)
+        .await?;
+
+    let mut image_sizes: HashMap<String, u64> = HashMap::new();
+    Ok(rows
+        .into_iter()
+        .map(
+            |(id, content_type, created_at, image_path, text_bytes, protected)| {
+                let image_bytes = image_path.as_ref().map_or(0, |path| {
+                    *image_sizes.entry(path.clone()).or_insert_with(|| {
+                        std::fs::metadata(Path::new(path)).map_or(0, |metadata| metadata.len())
+                    })
+                });
+                EntryUsage {
+                    id,
+                    content_type,
+                    created_at,
+                    image_path,
+                    text_bytes: text_bytes.max(0) as u64,
+                    image_bytes,
+                    protected,
+                }
+            },
+        )
+        .collect())
+}
+
+/// Indices into `entries` (newest first) to delete: everything past its age
+/// limit, then the oldest entries beyond the count limits, then the oldest
+/// remaining entries until the total size fits.
+fn select_entries_to_prune(
+    entries: &[EntryUsage],
+    policy: &RetentionSettings,
+    now: i64,
+) -> Vec<usize> {
+    let mut doomed = vec![false; entries.len()];
+
+    for (index, entry) in entries.iter().enumerate() {
+        if entry.protected {
+            continue;
+        }
+        let max_age_days = policy
+            .content_types
+            .get(&entry.content_type)
+            .and_then(|limits| limits.max_age_days)
+            .or(policy.max_age_days);
+        if let Some(days) = max_age_days {
+            if now - entry.created_at > days as i64 * SECS_PER_DAY {
+                doomed[index] = true;
+            }
+        }
+    }
+
+    let mut type_counts: HashMap<&str, usize> = HashMap::new();
+    let mut total_count = 0;
+    for (index, entry) in entries.iter().enumerate() {
+        if entry.protected || doomed[index] {
+            continue;
+        }
+        let type_count = type_counts.entry(entry.content_type.as_str()).or_default();
+        let type_limit = policy
+            .content_types
+            .get(&entry.content_type)
+            .and_then(|limits| limits.max_entries);
+        if type_limit.is_some_and(|limit| *type_count >= limit)
+            || policy.max_entries.is_some_and(|limit| total_count >= limit)
+        {
+            doomed[index] = true;
+            continue;
+        }
+        *type_count += 1;
+        total_count += 1;
+    }
+
+    if let Some(max_mb) = policy.max_total_mb {
+        let limit = max_mb.saturating_mul(1024 * 1024);
+        // Images are stored once per content hash, so a file is only freed
+        // when its last entry goes.
+        let mut image_refs: HashMap<&str, (u64, usize)> = HashMap::new();
+        let mut total_bytes = 0u64;
+        for (index, entry) in entries.iter().enumerate() {
+            if doomed[index] {
+                continue;
+            }
+            total_bytes += entry.text_bytes;
+            if let Some(path) = entry.image_path.as_deref() {
+                let (bytes, refs) = image_refs.entry(path).or_insert((entry.image_bytes, 0));
+                if *refs == 0 {
+                    total_bytes += *bytes;
+                }
+                *refs += 1;
+            }
+        }
+        for (index, entry) in entries.iter().enumerate().rev() {
+            if total_bytes <= limit {
+                break;
+            }
+            if entry.protected || doomed[index] {
+                continue;
+            }
+            doomed[index] = true;
+            total_bytes -= entry.text_bytes;
+            if let Some((bytes, refs)) = entry
+                .image_path
+                .as_deref()
+                .and_then(|path| image_refs.get_mut(path))
+            {
+                *refs -= 1;
+                if *refs == 0 {
+                    total_bytes -= *bytes;
+                }
+            }
+        }
+    }
+
+    doomed
+        .iter()
+        .enumerate()
+        .filter_map(|(index, &doomed)| doomed.then_some(index))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::RetentionLimits;
+
+    const NOW: i64 = 1_000 * SECS_PER_DAY;
+    const KB: u64 = 1024;
+
+    fn entry(id: i32, content_type: &str, age_days: i64) -> EntryUsage {
+        EntryUsage {
+            id,
+            content_type: content_type.to_string(),
+            created_at: NOW - age_days * SECS_PER_DAY,
+            image_path: None,
+            text_bytes: 0,
+            image_bytes: 0,
+            protected: false,
+        }
+    }
+
+    fn protected(entry: EntryUsage) -> EntryUsage {
+        EntryUsage {
+            protected: true,
+            ..entry
+        }
+    }
+
+    fn with_image(entry: EntryUsage, path: &str, bytes: u64) -> EntryUsage {
+        EntryUsage {
+            image_path: Some(path.to_string()),
+            image_bytes: bytes,
+            ..entry
+        }
+    }
+
+    fn with_text(entry: EntryUsage, bytes: u64) -> EntryUsage {
+        EntryUsage {
+            text_bytes: bytes,
+            ..entry
+        }
+    }
+
+    fn limits(max_entries: Option<usize>, max_age_days: Option<u64>) -> RetentionLimits {
+        RetentionLimits {
+            max_entries,
+            max_age_days,
+        }
+    }
+
+    struct Case {
+        name: &'static str,
+        policy: RetentionSettings,
+        /// Newest first, as `load_usage` returns them.
+        entries: Vec<EntryUsage>,
+        pruned: Vec<i32>,
+    }
+
+    #[test]
+    fn selects_entries_outside_the_limits() {
+        let cases = [
+            Case {
+                name: "pinned and tagged entries are never pruned",
+                policy: RetentionSettings {
+                    max_entries: Some(0),
+                    max_age_days: Some(1),
+                    max_total_mb: Some(0),
+                    content_types: [("text".to_string(), limits(Some(0), Some(0)))].into(),
+                    ..RetentionSettings::default()
+                },
+                entries: vec![
+                    protected(with_text(entry(1, "text", 0), 10 * KB)),
+                    protected(with_image(entry(2, "image", 400), "a.bmp", 500 * KB)),
+                    with_text(entry(3, "text", 0), KB),
+                    protected(with_text(entry(4, "text", 900), KB)),
+                ],
+                pruned: vec![3],
+            },
+            Case {
+                name: "per-type age overrides the global age",
+                policy: RetentionSettings {
+                    max_age_days: Some(30),
+                    content_types: [
+                        ("text".to_string(), limits(None, Some(365))),
+                        ("image".to_string(), limits(None, Some(1))),
+                    ]
+                    .into(),
+                    ..RetentionSettings::default()
+                },
+                entries: vec![
+                    entry(1, "link", 10),
+                    entry(2, "image", 2),
+                    entry(3, "link", 40),
+                    entry(4, "text", 100),
+                    entry(5, "text", 400),
+                ],
+                pruned: vec![2, 3, 5],
+            },
+            Case {
+                name: "per-type count applies on top of the global count",
+                policy: RetentionSettings {
+                    max_entries: Some(3),
+                    content_types: [("image".to_string(), limits(Some(1), None))].into(),
+                    ..RetentionSettings::default()
+                },
+                entries: vec![
+                    entry(1, "image", 0),
+                    entry(2, "text", 0),
+                    entry(3, "image", 0),
+                    entry(4, "text", 0),
+                    entry(5, "text", 0),
+                ],
+                pruned: vec![3, 5],
+            },
+            Case {
+                name: "a shared image is only freed with its last entry",
+                policy: RetentionSettings {
+                    max_total_mb: Some(1),
+                    ..RetentionSettings::default()
+                },
+                entries: vec![
+                    with_text(entry(1, "text", 0), 100 * KB),
+                    with_image(entry(2, "image", 0), "b.bmp", 600 * KB),
+                    with_image(entry(3, "image", 0), "a.bmp", 600 * KB),
+                    with_image(entry(4, "image", 0), "a.bmp", 600 * KB),
+                ],
+                pruned: vec![3, 4],
+            },
+        ];
+
+        for case in cases {
+            let mut pruned: Vec<i32> = select_entries_to_prune(&case.entries, &case.policy, NOW)
+                .into_iter()
+                .map(|index| case.entries[index].id)
+                .collect();
+            pruned.sort();
+            assert_eq!(pruned, case.pruned, "{}", case.name);
+        }
+    }
+}
@@ -0,0 +1,880 @@
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use regex::Regex;
+
+/// A parsed search box query. Terms separated by whitespace must all match;
+/// `OR` between terms and `( ... )` groups relax that.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchQuery {
+    pub root: QueryNode,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum QueryNode {
+    And(Vec<QueryNode>),
+    Or(Vec<QueryNode>),
+    Not(Box<QueryNode>),
+    Term(QueryTerm),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum QueryTerm {
+    /// Matches words starting with the text.
+    Text(String),
+    /// A quoted phrase, matched as consecutive words.
+    Phrase(String),
+    /// A `/pattern/` regular expression, already validated. Case-insensitive
+    /// patterns carry an inline `(?i)` flag.
+    Regex(String),
+    Type(String),
+    App(String),
+    Site(String),
+    /// Unix timestamps in seconds.
+    Before(i64),
+    After(i64),
+    Has(HasFilter),
+    /// `is:pinned`.
+    Pinned,
+    /// Entries carrying the tag, compared without regard to case.
+    Tag(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HasFilter {
+    Link,
+    Email,
+    Code,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QueryError {
+    pub message: String,
+    /// Character column (0-based) where the problem starts.
+    pub column: usize,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (at column {})", self.message, self.column + 1)
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+const CONTENT_TYPES: [(&str, &str); 8] = [
+    ("text", "text"),
+    ("image", "image"),
+    ("img", "image"),
+    ("link", "link"),
+    ("url", "link"),
+    ("files", "files"),
+    ("file", "files"),
+    ("path", "files"),
+];
+
+impl SearchQuery {
+    /// Positive text and phrase terms, used to rank results. Terms under a
+    /// negation never contribute.
+    pub fn ranking_terms(&self) -> Vec<&QueryTerm> {
+        let mut terms = Vec::new();
+        collect_ranking_terms(&self.root, &mut terms);
+        terms
+    }
+}
+
+impl SearchQuery {
+    /// Plain words that must match for the whole query to match, i.e. the
+    /// top-level terms. Fuzzy mode scores these in Rust instead of SQL; words
+    /// inside groups or negations keep exact matching.
+    pub fn fuzzy_terms(&self) -> Vec<&str> {
+        let children = match &self.root {
+            QueryNode::And(children) => children.as_slice(),
+            root => std::slice::from_ref(root),
+        };
+        children
+            .iter()
+            .filter_map(|child| match child {
+                QueryNode::Term(QueryTerm::Text(text)) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+fn collect_ranking_terms<'a>(node: &'a QueryNode, terms: &mut Vec<&'a QueryTerm>) {
+    match node {
+        QueryNode::And(children) | QueryNode::Or(children) => {
+            for child in children {
+                collect_ranking_terms(child, terms);
+            }
+        }
+        QueryNode::Not(_) => {}
+        QueryNode::Term(term @ (QueryTerm::Text(_) | QueryTerm::Phrase(_))) => terms.push(term),
+        QueryNode::Term(_) => {}
+    }
+}
+
+/// Parses the search box text. Returns `Ok(None)` for a blank query.
+pub fn parse_query(input: &str) -> Result<Option<SearchQuery>, QueryError> {
+    let tokens = lex(input)?;
+    if tokens.is_empty() {
+        return Ok(None);
+    }
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+        input_len: input.chars().count(),
+    };
+    let root = parser.parse_and(false)?;
+    if let Some((token, column)) = parser.peek() {
+        let message = match token {
+            Token::Close => "Unmatched `)`".to_string(),
+            _ => "Unexpected input".to_string(),
+        };
+        return Err(QueryError {
+            message,
+            column: *column,
+        });
+    }
+    Ok(Some(SearchQuery { root }))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    Or,
+    Not,
+    Word {
+        field: Option<String>,
+        value: String,
+        quoted: bool,
+    },
+    Regex(String),
+}
+
+fn lex(input: &str) -> Result<Vec<(Token, usize)>, QueryError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut depth = 0usize;
+    let mut index = 0;
+
+    while index < chars.len() {
+        let ch = chars[index];
+        if ch.is_whitespace() {
+            index += 1;
+            continue;
+        }
+        let start = index;
+        match ch {
+            '(' => {
+                depth += 1;
+                tokens.push((Token::Open, start));
+                index += 1;
+            }
+            ')' => {
+                depth = depth.saturating_sub(1);
+                tokens.push((Token::Close, start));
+                index += 1;
+            }
+            '-' if chars
+                .get(index + 1)
+                .is_some_and(|next| !next.is_whitespace() && *next != ')') =>
+            {
+                tokens.push((Token::Not, start));
+                index += 1;
+            }
+            '"' => {
+                let (value, next) = read_quoted(&chars, index)?;
+                tokens.push((
+                    Token::Word {
+                        field: None,
+                        value,
+                        quoted: true,
+                    },
+                    start,
+                ));
+                index = next;
+            }
+            '/' => match read_regex(&chars, index)? {
+                Some((pattern, next)) => {
+                    tokens.push((Token::Regex(pattern), start));
+                    index = next;
+                }
+                None => {
+                    index = read_word(&chars, index, &mut depth, &mut tokens)?;
+                }
+            },
+            _ => {
+                index = read_word(&chars, index, &mut depth, &mut tokens)?;
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Reads a bare word, or a `field:value` pair whose value may be quoted.
+/// Closing parentheses at the end of a word close open groups; inside URLs
+/// with no open group they are kept.
+fn read_word(
+    chars: &[char],
+    start: usize,
+    depth: &mut usize,
+    tokens: &mut Vec<(Token, usize)>,
+) -> Result<usize, QueryError> {
+    let mut end = start;
+    while end < chars.len() && !chars[end].is_whitespace() {
+        if chars[end] == ':' && end + 1 < chars.len() && chars[end + 1] == '"' {
+            let field: String = chars[start..end].iter().collect();
+            if is_field(&field) {
+                let (value, next) = read_quoted(chars, end + 1)?;
+                tokens.push((
+                    Token::Word {
+                        field: Some(field.to_ascii_lowercase()),
+                        value,
+                        quoted: true,
+                    },
+                    start,
+                ));
+                return Ok(next);
+            }
+        }
+        end += 1;
+    }
+
+    let mut word_end = end;
+    let mut closes = 0;
+    while closes < *depth && word_end > start && chars[word_end - 1] == ')' {
+        word_end -= 1;
+        closes += 1;
+    }
+    let word: String = chars[start..word_end].iter().collect();
+
+    if !word.is_empty() {
+        let token = if word == "OR" {
+            Token::Or
+        } else {
+            match word.split_once(':') {
+                Some((field, value)) if is_field(field) && !value.is_empty() => Token::Word {
+                    field: Some(field.to_ascii_lowercase()),
+                    value: value.to_string(),
+                    quoted: false,
+                },
+                _ => Token::Word {
+                    field: None,
+                    value: word,
+                    quoted: false,
+                },
+            }
+        };
+        tokens.push((token, start));
+    }
+    for offset in 0..closes {
+        tokens.push((Token::Close, word_end + offset));
+    }
+    *depth -= closes;
+    Ok(end)
+}
+
+fn read_quoted(chars: &[char], open: usize) -> Result<(String, usize), QueryError> {
+    let mut value = String::new();
+    let mut index = open + 1;
+    while index < chars.len() {
+        match chars[index] {
+            '\\' if chars.get(index + 1) == Some(&'"') => {
+                value.push('"');
+                index += 2;
+            }
+            '"' => return Ok((value, index + 1)),
+            ch => {
+                value.push(ch);
+                index += 1;
+            }
+        }
+    }
+    Err(QueryError {
+        message: "Missing closing quote".to_string(),
+        column: open,
+    })
+}
+
+/// Reads `/pattern/flags`. Returns `None` when the text is not shaped like a
+/// regex (e.g. a path such as `/usr/bin`), so it is searched as plain text.
+fn read_regex(chars: &[char], open: usize) -> Result<Option<(String, usize)>, QueryError> {
+    let mut index = open + 1;
+    let mut pattern = String::new();
+    while index < chars.len() {
+        match chars[index] {
+            '\\' if chars.get(index + 1) == Some(&'/') => {
+                pattern.push('/');
+                index += 2;
+            }
+            '/' => break,
+            ch => {
+                pattern.push(ch);
+                index += 1;
+            }
+        }
+    }
+    if index >= chars.len() {
+        return Ok(None);
+    }
+
+    let mut end = index + 1;
+    while end < chars.len() && chars[end].is_ascii_alphabetic() {
+        end += 1;
+    }
+    let flags: String = chars[index + 1..end].iter().collect();
+    let at_boundary = end >= chars.len() || chars[end].is_whitespace() || chars[end] == ')';
+    if !at_boundary || !flags.chars().all(|flag| flag == 'i') {
+        return Ok(None);
+    }
+    if pattern.is_empty() {
+        return Err(QueryError {
+            message: "Empty regular expression".to_string(),
+            column: open,
+        });
+    }
+
+    let pattern = if flags.is_empty() {
+        pattern
+    } else {
+        format!("(?i){pattern}")
+    };
+    if let Err(err) = Regex::new(&pattern) {
+        let detail = err.to_string();
+        let detail = detail.lines().last().unwrap_or_default().trim().to_string();
+        return Err(QueryError {
+            message: format!("Invalid regular expression: {detail}"),
+            column: open,
+        });
+    }
+    Ok(Some((pattern, end)))
+}
+
+fn is_field(field: &str) -> bool {
+    matches!(
+        field.to_ascii_lowercase().as_str(),
+        "type" | "app" | "site" | "before" | "after" | "is" | "has" | "tag"
+    )
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    input_len: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&(Token, usize)> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<(Token, usize)> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn parse_and(&mut self, in_group: bool) -> Result<QueryNode, QueryError> {
+        let mut children = Vec::new();
+        while let Some((token, column)) = self.peek() {
+            match token {
+                Token::Close if in_group => break,
+                Token::Close => {
+                    return Err(QueryError {
+                        message: "Unmatched `)`".to_string(),
+                        column: *column,
+                    })
+                }
+                Token::Or => {
+                    return Err(QueryError {
+                        message: "`OR` needs a term on both sides".to_string(),
+                        column: *column,
+                    })
+                }
+                _ => children.push(self.parse_or()?),
+            }
+        }
+        Ok(match children.len() {
+            1 => children.remove(0),
+            _ => QueryNode::And(children),
+        })
+    }
+
+    fn parse_or(&mut self) -> Result<QueryNode, QueryError> {
+        let mut children = vec![self.parse_unary()?];
+        while let Some((Token::Or, column)) = self.peek() {
+            let column = *column;
+            self.position += 1;
+            match self.peek() {
+                None | Some((Token::Or | Token::Close, _)) => {
+                    return Err(QueryError {
+                        message: "`OR` needs a term on both sides".to_string(),
+                        column,
+                    })
+                }
+                _ => children.push(self.parse_unary()?),
+            }
+        }
+        Ok(match children.len() {
+            1 => children.remove(0),
+            _ => QueryNode::Or(children),
+        })
+    }
+
+    fn parse_unary(&mut self) -> Result<QueryNode, QueryError> {
+        let Some((token, column)) = self.next() else {
+            return Err(QueryError {
+                message: "Expected a search term".to_string(),
+                column: self.input_len,
+            });
+        };
+        match token {
+            Token::Not => Ok(QueryNode::Not(Box::new(self.parse_unary()?))),
+            Token::Open => {
+                let inner = self.parse_and(true)?;
+                match self.next() {
+                    Some((Token::Close, _)) => {}
+                    _ => {
+                        return Err(QueryError {
+                            message: "Missing closing `)`".to_string(),
+                            column,
+                        })
+                    }
+                }
+                if inner == QueryNode::And(Vec::new()) {
+                    return Err(QueryError {
+                        message: "Empty group".to_string(),
+                        column,
+                    });
+                }
+                Ok(inner)
+            }
+            Token::Regex(pattern) => Ok(QueryNode::Term(QueryTerm::Regex(pattern))),
+            Token::Word {
+                field,
+                value,
+                quoted,
+            } => parse_term(field.as_deref(), value, quoted, column).map(QueryNode::Term),
+            Token::Close | Token::Or => Err(QueryError {
+                message: "Expected a search term".to_string(),
+                column,
+            }),
+        }
+    }
+}
+
+fn parse_term(
+    field: Option<&str>,
+    value: String,
+    quoted: bool,
+    column: usize,
+) -> Result<QueryTerm, QueryError> {
+    let error = |message: String| QueryError { message, column };
+    let Some(field) = field else {
+        return Ok(if quoted {
+            QueryTerm::Phrase(value)
+        } else {
+            QueryTerm::Text(value)
+        });
+    };
+
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        return Err(error(format!("`{field}:` needs a value")));
+    }
+    match field {
+        "type" => {
+            let lower = trimmed.to_ascii_lowercase();
+            CONTENT_TYPES
+                .iter()
+                .find(|(alias, _)| *alias == lower)
+                .map(|(_, content_type)| QueryTerm::Type(content_type.to_string()))
+                .ok_or_else(|| {
+                    error(format!(
+                        "Unknown type `{trimmed}`; use text, image, link or files"
+                    ))
+                })
+        }
+        "app" => Ok(QueryTerm::App(trimmed.to_string())),
+        "tag" => Ok(QueryTerm::Tag(trimmed.to_string())),
+        "site" => {
+            let site = trimmed
+                .trim_start_matches("https://")
+                .trim_start_matches("http://")
+                .trim_start_matches("www.")
+                .trim_end_matches('/')
+                .to_ascii_lowercase();
+            if site.is_empty() || site.contains('/') {
+                return Err(error(format!(
+                    "`site:` expects a domain such as github.com, not `{trimmed}`"
+                )));
+            }
+            Ok(QueryTerm::Site(site))
+        }
+        "before" => parse_date(trimmed)
+            .map(QueryTerm::Before)
+            .ok_or_else(|| error(date_error("before", trimmed))),
+        "after" => parse_date(trimmed)
+            .map(QueryTerm::After)
+            .ok_or_else(|| error(date_error("after", trimmed))),
+        "has" => match trimmed.to_ascii_lowercase().as_str() {
+            "link" | "url" => Ok(QueryTerm::Has(HasFilter::Link)),
+            "email" | "mail" => Ok(QueryTerm::Has(HasFilter::Email)),
+            "code" | "qr" | "barcode" => Ok(QueryTerm::Has(HasFilter::Code)),
+            _ => Err(error(format!(
+                "Unknown filter `has:{trimmed}`; use has:link, has:email or has:code"
+            ))),
+        },
+        "is" => match trimmed.to_ascii_lowercase().as_str() {
+            "pinned" | "pin" => Ok(QueryTerm::Pinned),
+            _ => Err(error(format!(
+                "Unknown filter `is:{trimmed}`; use is:pinned"
+            ))),
+        },
+        _ => Ok(QueryTerm::Text(format!("{field}:{value}"))),
+    }
+}
+
+fn date_error(field: &str, value: &str) -> String {
+    format!(
+        "Can't read `{field}:{value}`; use a date like 2024-05-31, today, yesterday, \
+         or a relative time like -2d, -3h or -1w"
+    )
+}
+
+/// Accepts `YYYY-MM-DD` (UTC midnight), `today`, `yesterday`, and relative
+/// offsets into the past such as `-30m`, `-2h`, `-3d` or `-1w`.
+fn parse_date(value: &str) -> Option<i64> {
+    const DAY: i64 = 24 * 60 * 60;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64;
+    let today = now - now.rem_euclid(DAY);
+
+    match value.to_ascii_lowercase().as_str() {
+        "today" => return Some(today),
+        "yesterday" => return Some(today - DAY),
+        _ => {}
+    }
+
+    if let Some(relative) = value.strip_prefix('-') {
+        let unit = relative.chars().last()?;
+        let amount: i64 = relative[..relative.len() - unit.len_utf8()].parse().ok()?;
+        let seconds = match unit.to_ascii_lowercase() {
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => DAY,
+            'w' => 7 * DAY,
+            _ => return None,
+        };
+        return Some(now - amount.checked_mul(seconds)?);
+    }
+
+    let mut parts = value.splitn(3, '-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: u32 = parts.next()?.parse().ok()?;
+    let day: u32 = parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+        return None;
+    }
+    Some(days_from_civil(year, month, day) * DAY)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if (year % 4 == 0 && year % 100 != 0) || year % 400 == 0 => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date (Howard Hinnant's
+/// `days_from_civil`).
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> QueryNode {
+        parse_query(input)
+            .unwrap_or_else(|err| panic!("{input}: {err}"))
+            .expect("non-blank query")
+            .root
+    }
+
+    fn parse_error(input: &str) -> QueryError {
+        parse_query(input).expect_err(input)
+    }
+
+    fn term(term: QueryTerm) -> QueryNode {
+        QueryNode::Term(term)
+    }
+
+    fn text(value: &str) -> QueryNode {
+        term(QueryTerm::Text(value.to_string()))
+    }
+
+    fn now() -> i64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64
+    }
+
+    #[test]
+    fn blank_queries_parse_to_none() {
+        for input in ["", "   ", "\t\n"] {
+            assert_eq!(parse_query(input), Ok(None), "{input:?}");
+        }
+    }
+
+    #[test]
+    fn combines_terms_groups_and_negation() {
+        let cases = [
+            ("rust", text("rust")),
+            (
+                "rust async",
+                QueryNode::And(vec![text("rust"), text("async")]),
+            ),
+            (
+                "\"exact phrase\" word",
+                QueryNode::And(vec![
+                    term(QueryTerm::Phrase("exact phrase".to_string())),
+                    text("word"),
+                ]),
+            ),
+            (
+                "\"say \\\"hi\\\"\"",
+                term(QueryTerm::Phrase("say \"hi\"".to_string())),
+            ),
+            ("-draft", QueryNode::Not(Box::new(text("draft")))),
+            (
+                "a OR b c",
+                QueryNode::And(vec![QueryNode::Or(vec![text("a"), text("b")]), text("c")]),
+            ),
+            (
+                "(a OR b) -(c d)",
+                QueryNode::And(vec![
+                    QueryNode::Or(vec![text("a"), text("b")]),
+                    QueryNode::Not(Box::new(QueryNode::And(vec![text("c"), text("d")]))),
+                ]),
+            ),
+            ("(word)", text("word")),
+            // `or` in lower case and a lone `-` are plain words.
+            (
+                "a or -",
+                QueryNode::And(vec![text("a"), text("or"), text("-")]),
+            ),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(parse(input), expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn keeps_parentheses_inside_urls() {
+        assert_eq!(
+            parse("https://en.wikipedia.org/wiki/Rust_(language)"),
+            text("https://en.wikipedia.org/wiki/Rust_(language)")
+        );
+        assert_eq!(
+            parse("(see https://example.com/a_(b))"),
+            QueryNode::And(vec![text("see"), text("https://example.com/a_(b)")])
+        );
+    }
+
+    #[test]
+    fn reads_field_filters() {
+        let cases = [
+            ("type:img", QueryTerm::Type("image".to_string())),
+            ("TYPE:Url", QueryTerm::Type("link".to_string())),
+            ("type:path", QueryTerm::Type("files".to_string())),
+            (
+                "app:\"Visual Studio Code\"",
+                QueryTerm::App("Visual Studio Code".to_string()),
+            ),
+            ("app:firefox", QueryTerm::App("firefox".to_string())),
+            (
+                "site:https://www.GitHub.com/",
+                QueryTerm::Site("github.com".to_string()),
+            ),
+            ("has:url", QueryTerm::Has(HasFilter::Link)),
+            ("has:mail", QueryTerm::Has(HasFilter::Email)),
+            ("has:QR", QueryTerm::Has(HasFilter::Code)),
+            ("is:pin", QueryTerm::Pinned),
+            ("tag:Work", QueryTerm::Tag("Work".to_string())),
+            ("after:2024-05-31", QueryTerm::After(1_717_113_600)),
+            ("before:2024-02-29", QueryTerm::Before(1_709_164_800)),
+            ("before:1969-12-31", QueryTerm::Before(-86_400)),
+            // Unknown fields are searched as text.
+            ("note:later", QueryTerm::Text("note:later".to_string())),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(parse(input), term(expected), "{input}");
+        }
+    }
+
+    #[test]
+    fn reads_relative_dates() {
+        const DAY: i64 = 24 * 60 * 60;
+        let cases = [
+            ("after:-30m", 30 * 60),
+            ("after:-2h", 2 * 60 * 60),
+            ("after:-3D", 3 * DAY),
+            ("after:-1w", 7 * DAY),
+        ];
+        for (input, offset) in cases {
+            let before = now();
+            let QueryNode::Term(QueryTerm::After(timestamp)) = parse(input) else {
+                panic!("{input} is not an after: filter");
+            };
+            assert!(
+                (before - offset..=now() - offset).contains(&timestamp),
+                "{input}"
+            );
+        }
+
+        let QueryNode::Term(QueryTerm::Before(today)) = parse("before:today") else {
+            panic!("before:today is not a before: filter");
+        };
+        assert_eq!(today % DAY, 0);
+        assert!(today <= now() && now() - today < DAY);
+        assert_eq!(
+            parse("after:Yesterday"),
+            term(QueryTerm::After(today - DAY))
+        );
+    }
+
+    #[test]
+    fn reads_regexes() {
+        let cases = [
+            ("/err(or)?/", "err(or)?"),
+            ("/TODO|FIXME/i", "(?i)TODO|FIXME"),
+            ("/a\\/b/", "a/b"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(
+                parse(input),
+                term(QueryTerm::Regex(expected.to_string())),
+                "{input}"
+            );
+        }
+        // Paths and slashes followed by more text are plain words.
+        assert_eq!(parse("/usr/bin"), text("/usr/bin"));
+        assert_eq!(parse("/a/b/"), text("/a/b/"));
+        assert_eq!(parse("/x/g"), text("/x/g"));
+    }
+
+    #[test]
+    fn reports_errors_with_columns() {
+        let date_help = "use a date like 2024-05-31, today, yesterday, \
+                         or a relative time like -2d, -3h or -1w";
+        let cases = [
+            ("\"open", "Missing closing quote".to_string(), 0),
+            ("a app:\"open", "Missing closing quote".to_string(), 6),
+            ("foo )", "Unmatched `)`".to_string(), 4),
+            ("(foo", "Missing closing `)`".to_string(), 0),
+            ("x ()", "Empty group".to_string(), 2),
+            ("OR foo", "`OR` needs a term on both sides".to_string(), 0),
+            ("foo OR", "`OR` needs a term on both sides".to_string(), 4),
+            (
+                "a OR OR b",
+                "`OR` needs a term on both sides".to_string(),
+                2,
+            ),
+            ("//", "Empty regular expression".to_string(), 0),
+            (
+                "type:video",
+                "Unknown type `video`; use text, image, link or files".to_string(),
+                0,
+            ),
+            (
+                "x has:pdf",
+                "Unknown filter `has:pdf`; use has:link, has:email or has:code".to_string(),
+                2,
+            ),
+            (
+                "is:starred",
+                "Unknown filter `is:starred`; use is:pinned".to_string(),
+                0,
+            ),
+            (
+                "site:example.com/path",
+                "`site:` expects a domain such as github.com, not `example.com/path`".to_string(),
+                0,
+            ),
+            ("app:\"  \"", "`app:` needs a value".to_string(), 0),
+            (
+                "before:last-week",
+                format!("Can't read `before:last-week`; {date_help}"),
+                0,
+            ),
+            (
+                "after:2023-02-29",
+                format!("Can't read `after:2023-02-29`; {date_help}"),
+                0,
+            ),
+            (
+                "after:-5y",
+                format!("Can't read `after:-5y`; {date_help}"),
+                0,
+            ),
+            // Columns count characters, not bytes.
+            ("café )", "Unmatched `)`".to_string(), 5),
+        ];
+        for (input, message, column) in cases {
+            assert_eq!(
+                parse_error(input),
+                QueryError { message, column },
+                "{input}"
+            );
+        }
+
+        let err = parse_error("x /(unclosed/");
+        assert!(
+            err.message.starts_with("Invalid regular expression: "),
+            "{}",
+            err.message
+        );
+        assert_eq!(err.column, 2);
+        assert_eq!(
+            parse_error("foo )").to_string(),
+            "Unmatched `)` (at column 5)"
+        );
+    }
+
+    #[test]
+    fn collects_ranking_and_fuzzy_terms() {
+        let query = parse_query("rust -java \"exact words\" (a OR b) type:text")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            query.ranking_terms(),
+            vec![
+                &QueryTerm::Text("rust".to_string()),
+                &QueryTerm::Phrase("exact words".to_string()),
+                &QueryTerm::Text("a".to_string()),
+                &QueryTerm::Text("b".to_string()),
+            ]
+        );
+        assert_eq!(query.fuzzy_terms(), vec!["rust"]);
+
+        let single = parse_query("clipbaord").unwrap().unwrap();
+        assert_eq!(single.fuzzy_terms(), vec!["clipbaord"]);
+    }
+}
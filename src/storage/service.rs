@@ -0,0 +1,333 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+
+use anyhow::anyhow;
+use async_std::channel::{unbounded, Receiver, Sender};
+use async_std::sync::{Mutex, MutexGuard};
+use sea_orm::{DatabaseConnection, TransactionTrait};
+
+use crate::clipboard::importers::{import_foreign_history, ForeignSource};
+use crate::settings::{settings, BackupSettings, RetentionSettings};
+use crate::storage::archive::{export_history, import_history, ExportReport, ImportReport};
+use crate::storage::backup::{
+    check_database, finish_pending_restore, run_scheduled_backup, stage_restore, Backup,
+    RestoreReport,
+};
+use crate::storage::encryption::prepare_encryption;
+use crate::storage::entity::Model;
+use crate::storage::entry_link;
+use crate::storage::history::{
+    delete_clipboard_entry, insert_clipboard_entry, insert_entry_links, load_entries_page,
+    load_entry_links, load_entry_tags, load_images_missing_ocr, load_last_hash,
+    load_matching_entry, load_tags, move_pinned_entry, open_db, set_entry_pinned, tag_entry,
+    untag_entry, update_entry_link_metadata, update_link_metadata, update_ocr_result,
+    ClipboardEntryInput, EntryLinkInput, EntryPage, LinkMetadataUpdate, PageCursor, TagSummary,
+};
+use crate::storage::integrity::{verify_storage, IntegrityReport};
+use crate::storage::path::default_db_path;
+use crate::storage::relocate::{copy_data_dir, finish_data_dir_move, MoveReport};
+use crate::storage::retention::{prune_history, PruneReport};
+use crate::storage::search_query::SearchQuery;
+use crate::storage::tag;
+
+static SHARED: OnceLock<Mutex<Option<Storage>>> = OnceLock::new();
+
+/// What changed in the history, sent to every subscriber after the write
+/// is committed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StorageEvent {
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    EntryAdded(i32),
+    EntryRemoved(i32),
+    /// Pins, tags, OCR text or link previews of one entry changed.
+    EntryUpdated(i32),
+    /// Many entries changed at once: an import, a pruning run or a repair.
+    HistoryChanged,
+}
+
+/// The history database, opened once per process and shared by capture
+/// and the popup. Reads run concurrently on the connection pool; writes
+/// are queued so SQLite only ever sees one writer.
+#[derive(Clone)]
+pub struct Storage {
+    inner: Arc<StorageInner>,
+}
+
+struct StorageInner {
+    db: DatabaseConnection,
+    /// Held by every write; `true` once the history moved elsewhere or a
+    /// backup is waiting to replace it.
+    writes: Mutex<bool>,
+    subscribers: std::sync::Mutex<Vec<Sender<StorageEvent>>>,
+}
+
+impl Storage {
+    /// The process-wide storage, opened and migrated on first use. A
+    /// damaged database fails with `CorruptDatabase` and stays closed.
+    pub async fn shared() -> anyhow::Result<Self> {
+        let mut shared = SHARED.get_or_init(|| Mutex::new(None)).lock().await;
+        if let Some(storage) = shared.as_ref() {
+            return Ok(storage.clone());
+        }
+        finish_data_dir_move();
+        finish_pending_restore();
+        let path = default_db_path()?;
+        check_database(&path).await?;
+        let storage = Self::open(&path).await?;
+        *shared = Some(storage.clone());
+        Ok(storage)
+    }
+
+    async fn open(path: &Path) -> anyhow::Result<Self> {
+        let db = open_db(path, settings().encryption.enabled).await?;
+        prepare_encryption(&db).await?;
+        Ok(Self {
+            inner: Arc::new(StorageInner {
+                db,
+                writes: Mutex::new(false),
+                subscribers: std::sync::Mutex::new(Vec::new()),
+            }),
+        })
+    }
+
+    /// Events for every write from now on, in commit order.
+    pub fn subscribe(&self) -> Receiver<StorageEvent> {
+        let (tx, rx) = unbounded();
+        if let Ok(mut subscribers) = self.inner.subscribers.lock() {
+            subscribers.push(tx);
+        }
+        rx
+    }
+
+    async fn write_lock(&self) -> anyhow::Result<MutexGuard<'_, bool>> {
+        let moved = self.inner.writes.lock().await;
+        if *moved {
+            return Err(anyhow!(
+                "The history was moved or restored; restart to keep using it"
+            ));
+        }
+        Ok(moved)
+    }
+
+    fn emit(&self, event: StorageEvent) {
+        if let Ok(mut subscribers) = self.inner.subscribers.lock() {
+            subscribers.retain(|tx| tx.try_send(event).is_ok());
+        }
+    }
+
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    pub async fn last_hash(&self) -> anyhow::Result<Option<String>> {
+        load_last_hash(&self.inner.db).await
+    }
+
+    pub async fn entries_page(
+        &self,
+        query: Option<&SearchQuery>,
+        fuzzy: bool,
+        after: Option<&PageCursor>,
+        limit: u64,
+    ) -> anyhow::Result<EntryPage> {
+        load_entries_page(&self.inner.db, query, fuzzy, after, limit).await
+    }
+
+    pub async fn matching_entry(
+        &self,
+        query: Option<&SearchQuery>,
+        fuzzy: bool,
+        id: i32,
+    ) -> anyhow::Result<Option<Model>> {
+        load_matching_entry(&self.inner.db, query, fuzzy, id).await
+    }
+
+    pub async fn entry_links(
+        &self,
+        entry_ids: &[i32],
+    ) -> anyhow::Result<HashMap<i32, Vec<entry_link::Model>>> {
+        load_entry_links(&self.inner.db, entry_ids).await
+    }
+
+    pub async fn entry_tags(
+        &self,
+        entry_ids: &[i32],
+    ) -> anyhow::Result<HashMap<i32, Vec<tag::Model>>> {
+        load_entry_tags(&self.inner.db, entry_ids).await
+    }
+
+    pub async fn tags(&self) -> anyhow::Result<Vec<TagSummary>> {
+        load_tags(&self.inner.db).await
+    }
+
+    pub async fn images_missing_ocr(&self, attempt: &str) -> anyhow::Result<Vec<(i32, String)>> {
+        load_images_missing_ocr(&self.inner.db, attempt).await
+    }
+
+    pub async fn export_history(
+        &self,
+        query: Option<&SearchQuery>,
+        path: &Path,
+    ) -> anyhow::Result<ExportReport> {
+        export_history(&self.inner.db, query, path).await
+    }
+
+    /// Stores a capture with its links and tags as one write.
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    pub async fn add_entry(
+        &self,
+        input: ClipboardEntryInput<'_>,
+        links: &[EntryLinkInput<'_>],
+        tags: &[String],
+    ) -> anyhow::Result<i32> {
+        let _write = self.write_lock().await?;
+        let txn = self.inner.db.begin().await?;
+        let id = insert_clipboard_entry(&txn, input).await?;
+        insert_entry_links(&txn, id, links).await?;
+        for tag in tags {
+            tag_entry(&txn, id, tag).await?;
+        }
+        txn.commit().await?;
+        self.emit(StorageEvent::EntryAdded(id));
+        Ok(id)
+    }
+
+    pub async fn delete_entry(&self, id: i32) -> anyhow::Result<()> {
+        let _write = self.write_lock().await?;
+        delete_clipboard_entry(&self.inner.db, id).await?;
+        self.emit(StorageEvent::EntryRemoved(id));
+        Ok(())
+    }
+
+    pub async fn set_pinned(&self, id: i32, pinned: bool) -> anyhow::Result<()> {
+        let _write = self.write_lock().await?;
+        set_entry_pinned(&self.inner.db, id, pinned).await?;
+        self.emit(StorageEvent::EntryUpdated(id));
+        Ok(())
+    }
+
+    pub async fn move_pin(&self, id: i32, target_id: i32) -> anyhow::Result<()> {
+        let _write = self.write_lock().await?;
+        move_pinned_entry(&self.inner.db, id, target_id).await?;
+        self.emit(StorageEvent::EntryUpdated(id));
+        Ok(())
+    }
+
+    pub async fn tag_entry(&self, entry_id: i32, name: &str) -> anyhow::Result<()> {
+        let _write = self.write_lock().await?;
+        tag_entry(&self.inner.db, entry_id, name).await?;
+        self.emit(StorageEvent::EntryUpdated(entry_id));
+        Ok(())
+    }
+
+    pub async fn untag_entry(&self, entry_id: i32, tag_id: i32) -> anyhow::Result<()> {
+        let _write = self.write_lock().await?;
+        untag_entry(&self.inner.db, entry_id, tag_id).await?;
+        self.emit(StorageEvent::EntryUpdated(entry_id));
+        Ok(())
+    }
+
+    pub async fn update_ocr_result(
+        &self,
+        id: i32,
+        text: Option<&str>,
+        layout: Option<&str>,
+        attempt: &str,
+    ) -> anyhow::Result<()> {
+        let _write = self.write_lock().await?;
+        update_ocr_result(&self.inner.db, id, text, layout, attempt).await?;
+        self.emit(StorageEvent::EntryUpdated(id));
+        Ok(())
+    }
+
+    pub async fn update_link_metadata(
+        &self,
+        id: i32,
+        update: LinkMetadataUpdate<'_>,
+    ) -> anyhow::Result<()> {
+        let _write = self.write_lock().await?;
+        update_link_metadata(&self.inner.db, id, update).await?;
+        self.emit(StorageEvent::EntryUpdated(id));
+        Ok(())
+    }
+
+    pub async fn update_entry_link_metadata(
+        &self,
+        entry_id: i32,
+        link_id: i32,
+        update: LinkMetadataUpdate<'_>,
+    ) -> anyhow::Result<()> {
+        let _write = self.write_lock().await?;
+        update_entry_link_metadata(&self.inner.db, link_id, update).await?;
+        self.emit(StorageEvent::EntryUpdated(entry_id));
+        Ok(())
+    }
+
+    pub async fn import_history(&self, path: &Path) -> anyhow::Result<ImportReport> {
+        let _write = self.write_lock().await?;
+        let report = import_history(&self.inner.db, path).await?;
+        if report.imported > 0 || report.duplicates > 0 {
+            self.emit(StorageEvent::HistoryChanged);
+        }
+        Ok(report)
+    }
+
+    pub async fn import_foreign_history(
+        &self,
+        source: ForeignSource,
+        path: &Path,
+    ) -> anyhow::Result<ImportReport> {
+        let _write = self.write_lock().await?;
+        let report = import_foreign_history(&self.inner.db, source, path).await?;
+        if report.imported > 0 {
+            self.emit(StorageEvent::HistoryChanged);
+        }
+        Ok(report)
+    }
+
+    pub async fn prune(&self, policy: &RetentionSettings) -> anyhow::Result<PruneReport> {
+        let _write = self.write_lock().await?;
+        let report = prune_history(&self.inner.db, policy).await?;
+        if report.entries > 0 {
+            self.emit(StorageEvent::HistoryChanged);
+        }
+        Ok(report)
+    }
+
+    /// Checks the database and image files, fixing what it can when
+    /// `repair` is set.
+    pub async fn verify(&self, repair: bool) -> anyhow::Result<IntegrityReport> {
+        let _write = if repair {
+            Some(self.write_lock().await?)
+        } else {
+            None
+        };
+        let report = verify_storage(&self.inner.db, repair).await?;
+        if report.repaired {
+            self.emit(StorageEvent::HistoryChanged);
+        }
+        Ok(report)
+    }
+
+    /// Backs the database up if the last backup is older than the policy's
+    /// interval.
+    pub async fn backup_if_due(&self, policy: &BackupSettings) -> anyhow::Result<Option<Backup>> {
+        run_scheduled_backup(&self.inner.db, policy).await
+    }
+
+    /// Stages `backup` to replace the history on the next start, then
+    /// refuses further writes so nothing lands in the copy being replaced.
+    pub async fn restore_backup(&self, backup: &Path) -> anyhow::Result<RestoreReport> {
+        let mut retired = self.write_lock().await?;
+        let report = stage_restore(backup).await?;
+        *retired = true;
+        Ok(report)
+    }
+
+    /// Copies the history to `target` for the next start, then refuses
+    /// further writes so nothing lands in the old copy.
+    pub async fn move_data_dir(&self, target: &Path) -> anyhow::Result<MoveReport> {
+        let mut moved = self.write_lock().await?;
+        let report = copy_data_dir(&self.inner.db, target).await?;
+        *moved = true;
+        Ok(report)
+    }
+}
@@ -0,0 +1,258 @@
+use unicode_normalization::char::{decompose_canonical, is_combining_mark};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// A character of folded text and the byte offset of the original character
+/// it came from. Folding can expand one character into several (`ß` → `ss`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FoldedChar {
+    pub ch: char,
+    pub byte: usize,
+}
+
+/// Case- and diacritic-folded text, as used for highlighting.
+pub struct FoldedText {
+    pub chars: Vec<FoldedChar>,
+    /// Indices into `chars` where a word starts.
+    pub word_starts: Vec<usize>,
+}
+
+impl FoldedText {
+    pub fn new(text: &str) -> Self {
+        let mut chars = Vec::new();
+        let mut word_starts = Vec::new();
+        let mut words = words(text).map(|(offset, _)| offset).peekable();
+        for (byte, ch) in text.char_indices() {
+            if words.next_if_eq(&byte).is_some() {
+                word_starts.push(chars.len());
+            }
+            fold_char(ch, |folded| chars.push(FoldedChar { ch: folded, byte }));
+        }
+        Self { chars, word_starts }
+    }
+
+    /// Byte offsets of the original characters matched by the first word
+    /// starting with `token`, compared after folding both sides.
+    pub fn prefix_positions(&self, token: &str) -> Option<Vec<usize>> {
+        let token: Vec<char> = fold(token).chars().collect();
+        if token.is_empty() {
+            return Some(Vec::new());
+        }
+        self.word_starts.iter().find_map(|&start| {
+            let candidate = self.chars.get(start..start + token.len())?;
+            if !candidate.iter().map(|ch| ch.ch).eq(token.iter().copied()) {
+                return None;
+            }
+            let mut positions: Vec<usize> = candidate.iter().map(|ch| ch.byte).collect();
+            positions.dedup();
+            Some(positions)
+        })
+    }
+}
+
+/// Full case folding plus diacritic removal, e.g. `Straße` → `strasse` and
+/// `Crème` → `creme`. CJK and Hangul text is kept as is, since decomposing
+/// it would erase meaningful distinctions such as kana voicing marks.
+pub fn fold(text: &str) -> String {
+    let mut folded = String::with_capacity(text.len());
+    for ch in text.chars() {
+        fold_char(ch, |ch| folded.push(ch));
+    }
+    folded
+}
+
+pub fn fold_char(ch: char, mut push: impl FnMut(char)) {
+    if ch.is_ascii() {
+        return push(ch.to_ascii_lowercase());
+    }
+    if is_cjk(ch) || is_hangul(ch) {
+        return push(ch);
+    }
+    // Fullwidth ASCII, as typed by Japanese and Chinese input methods.
+    if ('\u{ff01}'..='\u{ff5e}').contains(&ch) {
+        let ascii = char::from_u32(ch as u32 - 0xfee0).unwrap_or(ch);
+        return push(ascii.to_ascii_lowercase());
+    }
+    let expanded: &[char] = match ch {
+        'ß' | 'ẞ' => &['s', 's'],
+        'æ' | 'Æ' => &['a', 'e'],
+        'œ' | 'Œ' => &['o', 'e'],
+        'ſ' => &['s'],
+        'ς' => &['σ'],
+        'ı' => &['i'],
+        // Letters with a stroke have no canonical decomposition.
+        'ł' | 'Ł' => &['l'],
+        'ø' | 'Ø' => &['o'],
+        'đ' | 'Đ' => &['d'],
+        'ħ' | 'Ħ' => &['h'],
+        _ => &[],
+    };
+    if !expanded.is_empty() {
+        return expanded.iter().copied().for_each(push);
+    }
+    decompose_canonical(ch, |part| {
+        if !is_combining_mark(part) {
+            part.to_lowercase().for_each(&mut push);
+        }
+    });
+}
+
+/// Han ideographs and Japanese kana: scripts written without spaces, where
+/// every character may start a word.
+pub fn is_cjk(ch: char) -> bool {
+    matches!(
+        ch,
+        '\u{3040}'..='\u{30ff}'
+            | '\u{31f0}'..='\u{31ff}'
+            | '\u{3400}'..='\u{4dbf}'
+            | '\u{4e00}'..='\u{9fff}'
+            | '\u{f900}'..='\u{faff}'
+            | '\u{ff66}'..='\u{ff9f}'
+            | '\u{20000}'..='\u{2fa1f}'
+    )
+}
+
+fn is_hangul(ch: char) -> bool {
+    matches!(ch, '\u{1100}'..='\u{11ff}' | '\u{3130}'..='\u{318f}' | '\u{ac00}'..='\u{d7af}')
+}
+
+/// Words of `text` with their byte offsets, following Unicode word
+/// segmentation. Segments are further split at inner punctuation the way
+/// the FTS tokenizer does (`example.com`, `snake_case`), and CJK text yields
+/// one word per character.
+pub fn words(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.split_word_bound_indices()
+        .filter(|(_, segment)| segment.chars().any(char::is_alphanumeric))
+        .flat_map(|(offset, segment)| {
+            split_segment(segment)
+                .into_iter()
+                .map(move |(index, word)| (offset + index, word))
+        })
+}
+
+fn split_segment(segment: &str) -> Vec<(usize, &str)> {
+    let mut words = Vec::new();
+    let mut start: Option<usize> = None;
+    for (index, ch) in segment.char_indices() {
+        let is_word_char = ch.is_alphanumeric() || (start.is_some() && is_combining_mark(ch));
+        if is_cjk(ch) {
+            if let Some(start) = start.take() {
+                words.push((start, &segment[start..index]));
+            }
+            words.push((index, &segment[index..index + ch.len_utf8()]));
+        } else if !is_word_char {
+            if let Some(start) = start.take() {
+                words.push((start, &segment[start..index]));
+            }
+        } else if start.is_none() {
+            start = Some(index);
+        }
+    }
+    if let Some(start) = start {
+        words.push((start, &segment[start..]));
+    }
+    words
+}
+
+/// Terms stored in the `search_terms` column for `fields`: folded words, and
+/// overlapping character pairs for CJK runs, which the FTS tokenizer would
+/// otherwise index as one long token. The last character of each run is
+/// added on its own after the field's other terms, so one-character queries
+/// match anywhere in a run without breaking up phrases.
+pub fn search_terms<'a>(fields: impl IntoIterator<Item = &'a str>) -> Option<String> {
+    let mut terms = Vec::new();
+    for field in fields {
+        let mut run_ends = Vec::new();
+        terms.extend(terms_for(field, Some(&mut run_ends)));
+        terms.extend(run_ends);
+    }
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+/// The terms of a search box word or phrase, matching what `search_terms`
+/// stores for the same text.
+pub fn query_terms(text: &str) -> Vec<String> {
+    terms_for(text, None)
+}
+
+fn terms_for(text: &str, mut run_ends: Option<&mut Vec<String>>) -> Vec<String> {
+    let mut terms = Vec::new();
+    let mut run: Vec<char> = Vec::new();
+    let mut run_end = 0;
+    for (offset, word) in words(text) {
+        let first = word.chars().next().unwrap_or_default();
+        if is_cjk(first) {
+            if offset != run_end {
+                flush_cjk_run(&mut run, &mut terms, run_ends.as_deref_mut());
+            }
+            run.push(first);
+            run_end = offset + word.len();
+            continue;
+        }
+        flush_cjk_run(&mut run, &mut terms, run_ends.as_deref_mut());
+        terms.push(fold(word));
+    }
+    flush_cjk_run(&mut run, &mut terms, run_ends);
+    terms
+}
+
+fn flush_cjk_run(run: &mut Vec<char>, terms: &mut Vec<String>, run_ends: Option<&mut Vec<String>>) {
+    match run.as_slice() {
+        [] => {}
+        [single] => terms.push(single.to_string()),
+        chars => {
+            terms.extend(chars.windows(2).map(|pair| pair.iter().collect::<String>()));
+            if let (Some(run_ends), Some(last)) = (run_ends, chars.last()) {
+                run_ends.push(last.to_string());
+            }
+        }
+    }
+    run.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cjk_runs_become_overlapping_pairs() {
+        assert_eq!(
+            search_terms(["東京都庁"]).as_deref(),
+            Some("東京 京都 都庁 庁")
+        );
+        assert_eq!(query_terms("東京都"), ["東京", "京都"]);
+        assert_eq!(query_terms("東"), ["東"]);
+        assert_eq!(query_terms("Tokyo 東京"), ["tokyo", "東京"]);
+        let words: Vec<&str> = words("ひらがな").map(|(_, word)| word).collect();
+        assert_eq!(words, ["ひ", "ら", "が", "な"]);
+    }
+
+    #[test]
+    fn diacritics_fold_away() {
+        assert_eq!(fold("Crème Brûlée"), "creme brulee");
+        assert_eq!(fold("Łódź"), "lodz");
+        assert_eq!(fold("Ｈｅｌｌｏ"), "hello");
+        // Kana voicing marks are part of the letter.
+        assert_eq!(fold("が"), "が");
+    }
+
+    #[test]
+    fn case_folding_is_full() {
+        assert_eq!(fold("Straße"), "strasse");
+        assert_eq!(fold("STRAẞE"), "strasse");
+        assert_eq!(fold("İstanbul"), "istanbul");
+        assert_eq!(fold("ıi"), "ii");
+        assert_eq!(fold("ΟΔΟΣ"), fold("οδος"));
+    }
+
+    #[test]
+    fn prefix_positions_map_to_original_bytes() {
+        let text = FoldedText::new("Straße café");
+        // Both halves of `ß` point at its single original character.
+        assert_eq!(text.prefix_positions("strass"), Some(vec![0, 1, 2, 3, 4]));
+        assert_eq!(text.prefix_positions("CAFE"), Some(vec![8, 9, 10, 11]));
+        assert_eq!(text.prefix_positions("afe"), None);
+
+        let text = FoldedText::new("東京都");
+        assert_eq!(text.prefix_positions("京都"), Some(vec![3, 6]));
+    }
+}
@@ -0,0 +1,77 @@
+//! Helpers shared by the unit tests.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::thread;
+
+use crate::storage::crypto;
+
+static NEXT_DIR: AtomicUsize = AtomicUsize::new(0);
+static STORAGE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Serves canned bodies by request path (query strings are ignored) on a
+/// local port for the rest of the test run. Returns the base URL, e.g.
+/// `http://127.0.0.1:41234`; unknown paths get a 404.
+pub fn serve(routes: Vec<(&'static str, String)>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind test server");
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else {
+                continue;
+            };
+            let mut reader = BufReader::new(&mut stream);
+            let mut request_line = String::new();
+            if reader.read_line(&mut request_line).is_err() {
+                continue;
+            }
+            let mut header = String::new();
+            while reader.read_line(&mut header).is_ok_and(|read| read > 2) {
+                header.clear();
+            }
+            let target = request_line.split_whitespace().nth(1).unwrap_or("/");
+            let path = target.split('?').next().unwrap_or(target);
+            let response = match routes.iter().find(|(route, _)| *route == path) {
+                Some((_, body)) => {
+                    let content_type = if body.starts_with('{') || body.starts_with('[') {
+                        "application/json"
+                    } else {
+                        "text/html; charset=utf-8"
+                    };
+                    format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                        body.len()
+                    )
+                }
+                None => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                    .to_string(),
+            };
+            let _ = stream.write_all(response.as_bytes());
+        }
+    });
+    base
+}
+
+/// A fresh, empty directory under the system temp directory.
+pub fn temp_dir(label: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "gpui-clipboard-manager-test-{}-{}-{label}",
+        std::process::id(),
+        NEXT_DIR.fetch_add(1, Ordering::Relaxed)
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).expect("create test directory");
+    dir
+}
+
+/// Serializes tests that store or read history, since the history key and
+/// whether writes are encrypted are process-wide. Each holder starts with no
+/// key installed.
+pub fn lock_storage() -> MutexGuard<'static, ()> {
+    let guard = STORAGE_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+    crypto::reset();
+    guard
+}
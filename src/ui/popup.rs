@@ -2,30 +2,74 @@
 use clipboard_win::{formats, raw, Clipboard, Setter};
 use gpui::{
     actions, canvas, div, fill, img, list, point, prelude::*, px, relative, rgb, rgba, size,
-    uniform_list, AnyElement, App, AppContext, Bounds, ClipboardItem, Context, Element, ElementId,
-    GlobalElementId, InteractiveElement, KeyBinding, LayoutId, ListAlignment, ListState,
-    MouseButton, MouseDownEvent, MouseMoveEvent, MouseUpEvent, ObjectFit, Pixels, Point,
-    ScrollStrategy, ShapedLine, SharedString, Style, TextRun, UniformListScrollHandle, Window,
+    uniform_list, AnyElement, AnyWindowHandle, App, AppContext, Bounds, ClipboardItem, Context,
+    DispatchPhase, Element, ElementId, GlobalElementId, HitboxBehavior, Image, ImageFormat,
+    ImageSource, InteractiveElement, KeyBinding, LayoutId, ListAlignment, ListOffset, ListState,
+    MouseButton, MouseDownEvent, MouseMoveEvent, MouseUpEvent, ObjectFit, PathPromptOptions,
+    Pixels, Point, ScrollStrategy, ShapedLine, SharedString, Style, TextRun,
+    UniformListScrollHandle, Window,
 };
 use gpui_component::{
     input::{Input, InputState},
-    menu::{ContextMenuExt, PopupMenuItem},
+    menu::{ContextMenuExt, PopupMenu, PopupMenuItem},
     Icon, IconName, Root, Sizable, WindowExt,
 };
-use sea_orm::DatabaseConnection;
-use std::path::PathBuf;
-use std::time::Duration;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use unicode_segmentation::UnicodeSegmentation;
 
+use crate::clipboard::barcodes::{codes_from_json, DecodedCode};
+use crate::clipboard::importers::ForeignSource;
+use crate::clipboard::link_metadata::{
+    fetch_link_metadata, parse_link_url, LinkDetails, LinkMetadata,
+};
+use crate::clipboard::network::PolicyViolation;
+use crate::clipboard::ocr::{
+    attempt_key, load_image_file, recognize_decoded, OcrLayout, OcrResult, OcrWord,
+};
 use crate::clipboard::watcher::ignore_next_hash;
+use crate::settings::{settings, update_settings, LinkEnterAction};
+use crate::storage::backup::{list_backups, recover_corrupt_database, Backup, CorruptDatabase};
+use crate::storage::crypto;
 use crate::storage::entity::Model;
-use crate::storage::history::{delete_clipboard_entry, load_entries_page, open_db};
-use crate::storage::path::default_db_path;
+use crate::storage::entry_link;
+use crate::storage::fuzzy::fuzzy_match;
+use crate::storage::history::{LinkMetadataUpdate, PageCursor, TagSummary};
+use crate::storage::images::{read_image_bytes, thumbnail_path};
+use crate::storage::integrity::IntegrityReport;
 #[cfg(target_os = "windows")]
 use crate::storage::path::image_path_for_hash;
-use crate::utils::hash_bytes;
-
-actions!(popup, [TogglePopup, MoveUp, MoveDown, ConfirmSelection]);
+use crate::storage::search_query::{parse_query, QueryNode, QueryTerm, SearchQuery};
+use crate::storage::service::{Storage, StorageEvent};
+use crate::storage::tag;
+use crate::storage::tokenizer::FoldedText;
+
+actions!(
+    popup,
+    [
+        TogglePopup,
+        MoveUp,
+        MoveDown,
+        ConfirmSelection,
+        ToggleFuzzySearch,
+        ToggleFindInEntry,
+        NextMatch,
+        PreviousMatch,
+        TogglePin,
+        MovePinUp,
+        MovePinDown,
+        ToggleTagInput,
+        AddTag,
+        VerifyStorage,
+        ExportHistory,
+        ImportHistory,
+        MoveDataDir,
+        RestoreBackup
+    ]
+);
 
 pub fn bind_popup_keys(cx: &mut App) {
     cx.bind_keys([
@@ -33,6 +77,22 @@ pub fn bind_popup_keys(cx: &mut App) {
         KeyBinding::new("down", MoveDown, Some("Popup")),
         KeyBinding::new("enter", ConfirmSelection, Some("Popup")),
         KeyBinding::new("enter", ConfirmSelection, Some("Input")),
+        KeyBinding::new("alt-f", ToggleFuzzySearch, Some("Popup")),
+        KeyBinding::new("ctrl-f", ToggleFindInEntry, Some("Popup")),
+        KeyBinding::new("f3", NextMatch, Some("Popup")),
+        KeyBinding::new("shift-f3", PreviousMatch, Some("Popup")),
+        KeyBinding::new("enter", NextMatch, Some("FindBar > Input")),
+        KeyBinding::new("shift-enter", PreviousMatch, Some("FindBar > Input")),
+        KeyBinding::new("ctrl-p", TogglePin, Some("Popup")),
+        KeyBinding::new("alt-up", MovePinUp, Some("Popup")),
+        KeyBinding::new("alt-down", MovePinDown, Some("Popup")),
+        KeyBinding::new("ctrl-t", ToggleTagInput, Some("Popup")),
+        KeyBinding::new("enter", AddTag, Some("TagBar > Input")),
+        KeyBinding::new("ctrl-alt-v", VerifyStorage, Some("Popup")),
+        KeyBinding::new("ctrl-shift-e", ExportHistory, Some("Popup")),
+        KeyBinding::new("ctrl-shift-i", ImportHistory, Some("Popup")),
+        KeyBinding::new("ctrl-shift-m", MoveDataDir, Some("Popup")),
+        KeyBinding::new("ctrl-shift-r", RestoreBackup, Some("Popup")),
     ]);
 }
 
@@ -40,29 +100,54 @@ pub struct PopupView {
     is_visible: bool,
     search_input: gpui::Entity<InputState>,
     entries: Vec<Model>,
+    entry_links: HashMap<i32, Vec<entry_link::Model>>,
     search_query: String,
+    search: Option<SearchQuery>,
+    search_error: Option<String>,
+    fuzzy_search: bool,
     selected_index: usize,
-    db: Option<DatabaseConnection>,
+    storage: Option<Storage>,
     list_scroll: UniformListScrollHandle,
     detail_list_state: ListState,
+    /// Selected entry, query and fuzzy mode `detail_matches` were computed
+    /// for. `None` recomputes them on the next render.
+    detail_list_key: Option<(Option<i32>, String, bool)>,
+    /// Occurrences of the detail query in the selected entry's text, in
+    /// reading order.
+    detail_matches: Vec<DetailMatch>,
+    current_match: usize,
+    /// Scroll applied to the detail list on the next render.
+    pending_detail_scroll: Option<DetailScroll>,
+    find_input: gpui::Entity<InputState>,
+    find_visible: bool,
+    find_query: String,
+    /// Tags in use, for the filter strip and the context menu.
+    tags: Vec<TagSummary>,
+    entry_tags: HashMap<i32, Vec<tag::Model>>,
+    /// Only entries with this tag are listed.
+    tag_filter: Option<String>,
+    tag_input: gpui::Entity<InputState>,
+    tag_input_visible: bool,
     list_scroll_drag: Option<Point<Pixels>>,
+    ocr_selection: Option<OcrSelection>,
+    /// `(done, total)` while images without OCR text are being processed.
+    ocr_backfill: Option<(usize, usize)>,
+    /// Summary of the last pruning run, shown until the popup is hidden.
+    prune_notice: Option<String>,
     history_scrollbar_visible: bool,
     history_scrollbar_hide_gen: u64,
     last_scroll_offset: Option<Pixels>,
     is_loading: bool,
     has_more: bool,
-    page_offset: u64,
+    /// Where the next page of the list starts.
+    page_cursor: Option<PageCursor>,
     page_size: u64,
     load_generation: u64,
     entries_clear_gen: u64,
 }
 
 impl PopupView {
-    pub fn new(
-        window: &mut Window,
-        cx: &mut Context<Self>,
-        clipboard_updates: std::sync::mpsc::Receiver<()>,
-    ) -> Self {
+    pub fn new(window: &mut Window, cx: &mut Context<Self>) -> Self {
         cx.observe_window_activation(window, |view, window, cx| {
             if window.is_window_active() {
                 return;
@@ -75,22 +160,51 @@ impl PopupView {
                 .placeholder("Search clipboard...")
                 .clean_on_escape()
         });
+        let find_input = cx.new(|cx| {
+            InputState::new(window, cx)
+                .placeholder("Find in entry...")
+                .clean_on_escape()
+        });
+        let tag_input = cx.new(|cx| {
+            InputState::new(window, cx)
+                .placeholder("Add tag...")
+                .clean_on_escape()
+        });
         let view = Self {
             is_visible: true,
             search_input,
             entries: Vec::new(),
+            entry_links: HashMap::new(),
             search_query: String::new(),
+            search: None,
+            search_error: None,
+            fuzzy_search: settings().search.fuzzy,
             selected_index: 0,
-            db: None,
+            storage: None,
             list_scroll: UniformListScrollHandle::new(),
             detail_list_state: ListState::new(1, ListAlignment::Top, px(20.)),
+            detail_list_key: None,
+            detail_matches: Vec::new(),
+            current_match: 0,
+            pending_detail_scroll: None,
+            find_input,
+            find_visible: false,
+            find_query: String::new(),
+            tags: Vec::new(),
+            entry_tags: HashMap::new(),
+            tag_filter: None,
+            tag_input,
+            tag_input_visible: false,
             list_scroll_drag: None,
+            ocr_selection: None,
+            ocr_backfill: None,
+            prune_notice: None,
             history_scrollbar_visible: false,
             history_scrollbar_hide_gen: 0,
             last_scroll_offset: None,
             is_loading: false,
             has_more: true,
-            page_offset: 0,
+            page_cursor: None,
             page_size: 100,
             load_generation: 0,
             entries_clear_gen: 0,
@@ -100,61 +214,68 @@ impl PopupView {
             cx.focus_view(&view.search_input, window);
         });
 
+        let window_handle = window.window_handle();
         cx.spawn(
-            |view: gpui::WeakEntity<PopupView>, cx: &mut gpui::AsyncApp| {
+            move |view: gpui::WeakEntity<PopupView>, cx: &mut gpui::AsyncApp| {
                 let mut async_cx = cx.clone();
                 async move {
-                    let db_path = match default_db_path() {
-                        Ok(path) => path,
-                        Err(err) => {
-                            eprintln!("Failed to resolve clipboard database path: {err}");
-                            return;
-                        }
-                    };
-
-                    let db = match open_db(&db_path).await {
-                        Ok(db) => db,
+                    let storage = match Storage::shared().await {
+                        Ok(storage) => storage,
                         Err(err) => {
                             eprintln!("Failed to open clipboard database: {err}");
+                            if let Some(corrupt) = err.downcast_ref::<CorruptDatabase>() {
+                                let reason = corrupt.to_string();
+                                let _ = async_cx.update_window(window_handle, |_, window, cx| {
+                                    show_recovery_dialog(reason, window, cx);
+                                });
+                            }
                             return;
                         }
                     };
+                    let events = storage.subscribe();
 
-                    if let Some(handle) = view.upgrade() {
-                        let _ = async_cx.update_entity(&handle, |view: &mut PopupView, cx| {
-                            view.db = Some(db);
-                            view.reset_and_load(cx);
-                        });
-                    }
-                }
-            },
-        )
-        .detach();
-
-        cx.spawn(
-            move |view: gpui::WeakEntity<PopupView>, cx: &mut gpui::AsyncApp| {
-                let mut async_cx = cx.clone();
-                async move {
-                    loop {
-                        let mut received = false;
-                        while clipboard_updates.try_recv().is_ok() {
-                            received = true;
+                    let Some(handle) = view.upgrade() else {
+                        return;
+                    };
+                    let _ = async_cx.update_entity(&handle, |view: &mut PopupView, cx| {
+                        view.storage = Some(storage);
+                        view.reset_and_load(cx);
+                        view.start_ocr_backfill(cx);
+                        view.start_retention_pruner(cx);
+                        view.start_backups(cx);
+                    });
+                    drop(handle);
+
+                    // Single-entry updates are applied in place by whoever
+                    // made them and new captures go on top; anything else
+                    // that adds or removes rows reloads.
+                    while let Ok(event) = events.recv().await {
+                        let mut added = Vec::new();
+                        let mut reload = false;
+                        let mut next = Some(event);
+                        while let Some(event) = next {
+                            match event {
+                                StorageEvent::EntryAdded(id) => added.push(id),
+                                StorageEvent::EntryUpdated(_) => {}
+                                StorageEvent::EntryRemoved(_) | StorageEvent::HistoryChanged => {
+                                    reload = true
+                                }
+                            }
+                            next = events.try_recv().ok();
                         }
-                        if received {
-                            if let Some(handle) = view.upgrade() {
-                                let _ =
-                                    async_cx.update_entity(&handle, |view: &mut PopupView, cx| {
-                                        view.reset_and_load(cx);
-                                    });
+                        if !reload && added.is_empty() {
+                            continue;
+                        }
+                        let Some(handle) = view.upgrade() else {
+                            return;
+                        };
+                        let _ = async_cx.update_entity(&handle, |view: &mut PopupView, cx| {
+                            if reload {
+                                view.reset_and_load(cx);
                             } else {
-                                break;
+                                view.prepend_entries(added, cx);
                             }
-                        }
-
-                        async_cx
-                            .background_executor()
-                            .timer(Duration::from_millis(30))
-                            .await;
+                        });
                     }
                 }
             },
@@ -170,6 +291,7 @@ impl PopupView {
         }
         window.minimize_window();
         self.is_visible = false;
+        self.prune_notice = None;
         cx.notify();
 
         self.entries_clear_gen = self.entries_clear_gen.wrapping_add(1);
@@ -186,7 +308,9 @@ impl PopupView {
                         let _ = async_cx.update_entity(&handle, |view: &mut PopupView, cx| {
                             if view.entries_clear_gen == clear_gen && !view.is_visible {
                                 view.entries.clear();
-                                view.page_offset = 0;
+                                view.entry_links.clear();
+                                view.entry_tags.clear();
+                                view.page_cursor = None;
                                 view.has_more = true;
                                 view.is_loading = false;
                                 view.selected_index = 0;
@@ -207,7 +331,7 @@ impl PopupView {
         self.entries_clear_gen = self.entries_clear_gen.wrapping_add(1);
         window.activate_window();
         self.is_visible = true;
-        if self.entries.is_empty() && self.db.is_some() {
+        if self.entries.is_empty() && self.storage.is_some() {
             self.reset_and_load(cx);
         }
         cx.on_next_frame(window, |view, window, cx| {
@@ -238,6 +362,155 @@ impl PopupView {
         self.hide(window, cx);
     }
 
+    fn on_toggle_fuzzy_search(
+        &mut self,
+        _: &ToggleFuzzySearch,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.toggle_fuzzy_search(cx);
+    }
+
+    fn toggle_fuzzy_search(&mut self, cx: &mut Context<Self>) {
+        self.fuzzy_search = !self.fuzzy_search;
+        let fuzzy = self.fuzzy_search;
+        update_settings(|settings| settings.search.fuzzy = fuzzy);
+        self.reset_and_load(cx);
+        cx.notify();
+    }
+
+    fn on_toggle_find(
+        &mut self,
+        _: &ToggleFindInEntry,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.set_find_visible(!self.find_visible, window, cx);
+    }
+
+    fn on_next_match(&mut self, _: &NextMatch, _: &mut Window, cx: &mut Context<Self>) {
+        self.step_match(1, cx);
+    }
+
+    fn on_previous_match(&mut self, _: &PreviousMatch, _: &mut Window, cx: &mut Context<Self>) {
+        self.step_match(-1, cx);
+    }
+
+    fn set_find_visible(&mut self, visible: bool, window: &mut Window, cx: &mut Context<Self>) {
+        self.find_visible = visible;
+        if visible {
+            cx.focus_view(&self.find_input, window);
+        } else {
+            cx.focus_view(&self.search_input, window);
+        }
+        cx.notify();
+    }
+
+    /// The in-entry find text while the find bar is open and filled in,
+    /// otherwise the history search.
+    fn detail_query(&self) -> &str {
+        if self.find_visible && !self.find_query.is_empty() {
+            &self.find_query
+        } else {
+            &self.search_query
+        }
+    }
+
+    /// Recomputes `detail_matches` when the selected entry, the detail query
+    /// or fuzzy mode changed, and scrolls to the first match.
+    fn sync_detail_matches(&mut self) {
+        let entry = self.entries.get(self.selected_index);
+        let query = self.detail_query().to_string();
+        let key = (
+            entry.map(|entry| entry.id),
+            query.clone(),
+            self.fuzzy_search,
+        );
+        if self.detail_list_key.as_ref() == Some(&key) {
+            return;
+        }
+        self.detail_list_key = Some(key);
+
+        let fuzzy = self.fuzzy_search;
+        self.detail_matches = match entry {
+            Some(entry) if !query.is_empty() && !has_rich_detail(entry) => {
+                detail_lines(&self.entries, self.selected_index)
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(line, text)| {
+                        let count = match_ranges(text, &query, fuzzy).len();
+                        (0..count).map(move |range_index| DetailMatch { line, range_index })
+                    })
+                    .collect()
+            }
+            _ => Vec::new(),
+        };
+        self.current_match = 0;
+        let first_line = self.detail_matches.first().map_or(0, |found| found.line);
+        self.pending_detail_scroll = Some(DetailScroll::Jump(first_line));
+    }
+
+    fn step_match(&mut self, delta: isize, cx: &mut Context<Self>) {
+        let len = self.detail_matches.len();
+        if len == 0 {
+            return;
+        }
+        self.current_match =
+            (self.current_match as isize + delta).rem_euclid(len as isize) as usize;
+        let line = self.detail_matches[self.current_match].line;
+        self.pending_detail_scroll = Some(DetailScroll::Reveal(line));
+        cx.notify();
+    }
+
+    fn render_match_bar(&self, cx: &mut Context<Self>) -> Option<AnyElement> {
+        let is_text_entry = self
+            .entries
+            .get(self.selected_index)
+            .is_some_and(|entry| !has_rich_detail(entry));
+        if !self.find_visible && (self.search_query.is_empty() || !is_text_entry) {
+            return None;
+        }
+
+        let count_label = match self.detail_matches.len() {
+            0 if self.detail_query().is_empty() => String::new(),
+            0 => "No matches".to_string(),
+            total => format!("{} of {total}", self.current_match + 1),
+        };
+        let mut bar = div()
+            .key_context("FindBar")
+            .w_full()
+            .flex()
+            .items_center()
+            .gap_1()
+            .text_xs()
+            .text_color(rgb(0x9aa4af));
+        if self.find_visible {
+            bar = bar.child(
+                div()
+                    .flex_1()
+                    .child(Input::new(&self.find_input).small().appearance(false)),
+            );
+        } else {
+            bar = bar.child(div().flex_1());
+        }
+        bar = bar
+            .child(count_label)
+            .child(
+                match_bar_button("previous-match", "↑")
+                    .on_click(cx.listener(|view, _, _, cx| view.step_match(-1, cx))),
+            )
+            .child(
+                match_bar_button("next-match", "↓")
+                    .on_click(cx.listener(|view, _, _, cx| view.step_match(1, cx))),
+            );
+        if self.find_visible {
+            bar = bar.child(match_bar_button("close-find", "×").on_click(
+                cx.listener(|view, _, window, cx| view.set_find_visible(false, window, cx)),
+            ));
+        }
+        Some(bar.into_any_element())
+    }
+
     fn move_selection(&mut self, delta: isize, cx: &mut Context<Self>) {
         if self.entries.is_empty() {
             return;
@@ -257,13 +530,14 @@ impl PopupView {
             };
             self.list_scroll
                 .scroll_to_item(self.selected_index, strategy);
-            self.detail_list_state.reset(1);
+            self.detail_list_key = None;
+            self.ocr_selection = None;
             cx.notify();
         }
     }
 
     fn refresh_entries(&mut self, cx: &mut Context<Self>) {
-        let Some(_) = self.db else {
+        let Some(_) = self.storage else {
             return;
         };
 
@@ -292,56 +566,944 @@ impl PopupView {
         .detach();
     }
 
-    fn copy_selected(&mut self, cx: &mut Context<Self>) {
-        let Some(entry) = self.entries.get(self.selected_index).cloned() else {
+    fn copy_selected(&mut self, cx: &mut Context<Self>) {
+        let Some(entry) = self.entries.get(self.selected_index).cloned() else {
+            return;
+        };
+        self.copy_entry(&entry, cx);
+    }
+
+    fn copy_entry_by_id(&mut self, id: i32, cx: &mut Context<Self>) {
+        let Some(entry) = self.entries.iter().find(|entry| entry.id == id).cloned() else {
+            return;
+        };
+        self.copy_entry(&entry, cx);
+    }
+
+    fn copy_entry(&mut self, entry: &Model, cx: &mut Context<Self>) {
+        #[cfg(target_os = "windows")]
+        if entry.content_type == "image" {
+            if let Err(err) = copy_image_to_clipboard(entry) {
+                eprintln!("Failed to copy image to clipboard: {err}");
+            } else {
+                self.refresh_entries(cx);
+                return;
+            }
+        }
+
+        self.copy_text(payload_for_entry(entry), cx);
+    }
+
+    fn copy_link_by_id(&mut self, id: i32, clean: bool, cx: &mut Context<Self>) {
+        let Some(entry) = self.entries.iter().find(|entry| entry.id == id) else {
+            return;
+        };
+        let payload = if clean {
+            distinct_clean_url(entry).map(str::to_string)
+        } else {
+            original_link_url(entry).map(str::to_string)
+        };
+        let payload = payload.unwrap_or_else(|| payload_for_entry(entry));
+        self.copy_text(payload, cx);
+    }
+
+    fn copy_text(&mut self, payload: String, cx: &mut Context<Self>) {
+        let hash = crypto::content_hash(payload.as_bytes());
+        ignore_next_hash(hash);
+        cx.write_to_clipboard(ClipboardItem::new_string(payload));
+        self.refresh_entries(cx);
+    }
+
+    fn delete_entry(&mut self, id: i32, content_hash: String, cx: &mut Context<Self>) {
+        let Some(storage) = self.storage.clone() else {
+            return;
+        };
+
+        ignore_next_hash(content_hash);
+
+        // The removal event reloads the list.
+        cx.spawn(
+            move |_view: gpui::WeakEntity<PopupView>, _cx: &mut gpui::AsyncApp| async move {
+                if let Err(err) = storage.delete_entry(id).await {
+                    eprintln!("Failed to delete clipboard entry: {err}");
+                }
+            },
+        )
+        .detach();
+    }
+
+    fn on_toggle_pin(&mut self, _: &TogglePin, _: &mut Window, cx: &mut Context<Self>) {
+        if let Some(entry) = self.entries.get(self.selected_index) {
+            self.toggle_pin(entry.id, cx);
+        }
+    }
+
+    fn on_move_pin_up(&mut self, _: &MovePinUp, _: &mut Window, cx: &mut Context<Self>) {
+        self.move_selected_pin(-1, cx);
+    }
+
+    fn on_move_pin_down(&mut self, _: &MovePinDown, _: &mut Window, cx: &mut Context<Self>) {
+        self.move_selected_pin(1, cx);
+    }
+
+    fn toggle_pin(&mut self, id: i32, cx: &mut Context<Self>) {
+        let Some(storage) = self.storage.clone() else {
+            return;
+        };
+        let Some(entry) = self.entries.iter().find(|entry| entry.id == id) else {
+            return;
+        };
+        let pinned = entry.pin_order.is_none();
+
+        cx.spawn(
+            move |view: gpui::WeakEntity<PopupView>, cx: &mut gpui::AsyncApp| {
+                let mut async_cx = cx.clone();
+                async move {
+                    if let Err(err) = storage.set_pinned(id, pinned).await {
+                        eprintln!("Failed to update pinned entry: {err}");
+                        return;
+                    }
+                    if let Some(handle) = view.upgrade() {
+                        let _ = async_cx.update_entity(&handle, |view: &mut PopupView, cx| {
+                            view.reset_and_load(cx);
+                        });
+                    }
+                }
+            },
+        )
+        .detach();
+    }
+
+    /// Swaps the selected pinned entry with its pinned neighbour.
+    fn move_selected_pin(&mut self, delta: isize, cx: &mut Context<Self>) {
+        let Some(target_index) = self.selected_index.checked_add_signed(delta) else {
+            return;
+        };
+        let (Some(entry), Some(target)) = (
+            self.entries.get(self.selected_index),
+            self.entries.get(target_index),
+        ) else {
+            return;
+        };
+        if entry.pin_order.is_none() || target.pin_order.is_none() {
+            return;
+        }
+        self.move_pin(entry.id, target.id, cx);
+    }
+
+    /// Moves a pinned entry to the row of another pinned entry, selecting it.
+    /// The list is reordered in place and the new order saved in the
+    /// background.
+    fn move_pin(&mut self, id: i32, target_id: i32, cx: &mut Context<Self>) {
+        let Some(storage) = self.storage.clone() else {
+            return;
+        };
+        let from = self.entries.iter().position(|entry| entry.id == id);
+        let to = self.entries.iter().position(|entry| entry.id == target_id);
+        let (Some(from), Some(to)) = (from, to) else {
+            return;
+        };
+        if from == to || self.entries[to].pin_order.is_none() {
+            return;
+        }
+        let entry = self.entries.remove(from);
+        self.entries.insert(to, entry);
+        if self.selected_index != to {
+            self.selected_index = to;
+            self.detail_list_key = None;
+            self.ocr_selection = None;
+        }
+        self.list_scroll.scroll_to_item(to, ScrollStrategy::Center);
+        cx.notify();
+
+        cx.spawn(
+            move |view: gpui::WeakEntity<PopupView>, cx: &mut gpui::AsyncApp| {
+                let mut async_cx = cx.clone();
+                async move {
+                    let Err(err) = storage.move_pin(id, target_id).await else {
+                        return;
+                    };
+                    eprintln!("Failed to reorder pinned entries: {err}");
+                    if let Some(handle) = view.upgrade() {
+                        let _ = async_cx.update_entity(&handle, |view: &mut PopupView, cx| {
+                            view.reset_and_load(cx);
+                        });
+                    }
+                }
+            },
+        )
+        .detach();
+    }
+
+    fn on_toggle_tag_input(
+        &mut self,
+        _: &ToggleTagInput,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.set_tag_input_visible(!self.tag_input_visible, window, cx);
+    }
+
+    fn on_add_tag(&mut self, _: &AddTag, window: &mut Window, cx: &mut Context<Self>) {
+        let name = self.tag_input.read(cx).value().trim().to_string();
+        let Some(entry) = self.entries.get(self.selected_index) else {
+            return;
+        };
+        if name.is_empty() {
+            return;
+        }
+        let entry_id = entry.id;
+        self.tag_input
+            .update(cx, |input, cx| input.set_value("", window, cx));
+        self.add_tag(entry_id, name, cx);
+    }
+
+    fn set_tag_input_visible(
+        &mut self,
+        visible: bool,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.tag_input_visible = visible;
+        if visible {
+            cx.focus_view(&self.tag_input, window);
+        } else {
+            cx.focus_view(&self.search_input, window);
+        }
+        cx.notify();
+    }
+
+    /// Selects the entry and opens the tag input for it.
+    fn open_tag_input_for(&mut self, entry_id: i32, window: &mut Window, cx: &mut Context<Self>) {
+        if let Some(index) = self.entries.iter().position(|entry| entry.id == entry_id) {
+            self.select_index(index, cx);
+            self.set_tag_input_visible(true, window, cx);
+        }
+    }
+
+    fn set_tag_filter(&mut self, tag: Option<String>, cx: &mut Context<Self>) {
+        if self.tag_filter == tag {
+            return;
+        }
+        self.tag_filter = tag;
+        self.reset_and_load(cx);
+        cx.notify();
+    }
+
+    fn add_tag(&mut self, entry_id: i32, name: String, cx: &mut Context<Self>) {
+        let Some(storage) = self.storage.clone() else {
+            return;
+        };
+
+        cx.spawn(
+            move |view: gpui::WeakEntity<PopupView>, cx: &mut gpui::AsyncApp| {
+                let mut async_cx = cx.clone();
+                async move {
+                    if let Err(err) = storage.tag_entry(entry_id, &name).await {
+                        eprintln!("Failed to tag clipboard entry: {err}");
+                        return;
+                    }
+                    if let Some(handle) = view.upgrade() {
+                        let _ = async_cx.update_entity(&handle, |view: &mut PopupView, cx| {
+                            view.reload_tags(entry_id, cx);
+                        });
+                    }
+                }
+            },
+        )
+        .detach();
+    }
+
+    fn remove_tag(&mut self, entry_id: i32, tag_id: i32, cx: &mut Context<Self>) {
+        let Some(storage) = self.storage.clone() else {
+            return;
+        };
+
+        cx.spawn(
+            move |view: gpui::WeakEntity<PopupView>, cx: &mut gpui::AsyncApp| {
+                let mut async_cx = cx.clone();
+                async move {
+                    if let Err(err) = storage.untag_entry(entry_id, tag_id).await {
+                        eprintln!("Failed to untag clipboard entry: {err}");
+                        return;
+                    }
+                    if let Some(handle) = view.upgrade() {
+                        let _ = async_cx.update_entity(&handle, |view: &mut PopupView, cx| {
+                            view.reload_tags(entry_id, cx);
+                        });
+                    }
+                }
+            },
+        )
+        .detach();
+    }
+
+    /// Reloads one entry's tags and the tag list without reloading entries.
+    fn reload_tags(&mut self, entry_id: i32, cx: &mut Context<Self>) {
+        let Some(storage) = self.storage.clone() else {
+            return;
+        };
+
+        cx.spawn(
+            move |view: gpui::WeakEntity<PopupView>, cx: &mut gpui::AsyncApp| {
+                let mut async_cx = cx.clone();
+                async move {
+                    let loaded = async {
+                        let mut entry_tags = storage.entry_tags(&[entry_id]).await?;
+                        let tags = storage.tags().await?;
+                        anyhow::Ok((entry_tags.remove(&entry_id).unwrap_or_default(), tags))
+                    };
+                    let (entry_tags, tags) = match loaded.await {
+                        Ok(loaded) => loaded,
+                        Err(err) => {
+                            eprintln!("Failed to load tags: {err}");
+                            return;
+                        }
+                    };
+                    if let Some(handle) = view.upgrade() {
+                        let _ = async_cx.update_entity(&handle, |view: &mut PopupView, cx| {
+                            view.tags = tags;
+                            if entry_tags.is_empty() {
+                                view.entry_tags.remove(&entry_id);
+                            } else {
+                                view.entry_tags.insert(entry_id, entry_tags);
+                            }
+                            cx.notify();
+                        });
+                    }
+                }
+            },
+        )
+        .detach();
+    }
+
+    fn render_tag_filter(&self, cx: &mut Context<Self>) -> Option<AnyElement> {
+        if self.tags.is_empty() && self.tag_filter.is_none() {
+            return None;
+        }
+        let mut strip = div()
+            .id("tag-filter")
+            .w_full()
+            .flex()
+            .gap_1()
+            .overflow_x_scroll()
+            .text_xs()
+            .child(
+                tag_chip("tag-filter-all", "All".into(), self.tag_filter.is_none())
+                    .on_click(cx.listener(|view, _, _, cx| view.set_tag_filter(None, cx))),
+            );
+        for (index, tag) in self.tags.iter().enumerate() {
+            let active = self
+                .tag_filter
+                .as_deref()
+                .is_some_and(|filter| filter.eq_ignore_ascii_case(&tag.name));
+            let name = tag.name.clone();
+            let label = format!("{} {}", tag.name, tag.entry_count);
+            strip = strip.child(
+                tag_chip(("tag-filter", index), label.into(), active).on_click(cx.listener(
+                    move |view, _, _, cx| {
+                        let tag = (!active).then(|| name.clone());
+                        view.set_tag_filter(tag, cx);
+                    },
+                )),
+            );
+        }
+        Some(strip.into_any_element())
+    }
+
+    /// The selected entry's tags, with the tag input when it is open.
+    fn render_tag_bar(&self, cx: &mut Context<Self>) -> Option<AnyElement> {
+        let entry = self.entries.get(self.selected_index)?;
+        let tags = self
+            .entry_tags
+            .get(&entry.id)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        if tags.is_empty() && !self.tag_input_visible {
+            return None;
+        }
+
+        let entry_id = entry.id;
+        let mut bar = div()
+            .key_context("TagBar")
+            .w_full()
+            .flex()
+            .flex_wrap()
+            .items_center()
+            .gap_1()
+            .text_xs();
+        for tag in tags {
+            let tag_id = tag.id;
+            bar = bar.child(
+                tag_chip(
+                    ("entry-tag", tag_id as usize),
+                    tag.name.clone().into(),
+                    false,
+                )
+                .flex()
+                .gap_1()
+                .child(
+                    div()
+                        .id(("remove-entry-tag", tag_id as usize))
+                        .hover(|style| style.text_color(rgb(0xf3f4f6)))
+                        .child("×")
+                        .on_click(cx.listener(move |view, _, _, cx| {
+                            view.remove_tag(entry_id, tag_id, cx);
+                        })),
+                ),
+            );
+        }
+        if self.tag_input_visible {
+            bar = bar
+                .child(
+                    div()
+                        .flex_1()
+                        .min_w(px(120.))
+                        .child(Input::new(&self.tag_input).small().appearance(false)),
+                )
+                .child(
+                    match_bar_button("close-tag-input", "×").on_click(cx.listener(
+                        |view, _, window, cx| view.set_tag_input_visible(false, window, cx),
+                    )),
+                );
+        } else {
+            bar = bar.child(match_bar_button("open-tag-input", "+").on_click(
+                cx.listener(|view, _, window, cx| view.set_tag_input_visible(true, window, cx)),
+            ));
+        }
+        Some(bar.into_any_element())
+    }
+
+    /// Fetches previews for an entry and its links, reporting anything the
+    /// network policy blocked or that failed.
+    fn fetch_link_preview(&mut self, id: i32, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(storage) = self.storage.clone() else {
+            return;
+        };
+        let Some(entry) = self.entries.iter().find(|entry| entry.id == id) else {
+            return;
+        };
+
+        let entry_url = if entry.content_type == "link" {
+            distinct_clean_url(entry)
+                .or(original_link_url(entry))
+                .and_then(parse_link_url)
+        } else {
+            None
+        };
+        let link_urls: Vec<(i32, url::Url)> = self
+            .entry_links
+            .get(&id)
+            .into_iter()
+            .flatten()
+            .filter(|link| link.kind == "url")
+            .filter_map(|link| Some((link.id, parse_link_url(&link.url)?)))
+            .collect();
+        let window = window.window_handle();
+
+        cx.spawn(
+            move |view: gpui::WeakEntity<PopupView>, cx: &mut gpui::AsyncApp| {
+                let mut async_cx = cx.clone();
+                async move {
+                    let mut problems = Vec::new();
+                    let mut entry_metadata = None;
+                    if let Some(url) = entry_url {
+                        match fetch_preview(&url).await {
+                            Ok(metadata) => {
+                                let details =
+                                    metadata.details.as_ref().and_then(LinkDetails::to_json);
+                                let update = link_metadata_update(&metadata, details.as_deref());
+                                match storage.update_link_metadata(id, update).await {
+                                    Ok(()) => entry_metadata = Some((metadata, details)),
+                                    Err(err) => {
+                                        problems.push(format!("Failed to save link preview: {err}"))
+                                    }
+                                }
+                            }
+                            Err(problem) => problems.push(problem),
+                        }
+                    }
+
+                    let mut link_metadata = Vec::new();
+                    for (link_id, url) in link_urls {
+                        let metadata = match fetch_preview(&url).await {
+                            Ok(metadata) => metadata,
+                            Err(problem) => {
+                                problems.push(problem);
+                                continue;
+                            }
+                        };
+                        let update = link_metadata_update(&metadata, None);
+                        match storage
+                            .update_entry_link_metadata(id, link_id, update)
+                            .await
+                        {
+                            Ok(()) => link_metadata.push((link_id, metadata)),
+                            Err(err) => {
+                                problems.push(format!("Failed to save link preview: {err}"))
+                            }
+                        }
+                    }
+
+                    if !problems.is_empty() {
+                        eprintln!("{}", problems.join("\n"));
+                        let _ = async_cx.update_window(window, |_, window, cx| {
+                            show_transfer_report("Link preview", problems, window, cx);
+                        });
+                    }
+
+                    if let Some(handle) = view.upgrade() {
+                        let _ = async_cx.update_entity(&handle, |view: &mut PopupView, cx| {
+                            if let Some((metadata, details)) = entry_metadata {
+                                if let Some(entry) =
+                                    view.entries.iter_mut().find(|entry| entry.id == id)
+                                {
+                                    entry.link_title = metadata.title;
+                                    entry.link_description = metadata.description;
+                                    entry.link_site_name = metadata.site_name;
+                                    entry.link_details = details;
+                                }
+                            }
+                            if let Some(links) = view.entry_links.get_mut(&id) {
+                                for (link_id, metadata) in link_metadata {
+                                    if let Some(link) =
+                                        links.iter_mut().find(|link| link.id == link_id)
+                                    {
+                                        link.title = metadata.title;
+                                        link.description = metadata.description;
+                                        link.site_name = metadata.site_name;
+                                    }
+                                }
+                            }
+                            cx.notify();
+                        });
+                    }
+                }
+            },
+        )
+        .detach();
+    }
+
+    fn rerun_ocr(&mut self, id: i32, cx: &mut Context<Self>) {
+        let Some(storage) = self.storage.clone() else {
+            return;
+        };
+        let Some(image_path) = self
+            .entries
+            .iter()
+            .find(|entry| entry.id == id)
+            .and_then(|entry| entry.image_path.clone())
+        else {
+            return;
+        };
+        let Some(attempt) = attempt_key() else {
+            return;
+        };
+
+        cx.spawn(
+            move |view: gpui::WeakEntity<PopupView>, cx: &mut gpui::AsyncApp| {
+                let mut async_cx = cx.clone();
+                async move {
+                    let result = match recognize_stored_image(&async_cx, image_path).await {
+                        Ok(result) => result,
+                        Err(err) => {
+                            eprintln!("Failed to OCR image: {err}");
+                            return;
+                        }
+                    };
+                    let Some((text, layout)) =
+                        save_ocr_result(&storage, id, result, &attempt).await
+                    else {
+                        return;
+                    };
+                    if let Some(handle) = view.upgrade() {
+                        let _ = async_cx.update_entity(&handle, |view: &mut PopupView, cx| {
+                            view.apply_ocr_result(id, text, layout, cx);
+                        });
+                    }
+                }
+            },
+        )
+        .detach();
+    }
+
+    fn on_verify_storage(
+        &mut self,
+        _: &VerifyStorage,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.verify_storage(false, window.window_handle(), cx);
+    }
+
+    /// Runs the storage check and shows its report, offering a repair when
+    /// there is something to fix.
+    fn verify_storage(&mut self, repair: bool, window: AnyWindowHandle, cx: &mut Context<Self>) {
+        let Some(storage) = self.storage.clone() else {
+            return;
+        };
+
+        cx.spawn(
+            move |view: gpui::WeakEntity<PopupView>, cx: &mut gpui::AsyncApp| {
+                let mut async_cx = cx.clone();
+                async move {
+                    let report = match storage.verify(repair).await {
+                        Ok(report) => report,
+                        Err(err) => {
+                            eprintln!("Failed to verify storage: {err}");
+                            return;
+                        }
+                    };
+                    eprintln!("{report}");
+                    let _ = async_cx.update_window(window, |_, window, cx| {
+                        show_storage_report(report, view, window, cx);
+                    });
+                }
+            },
+        )
+        .detach();
+    }
+
+    /// Exports what the list currently shows, in the format picked by the
+    /// chosen file's extension.
+    fn on_export_history(
+        &mut self,
+        _: &ExportHistory,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(storage) = self.storage.clone() else {
+            return;
+        };
+        let query = with_tag_filter(self.search.clone(), self.tag_filter.clone());
+        let path_rx = cx.prompt_for_new_path(&transfer_directory(), Some("clipboard-history.zip"));
+        let window = window.window_handle();
+
+        cx.spawn(
+            move |_view: gpui::WeakEntity<PopupView>, cx: &mut gpui::AsyncApp| {
+                let mut async_cx = cx.clone();
+                async move {
+                    let path = match path_rx.await {
+                        Ok(Ok(Some(path))) => path,
+                        Ok(Ok(None)) | Err(_) => return,
+                        Ok(Err(err)) => {
+                            eprintln!("Failed to choose an export file: {err}");
+                            return;
+                        }
+                    };
+                    let lines = match storage.export_history(query.as_ref(), &path).await {
+                        Ok(report) => vec![report.to_string(), path.display().to_string()],
+                        Err(err) => vec![format!("Export failed: {err}")],
+                    };
+                    eprintln!("{}", lines.join("\n"));
+                    let _ = async_cx.update_window(window, |_, window, cx| {
+                        show_transfer_report("Export", lines, window, cx);
+                    });
+                }
+            },
+        )
+        .detach();
+    }
+
+    /// Merges an archive or JSON export into the history.
+    fn on_import_history(
+        &mut self,
+        _: &ImportHistory,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(storage) = self.storage.clone() else {
+            return;
+        };
+        let paths_rx = cx.prompt_for_paths(PathPromptOptions {
+            files: true,
+            directories: false,
+            multiple: false,
+            prompt: None,
+        });
+        let window = window.window_handle();
+
+        cx.spawn(
+            move |_view: gpui::WeakEntity<PopupView>, cx: &mut gpui::AsyncApp| {
+                let mut async_cx = cx.clone();
+                async move {
+                    let path = match paths_rx.await {
+                        Ok(Ok(Some(paths))) => match paths.into_iter().next() {
+                            Some(path) => path,
+                            None => return,
+                        },
+                        Ok(Ok(None)) | Err(_) => return,
+                        Ok(Err(err)) => {
+                            eprintln!("Failed to choose a file to import: {err}");
+                            return;
+                        }
+                    };
+                    let result = match ForeignSource::from_path(&path) {
+                        Some(source) => storage.import_foreign_history(source, &path).await,
+                        None => storage.import_history(&path).await,
+                    };
+                    let lines = match result {
+                        Ok(report) => {
+                            let mut lines = vec![report.to_string()];
+                            lines.extend(report.skipped.iter().take(5).cloned());
+                            lines
+                        }
+                        Err(err) => vec![format!("Import failed: {err}")],
+                    };
+                    eprintln!("{}", lines.join("\n"));
+                    let _ = async_cx.update_window(window, |_, window, cx| {
+                        show_transfer_report("Import", lines, window, cx);
+                    });
+                }
+            },
+        )
+        .detach();
+    }
+
+    /// Moves the database and images to a chosen folder and restarts into
+    /// it.
+    fn on_move_data_dir(&mut self, _: &MoveDataDir, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(storage) = self.storage.clone() else {
+            return;
+        };
+        let paths_rx = cx.prompt_for_paths(PathPromptOptions {
+            files: false,
+            directories: true,
+            multiple: false,
+            prompt: None,
+        });
+        let window = window.window_handle();
+
+        cx.spawn(
+            move |_view: gpui::WeakEntity<PopupView>, cx: &mut gpui::AsyncApp| {
+                let mut async_cx = cx.clone();
+                async move {
+                    let target = match paths_rx.await {
+                        Ok(Ok(Some(paths))) => match paths.into_iter().next() {
+                            Some(path) => path,
+                            None => return,
+                        },
+                        Ok(Ok(None)) | Err(_) => return,
+                        Ok(Err(err)) => {
+                            eprintln!("Failed to choose a data directory: {err}");
+                            return;
+                        }
+                    };
+                    match storage.move_data_dir(&target).await {
+                        Ok(report) => {
+                            eprintln!("{report}");
+                            let _ = async_cx.update(|cx| cx.restart());
+                        }
+                        Err(err) => {
+                            eprintln!("Failed to move the data directory: {err}");
+                            let lines = vec![format!("Move failed: {err}")];
+                            let _ = async_cx.update_window(window, |_, window, cx| {
+                                show_transfer_report("Move data directory", lines, window, cx);
+                            });
+                        }
+                    }
+                }
+            },
+        )
+        .detach();
+    }
+
+    /// Lists the backups to restore one from.
+    fn on_restore_backup(
+        &mut self,
+        _: &RestoreBackup,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if self.storage.is_none() {
+            return;
+        }
+        match list_backups() {
+            Ok(backups) if !backups.is_empty() => {
+                show_backup_list(backups, cx.weak_entity(), window, cx);
+            }
+            Ok(_) => {
+                let lines = vec!["No backups yet".to_string()];
+                show_transfer_report("Restore backup", lines, window, cx);
+            }
+            Err(err) => {
+                let lines = vec![format!("Failed to list backups: {err}")];
+                show_transfer_report("Restore backup", lines, window, cx);
+            }
+        }
+    }
+
+    /// Stages `backup` to replace the history and restarts into it.
+    fn restore_backup(&mut self, backup: PathBuf, window: AnyWindowHandle, cx: &mut Context<Self>) {
+        let Some(storage) = self.storage.clone() else {
+            return;
+        };
+
+        cx.spawn(
+            move |_view: gpui::WeakEntity<PopupView>, cx: &mut gpui::AsyncApp| {
+                let mut async_cx = cx.clone();
+                async move {
+                    match storage.restore_backup(&backup).await {
+                        Ok(report) if report.missing_images > 0 => {
+                            eprintln!("{report}");
+                            let _ = async_cx.update_window(window, |_, window, cx| {
+                                show_restart_dialog(report.to_string(), window, cx);
+                            });
+                        }
+                        Ok(report) => {
+                            eprintln!("{report}");
+                            let _ = async_cx.update(|cx| cx.restart());
+                        }
+                        Err(err) => {
+                            eprintln!("Failed to restore the backup: {err}");
+                            let lines = vec![format!("Restore failed: {err}")];
+                            let _ = async_cx.update_window(window, |_, window, cx| {
+                                show_transfer_report("Restore backup", lines, window, cx);
+                            });
+                        }
+                    }
+                }
+            },
+        )
+        .detach();
+    }
+
+    /// Applies the retention settings now and then every
+    /// `interval_minutes`, re-reading them before each run.
+    fn start_retention_pruner(&mut self, cx: &mut Context<Self>) {
+        let Some(storage) = self.storage.clone() else {
+            return;
+        };
+
+        cx.spawn(
+            move |view: gpui::WeakEntity<PopupView>, cx: &mut gpui::AsyncApp| {
+                let mut async_cx = cx.clone();
+                async move {
+                    loop {
+                        let policy = settings().retention;
+                        match storage.prune(&policy).await {
+                            Ok(report) if report.entries > 0 => {
+                                eprintln!("{report}");
+                                let Some(handle) = view.upgrade() else {
+                                    return;
+                                };
+                                let _ =
+                                    async_cx.update_entity(&handle, |view: &mut PopupView, cx| {
+                                        view.prune_notice = Some(report.to_string());
+                                        cx.notify();
+                                    });
+                            }
+                            Ok(_) => {}
+                            Err(err) => eprintln!("Failed to prune clipboard history: {err}"),
+                        }
+                        if view.upgrade().is_none() {
+                            return;
+                        }
+                        async_cx
+                            .background_executor()
+                            .timer(Duration::from_secs(policy.interval_minutes.max(1) * 60))
+                            .await;
+                    }
+                }
+            },
+        )
+        .detach();
+    }
+
+    /// Backs the history up whenever the last backup is older than
+    /// `interval_hours`, checking once an hour.
+    fn start_backups(&mut self, cx: &mut Context<Self>) {
+        let Some(storage) = self.storage.clone() else {
+            return;
+        };
+
+        cx.spawn(
+            move |view: gpui::WeakEntity<PopupView>, cx: &mut gpui::AsyncApp| {
+                let async_cx = cx.clone();
+                async move {
+                    loop {
+                        match storage.backup_if_due(&settings().backup).await {
+                            Ok(Some(backup)) => eprintln!(
+                                "Backed up clipboard history to {}",
+                                backup.path.display()
+                            ),
+                            Ok(None) => {}
+                            Err(err) => eprintln!("Failed to back up clipboard history: {err}"),
+                        }
+                        if view.upgrade().is_none() {
+                            return;
+                        }
+                        async_cx
+                            .background_executor()
+                            .timer(BACKUP_CHECK_INTERVAL)
+                            .await;
+                    }
+                }
+            },
+        )
+        .detach();
+    }
+
+    /// OCRs every stored image that has no text yet, e.g. images captured
+    /// before OCR was configured or while it was failing. Images the current
+    /// engine and languages found nothing in are skipped.
+    fn start_ocr_backfill(&mut self, cx: &mut Context<Self>) {
+        if self.ocr_backfill.is_some() {
             return;
-        };
-        self.copy_entry(&entry, cx);
-    }
-
-    fn copy_entry_by_id(&mut self, id: i32, cx: &mut Context<Self>) {
-        let Some(entry) = self.entries.iter().find(|entry| entry.id == id).cloned() else {
+        }
+        let Some(attempt) = attempt_key() else {
             return;
         };
-        self.copy_entry(&entry, cx);
-    }
-
-    fn copy_entry(&mut self, entry: &Model, cx: &mut Context<Self>) {
-        #[cfg(target_os = "windows")]
-        if entry.content_type == "image" {
-            if let Err(err) = copy_image_to_clipboard(entry) {
-                eprintln!("Failed to copy image to clipboard: {err}");
-            } else {
-                self.refresh_entries(cx);
-                return;
-            }
-        }
-
-        let payload = payload_for_entry(entry);
-        let hash = hash_bytes(payload.as_bytes());
-        ignore_next_hash(hash);
-        cx.write_to_clipboard(ClipboardItem::new_string(payload));
-        self.refresh_entries(cx);
-    }
-
-    fn delete_entry(&mut self, id: i32, content_hash: String, cx: &mut Context<Self>) {
-        let Some(db) = self.db.clone() else {
+        let Some(storage) = self.storage.clone() else {
             return;
         };
-
-        ignore_next_hash(content_hash);
+        self.ocr_backfill = Some((0, 0));
+        cx.notify();
 
         cx.spawn(
             move |view: gpui::WeakEntity<PopupView>, cx: &mut gpui::AsyncApp| {
                 let mut async_cx = cx.clone();
                 async move {
-                    if let Err(err) = delete_clipboard_entry(&db, id).await {
-                        eprintln!("Failed to delete clipboard entry: {err}");
-                        return;
+                    let pending = match storage.images_missing_ocr(&attempt).await {
+                        Ok(pending) => pending,
+                        Err(err) => {
+                            eprintln!("Failed to load images for OCR: {err}");
+                            Vec::new()
+                        }
+                    };
+                    let total = pending.len();
+
+                    for (index, (id, image_path)) in pending.into_iter().enumerate() {
+                        let Some(handle) = view.upgrade() else {
+                            return;
+                        };
+                        let _ = async_cx.update_entity(&handle, |view: &mut PopupView, cx| {
+                            view.ocr_backfill = Some((index, total));
+                            cx.notify();
+                        });
+
+                        let result =
+                            match recognize_stored_image(&async_cx, image_path.clone()).await {
+                                Ok(result) => result,
+                                Err(err) => {
+                                    eprintln!("Failed to OCR {image_path}: {err}");
+                                    continue;
+                                }
+                            };
+                        let Some((text, layout)) =
+                            save_ocr_result(&storage, id, result, &attempt).await
+                        else {
+                            continue;
+                        };
+                        let _ = async_cx.update_entity(&handle, |view: &mut PopupView, cx| {
+                            view.apply_ocr_result(id, text, layout, cx);
+                        });
                     }
+
                     if let Some(handle) = view.upgrade() {
                         let _ = async_cx.update_entity(&handle, |view: &mut PopupView, cx| {
-                            view.reset_and_load(cx);
+                            view.ocr_backfill = None;
+                            cx.notify();
                         });
                     }
                 }
@@ -350,6 +1512,26 @@ impl PopupView {
         .detach();
     }
 
+    fn apply_ocr_result(
+        &mut self,
+        id: i32,
+        text: Option<String>,
+        layout: Option<String>,
+        cx: &mut Context<Self>,
+    ) {
+        if let Some(entry) = self.entries.iter_mut().find(|entry| entry.id == id) {
+            entry.ocr_text = text;
+            entry.ocr_layout = layout;
+            if self
+                .ocr_selection
+                .is_some_and(|selection| selection.entry_id == id)
+            {
+                self.ocr_selection = None;
+            }
+            cx.notify();
+        }
+    }
+
     fn select_index(&mut self, index: usize, cx: &mut Context<Self>) {
         if index >= self.entries.len() {
             return;
@@ -358,17 +1540,45 @@ impl PopupView {
             return;
         }
         self.selected_index = index;
-        self.detail_list_state.reset(1);
+        self.detail_list_key = None;
+        self.ocr_selection = None;
+        cx.notify();
+    }
+
+    fn begin_ocr_selection(&mut self, entry_id: i32, point: (f32, f32), cx: &mut Context<Self>) {
+        self.ocr_selection = Some(OcrSelection {
+            entry_id,
+            start: point,
+            end: point,
+            dragging: true,
+        });
+        cx.notify();
+    }
+
+    fn extend_ocr_selection(&mut self, point: (f32, f32), cx: &mut Context<Self>) {
+        let Some(selection) = self.ocr_selection.as_mut() else {
+            return;
+        };
+        if !selection.dragging {
+            return;
+        }
+        selection.end = point;
         cx.notify();
     }
 
+    fn finish_ocr_selection(&mut self) {
+        if let Some(selection) = self.ocr_selection.as_mut() {
+            selection.dragging = false;
+        }
+    }
+
     fn reset_and_load(&mut self, cx: &mut Context<Self>) {
         self.selected_index = 0;
-        self.page_offset = 0;
+        self.page_cursor = None;
         self.has_more = true;
         self.is_loading = false;
         self.load_generation = self.load_generation.wrapping_add(1);
-        self.detail_list_state.reset(1);
+        self.detail_list_key = None;
         self.list_scroll
             .scroll_to_item(self.selected_index, ScrollStrategy::Center);
         self.load_entries(true, cx);
@@ -378,13 +1588,13 @@ impl PopupView {
         if self.is_loading || !self.has_more {
             return;
         }
-        let Some(db) = self.db.clone() else {
+        let Some(storage) = self.storage.clone() else {
             return;
         };
 
-        let query = self.search_query.trim().to_string();
-        let query = if query.is_empty() { None } else { Some(query) };
-        let offset = if replace { 0 } else { self.page_offset };
+        let query = with_tag_filter(self.search.clone(), self.tag_filter.clone());
+        let fuzzy = self.fuzzy_search;
+        let after = if replace { None } else { self.page_cursor };
         let limit = self.page_size;
         let generation = self.load_generation.wrapping_add(1);
         self.load_generation = generation;
@@ -394,26 +1604,60 @@ impl PopupView {
             move |view: gpui::WeakEntity<PopupView>, cx: &mut gpui::AsyncApp| {
                 let mut async_cx = cx.clone();
                 async move {
-                    let entries =
-                        match load_entries_page(&db, query.as_deref(), offset, limit).await {
-                            Ok(entries) => entries,
+                    let (entries, next) = match storage
+                        .entries_page(query.as_ref(), fuzzy, after.as_ref(), limit)
+                        .await
+                    {
+                        Ok(page) => (page.entries, page.next),
+                        Err(err) => {
+                            eprintln!("Failed to load clipboard history: {err}");
+                            (Vec::new(), None)
+                        }
+                    };
+                    let entry_ids: Vec<i32> = entries.iter().map(|entry| entry.id).collect();
+                    let links = match storage.entry_links(&entry_ids).await {
+                        Ok(links) => links,
+                        Err(err) => {
+                            eprintln!("Failed to load clipboard links: {err}");
+                            HashMap::new()
+                        }
+                    };
+                    let entry_tags = match storage.entry_tags(&entry_ids).await {
+                        Ok(tags) => tags,
+                        Err(err) => {
+                            eprintln!("Failed to load entry tags: {err}");
+                            HashMap::new()
+                        }
+                    };
+                    let tags = if replace {
+                        match storage.tags().await {
+                            Ok(tags) => Some(tags),
                             Err(err) => {
-                                eprintln!("Failed to load clipboard history: {err}");
-                                Vec::new()
+                                eprintln!("Failed to load tags: {err}");
+                                None
                             }
-                        };
+                        }
+                    } else {
+                        None
+                    };
                     if let Some(handle) = view.upgrade() {
                         let _ = async_cx.update_entity(&handle, |view: &mut PopupView, cx| {
                             if view.load_generation != generation {
                                 return;
                             }
                             view.is_loading = false;
+                            if let Some(tags) = tags {
+                                view.tags = tags;
+                            }
                             if replace {
                                 view.entries = entries;
-                                view.page_offset = view.entries.len() as u64;
-                                view.has_more = view.entries.len() as u64 == limit;
+                                view.entry_links = links;
+                                view.entry_tags = entry_tags;
+                                view.page_cursor = next;
+                                view.has_more = next.is_some();
                                 view.selected_index = 0;
-                                view.detail_list_state.reset(1);
+                                view.detail_list_key = None;
+                                view.ocr_selection = None;
                                 view.list_scroll
                                     .scroll_to_item(view.selected_index, ScrollStrategy::Center);
                                 cx.notify();
@@ -424,9 +1668,11 @@ impl PopupView {
                                 cx.notify();
                                 return;
                             }
-                            view.page_offset += entries.len() as u64;
-                            view.has_more = entries.len() as u64 == limit;
+                            view.page_cursor = next;
+                            view.has_more = next.is_some();
                             view.entries.extend(entries);
+                            view.entry_links.extend(links);
+                            view.entry_tags.extend(entry_tags);
                             cx.notify();
                         });
                     }
@@ -436,6 +1682,110 @@ impl PopupView {
         .detach();
     }
 
+    /// Puts new captures that match the current search on top of the
+    /// unpinned entries. The loaded pages and their cursor stay as they are.
+    fn prepend_entries(&mut self, ids: Vec<i32>, cx: &mut Context<Self>) {
+        // A list cleared while hidden is loaded afresh when shown.
+        if self.entries.is_empty() && self.has_more {
+            return;
+        }
+        let Some(storage) = self.storage.clone() else {
+            return;
+        };
+
+        let query = with_tag_filter(self.search.clone(), self.tag_filter.clone());
+        let fuzzy = self.fuzzy_search;
+        let list_key = self.list_key();
+        cx.spawn(
+            move |view: gpui::WeakEntity<PopupView>, cx: &mut gpui::AsyncApp| {
+                let mut async_cx = cx.clone();
+                async move {
+                    let mut entries = Vec::new();
+                    for id in ids {
+                        match storage.matching_entry(query.as_ref(), fuzzy, id).await {
+                            Ok(Some(entry)) => entries.push(entry),
+                            Ok(None) => {}
+                            Err(err) => eprintln!("Failed to load clipboard entry {id}: {err}"),
+                        }
+                    }
+                    let entry_ids: Vec<i32> = entries.iter().map(|entry| entry.id).collect();
+                    let links = match storage.entry_links(&entry_ids).await {
+                        Ok(links) => links,
+                        Err(err) => {
+                            eprintln!("Failed to load clipboard links: {err}");
+                            HashMap::new()
+                        }
+                    };
+                    let entry_tags = match storage.entry_tags(&entry_ids).await {
+                        Ok(tags) => tags,
+                        Err(err) => {
+                            eprintln!("Failed to load entry tags: {err}");
+                            HashMap::new()
+                        }
+                    };
+                    // Auto-tagged captures change the tag counts.
+                    let tags = match storage.tags().await {
+                        Ok(tags) => Some(tags),
+                        Err(err) => {
+                            eprintln!("Failed to load tags: {err}");
+                            None
+                        }
+                    };
+                    let Some(handle) = view.upgrade() else {
+                        return;
+                    };
+                    let _ = async_cx.update_entity(&handle, |view: &mut PopupView, cx| {
+                        if let Some(tags) = tags {
+                            view.tags = tags;
+                        }
+                        if view.list_key() != list_key || (view.entries.is_empty() && view.has_more)
+                        {
+                            cx.notify();
+                            return;
+                        }
+                        let top = view
+                            .entries
+                            .iter()
+                            .take_while(|entry| entry.pin_order.is_some())
+                            .count();
+                        let mut inserted = 0;
+                        // Oldest first, so the newest capture ends up on top.
+                        for entry in entries {
+                            if view.entries.iter().any(|loaded| loaded.id == entry.id) {
+                                continue;
+                            }
+                            view.entries.insert(top, entry);
+                            inserted += 1;
+                        }
+                        view.entry_links.extend(links);
+                        view.entry_tags.extend(entry_tags);
+                        if !view.is_visible {
+                            // Opening the popup starts at the top, as after a
+                            // reload.
+                            view.selected_index = 0;
+                            view.list_scroll
+                                .scroll_to_item(view.selected_index, ScrollStrategy::Center);
+                        } else if inserted > 0 && view.selected_index >= top {
+                            // Keep the same entry selected while browsing.
+                            view.selected_index += inserted;
+                        }
+                        cx.notify();
+                    });
+                }
+            },
+        )
+        .detach();
+    }
+
+    /// What decides which entries the list shows.
+    fn list_key(&self) -> (String, Option<String>, bool) {
+        (
+            self.search_query.clone(),
+            self.tag_filter.clone(),
+            self.fuzzy_search,
+        )
+    }
+
     fn maybe_load_more(&mut self, cx: &mut Context<Self>) {
         if self.is_loading || !self.has_more {
             return;
@@ -605,8 +1955,20 @@ impl Render for PopupView {
     fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         let query = self.search_input.read(cx).value().trim().to_string();
         if query != self.search_query {
-            self.search_query = query;
-            self.reset_and_load(cx);
+            match parse_query(&query) {
+                Ok(search) => {
+                    self.search = search;
+                    self.search_error = None;
+                    self.search_query = query;
+                    self.reset_and_load(cx);
+                }
+                // Keep showing the last valid results while the query is
+                // being typed.
+                Err(err) => {
+                    self.search_error = Some(err.to_string());
+                    self.search_query = query;
+                }
+            }
         }
 
         if !self.is_visible {
@@ -615,6 +1977,14 @@ impl Render for PopupView {
                 .on_action(cx.listener(Self::on_toggle_action));
         }
 
+        self.find_query = self.find_input.read(cx).value().trim().to_string();
+        self.sync_detail_matches();
+        let detail_scroll = self.pending_detail_scroll.take();
+        let detail_query = self.detail_query().to_string();
+        let match_bar = self.render_match_bar(cx);
+        let tag_bar = self.render_tag_bar(cx);
+        let tag_filter = self.render_tag_filter(cx);
+
         let mut root = div()
             .size_full()
             .relative()
@@ -630,14 +2000,58 @@ impl Render for PopupView {
             .on_action(cx.listener(Self::on_move_up))
             .on_action(cx.listener(Self::on_move_down))
             .on_action(cx.listener(Self::on_confirm_selection))
+            .on_action(cx.listener(Self::on_toggle_fuzzy_search))
+            .on_action(cx.listener(Self::on_toggle_find))
+            .on_action(cx.listener(Self::on_next_match))
+            .on_action(cx.listener(Self::on_previous_match))
+            .on_action(cx.listener(Self::on_toggle_pin))
+            .on_action(cx.listener(Self::on_move_pin_up))
+            .on_action(cx.listener(Self::on_move_pin_down))
+            .on_action(cx.listener(Self::on_toggle_tag_input))
+            .on_action(cx.listener(Self::on_add_tag))
+            .on_action(cx.listener(Self::on_verify_storage))
+            .on_action(cx.listener(Self::on_export_history))
+            .on_action(cx.listener(Self::on_import_history))
+            .on_action(cx.listener(Self::on_move_data_dir))
+            .on_action(cx.listener(Self::on_restore_backup))
             .child(
                 div().w_full().child(
                     Input::new(&self.search_input)
                         .prefix(Icon::new(IconName::Search).small())
+                        .suffix(fuzzy_toggle(self.fuzzy_search, cx))
                         .appearance(false),
                 ),
             )
+            .children(self.search_error.clone().map(|error| {
+                div()
+                    .w_full()
+                    .px_1()
+                    .text_xs()
+                    .text_color(rgb(0xf87171))
+                    .child(error)
+            }))
             .child(div().w_full().h(px(1.)).bg(rgba(0xffffff20)).mb_1())
+            .children(self.ocr_backfill.map(|(done, total)| {
+                let label = if total == 0 {
+                    "Looking for images without OCR text...".to_string()
+                } else {
+                    format!("Running OCR on stored images: {done} of {total}")
+                };
+                div()
+                    .w_full()
+                    .px_1()
+                    .text_xs()
+                    .text_color(rgb(0x9aa4af))
+                    .child(label)
+            }))
+            .children(self.prune_notice.clone().map(|notice| {
+                div()
+                    .w_full()
+                    .px_1()
+                    .text_xs()
+                    .text_color(rgb(0x9aa4af))
+                    .child(notice)
+            }))
             .child(
                 div()
                     .flex_1()
@@ -651,6 +2065,7 @@ impl Render for PopupView {
                             .flex()
                             .flex_col()
                             .gap_2()
+                            .children(tag_filter)
                             .child(
                                 div()
                                     .relative()
@@ -673,11 +2088,15 @@ impl Render for PopupView {
                             .flex_col()
                             .justify_between()
                             .gap_3()
+                            .children(match_bar)
+                            .children(tag_bar)
                             .child(div().flex_1().px_1().text_color(rgb(0xf1f5f9)).child(
                                 detail_body_list(
                                     &self.entries,
+                                    &self.entry_links,
                                     self.selected_index,
-                                    &self.search_query,
+                                    &detail_query,
+                                    detail_scroll,
                                     cx,
                                     self.detail_list_state.clone(),
                                 ),
@@ -750,6 +2169,22 @@ fn history_list(
                 let text_color = rgb(0xd2d8df);
                 let entry_id = entry.id;
                 let entry_hash = entry.content_hash.clone();
+                let has_clean_url = distinct_clean_url(entry).is_some();
+                let is_image = entry.content_type == "image";
+                let is_pinned = entry.pin_order.is_some();
+                let own_tags: Vec<(i32, String)> = view
+                    .entry_tags
+                    .get(&entry_id)
+                    .into_iter()
+                    .flatten()
+                    .map(|tag| (tag.id, tag.name.clone()))
+                    .collect();
+                let tag_suggestions = suggested_tags(&view.tags, &own_tags);
+                let can_fetch_preview = entry.content_type == "link"
+                    || view
+                        .entry_links
+                        .get(&entry_id)
+                        .is_some_and(|links| links.iter().any(|link| link.kind == "url"));
                 let mut item = div()
                     .id(index)
                     .rounded_md()
@@ -769,21 +2204,90 @@ fn history_list(
                 if !is_selected {
                     item = item.hover(|style| style.bg(rgba(0x31313150)));
                 }
+                if is_pinned {
+                    // Pinned rows sort first; an accent marks the section and a
+                    // rule closes it off from the rest of the history.
+                    let ends_section = view
+                        .entries
+                        .get(index + 1)
+                        .is_some_and(|next| next.pin_order.is_none());
+                    let label: SharedString = history_preview_text(entry)
+                        .chars()
+                        .take(60)
+                        .collect::<String>()
+                        .into();
+                    item = item
+                        .border_l_2()
+                        .border_color(rgb(0xf59e0b))
+                        .when(ends_section, |item| {
+                            item.border_b_1().border_color(rgb(0x3a4250))
+                        })
+                        .on_drag(
+                            DraggedPin {
+                                id: entry_id,
+                                label,
+                            },
+                            |drag: &DraggedPin, _, _, cx| cx.new(|_| drag.clone()),
+                        )
+                        .drag_over::<DraggedPin>(|style, _, _, _| style.bg(rgba(0xf59e0b30)))
+                        .on_drop(_cx.listener(move |view, drag: &DraggedPin, _, cx| {
+                            view.move_pin(drag.id, entry_id, cx);
+                        }));
+                }
                 let view_handle = view_handle.clone();
                 let item = item.context_menu(move |menu, window, _cx| {
                     let view_handle = view_handle.clone();
                     let delete_listener_handle = view_handle.clone();
                     let delete_action_handle = view_handle.clone();
-                    menu.item(PopupMenuItem::new("Copy").on_click(window.listener_for(
+                    let mut menu = menu.item(PopupMenuItem::new("Copy").on_click(
+                        window.listener_for(&view_handle, move |view, _, _, cx| {
+                            view.copy_entry_by_id(entry_id, cx);
+                        }),
+                    ));
+                    let pin_label = if is_pinned { "Unpin" } else { "Pin" };
+                    menu = menu.item(PopupMenuItem::new(pin_label).on_click(window.listener_for(
                         &view_handle,
                         move |view, _, _, cx| {
-                            view.copy_entry_by_id(entry_id, cx);
+                            view.toggle_pin(entry_id, cx);
                         },
-                    )))
-                    .separator()
-                    .item(PopupMenuItem::new("Delete").on_click(window.listener_for(
-                        &delete_listener_handle,
-                        {
+                    )));
+                    menu = tag_menu_items(
+                        menu,
+                        window,
+                        &view_handle,
+                        entry_id,
+                        &own_tags,
+                        &tag_suggestions,
+                    );
+                    if has_clean_url {
+                        menu = menu
+                            .item(PopupMenuItem::new("Copy Clean URL").on_click(
+                                window.listener_for(&view_handle, move |view, _, _, cx| {
+                                    view.copy_link_by_id(entry_id, true, cx);
+                                }),
+                            ))
+                            .item(PopupMenuItem::new("Copy Original URL").on_click(
+                                window.listener_for(&view_handle, move |view, _, _, cx| {
+                                    view.copy_link_by_id(entry_id, false, cx);
+                                }),
+                            ));
+                    }
+                    if can_fetch_preview {
+                        menu = menu.item(PopupMenuItem::new("Fetch Link Preview").on_click(
+                            window.listener_for(&view_handle, move |view, _, window, cx| {
+                                view.fetch_link_preview(entry_id, window, cx);
+                            }),
+                        ));
+                    }
+                    if is_image {
+                        menu = menu.item(PopupMenuItem::new("Re-run OCR").on_click(
+                            window.listener_for(&view_handle, move |view, _, _, cx| {
+                                view.rerun_ocr(entry_id, cx);
+                            }),
+                        ));
+                    }
+                    menu.separator().item(PopupMenuItem::new("Delete").on_click(
+                        window.listener_for(&delete_listener_handle, {
                             let entry_hash = entry_hash.clone();
                             move |_view, _, window, cx| {
                                 let delete_action_handle = delete_action_handle.clone();
@@ -805,11 +2309,12 @@ fn history_list(
                                         .on_cancel(|_, _, _| true)
                                 });
                             }
-                        },
-                    )))
+                        }),
+                    ))
                 });
                 let query = view.search_query.clone();
-                let preview_text = history_preview_text(entry);
+                let preview_text =
+                    history_snippet(&history_preview_text(entry), &query, view.fuzzy_search);
                 if entry.content_type == "image" {
                     if let Some(path) = entry.image_path.as_ref() {
                         let thumbnail = div()
@@ -819,7 +2324,7 @@ fn history_list(
                             .rounded_sm()
                             .bg(rgba(0xffffff0f))
                             .child(
-                                img(PathBuf::from(path))
+                                img(list_thumbnail(Path::new(path)))
                                     .w_full()
                                     .h_full()
                                     .object_fit(ObjectFit::Cover),
@@ -834,11 +2339,17 @@ fn history_list(
                         );
                     } else {
                         let item = item.p_2().h(px(36.)).text_ellipsis();
-                        items.push(item.child(HighlightedText::new(preview_text, query)));
+                        items.push(item.child(
+                            HighlightedText::new(preview_text, query).fuzzy(view.fuzzy_search),
+                        ));
                     }
                 } else {
                     let item = item.p_2().h(px(36.)).text_ellipsis();
-                    items.push(item.child(HighlightedText::new(preview_text, query)));
+                    items.push(
+                        item.child(
+                            HighlightedText::new(preview_text, query).fuzzy(view.fuzzy_search),
+                        ),
+                    );
                 }
             }
             items
@@ -850,6 +2361,299 @@ fn history_list(
     .into_any_element()
 }
 
+fn show_storage_report(
+    report: IntegrityReport,
+    view: gpui::WeakEntity<PopupView>,
+    window: &mut Window,
+    cx: &mut App,
+) {
+    let lines = report.summary_lines();
+    let can_repair = report.needs_repair() && !report.repaired;
+    window.open_dialog(cx, move |dialog, _, _| {
+        let dialog = dialog
+            .title("Storage check")
+            .child(div().flex().flex_col().gap_1().children(lines.clone()));
+        if !can_repair {
+            return dialog;
+        }
+        let view = view.clone();
+        dialog
+            .confirm()
+            .child("Repair now? Unused image files and leftover rows will be deleted.")
+            .on_ok(move |_, window, cx| {
+                let window = window.window_handle();
+                let _ = view.update(cx, |view, cx| view.verify_storage(true, window, cx));
+                true
+            })
+            .on_cancel(|_, _, _| true)
+    });
+}
+
+fn show_transfer_report(
+    title: &'static str,
+    lines: Vec<String>,
+    window: &mut Window,
+    cx: &mut App,
+) {
+    window.open_dialog(cx, move |dialog, _, _| {
+        dialog
+            .title(title)
+            .child(div().flex().flex_col().gap_1().children(lines.clone()))
+    });
+}
+
+/// How often the backup schedule is checked while the app is open.
+const BACKUP_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Backups offered by the restore dialog, newest first.
+const LISTED_BACKUPS: usize = 10;
+
+/// Lets the user pick a backup; clicking one restores it and restarts.
+fn show_backup_list(
+    backups: Vec<Backup>,
+    view: gpui::WeakEntity<PopupView>,
+    window: &mut Window,
+    cx: &mut App,
+) {
+    let now = unix_now();
+    window.open_dialog(cx, move |dialog, _, _| {
+        let rows = backups
+            .iter()
+            .take(LISTED_BACKUPS)
+            .enumerate()
+            .map(|(index, backup)| {
+                let view = view.clone();
+                let path = backup.path.clone();
+                div()
+                    .id(("backup", index))
+                    .px_2()
+                    .py_1()
+                    .rounded_sm()
+                    .cursor_pointer()
+                    .hover(|style| style.bg(rgba(0xffffff18)))
+                    .child(format!(
+                        "{} ({:.1} MB)",
+                        format_age(now - backup.created_at),
+                        backup.bytes as f64 / (1024.0 * 1024.0)
+                    ))
+                    .on_click(move |_, window, cx| {
+                        window.close_dialog(cx);
+                        let handle = window.window_handle();
+                        let _ = view
+                            .update(cx, |view, cx| view.restore_backup(path.clone(), handle, cx));
+                    })
+            });
+        dialog.title("Restore backup").child(
+            div()
+                .flex()
+                .flex_col()
+                .gap_1()
+                .child("The app restarts into the backup you pick.")
+                .child("The current history is kept in the quarantine folder.")
+                .children(rows),
+        )
+    });
+}
+
+/// Explains what a staged restore can't bring back before restarting into
+/// it; the history stays read-only until then.
+fn show_restart_dialog(message: String, window: &mut Window, cx: &mut App) {
+    window.open_dialog(cx, move |dialog, _, _| {
+        dialog
+            .title("Restore backup")
+            .child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .gap_1()
+                    .child(message.clone())
+                    .child("Restart now to finish restoring?"),
+            )
+            .confirm()
+            .on_ok(|_, _, cx| {
+                cx.restart();
+                true
+            })
+            .on_cancel(|_, _, _| true)
+    });
+}
+
+/// Offers to set a damaged database aside and restore the newest backup.
+fn show_recovery_dialog(reason: String, window: &mut Window, cx: &mut App) {
+    let offer = match list_backups()
+        .ok()
+        .and_then(|backups| backups.into_iter().next())
+    {
+        Some(backup) => format!(
+            "Move it to the quarantine folder and restore the backup from {}?",
+            format_age(unix_now() - backup.created_at)
+        ),
+        None => "Move it to the quarantine folder and start a new history? There are no backups."
+            .to_string(),
+    };
+    window.open_dialog(cx, move |dialog, _, _| {
+        dialog
+            .title("Clipboard history damaged")
+            .child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .gap_1()
+                    .child(reason.clone())
+                    .child(offer.clone()),
+            )
+            .confirm()
+            .on_ok(|_, _, cx| {
+                cx.spawn(async move |cx| match recover_corrupt_database().await {
+                    Ok(report) => {
+                        eprintln!("{report}");
+                        let _ = cx.update(|cx| cx.restart());
+                    }
+                    Err(err) => eprintln!("Failed to recover the clipboard database: {err}"),
+                })
+                .detach();
+                true
+            })
+            .on_cancel(|_, _, _| true)
+    });
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
+
+/// How long ago something was, in its largest whole unit.
+fn format_age(secs: i64) -> String {
+    let (count, unit) = match secs.max(0) {
+        secs if secs < 60 => return "just now".to_string(),
+        secs if secs < 60 * 60 => (secs / 60, "minute"),
+        secs if secs < 24 * 60 * 60 => (secs / (60 * 60), "hour"),
+        secs => (secs / (24 * 60 * 60), "day"),
+    };
+    let plural = if count == 1 { "" } else { "s" };
+    format!("{count} {unit}{plural} ago")
+}
+
+/// Where the export and import file pickers start.
+fn transfer_directory() -> PathBuf {
+    std::env::var_os("USERPROFILE")
+        .or_else(|| std::env::var_os("HOME"))
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// Existing tags offered in an entry's context menu.
+const MENU_TAG_SUGGESTIONS: usize = 5;
+
+/// The most used tags the entry doesn't have yet.
+fn suggested_tags(tags: &[TagSummary], own_tags: &[(i32, String)]) -> Vec<String> {
+    let mut candidates: Vec<&TagSummary> = tags
+        .iter()
+        .filter(|tag| !own_tags.iter().any(|(id, _)| *id == tag.id))
+        .collect();
+    candidates.sort_by_key(|tag| Reverse(tag.entry_count));
+    candidates
+        .into_iter()
+        .take(MENU_TAG_SUGGESTIONS)
+        .map(|tag| tag.name.clone())
+        .collect()
+}
+
+fn tag_menu_items(
+    mut menu: PopupMenu,
+    window: &mut Window,
+    view_handle: &gpui::Entity<PopupView>,
+    entry_id: i32,
+    own_tags: &[(i32, String)],
+    suggested_tags: &[String],
+) -> PopupMenu {
+    menu = menu.item(PopupMenuItem::new("Add Tag...").on_click(
+        window.listener_for(view_handle, move |view, _, window, cx| {
+            view.open_tag_input_for(entry_id, window, cx)
+        }),
+    ));
+    for name in suggested_tags {
+        let label = format!("Tag \u{201c}{name}\u{201d}");
+        let name = name.clone();
+        menu = menu.item(PopupMenuItem::new(label).on_click(
+            window.listener_for(view_handle, move |view, _, _, cx| {
+                view.add_tag(entry_id, name.clone(), cx)
+            }),
+        ));
+    }
+    for (tag_id, name) in own_tags {
+        let tag_id = *tag_id;
+        let label = format!("Remove Tag \u{201c}{name}\u{201d}");
+        menu = menu.item(PopupMenuItem::new(label).on_click(
+            window.listener_for(view_handle, move |view, _, _, cx| {
+                view.remove_tag(entry_id, tag_id, cx)
+            }),
+        ));
+    }
+    menu
+}
+
+/// Narrows the search query to entries carrying the tag. The tag joins the
+/// top-level terms so fuzzy mode still sees the query's plain words.
+fn with_tag_filter(query: Option<SearchQuery>, tag: Option<String>) -> Option<SearchQuery> {
+    let Some(tag) = tag else {
+        return query;
+    };
+    let tag = QueryNode::Term(QueryTerm::Tag(tag));
+    let root = match query.map(|query| query.root) {
+        Some(QueryNode::And(mut children)) => {
+            children.push(tag);
+            QueryNode::And(children)
+        }
+        Some(root) => QueryNode::And(vec![root, tag]),
+        None => tag,
+    };
+    Some(SearchQuery { root })
+}
+
+fn tag_chip(
+    id: impl Into<ElementId>,
+    label: SharedString,
+    active: bool,
+) -> gpui::Stateful<gpui::Div> {
+    let chip = div()
+        .id(id)
+        .flex_shrink_0()
+        .px_1p5()
+        .rounded_sm()
+        .cursor_pointer();
+    let chip = if active {
+        chip.bg(rgba(0x93c5fd30)).text_color(rgb(0x93c5fd))
+    } else {
+        chip.bg(rgba(0xffffff0f)).text_color(rgb(0x9aa4af))
+    };
+    chip.hover(|style| style.bg(rgba(0xffffff18))).child(label)
+}
+
+/// Drag payload for reordering pinned rows, rendered as the drag preview.
+#[derive(Clone)]
+struct DraggedPin {
+    id: i32,
+    label: SharedString,
+}
+
+impl Render for DraggedPin {
+    fn render(&mut self, _: &mut Window, _: &mut Context<Self>) -> impl IntoElement {
+        div()
+            .px_2()
+            .py_1()
+            .rounded_md()
+            .bg(rgb(0x313131))
+            .border_l_2()
+            .border_color(rgb(0xf59e0b))
+            .text_color(rgb(0xd2d8df))
+            .child(self.label.clone())
+    }
+}
+
 fn history_preview_text(entry: &Model) -> String {
     if entry.content_type == "link" {
         let url = entry.link_url.as_deref().or(entry.text_content.as_deref());
@@ -876,11 +2680,113 @@ fn history_preview_text(entry: &Model) -> String {
     }
 }
 
+/// Characters kept before the first match when a row shows a snippet.
+const SNIPPET_LEAD_CHARS: usize = 24;
+
+/// Longest snippet in a history row; the row clips it further to its width.
+const SNIPPET_MAX_CHARS: usize = 200;
+
+/// Bytes of the preview searched for the first match, so huge entries don't
+/// slow down scrolling.
+const SNIPPET_SCAN_BYTES: usize = 64 * 1024;
+
+/// Cuts `text` to a window starting shortly before the first match of the
+/// query, with ellipses where text was left out.
+fn history_snippet(text: &str, query: &str, fuzzy: bool) -> String {
+    let scan_end = (0..=SNIPPET_SCAN_BYTES.min(text.len()))
+        .rev()
+        .find(|&index| text.is_char_boundary(index))
+        .unwrap_or(0);
+    let first_match = highlight_positions(&text[..scan_end], query, fuzzy)
+        .first()
+        .copied();
+    let start = first_match.map_or(0, |position| snippet_start(text, position));
+
+    let mut snippet = String::new();
+    if start > 0 {
+        snippet.push('…');
+    }
+    let rest = &text[start..];
+    match rest.char_indices().nth(SNIPPET_MAX_CHARS) {
+        Some((end, _)) => {
+            snippet.push_str(&rest[..end]);
+            snippet.push('…');
+        }
+        None => snippet.push_str(rest),
+    }
+    snippet
+}
+
+/// Start of the snippet for a match at `match_start`: the first word start
+/// within `SNIPPET_LEAD_CHARS` characters before it, or the beginning of the
+/// text when the match is already that close to it.
+fn snippet_start(text: &str, match_start: usize) -> usize {
+    let mut lead: Vec<(usize, char)> = text[..match_start]
+        .char_indices()
+        .rev()
+        .take(SNIPPET_LEAD_CHARS + 1)
+        .collect();
+    if lead.len() <= SNIPPET_LEAD_CHARS {
+        return 0;
+    }
+    lead.pop();
+    lead.iter()
+        .rev()
+        .find(|(_, ch)| ch.is_whitespace())
+        .map(|(index, ch)| index + ch.len_utf8())
+        .or_else(|| lead.last().map(|(index, _)| *index))
+        .unwrap_or(match_start)
+}
+
+/// Decrypted images, keyed by path. Encrypted files can't be handed to
+/// `img` by path, and decrypting on every frame would be wasteful.
+static DECRYPTED_IMAGES: OnceLock<Mutex<HashMap<PathBuf, Arc<Image>>>> = OnceLock::new();
+
+/// Decrypted images kept in memory at most.
+const DECRYPTED_IMAGE_CACHE: usize = 64;
+
+/// Source for a stored image file, decrypting it when the history is
+/// encrypted.
+fn stored_image(path: &Path) -> ImageSource {
+    if crypto::cipher().is_none() {
+        return ImageSource::from(path.to_path_buf());
+    }
+    let cache = DECRYPTED_IMAGES.get_or_init(|| Mutex::new(HashMap::new()));
+    let Ok(mut cache) = cache.lock() else {
+        return ImageSource::from(path.to_path_buf());
+    };
+    if let Some(image) = cache.get(path) {
+        return ImageSource::from(image.clone());
+    }
+    let image = match read_image_bytes(path) {
+        Ok(bytes) => Arc::new(Image::from_bytes(ImageFormat::Bmp, bytes)),
+        Err(err) => {
+            eprintln!("Failed to read image {}: {err}", path.display());
+            return ImageSource::from(path.to_path_buf());
+        }
+    };
+    if cache.len() >= DECRYPTED_IMAGE_CACHE {
+        cache.clear();
+    }
+    cache.insert(path.to_path_buf(), image.clone());
+    ImageSource::from(image)
+}
+
+/// The cached thumbnail of a stored image, else the image itself.
+fn list_thumbnail(path: &Path) -> ImageSource {
+    if crypto::cipher().is_none() {
+        if let Some(thumbnail) = thumbnail_path(path) {
+            return ImageSource::from(thumbnail);
+        }
+    }
+    stored_image(path)
+}
+
 #[cfg(target_os = "windows")]
 fn copy_image_to_clipboard(entry: &Model) -> anyhow::Result<()> {
     let bytes = load_bitmap_bytes_for_clipboard(entry)?;
 
-    let hash = hash_bytes(&bytes);
+    let hash = crypto::content_hash(&bytes);
     ignore_next_hash(hash);
 
     let _clip = Clipboard::new_attempts(10)
@@ -909,10 +2815,10 @@ fn load_bitmap_bytes_for_clipboard(entry: &Model) -> anyhow::Result<Vec<u8>> {
 
     let mut last_err: Option<anyhow::Error> = None;
     for path in candidates {
-        let bytes = match std::fs::read(&path) {
+        let bytes = match read_image_bytes(&path) {
             Ok(bytes) => bytes,
             Err(err) => {
-                last_err = Some(err.into());
+                last_err = Some(err);
                 continue;
             }
         };
@@ -942,6 +2848,11 @@ fn load_bitmap_bytes_for_clipboard(entry: &Model) -> anyhow::Result<Vec<u8>> {
 }
 
 fn payload_for_entry(entry: &Model) -> String {
+    if entry.content_type == "link" && settings().links.enter_action == LinkEnterAction::CopyClean {
+        if let Some(clean_url) = distinct_clean_url(entry) {
+            return clean_url.to_string();
+        }
+    }
     entry
         .text_content
         .as_deref()
@@ -950,46 +2861,187 @@ fn payload_for_entry(entry: &Model) -> String {
         .to_string()
 }
 
+/// Decodes a stored image on the background executor, leaving only the
+/// engine call on this thread.
+async fn recognize_stored_image(
+    cx: &gpui::AsyncApp,
+    image_path: String,
+) -> anyhow::Result<Option<OcrResult>> {
+    let image = cx
+        .background_executor()
+        .spawn(async move { load_image_file(Path::new(&image_path)) })
+        .await?;
+    match image {
+        Some(image) => recognize_decoded(&image).await,
+        None => Ok(None),
+    }
+}
+
+/// Persists an OCR run under `attempt`; `None` clears stale text from a
+/// previous run. Returns the stored values so the caller can update its
+/// in-memory entry.
+async fn save_ocr_result(
+    storage: &Storage,
+    id: i32,
+    result: Option<OcrResult>,
+    attempt: &str,
+) -> Option<(Option<String>, Option<String>)> {
+    let (text, layout) = match result {
+        Some(result) => (Some(result.text), result.layout.to_json()),
+        None => (None, None),
+    };
+    match storage
+        .update_ocr_result(id, text.as_deref(), layout.as_deref(), attempt)
+        .await
+    {
+        Ok(()) => Some((text, layout)),
+        Err(err) => {
+            eprintln!("Failed to save OCR text: {err}");
+            None
+        }
+    }
+}
+
+/// Fetches a preview for the context menu; anything short of one comes
+/// back as a line for the report dialog.
+async fn fetch_preview(url: &url::Url) -> Result<LinkMetadata, String> {
+    match fetch_link_metadata(url).await {
+        Ok(Some(metadata)) => Ok(metadata),
+        Ok(None) => Err(format!("No link preview available for {url}")),
+        Err(err) => match err.downcast_ref::<PolicyViolation>() {
+            Some(violation) => Err(format!("Blocked {url}: {violation}")),
+            None => Err(format!("Failed to fetch {url}: {err}")),
+        },
+    }
+}
+
+fn link_metadata_update<'a>(
+    metadata: &'a LinkMetadata,
+    details: Option<&'a str>,
+) -> LinkMetadataUpdate<'a> {
+    LinkMetadataUpdate {
+        title: metadata.title.as_deref(),
+        description: metadata.description.as_deref(),
+        site_name: metadata.site_name.as_deref(),
+        details,
+    }
+}
+
+fn original_link_url(entry: &Model) -> Option<&str> {
+    entry
+        .link_url
+        .as_deref()
+        .or(entry.text_content.as_deref())
+        .map(str::trim)
+        .filter(|url| !url.is_empty())
+}
+
+/// The cleaned URL of a link entry, when cleaning actually changed something.
+fn distinct_clean_url(entry: &Model) -> Option<&str> {
+    let clean_url = entry
+        .link_clean_url
+        .as_deref()
+        .map(str::trim)
+        .filter(|url| !url.is_empty())?;
+    if original_link_url(entry) == Some(clean_url) {
+        None
+    } else {
+        Some(clean_url)
+    }
+}
+
+/// Lines shown above the first match when the detail view jumps to it.
+const DETAIL_MATCH_CONTEXT_LINES: usize = 2;
+
+/// One highlighted occurrence in the text detail view: the line, and which
+/// highlighted range of that line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct DetailMatch {
+    line: usize,
+    range_index: usize,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum DetailScroll {
+    /// Puts the line near the top, with a little context above it.
+    Jump(usize),
+    /// Scrolls only as far as needed to show the line.
+    Reveal(usize),
+}
+
+/// Images and links get their own previews instead of the line list, and
+/// have no match navigation.
+fn has_rich_detail(entry: &Model) -> bool {
+    (entry.content_type == "image" && entry.image_path.is_some()) || entry.content_type == "link"
+}
+
+fn detail_lines(entries: &[Model], selected_index: usize) -> Vec<SharedString> {
+    detail_body(entries, selected_index)
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| line.to_string().into())
+        .collect()
+}
+
+#[allow(clippy::too_many_arguments)]
 fn detail_body_list(
     entries: &[Model],
+    entry_links: &HashMap<i32, Vec<entry_link::Model>>,
     selected_index: usize,
     query: &str,
+    scroll: Option<DetailScroll>,
     cx: &mut Context<PopupView>,
     list_state: ListState,
 ) -> AnyElement {
+    let scroll_to_top = scroll.map(|_| DetailScroll::Jump(0));
     if let Some(entry) = entries.get(selected_index) {
         if entry.content_type == "image" {
             if let Some(path) = entry.image_path.as_deref() {
+                reset_detail_list(&list_state, 1, scroll_to_top);
                 return detail_image_body_list(entry, path, query, cx, list_state);
             }
         }
         if entry.content_type == "link" {
             if let Some(panel) = detail_link_body_list(entry, query, cx, list_state.clone()) {
+                reset_detail_list(&list_state, 1, scroll_to_top);
                 return panel;
             }
         }
     }
 
-    let body = detail_body(entries, selected_index);
+    let links = entries
+        .get(selected_index)
+        .and_then(|entry| entry_links.get(&entry.id))
+        .cloned()
+        .unwrap_or_default();
     let query = query.to_string();
-    let lines: Vec<SharedString> = body
-        .lines()
-        .map(str::trim)
-        .filter(|line| !line.is_empty())
-        .map(|line| line.to_string().into())
-        .collect();
+    let lines = detail_lines(entries, selected_index);
+    // One item per line, so long entries are virtualized and the view can
+    // scroll to the line holding a match.
+    let item_count = lines.len() + usize::from(!links.is_empty());
+    reset_detail_list(&list_state, item_count, scroll);
     list(
         list_state,
-        cx.processor(move |_view, _index, _window, _cx| {
-            let mut container = div().w_full().whitespace_normal().flex().flex_col().gap_1();
-            for line in &lines {
-                container = container.child(HighlightedText::new_with_mode(
-                    line.clone(),
-                    query.clone(),
-                    HighlightMatchMode::AnyToken,
-                ));
-            }
-            container.into_any_element()
+        cx.processor(move |view, index: usize, _window, _cx| {
+            let fuzzy = view.fuzzy_search;
+            let item = div().w_full().pb_1();
+            let Some(line) = lines.get(index) else {
+                return item
+                    .child(detail_links_section(&links, &query, fuzzy))
+                    .into_any_element();
+            };
+            let current = view
+                .detail_matches
+                .get(view.current_match)
+                .filter(|found| found.line == index)
+                .map(|found| found.range_index);
+            item.child(
+                HighlightedText::new(line.clone(), query.clone())
+                    .fuzzy(fuzzy)
+                    .current_match(current),
+            )
+            .into_any_element()
         }),
     )
     .h_full()
@@ -997,6 +3049,179 @@ fn detail_body_list(
     .into_any_element()
 }
 
+/// Resizes the detail list when its item count changed, and applies a
+/// pending scroll.
+fn reset_detail_list(list_state: &ListState, item_count: usize, scroll: Option<DetailScroll>) {
+    if list_state.item_count() != item_count {
+        list_state.reset(item_count);
+    }
+    match scroll {
+        Some(DetailScroll::Jump(line)) => list_state.scroll_to(ListOffset {
+            item_ix: line.saturating_sub(DETAIL_MATCH_CONTEXT_LINES),
+            offset_in_item: px(0.),
+        }),
+        Some(DetailScroll::Reveal(line)) => list_state.scroll_to_reveal_item(line),
+        None => {}
+    }
+}
+
+fn match_bar_button(id: &'static str, label: &'static str) -> gpui::Stateful<gpui::Div> {
+    div()
+        .id(id)
+        .px_1p5()
+        .rounded_sm()
+        .cursor_pointer()
+        .hover(|style| style.bg(rgba(0xffffff18)))
+        .child(label)
+}
+
+fn fuzzy_toggle(enabled: bool, cx: &mut Context<PopupView>) -> impl IntoElement {
+    let toggle = div()
+        .id("fuzzy-toggle")
+        .px_1p5()
+        .rounded_sm()
+        .text_xs()
+        .cursor_pointer();
+    let toggle = if enabled {
+        toggle.bg(rgba(0x93c5fd30)).text_color(rgb(0x93c5fd))
+    } else {
+        toggle.text_color(rgb(0x9aa4af))
+    };
+    toggle
+        .hover(|style| style.bg(rgba(0xffffff18)))
+        .child("Fuzzy")
+        .on_click(cx.listener(|view, _, _, cx| view.toggle_fuzzy_search(cx)))
+}
+
+fn detail_links_section(links: &[entry_link::Model], query: &str, fuzzy: bool) -> AnyElement {
+    let mut rows = div().w_full().flex().flex_col().gap_1();
+    for (index, link) in links.iter().enumerate() {
+        let target = if link.kind == "email" {
+            format!("mailto:{}", link.url)
+        } else {
+            link.url.clone()
+        };
+        let title = link.title.as_deref().and_then(|value| {
+            let trimmed = value.trim();
+            if trimmed.is_empty() {
+                None
+            } else {
+                Some(SharedString::from(trimmed.to_string()))
+            }
+        });
+
+        let mut row = div()
+            .id(("detail-entry-link", index))
+            .w_full()
+            .flex()
+            .flex_col()
+            .cursor_pointer()
+            .on_click(move |_, _, cx| cx.open_url(&target));
+        if let Some(title) = title {
+            row = row.child(
+                div()
+                    .text_color(rgb(0xf1f5f9))
+                    .child(HighlightedText::new(title, query.to_string()).fuzzy(fuzzy)),
+            );
+        }
+        row = row.child(
+            div()
+                .text_color(rgb(0x93c5fd))
+                .hover(|style| style.underline())
+                .child(HighlightedText::new(link.url.clone(), query.to_string()).fuzzy(fuzzy)),
+        );
+        rows = rows.child(row);
+    }
+
+    div()
+        .w_full()
+        .mt_2()
+        .pt_2()
+        .border_t_1()
+        .border_color(rgba(0xffffff20))
+        .flex()
+        .flex_col()
+        .gap_1()
+        .child(div().text_xs().text_color(rgb(0x9aa4af)).child("Links"))
+        .child(rows)
+        .into_any_element()
+}
+
+fn detail_codes_section(
+    codes: &[DecodedCode],
+    query: &str,
+    fuzzy: bool,
+    cx: &mut Context<PopupView>,
+) -> AnyElement {
+    let mut rows = div().w_full().flex().flex_col().gap_2();
+    for (index, code) in codes.iter().enumerate() {
+        let mut label = code.format.clone();
+        if let Some(summary) = code.summary() {
+            label = format!("{label} · {summary}");
+        }
+        let payload = code.payload.clone();
+        let mut text = div()
+            .id(("detail-code", index))
+            .flex_1()
+            .min_w_0()
+            .child(HighlightedText::new(code.payload.clone(), query.to_string()).fuzzy(fuzzy));
+        if let Some(url) = code.url() {
+            let target = url.to_string();
+            text = text
+                .text_color(rgb(0x93c5fd))
+                .cursor_pointer()
+                .hover(|style| style.underline())
+                .on_click(move |_, _, cx| cx.open_url(&target));
+        }
+
+        rows = rows.child(
+            div()
+                .w_full()
+                .flex()
+                .flex_col()
+                .gap_1()
+                .child(div().text_xs().text_color(rgb(0x9aa4af)).child(label))
+                .child(
+                    div()
+                        .w_full()
+                        .flex()
+                        .items_start()
+                        .gap_2()
+                        .child(text)
+                        .child(
+                            div()
+                                .id(("copy-detail-code", index))
+                                .flex_shrink_0()
+                                .px_2()
+                                .py_1()
+                                .rounded_md()
+                                .bg(rgba(0xffffff12))
+                                .hover(|style| style.bg(rgba(0xffffff24)))
+                                .cursor_pointer()
+                                .text_xs()
+                                .child("Copy")
+                                .on_click(cx.listener(move |view, _, _, cx| {
+                                    view.copy_text(payload.clone(), cx);
+                                })),
+                        ),
+                ),
+        );
+    }
+
+    div()
+        .w_full()
+        .mt_2()
+        .pt_2()
+        .border_t_1()
+        .border_color(rgba(0xffffff20))
+        .flex()
+        .flex_col()
+        .gap_1()
+        .child(div().text_xs().text_color(rgb(0x9aa4af)).child("Codes"))
+        .child(rows)
+        .into_any_element()
+}
+
 fn detail_image_body_list(
     entry: &Model,
     image_path: &str,
@@ -1005,18 +3230,69 @@ fn detail_image_body_list(
     list_state: ListState,
 ) -> AnyElement {
     let image_path = PathBuf::from(image_path);
+    let entry_id = entry.id;
     let ocr_text = entry.ocr_text.clone();
+    let ocr_layout = entry.ocr_layout.as_deref().and_then(OcrLayout::from_json);
+    let codes = entry
+        .image_codes
+        .as_deref()
+        .map(codes_from_json)
+        .unwrap_or_default();
     let query = query.to_string();
     list(
         list_state,
-        cx.processor(move |_view, _index, _window, _cx| {
+        cx.processor(move |view, _index, _window, cx| {
+            let fuzzy = view.fuzzy_search;
             let mut container = div().w_full().flex().flex_col().gap_2();
 
-            container = container.child(
-                img(image_path.clone())
+            let mut image_block = div().relative().w_full().child(
+                img(stored_image(&image_path))
                     .w_full()
                     .object_fit(ObjectFit::Contain),
             );
+            let selection = view
+                .ocr_selection
+                .filter(|selection| selection.entry_id == entry_id);
+            if let Some(layout) = ocr_layout.clone() {
+                let selected_text = selection
+                    .map(|selection| selection.selected_text(&layout))
+                    .unwrap_or_default();
+                image_block = image_block.child(ocr_overlay(
+                    entry_id,
+                    layout,
+                    query.clone(),
+                    fuzzy,
+                    selection,
+                    cx.entity(),
+                ));
+                container = container.child(image_block);
+                if !selected_text.is_empty() {
+                    // A row, so the button keeps its own width.
+                    container = container.child(
+                        div().flex().child(
+                            div()
+                                .id("copy-ocr-selection")
+                                .px_2()
+                                .py_1()
+                                .rounded_md()
+                                .bg(rgba(0xffffff12))
+                                .hover(|style| style.bg(rgba(0xffffff24)))
+                                .cursor_pointer()
+                                .text_xs()
+                                .child("Copy Selected Text")
+                                .on_click(cx.listener(move |view, _, _, cx| {
+                                    view.copy_text(selected_text.clone(), cx);
+                                })),
+                        ),
+                    );
+                }
+            } else {
+                container = container.child(image_block);
+            }
+
+            if !codes.is_empty() {
+                container = container.child(detail_codes_section(&codes, &query, fuzzy, cx));
+            }
 
             if let Some(ocr) = ocr_text.as_ref() {
                 let trimmed = ocr.trim();
@@ -1046,11 +3322,7 @@ fn detail_image_body_list(
                             )
                             .child(div().w_full().flex().flex_col().gap_1().children(
                                 lines.into_iter().map(|line| {
-                                    HighlightedText::new_with_mode(
-                                        line,
-                                        query.clone(),
-                                        HighlightMatchMode::AnyToken,
-                                    )
+                                    HighlightedText::new(line, query.clone()).fuzzy(fuzzy)
                                 }),
                             )),
                     );
@@ -1065,6 +3337,188 @@ fn detail_image_body_list(
     .into_any_element()
 }
 
+#[derive(Clone, Copy)]
+struct OcrSelection {
+    entry_id: i32,
+    /// Drag start and end in image pixel coordinates.
+    start: (f32, f32),
+    end: (f32, f32),
+    dragging: bool,
+}
+
+impl OcrSelection {
+    fn intersects(&self, word: &OcrWord) -> bool {
+        let (left, right) = (self.start.0.min(self.end.0), self.start.0.max(self.end.0));
+        let (top, bottom) = (self.start.1.min(self.end.1), self.start.1.max(self.end.1));
+        word.x <= right
+            && word.x + word.width >= left
+            && word.y <= bottom
+            && word.y + word.height >= top
+    }
+
+    fn selected_text(&self, layout: &OcrLayout) -> String {
+        layout
+            .lines
+            .iter()
+            .map(|line| {
+                line.words
+                    .iter()
+                    .filter(|word| self.intersects(word))
+                    .map(|word| word.text.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Maps between image pixels and the on-screen rect of an `ObjectFit::Contain`
+/// image laid out in `bounds`.
+#[derive(Clone, Copy)]
+struct OcrFrame {
+    origin: Point<Pixels>,
+    scale: f32,
+    width: f32,
+    height: f32,
+}
+
+impl OcrFrame {
+    fn fit(bounds: Bounds<Pixels>, layout: &OcrLayout) -> Option<Self> {
+        if layout.width == 0 || layout.height == 0 {
+            return None;
+        }
+        let width = layout.width as f32;
+        let height = layout.height as f32;
+        let scale = (bounds.size.width / px(width)).min(bounds.size.height / px(height));
+        if scale <= 0. {
+            return None;
+        }
+        let drawn_width = px(width * scale);
+        let drawn_height = px(height * scale);
+        let origin = point(
+            bounds.origin.x + (bounds.size.width - drawn_width) * 0.5,
+            bounds.origin.y + (bounds.size.height - drawn_height) * 0.5,
+        );
+        Some(Self {
+            origin,
+            scale,
+            width,
+            height,
+        })
+    }
+
+    fn word_bounds(&self, word: &OcrWord) -> Bounds<Pixels> {
+        Bounds::new(
+            point(
+                self.origin.x + px(word.x * self.scale),
+                self.origin.y + px(word.y * self.scale),
+            ),
+            size(px(word.width * self.scale), px(word.height * self.scale)),
+        )
+    }
+
+    fn selection_bounds(&self, selection: &OcrSelection) -> Bounds<Pixels> {
+        let left = selection.start.0.min(selection.end.0);
+        let top = selection.start.1.min(selection.end.1);
+        let width = (selection.start.0 - selection.end.0).abs();
+        let height = (selection.start.1 - selection.end.1).abs();
+        Bounds::new(
+            point(
+                self.origin.x + px(left * self.scale),
+                self.origin.y + px(top * self.scale),
+            ),
+            size(px(width * self.scale), px(height * self.scale)),
+        )
+    }
+
+    fn to_image(self, position: Point<Pixels>) -> (f32, f32) {
+        let x = (position.x - self.origin.x) / px(self.scale);
+        let y = (position.y - self.origin.y) / px(self.scale);
+        (x.clamp(0., self.width), y.clamp(0., self.height))
+    }
+}
+
+fn ocr_overlay(
+    entry_id: i32,
+    layout: OcrLayout,
+    query: String,
+    fuzzy: bool,
+    selection: Option<OcrSelection>,
+    entity: gpui::Entity<PopupView>,
+) -> impl IntoElement {
+    canvas(
+        |bounds, window, _| window.insert_hitbox(bounds, HitboxBehavior::Normal),
+        move |bounds, hitbox, window, _| {
+            let Some(frame) = OcrFrame::fit(bounds, &layout) else {
+                return;
+            };
+
+            for word in layout.lines.iter().flat_map(|line| line.words.iter()) {
+                let is_selected = selection.is_some_and(|selection| selection.intersects(word));
+                let is_match =
+                    !query.is_empty() && !highlight_positions(&word.text, &query, fuzzy).is_empty();
+                let color = if is_selected {
+                    rgba(0x93c5fd60)
+                } else if is_match {
+                    rgba(0xfde04760)
+                } else {
+                    continue;
+                };
+                window.paint_quad(fill(frame.word_bounds(word), color));
+            }
+            if let Some(selection) = selection.filter(|selection| selection.dragging) {
+                window.paint_quad(fill(frame.selection_bounds(&selection), rgba(0x93c5fd24)));
+            }
+
+            window.on_mouse_event({
+                let entity = entity.clone();
+                let hitbox = hitbox.clone();
+                move |ev: &MouseDownEvent, phase, window, cx| {
+                    if phase != DispatchPhase::Bubble
+                        || ev.button != MouseButton::Left
+                        || !hitbox.is_hovered(window)
+                    {
+                        return;
+                    }
+                    let point = frame.to_image(ev.position);
+                    entity.update(cx, |view, cx| {
+                        view.begin_ocr_selection(entry_id, point, cx);
+                    });
+                }
+            });
+
+            window.on_mouse_event({
+                let entity = entity.clone();
+                // A drag released outside the image still has to end.
+                move |_: &MouseUpEvent, phase, _, cx| {
+                    if phase != DispatchPhase::Bubble {
+                        return;
+                    }
+                    entity.update(cx, |view, _| {
+                        view.finish_ocr_selection();
+                    });
+                }
+            });
+
+            window.on_mouse_event(move |ev: &MouseMoveEvent, phase, window, cx| {
+                if phase != DispatchPhase::Bubble || !ev.dragging() || !hitbox.is_hovered(window) {
+                    return;
+                }
+                let point = frame.to_image(ev.position);
+                entity.update(cx, |view, cx| {
+                    view.extend_ocr_selection(point, cx);
+                });
+            });
+        },
+    )
+    .absolute()
+    .top_0()
+    .left_0()
+    .size_full()
+}
+
 fn detail_link_body_list(
     entry: &Model,
     query: &str,
@@ -1113,6 +3567,7 @@ fn detail_link_body_list(
         return None;
     }
 
+    let clean_url = distinct_clean_url(entry).map(str::to_string);
     let title = title.map(SharedString::from);
     let url = url.map(SharedString::from);
     let description = description.map(SharedString::from);
@@ -1122,7 +3577,8 @@ fn detail_link_body_list(
     Some(
         list(
             list_state,
-            cx.processor(move |_view, _index, _window, _cx| {
+            cx.processor(move |view, _index, _window, _cx| {
+                let fuzzy = view.fuzzy_search;
                 let mut container = div()
                     .w_full()
                     .flex()
@@ -1133,18 +3589,16 @@ fn detail_link_body_list(
                     .p_2();
 
                 if let Some(title) = title.clone() {
-                    container =
-                        container.child(div().text_color(rgb(0xf1f5f9)).whitespace_normal().child(
-                            HighlightedText::new_with_mode(
-                                title,
-                                query.clone(),
-                                HighlightMatchMode::AnyToken,
-                            ),
-                        ));
+                    container = container.child(
+                        div()
+                            .text_color(rgb(0xf1f5f9))
+                            .whitespace_normal()
+                            .child(HighlightedText::new(title, query.clone()).fuzzy(fuzzy)),
+                    );
                 }
 
                 if let Some(url) = url.clone() {
-                    let url_for_open = url.to_string();
+                    let url_for_open = clean_url.clone().unwrap_or_else(|| url.to_string());
                     container = container.child(
                         div()
                             .id("detail-link-url")
@@ -1153,33 +3607,26 @@ fn detail_link_body_list(
                             .cursor_pointer()
                             .hover(|style| style.underline())
                             .on_click(move |_, _, cx| cx.open_url(&url_for_open))
-                            .child(HighlightedText::new_with_mode(
-                                url,
-                                query.clone(),
-                                HighlightMatchMode::AnyToken,
-                            )),
+                            .child(HighlightedText::new(url, query.clone()).fuzzy(fuzzy)),
                     );
                 }
 
                 if let Some(description) = description.clone() {
-                    container =
-                        container.child(div().text_color(rgb(0x9aa4af)).whitespace_normal().child(
-                            HighlightedText::new_with_mode(
-                                description,
-                                query.clone(),
-                                HighlightMatchMode::AnyToken,
-                            ),
-                        ));
+                    container = container.child(
+                        div()
+                            .text_color(rgb(0x9aa4af))
+                            .whitespace_normal()
+                            .child(HighlightedText::new(description, query.clone()).fuzzy(fuzzy)),
+                    );
                 }
 
                 if let Some(site_label) = site_label.clone() {
-                    container = container.child(div().text_xs().text_color(rgb(0x94a3b8)).child(
-                        HighlightedText::new_with_mode(
-                            site_label,
-                            query.clone(),
-                            HighlightMatchMode::AnyToken,
-                        ),
-                    ));
+                    container = container.child(
+                        div()
+                            .text_xs()
+                            .text_color(rgb(0x94a3b8))
+                            .child(HighlightedText::new(site_label, query.clone()).fuzzy(fuzzy)),
+                    );
                 }
 
                 container.into_any_element()
@@ -1227,6 +3674,27 @@ fn detail_info_panel(entries: &[Model], selected_index: usize) -> AnyElement {
                 items.push(("URL".to_string(), url.to_string()));
             }
         }
+        if let Some(clean_url) = distinct_clean_url(entry) {
+            items.push(("Clean URL".to_string(), clean_url.to_string()));
+        }
+        if let Some(details) = entry
+            .link_details
+            .as_deref()
+            .and_then(LinkDetails::from_json)
+        {
+            if let Some(state) = details.state {
+                items.push(("State".to_string(), state));
+            }
+            if let Some(author) = details.author {
+                items.push(("Author".to_string(), author));
+            }
+            if !details.labels.is_empty() {
+                items.push(("Labels".to_string(), details.labels.join(", ")));
+            }
+            if let Some(duration) = details.duration {
+                items.push(("Duration".to_string(), duration));
+            }
+        }
     }
 
     if entry.content_type == "image" {
@@ -1380,35 +3848,36 @@ fn entry_metrics(entry: &Model) -> (usize, usize) {
     (characters, words)
 }
 
-enum HighlightMatchMode {
-    AllTokens,
-    AnyToken,
-}
-
+/// A single line of text with every query word found in it highlighted.
 struct HighlightedText {
     query: SharedString,
     display_text: SharedString,
-    match_mode: HighlightMatchMode,
+    fuzzy: bool,
+    /// Index of the highlighted range to emphasize as the current match.
+    current_match: Option<usize>,
 }
 
 impl HighlightedText {
     fn new(text: impl Into<SharedString>, query: impl Into<SharedString>) -> Self {
-        Self::new_with_mode(text, query, HighlightMatchMode::AllTokens)
-    }
-
-    fn new_with_mode(
-        text: impl Into<SharedString>,
-        query: impl Into<SharedString>,
-        match_mode: HighlightMatchMode,
-    ) -> Self {
         let text: SharedString = text.into();
         let display_text: SharedString = text.replace('\n', " ").into();
         Self {
             query: query.into(),
             display_text,
-            match_mode,
+            fuzzy: false,
+            current_match: None,
         }
     }
+
+    fn fuzzy(mut self, fuzzy: bool) -> Self {
+        self.fuzzy = fuzzy;
+        self
+    }
+
+    fn current_match(mut self, current_match: Option<usize>) -> Self {
+        self.current_match = current_match;
+        self
+    }
 }
 
 impl IntoElement for HighlightedText {
@@ -1467,7 +3936,7 @@ impl Element for HighlightedText {
             window
                 .text_system()
                 .shape_line(self.display_text.clone(), font_size, &runs, None);
-        let ranges = match_ranges(&self.display_text, &self.query, &self.match_mode);
+        let ranges = match_ranges(&self.display_text, &self.query, self.fuzzy);
         Some((line, ranges))
     }
 
@@ -1484,7 +3953,13 @@ impl Element for HighlightedText {
         let (line, ranges) = prepaint.take().unwrap();
         if !ranges.is_empty() {
             let highlight_color = rgba(0x3311ff30);
-            for range in ranges {
+            let current_color = rgba(0xf59e0b80);
+            for (index, range) in ranges.into_iter().enumerate() {
+                let color = if self.current_match == Some(index) {
+                    current_color
+                } else {
+                    highlight_color
+                };
                 let start_x = bounds.left() + line.x_for_index(range.start);
                 let end_x = bounds.left() + line.x_for_index(range.end);
                 if end_x <= start_x {
@@ -1495,7 +3970,7 @@ impl Element for HighlightedText {
                         point(start_x, bounds.top()),
                         size(end_x - start_x, bounds.bottom() - bounds.top()),
                     ),
-                    highlight_color,
+                    color,
                 );
                 window.paint_quad(quad);
             }
@@ -1505,40 +3980,62 @@ impl Element for HighlightedText {
     }
 }
 
-fn match_ranges(
-    text: &str,
-    query: &str,
-    match_mode: &HighlightMatchMode,
-) -> Vec<std::ops::Range<usize>> {
-    let positions = token_prefix_positions(text, query, match_mode);
+fn match_ranges(text: &str, query: &str, fuzzy: bool) -> Vec<std::ops::Range<usize>> {
+    let positions = highlight_positions(text, query, fuzzy);
     positions_to_ranges(text, &positions)
 }
 
-fn token_prefix_positions(text: &str, query: &str, match_mode: &HighlightMatchMode) -> Vec<usize> {
+fn highlight_positions(text: &str, query: &str, fuzzy: bool) -> Vec<usize> {
+    if fuzzy {
+        fuzzy_positions(text, query)
+    } else {
+        token_prefix_positions(text, query)
+    }
+}
+
+fn fuzzy_positions(text: &str, query: &str) -> Vec<usize> {
+    let mut all_positions = Vec::new();
+    for token in query.split_whitespace().filter_map(fuzzy_highlight_token) {
+        if let Some(found) = fuzzy_match(token, text) {
+            all_positions.extend(found.positions);
+        }
+    }
+    all_positions.sort_unstable();
+    all_positions.dedup();
+    all_positions
+}
+
+/// Filters, negations and operators would fuzzy-match almost anything, so
+/// only plain words are highlighted.
+fn fuzzy_highlight_token(token: &str) -> Option<&str> {
+    if token == "OR" || token.starts_with('-') || token.starts_with('/') {
+        return None;
+    }
+    let is_filter = token.split_once(':').is_some_and(|(field, _)| {
+        !field.is_empty() && field.chars().all(|ch| ch.is_ascii_alphabetic())
+    });
+    if is_filter {
+        return None;
+    }
+    let token = token.trim_matches(|ch| matches!(ch, '"' | '(' | ')'));
+    (!token.is_empty()).then_some(token)
+}
+
+fn token_prefix_positions(text: &str, query: &str) -> Vec<usize> {
     let tokens: Vec<&str> = query.split_whitespace().collect();
     if tokens.is_empty() {
         return Vec::new();
     }
 
-    let word_starts = word_start_positions(text);
+    let folded = FoldedText::new(text);
     let mut all_positions = Vec::new();
 
     for token in tokens {
         if token.is_empty() {
             continue;
         }
-        let positions = prefix_positions_for_token(text, &word_starts, token);
-        match (match_mode, positions) {
-            (HighlightMatchMode::AllTokens, Some(positions)) => {
-                all_positions.extend(positions);
-            }
-            (HighlightMatchMode::AllTokens, None) => {
-                return Vec::new();
-            }
-            (HighlightMatchMode::AnyToken, Some(positions)) => {
-                all_positions.extend(positions);
-            }
-            (HighlightMatchMode::AnyToken, None) => {}
+        if let Some(positions) = folded.prefix_positions(token) {
+            all_positions.extend(positions);
         }
     }
 
@@ -1551,59 +4048,6 @@ fn token_prefix_positions(text: &str, query: &str, match_mode: &HighlightMatchMo
     all_positions
 }
 
-fn word_start_positions(text: &str) -> Vec<usize> {
-    let mut starts = Vec::new();
-    let mut prev_is_word = false;
-    for (byte_index, ch) in text.char_indices() {
-        let is_word = ch.is_ascii_alphanumeric();
-        if is_word && !prev_is_word {
-            starts.push(byte_index);
-        }
-        prev_is_word = is_word;
-    }
-    starts
-}
-
-fn prefix_positions_for_token(
-    text: &str,
-    word_starts: &[usize],
-    token: &str,
-) -> Option<Vec<usize>> {
-    let token_chars: Vec<char> = token.chars().flat_map(|c| c.to_lowercase()).collect();
-    if token_chars.is_empty() {
-        return Some(Vec::new());
-    }
-
-    for &start in word_starts {
-        let mut positions = Vec::new();
-        let mut matched = true;
-        let mut token_index = 0;
-        let mut cursor = start;
-        while token_index < token_chars.len() {
-            if cursor >= text.len() {
-                matched = false;
-                break;
-            }
-            let ch = text[cursor..].chars().next().unwrap();
-            let mut ch_lower_iter = ch.to_lowercase();
-            let ch_lower = ch_lower_iter.next().unwrap();
-            if ch_lower != token_chars[token_index] {
-                matched = false;
-                break;
-            }
-            positions.push(cursor);
-            cursor += ch.len_utf8();
-            token_index += 1;
-        }
-
-        if matched {
-            return Some(positions);
-        }
-    }
-
-    None
-}
-
 fn positions_to_ranges(text: &str, positions: &[usize]) -> Vec<std::ops::Range<usize>> {
     if positions.is_empty() {
         return Vec::new();
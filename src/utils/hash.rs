@@ -11,3 +11,11 @@ pub fn hash_bytes(bytes: &[u8]) -> String {
     }
     output
 }
+
+/// Whether `hash` looks like `hash_bytes` output.
+pub fn is_sha256_hex(hash: &str) -> bool {
+    hash.len() == 64
+        && hash
+            .bytes()
+            .all(|byte| byte.is_ascii_digit() || (b'a'..=b'f').contains(&byte))
+}
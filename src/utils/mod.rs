@@ -1,3 +1,3 @@
 mod hash;
 
-pub use hash::hash_bytes;
+pub use hash::{hash_bytes, is_sha256_hex};